@@ -0,0 +1,212 @@
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use clap::Args;
+use hkdf::Hkdf;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::Sha256;
+use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
+
+use crate::failure::Failure;
+
+pub const KEY_LEN: usize = 32;
+pub const NONCE_LEN: usize = 12;
+pub const TAG_LEN: usize = 16;
+pub const PUBLIC_KEY_LEN: usize = 32;
+const AAD: &[u8] = b"toy-fec object v1";   // Binds ciphertexts to this format
+const KDF_INFO: &[u8] = b"toy-fec x25519 v1"; // Separates keys derived here from any other use of the shared secret
+
+// How the object key is agreed: given outright, or derived per object by
+// X25519 between a fresh sender key and the receiver's long-term key. The
+// second needs no back channel, so it suits the one-way broadcast modes.
+#[derive(Args, Debug, Clone, Default)]
+#[group(multiple = false)]
+pub struct CipherArgs {
+    /// Encrypt the object with ChaCha20-Poly1305 under this key (64 hex digits) before encoding, and decrypt after decoding
+    #[arg(long)]
+    pub encrypt_key: Option<String>,
+
+    /// Encrypt the object to this receiver's X25519 public key (64 hex digits, from `keygen`) before encoding
+    #[arg(long)]
+    pub encrypt_to: Option<String>,
+
+    /// Decrypt an object sent with --encrypt-to using this X25519 secret key (64 hex digits, from `keygen`)
+    #[arg(long)]
+    pub decrypt_secret: Option<String>,
+}
+
+fn key_bytes(flag: &str, text: &str) -> Result<[u8; KEY_LEN], Failure> {
+    let key = hex::decode(text.trim()).map_err(|e| Failure::Config(format!("invalid --{}: {}", flag, e)))?;
+    key.try_into()
+        .map_err(|k: Vec<u8>| Failure::Config(format!("--{} must be {} bytes, got {}", flag, KEY_LEN, k.len())))
+}
+
+impl CipherArgs {
+    // The object as it should be FEC-encoded, or None when no encryption was asked for
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Option<Vec<u8>>, Failure> {
+        if let Some(text) = &self.encrypt_key {
+            return Ok(Some(encrypt_object(&key_bytes("encrypt-key", text)?, plaintext)));
+        }
+        if let Some(text) = &self.encrypt_to {
+            return Ok(Some(encrypt_to(&PublicKey::from(key_bytes("encrypt-to", text)?), plaintext)?));
+        }
+        if self.decrypt_secret.is_some() {
+            return Err(Failure::Config("--decrypt-secret is for receiving; send with --encrypt-to".into()));
+        }
+        Ok(None)
+    }
+
+    // The plaintext of a recovered object, or None when no encryption was asked for
+    pub fn decrypt(&self, object: &[u8]) -> Result<Option<Vec<u8>>, Failure> {
+        if let Some(text) = &self.encrypt_key {
+            return decrypt_object(&key_bytes("encrypt-key", text)?, object).map(Some);
+        }
+        if let Some(text) = &self.decrypt_secret {
+            return decrypt_from(&StaticSecret::from(key_bytes("decrypt-secret", text)?), object).map(Some);
+        }
+        if self.encrypt_to.is_some() {
+            return Err(Failure::Config("--encrypt-to is for sending; receive with --decrypt-secret".into()));
+        }
+        Ok(None)
+    }
+}
+
+// === The AEAD itself (RFC 8439, section 2.8) ===
+
+pub fn seal(key: &[u8; KEY_LEN], nonce: &[u8; NONCE_LEN], aad: &[u8], plaintext: &[u8]) -> Vec<u8> {
+    ChaCha20Poly1305::new(Key::from_slice(key))
+        .encrypt(Nonce::from_slice(nonce), Payload { msg: plaintext, aad })
+        .expect("ChaCha20-Poly1305 takes objects far larger than FEC allows")
+}
+
+pub fn open(key: &[u8; KEY_LEN], nonce: &[u8; NONCE_LEN], aad: &[u8], sealed: &[u8]) -> Option<Vec<u8>> {
+    ChaCha20Poly1305::new(Key::from_slice(key)).decrypt(Nonce::from_slice(nonce), Payload { msg: sealed, aad }).ok()
+}
+
+// What actually gets FEC-encoded: a fresh nonce, then the sealed object
+pub fn encrypt_object(key: &[u8; KEY_LEN], plaintext: &[u8]) -> Vec<u8> {
+    let mut nonce = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut nonce);
+    let mut out = nonce.to_vec();
+    out.extend(seal(key, &nonce, AAD, plaintext));
+    out
+}
+
+pub fn decrypt_object(key: &[u8; KEY_LEN], object: &[u8]) -> Result<Vec<u8>, Failure> {
+    if object.len() < NONCE_LEN + TAG_LEN {
+        return Err(Failure::Mismatch("recovered object is too short to be encrypted".into()));
+    }
+    let (nonce, sealed) = object.split_at(NONCE_LEN);
+    open(key, nonce.try_into().unwrap(), AAD, sealed)
+        .ok_or_else(|| Failure::Mismatch("recovered object fails authentication: wrong key or tampered data".into()))
+}
+
+// === X25519 key agreement ===
+
+// A receiver's long-term key pair: the secret stays with it, the public half goes to senders
+pub fn keygen() -> (StaticSecret, PublicKey) {
+    let secret = StaticSecret::random_from_rng(OsRng);
+    let public = PublicKey::from(&secret);
+    (secret, public)
+}
+
+// The object key both sides reach: HKDF-SHA256 over the shared secret, salted
+// with both public keys so it belongs to this sender key and this receiver
+fn agreed_key(shared: &[u8; 32], sender: &PublicKey, receiver: &PublicKey) -> [u8; KEY_LEN] {
+    let salt = [sender.as_bytes().as_slice(), receiver.as_bytes()].concat();
+    let mut key = [0u8; KEY_LEN];
+    Hkdf::<Sha256>::new(Some(&salt), shared).expand(KDF_INFO, &mut key).expect("32 bytes is a valid HKDF-SHA256 length");
+    key
+}
+
+// A fresh sender key's public half, then the object encrypted under the agreed key
+pub fn encrypt_to(receiver: &PublicKey, plaintext: &[u8]) -> Result<Vec<u8>, Failure> {
+    let ephemeral = EphemeralSecret::random_from_rng(OsRng);
+    let sender = PublicKey::from(&ephemeral);
+    let shared = ephemeral.diffie_hellman(receiver);
+    // A low-order receiver key makes the shared secret public knowledge
+    if !shared.was_contributory() {
+        return Err(Failure::Config("--encrypt-to is not a usable X25519 public key".into()));
+    }
+    let mut out = sender.as_bytes().to_vec();
+    out.extend(encrypt_object(&agreed_key(shared.as_bytes(), &sender, receiver), plaintext));
+    Ok(out)
+}
+
+pub fn decrypt_from(secret: &StaticSecret, object: &[u8]) -> Result<Vec<u8>, Failure> {
+    if object.len() < PUBLIC_KEY_LEN {
+        return Err(Failure::Mismatch("recovered object is too short to be encrypted".into()));
+    }
+    let (sender, sealed) = object.split_at(PUBLIC_KEY_LEN);
+    let sender = PublicKey::from(<[u8; PUBLIC_KEY_LEN]>::try_from(sender).unwrap());
+    let shared = secret.diffie_hellman(&sender);
+    if !shared.was_contributory() {
+        return Err(Failure::Mismatch("recovered object names an unusable X25519 sender key".into()));
+    }
+    decrypt_object(&agreed_key(shared.as_bytes(), &sender, &PublicKey::from(secret)), sealed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unhex(text: &str) -> Vec<u8> {
+        hex::decode(text.split_whitespace().collect::<String>()).unwrap()
+    }
+
+    #[test]
+    fn rfc8439_aead_vector() {
+        // RFC 8439, section 2.8.2
+        let key: [u8; KEY_LEN] = unhex("808182838485868788898a8b8c8d8e8f909192939495969798999a9b9c9d9e9f").try_into().unwrap();
+        let nonce: [u8; NONCE_LEN] = unhex("070000004041424344454647").try_into().unwrap();
+        let aad = unhex("50515253c0c1c2c3c4c5c6c7");
+        let plaintext = b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it.";
+        let expected = unhex(
+            "d31a8d34648e60db7b86afbc53ef7ec2 a4aded51296e08fea9e2b5a736ee62d6
+             3dbea45e8ca9671282fafb69da92728b 1a71de0a9e060b2905d6a5b67ecd3b36
+             92ddbd7f2d778b8c9803aee328091b58 fab324e4fad675945585808b4831d7bc
+             3ff4def08e4b7a9de576d26586cec64b 6116
+             1ae10b594f09e26a7e902ecbd0600691",
+        );
+
+        let sealed = seal(&key, &nonce, &aad, plaintext);
+        assert_eq!(sealed, expected);
+        assert_eq!(open(&key, &nonce, &aad, &sealed).as_deref(), Some(&plaintext[..]));
+    }
+
+    #[test]
+    fn tampered_ciphertext_or_tag_is_rejected() {
+        let key = [7u8; KEY_LEN];
+        let object = encrypt_object(&key, b"block headers");
+        assert_eq!(decrypt_object(&key, &object).unwrap(), b"block headers");
+
+        let mut ciphertext = object.clone();
+        ciphertext[NONCE_LEN] ^= 1;
+        assert!(decrypt_object(&key, &ciphertext).is_err());
+        let mut tag = object.clone();
+        *tag.last_mut().unwrap() ^= 0x80;
+        assert!(decrypt_object(&key, &tag).is_err());
+        assert!(decrypt_object(&key, &object[..object.len() - 1]).is_err());
+        assert!(decrypt_object(&[8u8; KEY_LEN], &object).is_err());
+    }
+
+    #[test]
+    fn x25519_objects_open_only_for_their_receiver() {
+        let (secret, public) = keygen();
+        let object = encrypt_to(&public, b"block headers").unwrap();
+        assert_eq!(decrypt_from(&secret, &object).unwrap(), b"block headers");
+        // A fresh sender key every time, so the same object never encrypts the same way twice
+        assert_ne!(encrypt_to(&public, b"block headers").unwrap()[..PUBLIC_KEY_LEN], object[..PUBLIC_KEY_LEN]);
+
+        let (other, _) = keygen();
+        assert!(decrypt_from(&other, &object).is_err());
+        let mut tampered = object.clone();
+        tampered[PUBLIC_KEY_LEN + NONCE_LEN] ^= 1;
+        assert!(decrypt_from(&secret, &tampered).is_err());
+    }
+
+    #[test]
+    fn low_order_receiver_keys_are_refused() {
+        assert!(encrypt_to(&PublicKey::from([0u8; PUBLIC_KEY_LEN]), b"x").is_err());
+    }
+}
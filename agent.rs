@@ -0,0 +1,276 @@
+use std::collections::VecDeque;
+
+use clap::Args;
+use rand::{Rng, RngCore};
+
+use crate::rng::{self, SimRng, SimStream};
+use crate::stitch::{StitchArgs, StitchKind, StitchPolicy};
+use crate::tips::{self, TipPolicy};
+use crate::{Color, ConsensusParams, ToyDag};
+
+#[derive(Args, Debug, Clone, Copy)]
+pub struct AgentParams {
+    /// Rounds between StitchBot activations
+    #[arg(long, default_value_t = 5)]
+    pub stitch_interval: u64,
+
+    /// Rounds for a block to reach StitchBot, and for its merge blocks to reach the network
+    #[arg(long, default_value_t = 0)]
+    pub stitch_delay: u64,
+
+    /// Chance per round that StitchBot's hashpower finds the merge block it is mining
+    #[arg(long, default_value_t = 1.0)]
+    pub stitch_hashpower: f64,
+}
+
+impl Default for AgentParams {
+    fn default() -> Self {
+        AgentParams { stitch_interval: 5, stitch_delay: 0, stitch_hashpower: 1.0 }
+    }
+}
+
+// What running StitchBot cost and how much of its work still mattered on arrival
+#[derive(Debug, Default)]
+pub struct AgentStats {
+    pub activations: usize,
+    pub published: Vec<u64>,            // Merge blocks that made it into the DAG
+    pub busy_rounds: u64,               // Rounds spent hashing on merge blocks
+    pub latency: u64,                   // Summed activation → publish rounds
+    pub parents: usize,
+    pub stale_parents: usize,           // Parents no longer tips when the merge block arrived
+    pub unproductive: usize,            // Merge blocks that did not shrink the tip set
+}
+
+// StitchBot as a network participant: it works from its own, delayed copy of
+// the DAG, wakes up on a schedule, and has to mine its merge blocks like anyone else.
+pub struct StitchBot {
+    policy: Box<dyn StitchPolicy>,
+    params: AgentParams,
+    view: ToyDag,                       // Blocks that have reached the bot so far
+    heard: u64,                         // Next DAG block ID not yet timestamped
+    inbox: VecDeque<(u64, u64)>,        // (arrival round, block ID) on the way to the bot
+    mining: VecDeque<(u64, Vec<u64>)>,  // (activation round, parents) still to be found
+    outbox: VecDeque<(u64, u64, Vec<u64>)>, // (arrival round, activation round, parents) in flight
+    pub stats: AgentStats,
+}
+
+impl StitchBot {
+    pub fn new(policy: Box<dyn StitchPolicy>, params: AgentParams, dag: &ToyDag) -> Self {
+        let view = ToyDag::with_params(dag.params);
+        StitchBot {
+            policy,
+            params,
+            heard: view.next_id,        // Genesis and the bootstrap blocks are known to everyone from the start
+            view,
+            inbox: VecDeque::new(),
+            mining: VecDeque::new(),
+            outbox: VecDeque::new(),
+            stats: AgentStats::default(),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.policy.name()
+    }
+
+    // One round of the bot's life; returns the merge blocks it published this round
+    pub fn step(&mut self, dag: &mut ToyDag, round: u64, rng: &mut dyn RngCore) -> Vec<u64> {
+        // Hear about new blocks, and replay the ones that have arrived (IDs arrive in order)
+        for id in self.heard..dag.next_id {
+            self.inbox.push_back((round + self.params.stitch_delay, id));
+        }
+        self.heard = dag.next_id;
+        while self.inbox.front().is_some_and(|&(arrival, _)| arrival <= round) {
+            let (_, id) = self.inbox.pop_front().unwrap();
+            self.view.create_block(dag.blocks[&id].parents.clone());
+        }
+
+        if round.is_multiple_of(self.params.stitch_interval.max(1)) && self.mining.is_empty() {
+            let plan = self.policy.plan(&self.view);
+            if !plan.is_empty() {
+                self.stats.activations += 1;
+            }
+            self.mining.extend(plan.into_iter().map(|parents| (round, parents)));
+        }
+
+        // Merge blocks are mined one at a time with the bot's share of hashpower
+        if !self.mining.is_empty() {
+            self.stats.busy_rounds += 1;
+            if rng.gen_bool(self.params.stitch_hashpower.clamp(0.0, 1.0)) {
+                let (activated, parents) = self.mining.pop_front().unwrap();
+                self.outbox.push_back((round + self.params.stitch_delay, activated, parents));
+            }
+        }
+
+        let mut published = Vec::new();
+        while self.outbox.front().is_some_and(|&(arrival, _, _)| arrival <= round) {
+            let (_, activated, parents) = self.outbox.pop_front().unwrap();
+            self.stats.parents += parents.len();
+            self.stats.stale_parents += parents.iter().filter(|&&p| !dag.is_tip(p)).count();
+            self.stats.latency += round - activated;
+            let tips_before = dag.tips.len();
+            let id = dag.create_block(parents);
+            if dag.tips.len() >= tips_before {
+                self.stats.unproductive += 1;
+            }
+            self.stats.published.push(id);
+            published.push(id);
+        }
+        dag.stitched(&published);
+        published
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct StitchAgentArgs {
+    /// Blocks to grow per run
+    #[arg(long, default_value_t = 300)]
+    pub blocks: u64,
+
+    /// Concurrent miners per round
+    #[arg(long, default_value_t = 8)]
+    pub miners: usize,
+
+    /// Tip selection used by every miner
+    #[arg(long, value_enum, default_value_t = TipPolicy::HighestBlue)]
+    pub tips: TipPolicy,
+
+    /// Run these stitch agents side by side in one network instead of a single bot
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub agents: Vec<StitchKind>,
+
+    #[command(flatten)]
+    pub stitch: StitchArgs,
+
+    #[command(flatten)]
+    pub agent: AgentParams,
+
+    #[command(flatten)]
+    pub consensus: ConsensusParams,
+}
+
+// Grow the DAG with every bot taking its turn after each mining round
+fn simulate(args: &StitchAgentArgs, bots: &mut [StitchBot]) -> (ToyDag, f64, usize) {
+    let root = SimStream::unseeded();
+    let (mut miners_rng, mut stitchbot) = (root.fork(rng::MINERS), root.fork(rng::STITCHBOT));
+    let mut dag = ToyDag::with_params(args.consensus);
+    let miners: Vec<_> = (0..args.miners.max(1)).map(|_| args.tips.selector()).collect();
+
+    let mut round = 0;
+    let mut tip_samples = 0;
+    while (dag.blocks.len() as u64) < args.blocks {
+        round += 1;
+        tips::mine_round(&mut dag, &miners, None, &mut miners_rng);
+        for bot in bots.iter_mut() {
+            bot.step(&mut dag, round, &mut stitchbot);
+        }
+        tip_samples += dag.tips.len();
+    }
+    (dag, tip_samples as f64 / round as f64, round as usize)
+}
+
+fn red_merges(dag: &ToyDag, stats: &AgentStats) -> usize {
+    stats.published.iter().filter(|id| dag.blocks[id].color == Color::Red).count()
+}
+
+// The bot as configured next to an idealized one that merges instantly and for free
+pub fn run(args: &StitchAgentArgs) {
+    if !args.agents.is_empty() {
+        return run_competing(args);
+    }
+
+    let free = AgentParams { stitch_interval: 1, stitch_delay: 0, stitch_hashpower: 1.0 };
+    println!(
+        "=== StitchBot as a network agent ({} policy, {} x{} miners, {} blocks) ===",
+        args.stitch.policy().name(),
+        args.tips.selector().name(),
+        args.miners,
+        args.blocks
+    );
+    println!(
+        "{:<34} {:>9} {:>7} {:>10} {:>12} {:>11} {:>10} {:>10}",
+        "agent", "mean tips", "merges", "red merges", "mean latency", "stale refs", "busy", "hash cost"
+    );
+    for (label, params) in [("free (every round, instant)".to_string(), free), (describe(&args.agent), args.agent)] {
+        let mut bots = [StitchBot::new(args.stitch.policy(), params, &ToyDag::with_params(args.consensus))];
+        let (dag, mean_tips, rounds) = simulate(args, &mut bots);
+        let stats = &bots[0].stats;
+        let merges = stats.published.len();
+        println!(
+            "{:<34} {:>9.2} {:>7} {:>10} {:>12.2} {:>10.1}% {:>10} {:>9.1}%",
+            label,
+            mean_tips,
+            merges,
+            red_merges(&dag, stats),
+            stats.latency as f64 / merges.max(1) as f64,
+            100.0 * stats.stale_parents as f64 / stats.parents.max(1) as f64,
+            stats.busy_rounds,
+            // Hashing time as a share of all block production in the run
+            100.0 * stats.busy_rounds as f64 * params.stitch_hashpower / (rounds * args.miners.max(1)) as f64
+        );
+    }
+    println!("=======================================================================");
+}
+
+// Several agents with different policies sharing one network: do their merge
+// blocks collide, end up red, or just keep reshuffling the tips?
+fn run_competing(args: &StitchAgentArgs) {
+    let empty = ToyDag::with_params(args.consensus);
+    let mut bots: Vec<StitchBot> = args
+        .agents
+        .iter()
+        .map(|&kind| StitchBot::new(StitchArgs { stitch: kind, ..args.stitch.clone() }.policy(), args.agent, &empty))
+        .collect();
+    let (dag, mean_tips, _) = simulate(args, &mut bots);
+
+    // Two agents' merge blocks conflict when they reference the same tip without seeing each other
+    let owners: Vec<(usize, u64)> = bots
+        .iter()
+        .enumerate()
+        .flat_map(|(agent, bot)| bot.stats.published.iter().map(move |&id| (agent, id)))
+        .collect();
+    let mut conflicts = vec![0usize; bots.len()];
+    for (i, &(a, x)) in owners.iter().enumerate() {
+        for &(b, y) in &owners[i + 1..] {
+            let shared = dag.blocks[&x].parents.iter().any(|p| dag.blocks[&y].parents.contains(p));
+            if a != b && shared && !dag.past_set(x).contains(&y) && !dag.past_set(y).contains(&x) {
+                conflicts[a] += 1;
+                conflicts[b] += 1;
+            }
+        }
+    }
+
+    println!(
+        "=== Competing stitch agents ({} x{} miners, {} blocks, {}) ===",
+        args.tips.selector().name(),
+        args.miners,
+        args.blocks,
+        describe(&args.agent)
+    );
+    println!("Mean tips over the run: {:.2}", mean_tips);
+    println!(
+        "{:<4} {:<10} {:>7} {:>10} {:>11} {:>10} {:>13}",
+        "#", "policy", "merges", "red merges", "stale refs", "conflicts", "no tip drop"
+    );
+    for (i, bot) in bots.iter().enumerate() {
+        let stats = &bot.stats;
+        println!(
+            "{:<4} {:<10} {:>7} {:>10} {:>10.1}% {:>10} {:>13}",
+            i,
+            bot.name(),
+            stats.published.len(),
+            red_merges(&dag, stats),
+            100.0 * stats.stale_parents as f64 / stats.parents.max(1) as f64,
+            conflicts[i],
+            stats.unproductive
+        );
+    }
+    println!("===============================================================");
+}
+
+fn describe(params: &AgentParams) -> String {
+    format!(
+        "every {}, delay {}, hashpower {:.2}",
+        params.stitch_interval, params.stitch_delay, params.stitch_hashpower
+    )
+}
@@ -0,0 +1,627 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use clap::{Args, Subcommand, ValueEnum};
+
+use crate::ghostdag::{self, Candidate, OrderStep};
+use crate::json::{obj, Json};
+use crate::branches::abandoned_branches;
+use crate::failure::Failure;
+use crate::graph::Annotation;
+use crate::{dot, series, snapshot, Color, ToyDag};
+
+#[derive(Args, Debug)]
+pub struct AnalyzeArgs {
+    /// DAG file written by `demo --save-dag` or the REPL's `save`
+    #[arg(long, global = true, default_value = "dag.txt")]
+    pub dag: PathBuf,
+
+    /// Print explanations as JSON (decision, inputs, thresholds) instead of prose
+    #[arg(long, global = true)]
+    pub json: bool,
+
+    #[command(subcommand)]
+    pub query: Query,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Json,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Query {
+    /// Size, tips, red ratio, chain length, layers, reorgs, parent and mergeset counts
+    Stats,
+    /// The selected chain from genesis to the selected tip
+    Chain,
+    /// A block's children (blocks listing it as a parent) and whether it is a tip
+    Children { block: u64 },
+    /// A block's anticone, or whether two blocks are in each other's anticone
+    Anticone { block: u64, other: Option<u64> },
+    /// One row per block: parents, color, blue score, selected parent, mergeset and past sizes, depth, DAA score, blue work
+    Export {
+        #[arg(long, value_enum, default_value_t)]
+        format: ExportFormat,
+
+        /// Output file (stdout when omitted)
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// One row per insertion: tip count, newest layer and its width, selected tip and its mergeset size
+    Series {
+        #[arg(long, value_enum, default_value_t)]
+        format: ExportFormat,
+
+        /// Output file (stdout when omitted)
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// Show how a block colored its own mergeset
+    Explain { block: u64 },
+    /// Explain a block's color from the virtual block's point of view
+    WhyRed { block: u64 },
+    /// Explain whether and why block A precedes block B in the consensus ordering
+    WhyOrderedBefore { a: u64, b: u64 },
+    /// Graphviz DOT with blue scores, mergeset sizes and a box per chain block's mergeset
+    Dot {
+        /// DOT file (stdout when omitted); render with `dot -Tsvg`
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// Whether another saved DAG is the same one, or the same up to block numbering
+    Compare { other: PathBuf },
+    /// Write how the consensus ordering is built, chain block by chain block, for diffing
+    OrderTrace {
+        /// Trace file (stdout when omitted)
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+}
+
+pub fn run(args: &AnalyzeArgs) -> Result<(), Failure> {
+    let dag = snapshot::load(&args.dag)?;
+    let known = |id: &u64| {
+        if dag.blocks.contains_key(id) {
+            Ok(*id)
+        } else {
+            Err(Failure::Config(format!("no block {} in {}", id, args.dag.display())))
+        }
+    };
+    let (text, json) = match &args.query {
+        Query::Stats => {
+            let stats = DagStats::new(&dag);
+            (stats.text(), stats.json())
+        }
+        Query::Chain => {
+            let chain = dag.selected_chain();
+            let text = chain
+                .iter()
+                .map(|id| format!("{} (score {})", id, dag.blocks[id].blue_score))
+                .collect::<Vec<_>>()
+                .join(" → ");
+            (format!("Selected chain ({} blocks): {}", chain.len(), text), chain.into())
+        }
+        Query::Children { block } => {
+            let block = known(block)?;
+            let children = dag.children(block).to_vec();
+            let text = if dag.is_tip(block) {
+                format!("{} is a tip: no block references it yet", block)
+            } else {
+                format!("Children of {} ({} blocks): {:?}", block, children.len(), children)
+            };
+            let json = obj([("block", block.into()), ("children", children.into()), ("tip", dag.is_tip(block).into())]);
+            (text, json)
+        }
+        Query::Anticone { block, other: None } => {
+            let block = known(block)?;
+            let anticone = dag.anticone(block);
+            let text = format!("Anticone of {} ({} blocks): {:?}", block, anticone.len(), anticone);
+            (text, obj([("block", block.into()), ("anticone", anticone.into())]))
+        }
+        Query::Anticone { block, other: Some(other) } => {
+            let (a, b) = (known(block)?, known(other)?);
+            let relation = if a == b {
+                "same"
+            } else if dag.past_set(b).contains(&a) {
+                "a_in_past_of_b"
+            } else if dag.past_set(a).contains(&b) {
+                "b_in_past_of_a"
+            } else {
+                "anticone"
+            };
+            let text = match relation {
+                "same" => format!("{} is the same block", a),
+                "a_in_past_of_b" => format!("{} is in the past of {}: not in each other's anticone", a, b),
+                "b_in_past_of_a" => format!("{} is in the past of {}: not in each other's anticone", b, a),
+                _ => format!("{} and {} are in each other's anticone (neither saw the other)", a, b),
+            };
+            (text, obj([("a", a.into()), ("b", b.into()), ("relation", relation.into())]))
+        }
+        Query::Export { format, out } => {
+            return write_out(out, "block table", |w| export(&dag, *format, w));
+        }
+        Query::Series { format, out } => {
+            return write_out(out, "insertion series", |w| series::write_series(&dag, *format, w));
+        }
+        Query::Explain { block } => {
+            let block = known(block)?;
+            (ghostdag::explain(&dag, block), ghostdag::explain_json(&dag, block))
+        }
+        Query::WhyRed { block } => {
+            let reason = ColorReason::new(&dag, known(block)?);
+            (reason.text(), reason.json())
+        }
+        Query::WhyOrderedBefore { a, b } => {
+            let reason = OrderReason::new(&dag, known(a)?, known(b)?);
+            (reason.text(), reason.json())
+        }
+        Query::Compare { other } => {
+            let theirs = snapshot::load(other)?;
+            let verdict = if dag.structurally_equal(&theirs) {
+                "identical"
+            } else if dag.isomorphic(&theirs) {
+                "isomorphic"
+            } else {
+                "different"
+            };
+            let text = match verdict {
+                "identical" => format!("{} and {} hold the same blocks under the same IDs", args.dag.display(), other.display()),
+                "isomorphic" => format!("{} and {} are the same DAG numbered differently", args.dag.display(), other.display()),
+                _ => format!("{} and {} are different DAGs", args.dag.display(), other.display()),
+            };
+            (text, obj([("other", other.display().to_string().into()), ("verdict", verdict.into())]))
+        }
+        Query::OrderTrace { out } => {
+            return write_out(out, "ordering trace", |w| ghostdag::write_order_trace(&dag, w));
+        }
+        Query::Dot { out } => return write_out(out, "Graphviz DAG", |w| dot::write_dot(&dag, Annotation::Full, w)),
+    };
+    if args.json {
+        println!("{}", json);
+    } else {
+        println!("{}", text);
+    }
+    Ok(())
+}
+
+// Send a report to a file, or to stdout when no path is given
+fn write_out<F>(out: &Option<PathBuf>, what: &str, write: F) -> Result<(), Failure>
+where
+    F: FnOnce(&mut dyn Write) -> io::Result<()>,
+{
+    match out {
+        Some(path) => {
+            File::create(path)
+                .and_then(|mut f| write(&mut f))
+                .map_err(|e| Failure::Io(format!("cannot write {}: {}", path.display(), e)))?;
+            println!("Wrote {} to {}", what, path.display());
+        }
+        None => write(&mut io::stdout().lock()).map_err(|e| Failure::Io(format!("cannot write {}: {}", what, e)))?,
+    }
+    Ok(())
+}
+
+// Whole-DAG numbers worth comparing between runs
+pub struct DagStats {
+    blocks: usize,
+    tips: usize,
+    reds: usize,
+    chain: usize,
+    selected_tip: u64,
+    selected_blue_score: usize,
+    mean_parents: f64,
+    mean_mergeset: f64,
+    max_mergeset: usize,
+    branches: usize,                    // Connected groups of red blocks
+    layers: usize,                      // Longest parent path from genesis, plus one
+    max_width: usize,                   // Blocks in the widest layer
+    reorgs: Vec<ghostdag::Reorg>,       // Replayed while loading, in insertion order
+    k: usize,
+    max_parents: usize,
+}
+
+impl DagStats {
+    pub fn new(dag: &ToyDag) -> Self {
+        let non_genesis = (dag.blocks.len() - 1).max(1) as f64;
+        let layers = dag.layers();
+        DagStats {
+            blocks: dag.blocks.len(),
+            tips: dag.tips.len(),
+            reds: dag.blocks.values().filter(|b| b.color == Color::Red).count(),
+            chain: dag.selected_chain().len(),
+            selected_tip: dag.selected_parent,
+            selected_blue_score: dag.blocks[&dag.selected_parent].blue_score,
+            mean_parents: dag.blocks.values().map(|b| b.parents.len()).sum::<usize>() as f64 / non_genesis,
+            mean_mergeset: dag.blocks.values().map(|b| b.mergeset.len()).sum::<usize>() as f64 / non_genesis,
+            max_mergeset: dag.blocks.values().map(|b| b.mergeset.len()).max().unwrap_or(0),
+            branches: abandoned_branches(dag).len(),
+            layers: layers.len(),
+            max_width: layers.iter().map(Vec::len).max().unwrap_or(0),
+            reorgs: dag.reorgs.clone(),
+            k: dag.params.k,
+            max_parents: dag.params.max_parents,
+        }
+    }
+
+    fn red_ratio(&self) -> f64 {
+        self.reds as f64 / self.blocks as f64
+    }
+
+    fn text(&self) -> String {
+        [
+            format!("Blocks: {} (k = {}, max parents {})", self.blocks, self.k, self.max_parents),
+            format!("Tips: {}", self.tips),
+            format!("Red: {} ({:.1}%) in {} branches", self.reds, 100.0 * self.red_ratio(), self.branches),
+            format!(
+                "Selected chain: {} blocks up to {} (blue score {})",
+                self.chain, self.selected_tip, self.selected_blue_score
+            ),
+            format!("Layers: {} (widest {} blocks)", self.layers, self.max_width),
+            format!("Reorgs: {} (count×depth: {})", self.reorgs.len(), ghostdag::describe_reorgs(&self.reorgs)),
+            format!("Parents per block: {:.2}", self.mean_parents),
+            format!("Mergeset size: mean {:.2}, max {}", self.mean_mergeset, self.max_mergeset),
+        ]
+        .join("\n")
+    }
+
+    pub fn json(&self) -> Json {
+        obj([
+            ("blocks", self.blocks.into()),
+            ("k", self.k.into()),
+            ("max_parents", self.max_parents.into()),
+            ("tips", self.tips.into()),
+            ("red", self.reds.into()),
+            ("red_ratio", self.red_ratio().into()),
+            ("red_branches", self.branches.into()),
+            ("chain_length", self.chain.into()),
+            ("selected_tip", self.selected_tip.into()),
+            ("selected_blue_score", self.selected_blue_score.into()),
+            ("layers", self.layers.into()),
+            ("max_width", self.max_width.into()),
+            ("reorgs", self.reorgs.len().into()),
+            ("reorg_depths", ghostdag::reorg_histogram(&self.reorgs).into()),
+            ("mean_parents", self.mean_parents.into()),
+            ("mean_mergeset", self.mean_mergeset.into()),
+            ("max_mergeset", self.max_mergeset.into()),
+        ])
+    }
+}
+
+pub fn export<W: Write + ?Sized>(dag: &ToyDag, format: ExportFormat, out: &mut W) -> io::Result<()> {
+    let chain: HashSet<u64> = dag.selected_chain().into_iter().collect();
+    if format == ExportFormat::Csv {
+        writeln!(out, "id,parents,color,blue_score,selected_parent,mergeset,past_size,chain,depth,daa_score,blue_work")?;
+    }
+    for id in 0..dag.next_id {
+        let block = &dag.blocks[&id];
+        let color = format!("{:?}", block.color).to_lowercase();
+        let past_size = dag.past_set(id).len();
+        match format {
+            ExportFormat::Csv => {
+                let parents: Vec<String> = block.parents.iter().map(u64::to_string).collect();
+                writeln!(
+                    out,
+                    "{},{},{},{},{},{},{},{},{},{},{}",
+                    id,
+                    parents.join(" "),
+                    color,
+                    block.blue_score,
+                    block.selected_parent.map_or(String::new(), |sp| sp.to_string()),
+                    block.mergeset.len(),
+                    past_size,
+                    chain.contains(&id),
+                    block.depth,
+                    block.daa_score,
+                    block.blue_work
+                )?;
+            }
+            // One JSON object per line
+            ExportFormat::Json => {
+                let row = obj([
+                    ("id", id.into()),
+                    ("parents", block.parents.clone().into()),
+                    ("color", color.into()),
+                    ("blue_score", block.blue_score.into()),
+                    ("selected_parent", block.selected_parent.into()),
+                    ("mergeset", block.mergeset.len().into()),
+                    ("past_size", past_size.into()),
+                    ("chain", chain.contains(&id).into()),
+                    ("depth", block.depth.into()),
+                    ("daa_score", block.daa_score.into()),
+                    ("blue_work", block.blue_work.into()),
+                ]);
+                writeln!(out, "{}", row)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Where a block lands in the ordering: its step, and its candidate entry unless it is the chain block
+fn locate(steps: &[OrderStep], id: u64) -> (usize, Option<&Candidate>) {
+    steps
+        .iter()
+        .enumerate()
+        .find_map(|(i, step)| {
+            if step.chain_block == Some(id) {
+                Some((i, None))
+            } else {
+                step.merged.iter().find(|c| c.id == id).map(|c| (i, Some(c)))
+            }
+        })
+        .expect("every block is ordered exactly once")
+}
+
+fn merger_name(merged_by: Option<u64>) -> String {
+    match merged_by {
+        Some(c) => format!("chain block {}", c),
+        None => "the virtual block (no chain block has it in its past yet)".to_string(),
+    }
+}
+
+// Where one block sits in the ordering
+struct Placement {
+    id: u64,
+    position: usize,
+    step: usize,
+    chain: bool,                        // The step's chain block itself, not part of its mergeset
+    merged_by: Option<u64>,             // Chain block of the step; None for the virtual block
+}
+
+impl Placement {
+    fn new(steps: &[OrderStep], order: &[u64], id: u64) -> Self {
+        let (step, candidate) = locate(steps, id);
+        Placement {
+            id,
+            position: order.iter().position(|&o| o == id).unwrap(),
+            step,
+            chain: candidate.is_none(),
+            merged_by: steps[step].chain_block,
+        }
+    }
+
+    fn text(&self) -> String {
+        if self.chain {
+            format!("{} is selected-chain block #{}", self.id, self.step)
+        } else {
+            format!("{} is merged by {} at ordering step #{}", self.id, merger_name(self.merged_by), self.step)
+        }
+    }
+
+    fn json(&self) -> Json {
+        obj([
+            ("block", self.id.into()),
+            ("position", self.position.into()),
+            ("step", self.step.into()),
+            ("role", if self.chain { "chain" } else { "merged" }.into()),
+            ("step_chain_block", self.merged_by.into()),
+        ])
+    }
+}
+
+// Everything behind a block's color from the virtual block's point of view
+struct ColorReason {
+    block: u64,
+    color: Color,
+    k: usize,
+    placement: Placement,
+    decision: Option<Candidate>,        // None for selected-chain blocks, blue by construction
+    unseen_blues: Vec<u64>,             // Blues in its anticone when it was judged
+    unseen_on_chain: usize,
+    parents: Vec<u64>,
+    blue_score: usize,
+    chain_score: usize,                 // Blue score of the block that merged it
+    dissent: Vec<u64>,                  // Off-chain blocks that judged it the other way
+}
+
+impl ColorReason {
+    fn new(dag: &ToyDag, block: u64) -> Self {
+        let steps = dag.ordering_steps();
+        let order = dag.consensus_order();
+        let placement = Placement::new(&steps, &order, block);
+        let step = &steps[placement.step];
+        let decision = step.merged.iter().find(|c| c.id == block).cloned();
+        let chain: HashSet<u64> = dag.selected_chain().into_iter().collect();
+
+        let mut unseen_blues = Vec::new();
+        let mut dissent = Vec::new();
+        if let Some(candidate) = &decision {
+            // Rebuild the blue set as it stood when this candidate came up
+            let selected_parent = match step.chain_block {
+                Some(c) => dag.blocks[&c].selected_parent.expect("a chain block that merges something is not genesis"),
+                None => dag.selected_parent,
+            };
+            let mut blues = dag.blue_set(selected_parent);
+            blues.extend(step.merged.iter().take_while(|c| c.id != block).filter(|c| c.blue).map(|c| c.id));
+            let past = dag.past_set(block);
+            let future = dag.future_set(block);
+            unseen_blues = blues.into_iter().filter(|b| !past.contains(b) && !future.contains(b)).collect();
+            unseen_blues.sort();
+
+            // Blocks off the chain may have judged it differently; their view does not count
+            dissent = dag
+                .blocks
+                .values()
+                .filter(|b| !chain.contains(&b.id))
+                .filter(|b| b.mergeset.iter().any(|c| c.id == block && c.blue != candidate.blue))
+                .map(|b| b.id)
+                .collect();
+            dissent.sort();
+        }
+
+        ColorReason {
+            block,
+            color: dag.blocks[&block].color.clone(),
+            k: dag.params.k,
+            unseen_on_chain: unseen_blues.iter().filter(|b| chain.contains(b)).count(),
+            unseen_blues,
+            parents: dag.blocks[&block].parents.clone(),
+            blue_score: dag.blocks[&block].blue_score,
+            chain_score: dag.blocks[&step.chain_block.unwrap_or(dag.selected_parent)].blue_score,
+            placement,
+            decision,
+            dissent,
+        }
+    }
+
+    fn text(&self) -> String {
+        let Some(candidate) = &self.decision else {
+            return format!(
+                "Block {} is {:?}: it is selected-chain block #{}, and the chain is blue by construction.",
+                self.block, self.color, self.placement.step
+            );
+        };
+
+        let mut lines = vec![
+            format!(
+                "Block {} is {:?}: {} merged it with {} blues in its anticone, {} k = {}.",
+                self.block,
+                self.color,
+                merger_name(self.placement.merged_by),
+                candidate.blue_anticone,
+                if candidate.blue { "within" } else { "more than" },
+                self.k
+            ),
+            format!(
+                "  blues it neither saw nor was seen by ({}, {} on the selected chain): {:?}",
+                self.unseen_blues.len(),
+                self.unseen_on_chain,
+                self.unseen_blues
+            ),
+            format!(
+                "  it built on {:?} and reached blue score {}; the selected chain stood at {} where it was merged",
+                self.parents, self.blue_score, self.chain_score
+            ),
+        ];
+        if !self.dissent.is_empty() {
+            lines.push(format!(
+                "  off-chain blocks that colored it {} instead: {:?} (only the selected chain's view decides)",
+                if candidate.blue { "Red" } else { "Blue" },
+                self.dissent
+            ));
+        }
+        if self.color == Color::Red {
+            lines.push("  it keeps its place in the ordering but adds nothing to any blue score".to_string());
+        }
+        lines.join("\n")
+    }
+
+    fn json(&self) -> Json {
+        obj([
+            ("block", self.block.into()),
+            ("color", format!("{:?}", self.color).to_lowercase().into()),
+            ("placement", self.placement.json()),
+            ("rule", if self.decision.is_some() { "blue_anticone <= k" } else { "selected chain is blue" }.into()),
+            ("blue_anticone", self.decision.as_ref().map(|c| c.blue_anticone).into()),
+            ("threshold", self.k.into()),
+            ("unseen_blues", self.unseen_blues.clone().into()),
+            ("unseen_on_chain", self.unseen_on_chain.into()),
+            ("parents", self.parents.clone().into()),
+            ("blue_score", self.blue_score.into()),
+            ("merging_blue_score", self.chain_score.into()),
+            ("dissenting_blocks", self.dissent.clone().into()),
+        ])
+    }
+}
+
+// What decides the relative order of two blocks
+enum OrderRule {
+    Past,                               // The first is an ancestor of the second
+    ChainStep,                          // Merged at different steps of the selected chain
+    MergesetBeforeChain,                // The second is the chain block that merged the first
+    MergesetSort { first_score: usize, second_score: usize },
+}
+
+struct OrderReason {
+    asked_first: u64,
+    total: usize,
+    first: Placement,
+    second: Placement,
+    rule: OrderRule,
+}
+
+impl OrderReason {
+    fn new(dag: &ToyDag, a: u64, b: u64) -> Self {
+        let steps = dag.ordering_steps();
+        let order = dag.consensus_order();
+        let (a, b) = (Placement::new(&steps, &order, a), Placement::new(&steps, &order, b));
+        let asked_first = a.id;
+        let (first, second) = if a.position <= b.position { (a, b) } else { (b, a) };
+
+        let rule = if dag.past_set(second.id).contains(&first.id) {
+            OrderRule::Past
+        } else if first.step != second.step {
+            OrderRule::ChainStep
+        } else if second.chain {
+            OrderRule::MergesetBeforeChain
+        } else {
+            OrderRule::MergesetSort {
+                first_score: dag.blocks[&first.id].blue_score,
+                second_score: dag.blocks[&second.id].blue_score,
+            }
+        };
+        OrderReason { asked_first, total: order.len(), first, second, rule }
+    }
+
+    fn text(&self) -> String {
+        let (first, second) = (self.first.id, self.second.id);
+        if first == second {
+            return format!("{} and {} are the same block", first, second);
+        }
+        let reason = match self.rule {
+            OrderRule::Past => format!("{} is in {}'s past, and the ordering never puts a block before its past", first, second),
+            OrderRule::ChainStep => format!(
+                "they are in each other's anticone; GHOSTDAG orders them by the ordering step that merges them, {} before {}",
+                self.first.step, self.second.step
+            ),
+            OrderRule::MergesetBeforeChain => {
+                format!("{} is in chain block {}'s mergeset, which is ordered before the chain block itself", first, second)
+            }
+            OrderRule::MergesetSort { first_score, second_score } => format!(
+                "both sit in the same mergeset, sorted by (blue score, ID): ({}, {}) < ({}, {})",
+                first_score, first, second_score, second
+            ),
+        };
+        [
+            format!(
+                "{}: {} is ordered before {} (positions {} and {} of {})",
+                if first == self.asked_first { "Yes" } else { "No" },
+                first,
+                second,
+                self.first.position,
+                self.second.position,
+                self.total
+            ),
+            format!("  {}", self.first.text()),
+            format!("  {}", self.second.text()),
+            format!("  {}", reason),
+        ]
+        .join("\n")
+    }
+
+    fn json(&self) -> Json {
+        let rule = match self.rule {
+            OrderRule::Past => obj([("rule", "past".into())]),
+            OrderRule::ChainStep => obj([("rule", "chain_step".into())]),
+            OrderRule::MergesetBeforeChain => obj([("rule", "mergeset_before_chain_block".into())]),
+            OrderRule::MergesetSort { first_score, second_score } => obj([
+                ("rule", "mergeset_sort".into()),
+                ("key", "blue score, then ID".into()),
+                ("first_blue_score", first_score.into()),
+                ("second_blue_score", second_score.into()),
+            ]),
+        };
+        obj([
+            ("answer", (self.first.id == self.asked_first && self.first.id != self.second.id).into()),
+            ("blocks", self.total.into()),
+            ("first", self.first.json()),
+            ("second", self.second.json()),
+            ("reason", rule),
+        ])
+    }
+}
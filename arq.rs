@@ -0,0 +1,395 @@
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
+
+use clap::{Args, ValueEnum};
+use raptorq::{Encoder, EncodingPacket};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::bodies::BodyArgs;
+use crate::channel::{ChannelArgs, LossModel};
+use crate::failure::Failure;
+use crate::fec::{DecodeSession, SymbolArgs};
+use crate::feedback;
+use crate::{frame, grow_dag, headers, snapshot, PAYLOAD_ID_LEN};
+
+#[derive(Args, Debug, Clone)]
+pub struct ArqCompareArgs {
+    /// Saved DAG whose block headers are the payload; without it a fresh DAG of --blocks is grown
+    #[arg(long)]
+    pub dag: Option<PathBuf>,
+
+    /// Blocks in a fresh DAG
+    #[arg(long, default_value_t = 300)]
+    pub blocks: u64,
+
+    /// Take the channel, RTT and link rate from a named link instead of the flags below
+    #[arg(long, value_enum)]
+    pub preset: Option<Preset>,
+
+    /// Run every preset link and report them side by side
+    #[arg(long, conflicts_with = "preset")]
+    pub presets: bool,
+
+    #[command(flatten)]
+    pub channel: ChannelArgs,
+
+    /// Round-trip time between sender and receiver
+    #[arg(long, default_value_t = 100.0)]
+    pub rtt_ms: f64,
+
+    /// Packets the link carries per second
+    #[arg(long, default_value_t = 1000.0)]
+    pub rate: f64,
+
+    /// Pure FEC: repair packets as a fraction of the source packets, all sent up front
+    #[arg(long, default_value_t = 0.3)]
+    pub fec_overhead: f64,
+
+    /// Hybrid: repair sent up front as a fraction of the source packets, before NACKs ask for the rest
+    #[arg(long, default_value_t = 0.1)]
+    pub hybrid_overhead: f64,
+
+    /// Rounds a strategy may take before it counts as failed
+    #[arg(long, default_value_t = 50)]
+    pub max_rounds: usize,
+
+    /// Loss patterns to run each strategy through (seeds 0..runs, the same for every strategy)
+    #[arg(long, default_value_t = 50)]
+    pub runs: u64,
+
+    #[command(flatten)]
+    pub symbols: SymbolArgs,
+}
+
+// Links from a rack to deep space. The long ones are where waiting on feedback
+// costs more than any repair, so a fountain code's one-way delivery wins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Preset {
+    /// Local network: sub-millisecond RTT, rare independent loss
+    Lan,
+    /// Low Earth orbit satellite: 40 ms RTT, short loss bursts at handovers
+    LeoSatellite,
+    /// Geostationary satellite: 600 ms RTT, rain fade bursts
+    GeoSatellite,
+    /// Earth to Moon: 2.6 s RTT, thin link
+    Lunar,
+    /// Earth to Mars at mean distance: 25 minute RTT, deep-space loss
+    Mars,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 5] = [Preset::Lan, Preset::LeoSatellite, Preset::GeoSatellite, Preset::Lunar, Preset::Mars];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Preset::Lan => "lan",
+            Preset::LeoSatellite => "leo-satellite",
+            Preset::GeoSatellite => "geo-satellite",
+            Preset::Lunar => "lunar",
+            Preset::Mars => "mars",
+        }
+    }
+
+    // (channel, RTT in ms, packets per second)
+    pub fn link(&self) -> (ChannelArgs, f64, f64) {
+        let channel = |loss, burst_len| ChannelArgs { loss, burst_len, trace: None };
+        match self {
+            Preset::Lan => (channel(0.01, 1.0), 0.5, 100_000.0),
+            Preset::LeoSatellite => (channel(0.02, 3.0), 40.0, 10_000.0),
+            Preset::GeoSatellite => (channel(0.03, 4.0), 600.0, 2_000.0),
+            Preset::Lunar => (channel(0.05, 2.0), 2_600.0, 500.0),
+            Preset::Mars => (channel(0.10, 4.0), 1_500_000.0, 100.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    Arq,                                // Source packets only; the receiver names what it lacks and those go again
+    Fec,                                // Source plus a fixed amount of repair, no feedback at all
+    Hybrid,                             // Some repair up front, then fresh repair for whatever the NACKs say is short
+}
+
+impl Strategy {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Strategy::Arq => "arq",
+            Strategy::Fec => "fec",
+            Strategy::Hybrid => "hybrid",
+        }
+    }
+}
+
+// One strategy through one loss pattern
+#[derive(Debug, Clone, Copy)]
+pub struct Attempt {
+    pub completed: bool,
+    pub packets: usize,
+    pub rounds: usize,                  // Sending rounds; every one after the first waited on feedback
+    pub secs: f64,                      // Until the receiver held the object (or gave up)
+}
+
+impl ArqCompareArgs {
+    // These arguments with the link taken from `preset`; a --trace still
+    // supplies the losses, so a measured pattern can meet the preset's delay
+    pub fn with_preset(&self, preset: Preset) -> ArqCompareArgs {
+        let (channel, rtt_ms, rate) = preset.link();
+        let channel = ChannelArgs { trace: self.channel.trace.clone(), ..channel };
+        ArqCompareArgs { preset: Some(preset), channel, rtt_ms, rate, ..self.clone() }
+    }
+}
+
+impl Attempt {
+    pub fn round_trips(&self) -> usize {
+        self.rounds - 1
+    }
+}
+
+// Each round goes out back to back at the link rate and lands half an RTT
+// later; the next round waits for feedback sent once the last packet landed.
+// Completion is when the packet that finished the object arrived.
+pub fn attempt(args: &ArqCompareArgs, channel: &LossModel, strategy: Strategy, data: &[u8], symbol_size: u16, seed: u64) -> Attempt {
+    let encoder = Encoder::with_defaults(data, symbol_size);
+    let config = encoder.get_config();
+    let blocks = encoder.get_block_encoders();
+    let mut rng = StdRng::seed_from_u64(seed);
+    let (packet_secs, one_way) = (1.0 / args.rate.max(f64::MIN_POSITIVE), args.rtt_ms / 2000.0);
+
+    let source: Vec<EncodingPacket> = blocks.iter().flat_map(|b| b.source_packets()).collect();
+    let mut session = DecodeSession::new(config);
+    let mut esis: BTreeMap<u8, HashSet<u32>> = BTreeMap::new();
+    let mut next_repair = vec![0u32; blocks.len()];
+    let mut repair = |block: usize, count: u32| {
+        let packets = blocks[block].repair_packets(next_repair[block], count);
+        next_repair[block] += count;
+        packets
+    };
+    let upfront = |overhead: f64| (source.len() as f64 * overhead.max(0.0)).ceil() as usize;
+
+    let mut round: Vec<EncodingPacket> = source.clone();
+    match strategy {
+        Strategy::Arq => {}
+        Strategy::Fec | Strategy::Hybrid => {
+            let count = upfront(if strategy == Strategy::Fec { args.fec_overhead } else { args.hybrid_overhead });
+            round.extend((0..count).flat_map(|i| repair(i % blocks.len(), 1)));
+        }
+    }
+
+    let (mut packets, mut rounds, mut clock) = (0, 0, 0.0);
+    loop {
+        rounds += 1;
+        packets += round.len();
+        let sent = round.len();
+        let arrived = channel.transmit(round.drain(..).enumerate().collect(), &mut rng);
+        for (i, packet) in arrived {
+            let id = packet.payload_id();
+            esis.entry(id.source_block_number()).or_default().insert(id.encoding_symbol_id());
+            if session.push(packet) {
+                let secs = clock + (i + 1) as f64 * packet_secs + one_way;
+                return Attempt { completed: true, packets, rounds, secs };
+            }
+        }
+        clock += sent as f64 * packet_secs + 2.0 * one_way;
+        if strategy == Strategy::Fec || rounds >= args.max_rounds {
+            return Attempt { completed: false, packets, rounds, secs: clock };
+        }
+        round = match strategy {
+            Strategy::Arq => {
+                let held = |p: &&EncodingPacket| {
+                    let id = p.payload_id();
+                    esis.get(&id.source_block_number()).is_some_and(|e| e.contains(&id.encoding_symbol_id()))
+                };
+                source.iter().filter(|p| !held(p)).cloned().collect()
+            }
+            _ => feedback::block_needs(&config, &esis).into_iter().flat_map(|(block, needed, _)| repair(block as usize, needed)).collect(),
+        };
+    }
+}
+
+// Means over every run, times over the completed ones
+struct Summary {
+    completed: usize,
+    packets: f64,
+    round_trips: f64,
+    mean_secs: f64,
+    max_secs: f64,
+}
+
+fn summarize(attempts: &[Attempt]) -> Summary {
+    let n = attempts.len().max(1) as f64;
+    let done: Vec<f64> = attempts.iter().filter(|a| a.completed).map(|a| a.secs).collect();
+    Summary {
+        completed: done.len(),
+        packets: attempts.iter().map(|a| a.packets as f64).sum::<f64>() / n,
+        round_trips: attempts.iter().map(|a| a.round_trips() as f64).sum::<f64>() / n,
+        mean_secs: done.iter().sum::<f64>() / done.len().max(1) as f64,
+        max_secs: done.iter().copied().fold(0.0, f64::max),
+    }
+}
+
+// Milliseconds for short times, seconds or minutes for interplanetary ones
+fn human(secs: f64) -> String {
+    match secs {
+        s if s < 1.0 => format!("{:.1}ms", s * 1000.0),
+        s if s < 600.0 => format!("{:.1}s", s),
+        s => format!("{:.1}min", s / 60.0),
+    }
+}
+
+fn summaries(args: &ArqCompareArgs, channel: &LossModel, data: &[u8], symbol_size: u16) -> Vec<(Strategy, Summary)> {
+    [Strategy::Arq, Strategy::Fec, Strategy::Hybrid]
+        .into_iter()
+        .map(|strategy| {
+            let attempts: Vec<Attempt> = (0..args.runs).map(|seed| attempt(args, channel, strategy, data, symbol_size, seed)).collect();
+            (strategy, summarize(&attempts))
+        })
+        .collect()
+}
+
+// The same header object through pure retransmission, pure FEC and the
+// hybrid of the two, each meeting the same seeded loss patterns
+pub fn run(args: &ArqCompareArgs) -> Result<(), Failure> {
+    let dag = match &args.dag {
+        Some(path) => snapshot::load(path)?,
+        None => grow_dag(args.blocks, &mut StdRng::seed_from_u64(0)),
+    };
+    let (data, _) = headers::serialize(&dag, &BodyArgs::default());
+    let symbols = args.symbols.choose(data.len(), frame::UDP_IPV4_LEN + frame::HEADER_LEN + PAYLOAD_ID_LEN);
+
+    println!("=== FEC vs ARQ vs hybrid: headers of {} blocks, {} bytes ===", dag.blocks.len(), data.len());
+    println!("{}", symbols.describe());
+    if args.presets {
+        return report_presets(args, &data, symbols.size);
+    }
+    let args = &args.preset.map_or_else(|| args.clone(), |p| args.with_preset(p));
+    let channel = args.channel.model()?;
+    println!(
+        "Channel{}: {}, RTT {}, {} packets/s; FEC {:.0}% repair, hybrid {:.0}% up front; {} runs",
+        args.preset.map_or(String::new(), |p| format!(" ({})", p.name())),
+        channel.describe(),
+        human(args.rtt_ms / 1000.0),
+        args.rate,
+        args.fec_overhead * 100.0,
+        args.hybrid_overhead * 100.0,
+        args.runs
+    );
+    println!("{:<8} {:>9} {:>8} {:>10} {:>11} {:>10} {:>10}", "strategy", "completed", "packets", "bytes", "round trips", "mean time", "max time");
+    for (strategy, s) in summaries(args, &channel, &data, symbols.size) {
+        println!(
+            "{:<8} {:>8.1}% {:>8.1} {:>10.0} {:>11.2} {:>10} {:>10}",
+            strategy.name(),
+            s.completed as f64 * 100.0 / args.runs.max(1) as f64,
+            s.packets,
+            s.packets * symbols.packet_len as f64,
+            s.round_trips,
+            human(s.mean_secs),
+            human(s.max_secs)
+        );
+    }
+    println!("Bytes count every datagram on the wire with its IP, UDP and frame headers; times cover completed runs only.");
+    Ok(())
+}
+
+// Every preset link, one row each: how often each strategy finished and its
+// mean time, then how much sooner pure FEC and the hybrid finished than ARQ
+fn report_presets(args: &ArqCompareArgs, data: &[u8], symbol_size: u16) -> Result<(), Failure> {
+    println!(
+        "FEC {:.0}% repair, hybrid {:.0}% up front; {} runs per preset; each cell is completion rate and mean time",
+        args.fec_overhead * 100.0,
+        args.hybrid_overhead * 100.0,
+        args.runs
+    );
+    println!(
+        "{:<14} {:>9} {:>22} {:>16} {:>16} {:>16} {:>8} {:>8}",
+        "preset", "RTT", "channel", "arq", "fec", "hybrid", "fec/arq", "hyb/arq"
+    );
+    for preset in Preset::ALL {
+        let args = args.with_preset(preset);
+        let rows = summaries(&args, &args.channel.model()?, data, symbol_size);
+        let cell = |s: &Summary| format!("{:.0}% {}", s.completed as f64 * 100.0 / args.runs.max(1) as f64, human(s.mean_secs));
+        let speedup = |s: &Summary| if s.completed == 0 { "-".into() } else { format!("{:.1}x", rows[0].1.mean_secs / s.mean_secs) };
+        println!(
+            "{:<14} {:>9} {:>22} {:>16} {:>16} {:>16} {:>8} {:>8}",
+            preset.name(),
+            human(args.rtt_ms / 1000.0),
+            match (&args.channel.trace, args.channel.burst_len) {
+                (Some(_), _) => "trace".to_string(),
+                (None, b) if b <= 1.0 => format!("{:.0}% loss", args.channel.loss * 100.0),
+                (None, b) => format!("{:.0}% loss, bursts of {}", args.channel.loss * 100.0, b),
+            },
+            cell(&rows[0].1),
+            cell(&rows[1].1),
+            cell(&rows[2].1),
+            speedup(&rows[1].1),
+            speedup(&rows[2].1)
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channel::ChannelArgs;
+
+    fn args(loss: f64) -> ArqCompareArgs {
+        ArqCompareArgs {
+            dag: None,
+            blocks: 40,
+            preset: None,
+            presets: false,
+            channel: ChannelArgs { loss, burst_len: 1.0, trace: None },
+            rtt_ms: 100.0,
+            rate: 1000.0,
+            fec_overhead: 0.3,
+            hybrid_overhead: 0.1,
+            max_rounds: 50,
+            runs: 1,
+            symbols: SymbolArgs::default(),
+        }
+    }
+
+    fn payload() -> Vec<u8> {
+        headers::serialize(&grow_dag(40, &mut StdRng::seed_from_u64(0)), &BodyArgs::default()).0
+    }
+
+    #[test]
+    fn a_clean_channel_needs_one_round() {
+        let data = payload();
+        let source = data.len().div_ceil(64);
+        for strategy in [Strategy::Arq, Strategy::Fec, Strategy::Hybrid] {
+            let a = attempt(&args(0.0), &LossModel::Uniform { rate: 0.0 }, strategy, &data, 64, 0);
+            assert!(a.completed, "{:?}", strategy);
+            assert_eq!(a.round_trips(), 0, "{:?}", strategy);
+            // The last source packet completes the object, one way after it was sent
+            if strategy == Strategy::Arq {
+                assert_eq!(a.packets, source);
+                assert!((a.secs - (source as f64 / 1000.0 + 0.05)).abs() < 1e-9, "{}s", a.secs);
+            }
+        }
+    }
+
+    // Past a few RTTs of one-way delay, feedback costs more than repair ever could
+    #[test]
+    fn fountain_codes_win_on_deep_space_links() {
+        let data = payload();
+        let mars = args(0.0).with_preset(Preset::Mars);
+        let channel = mars.channel.model().unwrap();
+        let time = |strategy| (0..10).map(|seed| attempt(&mars, &channel, strategy, &data, 64, seed).secs).sum::<f64>();
+        let fec: Vec<Attempt> = (0..10).map(|seed| attempt(&mars, &channel, Strategy::Fec, &data, 64, seed)).collect();
+        assert!(fec.iter().filter(|a| a.completed).count() >= 8, "{:?}", fec);
+        assert!(time(Strategy::Fec) * 2.0 < time(Strategy::Arq));
+    }
+
+    #[test]
+    fn feedback_strategies_always_finish() {
+        let data = payload();
+        for seed in 0..5 {
+            for strategy in [Strategy::Arq, Strategy::Hybrid] {
+                let a = attempt(&args(0.3), &LossModel::Uniform { rate: 0.3 }, strategy, &data, 64, seed);
+                assert!(a.completed && a.round_trips() > 0, "{:?} seed {}: {:?}", strategy, seed, a);
+            }
+        }
+    }
+}
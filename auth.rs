@@ -0,0 +1,146 @@
+use clap::Args;
+use hmac::{Hmac, Mac};
+use raptorq::{EncodingPacket, ObjectTransmissionInformation};
+use sha2::Sha256;
+
+use crate::failure::Failure;
+
+pub const TAG_LEN: usize = 16;          // HMAC-SHA256 truncated to 128 bits, appended to every packet
+const MIN_KEY_LEN: usize = 16;
+
+#[derive(Args, Debug, Clone, Default)]
+pub struct AuthArgs {
+    /// Shared session key (hex, at least 16 bytes): tag every packet with an HMAC, and drop untagged or forged ones before decoding
+    #[arg(long)]
+    pub auth_key: Option<String>,
+}
+
+impl AuthArgs {
+    pub fn session(&self, config: &ObjectTransmissionInformation) -> Result<Option<PacketAuth>, Failure> {
+        let Some(text) = &self.auth_key else { return Ok(None) };
+        let key = hex::decode(text.trim()).map_err(|e| Failure::Config(format!("invalid --auth-key: {}", e)))?;
+        if key.len() < MIN_KEY_LEN {
+            return Err(Failure::Config(format!("--auth-key must be at least {} bytes, got {}", MIN_KEY_LEN, key.len())));
+        }
+        Ok(Some(PacketAuth::new(&key, config)))
+    }
+}
+
+type HmacSha256 = Hmac<Sha256>;
+
+// A session's packet authenticator. Tags also cover the object config, so a
+// packet from another object sent under the same key is rejected too.
+pub struct PacketAuth {
+    mac: HmacSha256,                    // Keyed and already fed the object config
+}
+
+impl PacketAuth {
+    pub fn new(key: &[u8], config: &ObjectTransmissionInformation) -> Self {
+        let mac = HmacSha256::new_from_slice(key).expect("HMAC takes keys of any length").chain_update(config.serialize());
+        PacketAuth { mac }
+    }
+
+    fn tag(&self, packet: &[u8]) -> [u8; TAG_LEN] {
+        self.mac.clone().chain_update(packet).finalize().into_bytes()[..TAG_LEN].try_into().unwrap()
+    }
+
+    // Any message followed by its tag, for session data other than packets
+    pub fn seal_bytes(&self, message: &[u8]) -> Vec<u8> {
+        let mut bytes = message.to_vec();
+        bytes.extend_from_slice(&self.tag(message));
+        bytes
+    }
+
+    // The message, if the frame carries a valid tag for it
+    pub fn open_bytes<'a>(&self, frame: &'a [u8]) -> Option<&'a [u8]> {
+        let split = frame.len().checked_sub(TAG_LEN)?;
+        let (message, tag) = frame.split_at(split);
+        // Constant time, so timing says nothing about how much of a forged tag was right
+        self.mac.clone().chain_update(message).verify_truncated_left(tag).ok()?;
+        Some(message)
+    }
+
+    // Serialized packet followed by its tag
+    pub fn seal(&self, packet: &EncodingPacket) -> Vec<u8> {
+        self.seal_bytes(&packet.serialize())
+    }
+
+    // The packet, if the frame carries a valid tag for it
+    pub fn open(&self, frame: &[u8]) -> Option<EncodingPacket> {
+        self.open_bytes(frame).filter(|packet| packet.len() >= 4).map(EncodingPacket::deserialize)
+    }
+}
+
+// Frames that authenticate, and how many did not
+pub fn filter_frames(auth: &PacketAuth, frames: Vec<Vec<u8>>) -> (Vec<EncodingPacket>, usize) {
+    let total = frames.len();
+    let accepted: Vec<EncodingPacket> = frames.iter().filter_map(|f| auth.open(f)).collect();
+    let rejected = total - accepted.len();
+    (accepted, rejected)
+}
+
+#[cfg(test)]
+mod tests {
+    use raptorq::Encoder;
+
+    use super::*;
+
+    fn unhex(text: &str) -> Vec<u8> {
+        hex::decode(text).unwrap()
+    }
+
+    #[test]
+    fn rfc4231_vectors() {
+        let cases: [(Vec<u8>, &[u8], &str); 4] = [
+            (vec![0x0b; 20], b"Hi There", "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7"),
+            (b"Jefe".to_vec(), b"what do ya want for nothing?", "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"),
+            (vec![0x0c; 20], b"Test With Truncation", "a3b6167473100ee06e0c796c2955552b"),
+            (
+                vec![0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First",
+                "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54",
+            ),
+        ];
+        for (key, message, expected) in cases {
+            let expected = unhex(expected);
+            // Test case 5 only publishes the 128-bit truncation, the length packet tags use
+            let mac = HmacSha256::new_from_slice(&key).unwrap().chain_update(message).finalize().into_bytes();
+            assert_eq!(mac[..expected.len()], expected[..]);
+        }
+    }
+
+    fn sealed() -> (PacketAuth, Vec<u8>) {
+        let encoder = Encoder::with_defaults(&[5; 300], 64);
+        let auth = PacketAuth::new(&[7; 32], &encoder.get_config());
+        let frame = auth.seal(&encoder.get_encoded_packets(0)[0]);
+        (auth, frame)
+    }
+
+    #[test]
+    fn forged_packets_are_rejected() {
+        let (auth, frame) = sealed();
+        assert!(auth.open(&frame).is_some());
+        for bit in [0, 8 * (frame.len() - TAG_LEN) - 1, 8 * (frame.len() - TAG_LEN), 8 * frame.len() - 1] {
+            let mut forged = frame.clone();
+            forged[bit / 8] ^= 1 << (bit % 8);
+            assert!(auth.open(&forged).is_none(), "bit {} flipped", bit);
+        }
+    }
+
+    #[test]
+    fn truncated_tags_are_rejected() {
+        let (auth, frame) = sealed();
+        assert!(auth.open(&frame[..frame.len() - 1]).is_none());
+        assert!(auth.open(&frame[..frame.len() - TAG_LEN]).is_none());
+        assert!(auth.open(&frame[..3]).is_none());
+    }
+
+    #[test]
+    fn tags_are_bound_to_the_key_and_the_object() {
+        let (_, frame) = sealed();
+        let config = Encoder::with_defaults(&[5; 300], 64).get_config();
+        assert!(PacketAuth::new(&[8; 32], &config).open(&frame).is_none());
+        let other = Encoder::with_defaults(&[5; 301], 64).get_config();
+        assert!(PacketAuth::new(&[7; 32], &other).open(&frame).is_none());
+    }
+}
@@ -0,0 +1,147 @@
+use std::time::{Duration, Instant};
+
+use clap::Args;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::erasure::{self, CodeKind};
+use crate::failure::Failure;
+use crate::pipeline::FecPipeline;
+use crate::{ConsensusParams, ToyDag, SYMBOL_SIZE};
+
+#[derive(Args, Debug)]
+pub struct BenchArgs {
+    /// Code family to time
+    #[arg(long, value_enum, default_value_t)]
+    pub code: CodeKind,
+
+    /// Size of the random object, in KiB
+    #[arg(long, default_value_t = 256)]
+    pub size_kib: usize,
+
+    /// Bytes per symbol
+    #[arg(long, default_value_t = SYMBOL_SIZE)]
+    pub symbol_size: u16,
+
+    /// Repair packets, as a percentage of the source packets
+    #[arg(long, default_value_t = 10)]
+    pub repair_percent: u32,
+
+    /// Packets dropped before each decode, as a percentage of the source packets
+    #[arg(long, default_value_t = 5)]
+    pub loss_percent: u32,
+
+    /// Timed encode and decode runs
+    #[arg(long, default_value_t = 5)]
+    pub runs: usize,
+
+    /// Blocks inserted for the DAG timing (0 skips it)
+    #[arg(long, default_value_t = 2000)]
+    pub blocks: u64,
+
+    /// Most parents each inserted block references
+    #[arg(long, default_value_t = 3)]
+    pub max_fanout: usize,
+
+    /// Seed for the object, the losses and the DAG shape
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+
+    #[command(flatten)]
+    pub consensus: ConsensusParams,
+}
+
+// Throughput in MB/s (10^6 bytes) from the best and the mean of several runs
+fn rates(bytes: usize, times: &[Duration]) -> (f64, f64) {
+    let mb = bytes as f64 / 1e6;
+    let best = times.iter().min().copied().unwrap_or_default();
+    let mean = times.iter().sum::<Duration>() / times.len().max(1) as u32;
+    (mb / best.as_secs_f64(), mb / mean.as_secs_f64())
+}
+
+// Grow a DAG on random subsets of the tips, timing only the insertions
+fn time_insertion<R: Rng>(args: &BenchArgs, rng: &mut R) -> (ToyDag, Duration) {
+    let mut dag = ToyDag::with_params(args.consensus);
+    let mut elapsed = Duration::ZERO;
+    for _ in 0..args.blocks {
+        let mut tips: Vec<u64> = dag.tips.iter().copied().collect();
+        tips.sort();
+        let count = rng.gen_range(1..=args.max_fanout.max(1)).min(tips.len());
+        let parents = tips.choose_multiple(rng, count).copied().collect();
+        let start = Instant::now();
+        dag.create_block(parents);
+        elapsed += start.elapsed();
+    }
+    (dag, elapsed)
+}
+
+pub fn run(args: &BenchArgs) -> Result<(), Failure> {
+    let pipeline = FecPipeline::builder()
+        .symbol_size(args.symbol_size)
+        .overhead_ratio(args.repair_percent as f64 / 100.0)
+        .code(args.code)
+        .build()
+        .map_err(Failure::Config)?;
+    let mut rng = StdRng::seed_from_u64(args.seed);
+    let code = pipeline.erasure_code();
+    let mut data = vec![0u8; args.size_kib.max(1) * 1024];
+    rng.fill(&mut data[..]);
+
+    let source = pipeline.source_symbols(data.len());
+    let repair = pipeline.repair_for(data.len());
+    let loss = source * args.loss_percent as usize / 100;
+    let runs = args.runs.max(1);
+
+    println!(
+        "=== Benchmark: {}, {} KiB object, {} byte symbols ===",
+        code.name(),
+        data.len() / 1024,
+        args.symbol_size
+    );
+    println!("{} source + {} repair packets, {} lost before each decode, {} runs\n", source, repair, loss, runs);
+
+    let mut encode_times = Vec::with_capacity(runs);
+    let mut packets = Vec::new();
+    for _ in 0..runs {
+        let start = Instant::now();
+        packets = code.encode(&data, repair);
+        encode_times.push(start.elapsed());
+    }
+
+    let mut decode_times = Vec::with_capacity(runs);
+    let mut failures = 0;
+    for _ in 0..runs {
+        let mut received = packets.clone();
+        received.shuffle(&mut rng);
+        received.truncate(received.len().saturating_sub(loss));
+        let start = Instant::now();
+        let outcome = erasure::decode_with(code.as_ref(), data.len(), repair, received);
+        decode_times.push(start.elapsed());
+        if outcome.data.as_deref() != Some(&data[..]) {
+            failures += 1;
+        }
+    }
+
+    let (best, mean) = rates(data.len(), &encode_times);
+    println!("Encode: {:8.2} MB/s best, {:8.2} MB/s mean", best, mean);
+    let (best, mean) = rates(data.len(), &decode_times);
+    println!("Decode: {:8.2} MB/s best, {:8.2} MB/s mean", best, mean);
+    if failures > 0 {
+        println!("        ({} of {} decodes failed; raise --repair-percent to time only successes)", failures, runs);
+    }
+
+    if args.blocks > 0 {
+        let (dag, elapsed) = time_insertion(args, &mut rng);
+        println!(
+            "\nDAG insertion: {} blocks in {:.3} s → {:.0} blocks/s (k = {}, up to {} parents, {} tips left)",
+            args.blocks,
+            elapsed.as_secs_f64(),
+            args.blocks as f64 / elapsed.as_secs_f64(),
+            dag.params.k,
+            args.max_fanout,
+            dag.tips.len()
+        );
+    }
+    Ok(())
+}
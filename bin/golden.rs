@@ -0,0 +1,3 @@
+fn main() {
+    toy_fec::golden::run_cli();
+}
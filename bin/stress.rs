@@ -0,0 +1,3 @@
+fn main() {
+    toy_fec::stress::run_cli();
+}
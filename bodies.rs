@@ -0,0 +1,210 @@
+use std::collections::BTreeSet;
+
+use clap::{Args, ValueEnum};
+use raptorq::Encoder;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::channel::LossModel;
+use crate::das::block_data;
+use crate::failure::Failure;
+use crate::fec::{self, SymbolArgs};
+use crate::frame::{self, FrameHeader};
+use crate::headers::BlockHeader;
+use crate::manager::{DecodeManager, Event};
+use crate::merkle::{self, Hash};
+use crate::{Block, ToyDag, PAYLOAD_ID_LEN};
+
+// How block bodies are cut into FEC objects
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum BodyGrouping {
+    /// One object per block
+    Block,
+    /// One object per epoch of --epoch-len selected-chain blocks, in consensus order
+    Epoch,
+}
+
+impl BodyGrouping {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BodyGrouping::Block => "per block",
+            BodyGrouping::Epoch => "per epoch",
+        }
+    }
+}
+
+#[derive(Args, Debug, Clone, Copy)]
+pub struct BodyArgs {
+    /// Also send block bodies after the headers, one FEC object per block or per epoch
+    #[arg(long, value_enum)]
+    pub bodies: Option<BodyGrouping>,
+
+    /// Most transactions in a synthetic block body (each block gets 1 to this many)
+    #[arg(long, default_value_t = 8)]
+    pub txs: usize,
+
+    /// Bytes per synthetic transaction
+    #[arg(long, default_value_t = 64)]
+    pub tx_size: usize,
+
+    /// Selected-chain blocks per epoch with --bodies epoch
+    #[arg(long, default_value_t = 8)]
+    pub epoch_len: usize,
+
+    /// Repair packets per body object, as a fraction of its source packets (at least 2)
+    #[arg(long, default_value_t = 1.0)]
+    pub body_repair: f64,
+}
+
+impl Default for BodyArgs {
+    fn default() -> Self {
+        BodyArgs { bodies: None, txs: 8, tx_size: 64, epoch_len: 8, body_repair: 1.0 }
+    }
+}
+
+pub type Body = Vec<Vec<u8>>;           // A block's transactions, in order
+
+// A block's transactions, expanded from its hash like the DAS block data. Genesis has none.
+pub fn transactions(block: &Block, args: &BodyArgs) -> Body {
+    if block.id == 0 {
+        return Vec::new();
+    }
+    let count = 1 + block.hash[0] as usize % args.txs.max(1);
+    let size = args.tx_size.max(1);
+    block_data(block, count * size).chunks(size).map(<[u8]>::to_vec).collect()
+}
+
+pub fn tx_root(txs: &[Vec<u8>]) -> Hash {
+    let leaves: Vec<Hash> = txs.iter().map(|tx| merkle::leaf_hash(tx)).collect();
+    merkle::merkle_root(&leaves)
+}
+
+// Body object: for each block, ID | transaction count | (length | bytes) per transaction
+pub fn serialize(dag: &ToyDag, ids: &[u64], args: &BodyArgs) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for &id in ids {
+        let txs = transactions(&dag.blocks[&id], args);
+        bytes.extend_from_slice(&id.to_be_bytes());
+        bytes.extend_from_slice(&(txs.len() as u16).to_be_bytes());
+        for tx in &txs {
+            bytes.extend_from_slice(&(tx.len() as u32).to_be_bytes());
+            bytes.extend_from_slice(tx);
+        }
+    }
+    bytes
+}
+
+pub fn parse(mut bytes: &[u8]) -> Result<Vec<(u64, Body)>, String> {
+    let mut take = |n: usize| -> Result<&[u8], String> {
+        if bytes.len() < n {
+            return Err("body data ends mid-field".into());
+        }
+        let (head, rest) = bytes.split_at(n);
+        bytes = rest;
+        Ok(head)
+    };
+    let mut blocks = Vec::new();
+    while let Ok(field) = take(8) {
+        let id = u64::from_be_bytes(field.try_into().unwrap());
+        let count = u16::from_be_bytes(take(2)?.try_into().unwrap());
+        let mut txs = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let len = u32::from_be_bytes(take(4)?.try_into().unwrap()) as usize;
+            txs.push(take(len)?.to_vec());
+        }
+        blocks.push((id, txs));
+    }
+    Ok(blocks)
+}
+
+// What came of sending the bodies
+#[derive(Debug, Default)]
+pub struct BodyReport {
+    pub objects: usize,
+    pub recovered_objects: usize,
+    pub frames_sent: usize,
+    pub frames_lost: usize,
+    pub verified: usize,                // Bodies whose transactions hash to their header's tx root
+    pub mismatched: Vec<u64>,
+    pub missing: Vec<u64>,              // Blocks whose object never decoded
+}
+
+// Send every body over one lossy channel and reassemble them against the
+// recovered headers, which the receiver already trusts
+pub fn transmit<R: Rng>(
+    dag: &ToyDag,
+    headers: &[BlockHeader],
+    args: &BodyArgs,
+    grouping: BodyGrouping,
+    symbols: &SymbolArgs,
+    loss_rate: f64,
+    rng: &mut R,
+) -> Result<BodyReport, Failure> {
+    let groups: Vec<Vec<u64>> = match grouping {
+        BodyGrouping::Block => (0..dag.next_id).map(|id| vec![id]).collect(),
+        BodyGrouping::Epoch => dag.epochs(args.epoch_len),
+    };
+
+    let session = rng.r#gen();
+    let mut frames = Vec::new();
+    for (object, ids) in groups.iter().enumerate() {
+        let data = serialize(dag, ids, args);
+        let encoder = Encoder::with_defaults(&data, symbols.choose(data.len(), frame::HEADER_LEN + PAYLOAD_ID_LEN).size);
+        let config = encoder.get_config();
+        let repair = (fec::source_symbol_count(&config) as f64 * args.body_repair).ceil().max(2.0) as u32;
+        let header = FrameHeader { session, object: object as u32, config };
+        frames.extend(encoder.get_encoded_packets(repair).iter().map(|p| frame::encode_frame(&header, p)));
+    }
+    frames.shuffle(rng);
+    let mut report = BodyReport { objects: groups.len(), frames_sent: frames.len(), ..Default::default() };
+    let frames = LossModel::Uniform { rate: loss_rate.clamp(0.0, 1.0) }.transmit(frames, rng);
+    report.frames_lost = report.frames_sent - frames.len();
+
+    let mut manager = DecodeManager::new(Some(session), groups.len(), None);
+    let mut events: Vec<Event> = frames.iter().flat_map(|f| manager.push_frame(f)).collect();
+    events.extend(manager.finish());
+
+    let mut unseen: BTreeSet<u64> = (0..dag.next_id).collect();
+    for event in events {
+        let Event::Completed { outcome, .. } = event else { continue };
+        let data = outcome.data.expect("completed objects carry their data");
+        let blocks = parse(&data).map_err(|e| Failure::Decode(format!("recovered body object is malformed: {}", e)))?;
+        report.recovered_objects += 1;
+        for (id, txs) in blocks {
+            unseen.remove(&id);
+            match headers.get(id as usize) {
+                Some(header) if header.tx_root == tx_root(&txs) => report.verified += 1,
+                _ => report.mismatched.push(id),
+            }
+        }
+    }
+    report.missing = unseen.into_iter().collect();
+    Ok(report)
+}
+
+impl BodyReport {
+    pub fn print(&self, grouping: BodyGrouping) {
+        println!("\n=== Block bodies ({}, {} objects) ===", grouping.name(), self.objects);
+        println!("{} frames sent, {} lost in transit", self.frames_sent, self.frames_lost);
+        println!(
+            "Recovered {} of {} objects: {} bodies match their header's tx root, {} do not",
+            self.recovered_objects,
+            self.objects,
+            self.verified,
+            self.mismatched.len()
+        );
+        if !self.missing.is_empty() {
+            println!("Blocks still without a body: {:?}", self.missing);
+        }
+    }
+
+    pub fn result(&self) -> Result<(), Failure> {
+        if !self.mismatched.is_empty() {
+            return Err(Failure::Mismatch(format!("{} block bodies do not match their headers", self.mismatched.len())));
+        }
+        if !self.missing.is_empty() {
+            return Err(Failure::Decode(format!("{} block bodies could not be recovered", self.missing.len())));
+        }
+        Ok(())
+    }
+}
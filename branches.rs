@@ -0,0 +1,123 @@
+use std::collections::{BTreeSet, HashSet};
+
+use clap::Args;
+
+use crate::stitch::StitchArgs;
+use crate::tips::{self, TipPolicy};
+use crate::{Color, ConsensusParams, ToyDag};
+
+// A connected group of red blocks: work the selected chain never counted
+#[derive(Debug, Clone)]
+pub struct Branch {
+    pub blocks: Vec<u64>,               // Sorted by ID; the first one is where the branch starts
+    pub origin: Vec<u64>,               // Blue blocks the branch grew out of
+    pub merged: bool,                   // Referenced by the selected tip's past, just colored red
+    pub tips: usize,                    // Branch blocks that are still DAG tips
+}
+
+// Group red blocks into sub-DAGs connected through parent/child links
+pub fn abandoned_branches(dag: &ToyDag) -> Vec<Branch> {
+    let red: HashSet<u64> = dag.blocks.values().filter(|b| b.color == Color::Red).map(|b| b.id).collect();
+    let selected_past = dag.past_set(dag.selected_parent);
+    let mut seen = HashSet::new();
+    let mut branches = Vec::new();
+
+    let mut ordered: Vec<u64> = red.iter().copied().collect();
+    ordered.sort();
+    for start in ordered {
+        if !seen.insert(start) {
+            continue;
+        }
+        let mut members = BTreeSet::from([start]);
+        let mut queue = vec![start];
+        while let Some(id) = queue.pop() {
+            let parents = dag.blocks[&id].parents.iter();
+            for &next in parents.chain(dag.children(id)) {
+                if red.contains(&next) && seen.insert(next) {
+                    members.insert(next);
+                    queue.push(next);
+                }
+            }
+        }
+
+        let origin: BTreeSet<u64> = members
+            .iter()
+            .flat_map(|id| dag.blocks[id].parents.iter().copied())
+            .filter(|p| !members.contains(p))
+            .collect();
+        branches.push(Branch {
+            merged: members.iter().any(|id| selected_past.contains(id)),
+            tips: members.iter().filter(|&&id| dag.is_tip(id)).count(),
+            blocks: members.into_iter().collect(),
+            origin: origin.into_iter().collect(),
+        });
+    }
+    branches.sort_by_key(|b| std::cmp::Reverse(b.blocks.len()));
+    branches
+}
+
+#[derive(Args, Debug)]
+pub struct BranchArgs {
+    /// Blocks to grow
+    #[arg(long, default_value_t = 300)]
+    pub blocks: u64,
+
+    /// Concurrent miners per round
+    #[arg(long, default_value_t = 8)]
+    pub miners: usize,
+
+    /// Tip selection used by every miner
+    #[arg(long, value_enum, default_value_t = TipPolicy::HighestBlue)]
+    pub tips: TipPolicy,
+
+    /// Branches to list individually
+    #[arg(long, default_value_t = 10)]
+    pub show: usize,
+
+    #[command(flatten)]
+    pub stitch: StitchArgs,
+
+    #[command(flatten)]
+    pub consensus: ConsensusParams,
+}
+
+pub fn run(args: &BranchArgs) {
+    let miners: Vec<_> = (0..args.miners.max(1)).map(|_| args.tips.selector()).collect();
+    let (dag, _) = tips::grow_with(args.blocks, args.consensus, &miners, None, args.stitch.policy().as_mut(), String::new());
+    let branches = abandoned_branches(&dag);
+
+    let wasted: usize = branches.iter().map(|b| b.blocks.len()).sum();
+    let unmerged: Vec<&Branch> = branches.iter().filter(|b| !b.merged).collect();
+    println!("=== Abandoned branches ===");
+    println!(
+        "{} blocks ({} x{} miners, {} stitching): {} red in {} branches ({:.1}% of all work)",
+        dag.blocks.len(),
+        args.tips.selector().name(),
+        args.miners,
+        args.stitch.policy().name(),
+        wasted,
+        branches.len(),
+        100.0 * wasted as f64 / dag.blocks.len() as f64
+    );
+    println!(
+        "Never merged under the selected chain: {} branches, {} blocks\n",
+        unmerged.len(),
+        unmerged.iter().map(|b| b.blocks.len()).sum::<usize>()
+    );
+
+    println!("{:>6} {:>6} {:>8} {:>6} {:<20} blocks", "size", "first", "status", "tips", "origin");
+    for branch in branches.iter().take(args.show) {
+        let blocks: Vec<String> = branch.blocks.iter().take(12).map(u64::to_string).collect();
+        println!(
+            "{:>6} {:>6} {:>8} {:>6} {:<20} {}{}",
+            branch.blocks.len(),
+            branch.blocks[0],
+            if branch.merged { "merged" } else { "never" },
+            branch.tips,
+            format!("{:?}", branch.origin),
+            blocks.join(","),
+            if branch.blocks.len() > 12 { ",…" } else { "" }
+        );
+    }
+    println!("==========================");
+}
@@ -0,0 +1,216 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use raptorq::Encoder;
+use rand::Rng;
+
+use crate::agent::AgentParams;
+use crate::bodies::BodyArgs;
+use crate::channel::LossModel;
+use crate::failure::Failure;
+use crate::frame::{self, FrameHeader};
+use crate::headers::{self, BlockHeader, HeaderSet};
+use crate::manager::{DecodeManager, Event};
+use crate::rng::{self, SimRng, SimStream};
+use crate::simulate::{self, MiningArgs, OutputArgs, SimArgs};
+use crate::stitch::StitchArgs;
+use crate::{snapshot, ConsensusParams, ToyDag, SYMBOL_SIZE};
+
+#[derive(Args, Debug)]
+pub struct BroadcastArgs {
+    /// Saved DAG to broadcast; without it a fresh DAG is grown from the flags below
+    #[arg(long)]
+    pub dag: Option<PathBuf>,
+
+    /// Selected-chain blocks per epoch; each epoch's headers become one FEC object
+    #[arg(long, default_value_t = 8)]
+    pub epoch_len: usize,
+
+    /// Newest epochs sent at --recent-repair; older ones get --old-repair
+    #[arg(long, default_value_t = 2)]
+    pub recent: usize,
+
+    /// Repair packets for recent epochs, as a fraction of their source packets
+    #[arg(long, default_value_t = 1.0)]
+    pub recent_repair: f64,
+
+    /// Repair packets for older epochs, as a fraction of their source packets
+    #[arg(long, default_value_t = 0.25)]
+    pub old_repair: f64,
+
+    /// Chance each packet is lost on the way to the receiver
+    #[arg(long, default_value_t = 0.2)]
+    pub loss: f64,
+
+    /// Bytes per symbol
+    #[arg(long, default_value_t = SYMBOL_SIZE)]
+    pub symbol_size: u16,
+
+    /// Seed for the packet losses and a fresh DAG's session ID
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+
+    /// Blocks mined for a fresh DAG
+    #[arg(long, default_value_t = 150)]
+    pub blocks: u64,
+
+    #[command(flatten)]
+    pub mining: MiningArgs,
+
+    #[command(flatten)]
+    pub stitch: StitchArgs,
+
+    #[command(flatten)]
+    pub agent: AgentParams,
+
+    #[command(flatten)]
+    pub consensus: ConsensusParams,
+}
+
+// One epoch's place in the broadcast
+pub struct EpochPlan {
+    pub epoch: usize,
+    pub ids: Vec<u64>,                  // Blocks in the epoch, in ID order
+    pub recent: bool,
+    pub data: Vec<u8>,                  // The epoch's header object
+    pub source: usize,
+    pub repair: u32,
+}
+
+// Cut the DAG into epochs and give the newest ones the extra redundancy:
+// they are what most receivers are still missing.
+pub fn plan(dag: &ToyDag, args: &BroadcastArgs) -> Vec<EpochPlan> {
+    let epochs = dag.epochs(args.epoch_len);
+    let first_recent = epochs.len().saturating_sub(args.recent);
+    epochs
+        .into_iter()
+        .enumerate()
+        .map(|(epoch, mut ids)| {
+            ids.sort();
+            let (data, _) = headers::serialize_blocks(dag, 0, &ids, &BodyArgs::default());
+            let recent = epoch >= first_recent;
+            let source = data.len().div_ceil(args.symbol_size as usize);
+            let ratio = if recent { args.recent_repair } else { args.old_repair };
+            let repair = (source as f64 * ratio).ceil() as u32;
+            EpochPlan { epoch, ids, recent, data, source, repair }
+        })
+        .collect()
+}
+
+// Every epoch's frames, newest epoch first within each round so recent data
+// leads the carousel, and epochs interleaved so a loss burst spreads out
+pub fn schedule(plans: &[EpochPlan], session: u32, symbol_size: u16) -> Vec<Vec<u8>> {
+    let queues: Vec<Vec<Vec<u8>>> = plans
+        .iter()
+        .rev()
+        .map(|plan| {
+            let encoder = Encoder::with_defaults(&plan.data, symbol_size);
+            let header = FrameHeader { session, object: plan.epoch as u32, config: encoder.get_config() };
+            encoder.get_encoded_packets(plan.repair).iter().map(|p| frame::encode_frame(&header, p)).collect()
+        })
+        .collect();
+    let longest = queues.iter().map(Vec::len).max().unwrap_or(0);
+    (0..longest).flat_map(|i| queues.iter().filter_map(move |q| q.get(i).cloned())).collect()
+}
+
+pub fn run(args: &BroadcastArgs) -> Result<(), Failure> {
+    let root = SimStream::seeded(args.seed);
+    let dag = match &args.dag {
+        Some(path) => snapshot::load(path)?,
+        None => {
+            let sim = SimArgs {
+                blocks: args.blocks,
+                mining: args.mining,
+                stitch: args.stitch.clone(),
+                agent: args.agent,
+                consensus: args.consensus,
+                output: OutputArgs { quiet: true, verbose: 0 },
+                ..SimArgs::default()
+            };
+            simulate::grow(&sim, &root)
+        }
+    };
+
+    let plans = plan(&dag, args);
+    let session = root.fork("session").r#gen();
+    let sent = schedule(&plans, session, args.symbol_size);
+    let sent_count = sent.len();
+    let received = LossModel::Uniform { rate: args.loss.clamp(0.0, 1.0) }.transmit(sent, &mut root.fork(rng::CHANNEL));
+
+    let mut manager = DecodeManager::new(Some(session), plans.len(), None);
+    let mut outcomes = vec![None; plans.len()];
+    let mut arrived = vec![0; plans.len()];
+    for (header, _) in received.iter().filter_map(|f| frame::decode_frame(f)) {
+        arrived[header.object as usize] += 1;
+    }
+    let mut events: Vec<Event> = received.iter().flat_map(|f| manager.push_frame(f)).collect();
+    events.extend(manager.finish());
+    for event in events {
+        if let Event::Completed { key, outcome } = event {
+            outcomes[key.1 as usize] = outcome.data;
+        }
+    }
+
+    println!(
+        "=== Epoch broadcast ({} epochs of {} chain blocks, newest {} at {:.0}% repair, older at {:.0}%) ===",
+        plans.len(),
+        args.epoch_len,
+        args.recent,
+        args.recent_repair * 100.0,
+        args.old_repair * 100.0
+    );
+    println!("{:>5} {:>7} {:>6} {:>7} {:>7} {:>8}  result", "epoch", "blocks", "bytes", "source", "repair", "arrived");
+    let mut recovered: Vec<BlockHeader> = Vec::new();
+    let mut params = None;
+    let mut failed = 0;
+    for plan in &plans {
+        let result = match &outcomes[plan.epoch] {
+            Some(data) => {
+                let set = headers::parse(data).map_err(|e| Failure::Decode(format!("epoch {} is malformed: {}", plan.epoch, e)))?;
+                params = Some(set.params);
+                recovered.extend(set.headers);
+                "decoded"
+            }
+            None => {
+                failed += 1;
+                "lost"
+            }
+        };
+        println!(
+            "{:>5} {:>7} {:>6} {:>7} {:>7} {:>8}  {}{}",
+            plan.epoch,
+            plan.ids.len(),
+            plan.data.len(),
+            plan.source,
+            plan.repair,
+            arrived[plan.epoch],
+            result,
+            if plan.recent { " (recent)" } else { "" }
+        );
+    }
+
+    // Blocks merged late can land in a later epoch than higher IDs, so the
+    // receiver replays whatever run of IDs it holds without a gap
+    recovered.sort_by_key(|h| h.id);
+    let prefix = recovered.iter().enumerate().take_while(|(i, h)| h.id == *i as u64).count();
+    recovered.truncate(prefix);
+    let rebuilt = match (params, recovered.first()) {
+        (Some(params), Some(genesis)) => {
+            let checkpoint_hash = genesis.hash;
+            let set = HeaderSet { params, checkpoint: 0, checkpoint_hash, headers: recovered };
+            headers::rebuild(&set).map_err(|e| Failure::Mismatch(format!("recovered epochs do not rebuild: {}", e)))?.blocks.len()
+        }
+        _ => 0,
+    };
+    println!(
+        "\n{} of {} packets arrived; the receiver rebuilt {} of {} blocks",
+        received.len(),
+        sent_count,
+        rebuilt,
+        dag.blocks.len()
+    );
+    if failed > 0 {
+        return Err(Failure::Decode(format!("{} of {} epochs lost", failed, plans.len())));
+    }
+    Ok(())
+}
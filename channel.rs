@@ -0,0 +1,281 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use clap::Args;
+use rand::Rng;
+
+use crate::failure::Failure;
+
+// How the network drops packets sent in order
+#[derive(Debug, Clone)]
+pub enum LossModel {
+    // Every packet is lost independently with the same probability
+    Uniform { rate: f64 },
+    // Two-state Markov channel: losses cluster while the link is in the bad state
+    GilbertElliott {
+        to_bad: f64,                    // Chance per packet of a good link turning bad
+        to_good: f64,                   // Chance per packet of a bad link recovering
+        bad_loss: f64,                  // Loss probability while bad (good state is lossless)
+    },
+    // A measured loss pattern played back from a random point, wrapping at its end
+    Trace(Rc<LossTrace>),
+}
+
+impl LossModel {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LossModel::Uniform { .. } => "uniform",
+            LossModel::GilbertElliott { .. } => "gilbert-elliott",
+            LossModel::Trace(_) => "trace",
+        }
+    }
+
+    // Long-run fraction of packets lost
+    pub fn mean_loss(&self) -> f64 {
+        match *self {
+            LossModel::Uniform { rate } => rate,
+            LossModel::GilbertElliott { to_bad, to_good, bad_loss } => bad_loss * to_bad / (to_bad + to_good),
+            LossModel::Trace(ref trace) => trace.mean_loss(),
+        }
+    }
+
+    // Packets that make it through, in their original order
+    pub fn transmit<T, R: Rng>(&self, packets: Vec<T>, rng: &mut R) -> Vec<T> {
+        match *self {
+            LossModel::Uniform { rate } => packets.into_iter().filter(|_| !rng.gen_bool(rate)).collect(),
+            LossModel::GilbertElliott { to_bad, to_good, bad_loss } => {
+                // Start from the stationary state so short transmissions see the advertised mean
+                let mut bad = rng.gen_bool(to_bad / (to_bad + to_good));
+                packets
+                    .into_iter()
+                    .filter(|_| {
+                        let lost = bad && rng.gen_bool(bad_loss);
+                        bad = if bad { !rng.gen_bool(to_good) } else { rng.gen_bool(to_bad) };
+                        !lost
+                    })
+                    .collect()
+            }
+            LossModel::Trace(ref trace) => {
+                let start = rng.gen_range(0..trace.lost.len());
+                packets.into_iter().enumerate().filter(|(i, _)| !trace.lost[(start + i) % trace.lost.len()]).map(|(_, p)| p).collect()
+            }
+        }
+    }
+}
+
+// Per-packet outcomes recorded on a real path, in send order. The text form
+// holds one of two kinds of line, never both:
+//   flags:       runs of 1 (delivered) and 0 (lost), one character per packet
+//   timestamped: "<time> <status>" or "<time>,<status>", status being
+//                delivered/received/ok/1 or lost/dropped/drop/0; lines are
+//                sorted by time, so an unordered capture export works
+// Blank lines and lines starting with # are skipped.
+#[derive(Debug)]
+pub struct LossTrace {
+    pub origin: String,
+    pub lost: Vec<bool>,
+}
+
+impl LossTrace {
+    pub fn load(path: &Path) -> Result<Self, Failure> {
+        let text = fs::read_to_string(path).map_err(|e| Failure::Io(format!("cannot read {}: {}", path.display(), e)))?;
+        Self::parse(&text, &path.display().to_string())
+    }
+
+    pub fn parse(text: &str, origin: &str) -> Result<Self, Failure> {
+        let mut flags: Vec<bool> = Vec::new();
+        let mut timed: Vec<(f64, bool)> = Vec::new();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let bad = |what: String| Failure::Config(format!("{} line {}: {}", origin, n + 1, what));
+            if line.bytes().all(|b| b == b'0' || b == b'1') {
+                flags.extend(line.bytes().map(|b| b == b'0'));
+                continue;
+            }
+            let mut fields = line.split(|c: char| c == ',' || c.is_whitespace()).filter(|f| !f.is_empty());
+            let (Some(time), Some(status), None) = (fields.next(), fields.next(), fields.next()) else {
+                return Err(bad(format!("expected 0/1 flags or '<time> <status>', found '{}'", line)));
+            };
+            let time: f64 = time.parse().map_err(|_| bad(format!("'{}' is not a timestamp", time)))?;
+            let lost = match status.to_ascii_lowercase().as_str() {
+                "delivered" | "received" | "ok" | "1" => false,
+                "lost" | "dropped" | "drop" | "0" => true,
+                other => return Err(bad(format!("'{}' is neither delivered nor lost", other))),
+            };
+            timed.push((time, lost));
+        }
+        let lost = match (flags.is_empty(), timed.is_empty()) {
+            (false, false) => return Err(Failure::Config(format!("{}: mixes flag lines with timestamped records", origin))),
+            (true, true) => return Err(Failure::Config(format!("{}: no packets recorded", origin))),
+            (false, true) => flags,
+            (true, false) => {
+                timed.sort_by(|a, b| a.0.total_cmp(&b.0));
+                timed.into_iter().map(|(_, lost)| lost).collect()
+            }
+        };
+        Ok(LossTrace { origin: origin.to_string(), lost })
+    }
+
+    pub fn mean_loss(&self) -> f64 {
+        self.lost.iter().filter(|&&l| l).count() as f64 / self.lost.len() as f64
+    }
+
+    // (bursts, longest) over the runs of consecutive losses
+    pub fn bursts(&self) -> (usize, usize) {
+        let (mut bursts, mut longest, mut run) = (0, 0, 0);
+        for &lost in &self.lost {
+            run = if lost { run + 1 } else { 0 };
+            bursts += (run == 1) as usize;
+            longest = longest.max(run);
+        }
+        (bursts, longest)
+    }
+
+    pub fn describe(&self) -> String {
+        let (bursts, longest) = self.bursts();
+        let lost = self.lost.iter().filter(|&&l| l).count();
+        format!(
+            "trace {}: {} packets, {:.1}% lost in {} bursts (mean {:.1}, longest {})",
+            self.origin,
+            self.lost.len(),
+            self.mean_loss() * 100.0,
+            bursts,
+            lost as f64 / bursts.max(1) as f64,
+            longest
+        )
+    }
+}
+
+// A simulated channel as flags: independent losses, or bursts of them with the same mean
+#[derive(Args, Debug, Clone)]
+pub struct ChannelArgs {
+    /// Long-run fraction of packets lost
+    #[arg(long, default_value_t = 0.1)]
+    pub loss: f64,
+
+    /// Mean length of a loss burst: 1 drops packets independently, longer runs a Gilbert-Elliott channel with the same mean loss
+    #[arg(long, default_value_t = 1.0)]
+    pub burst_len: f64,
+
+    /// Replay a recorded loss pattern instead: 0/1 flags per packet, or timestamped delivered/lost records (see the channel docs)
+    #[arg(long, conflicts_with_all = ["loss", "burst_len"])]
+    pub trace: Option<PathBuf>,
+}
+
+impl ChannelArgs {
+    pub fn model(&self) -> Result<LossModel, Failure> {
+        if let Some(path) = &self.trace {
+            return Ok(LossModel::Trace(Rc::new(LossTrace::load(path)?)));
+        }
+        let loss = self.loss.clamp(0.0, 0.999);
+        if self.burst_len <= 1.0 {
+            return Ok(LossModel::Uniform { rate: loss });
+        }
+        // Every packet in the bad state is lost, so bursts last 1/to_good on average
+        let to_good = 1.0 / self.burst_len;
+        Ok(LossModel::GilbertElliott { to_bad: (loss * to_good / (1.0 - loss)).min(1.0), to_good, bad_loss: 1.0 })
+    }
+}
+
+impl LossModel {
+    pub fn describe(&self) -> String {
+        match self {
+            LossModel::Uniform { rate } => format!("{:.1}% independent loss", rate * 100.0),
+            LossModel::GilbertElliott { to_good, .. } => {
+                format!("{:.1}% loss in bursts of {:.1} on average", self.mean_loss() * 100.0, 1.0 / to_good)
+            }
+            LossModel::Trace(trace) => trace.describe(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::{LossModel, LossTrace};
+    use crate::stattest::{assert_fits, assert_proportion, geometric, histogram, run_lengths};
+
+    const PACKETS: usize = 200_000;
+    const MAX_RUN: usize = 30;
+
+    // Which of PACKETS packets in a row the model drops
+    fn lost(model: &LossModel, seed: u64) -> Vec<bool> {
+        let arrived = model.transmit((0..PACKETS).collect(), &mut StdRng::seed_from_u64(seed));
+        let mut lost = vec![true; PACKETS];
+        for i in arrived {
+            lost[i] = false;
+        }
+        lost
+    }
+
+    #[test]
+    fn uniform_loss_matches_its_rate() {
+        let model = LossModel::Uniform { rate: 0.3 };
+        let lost = lost(&model, 1);
+        assert_proportion("uniform loss", lost.iter().filter(|&&l| l).count(), PACKETS, model.mean_loss());
+        // Independent losses: runs of arrivals between them are geometric in the delivery rate
+        let gaps = run_lengths(&lost, false);
+        assert_fits("uniform gaps", &histogram(&gaps, 1, MAX_RUN), &geometric(0.3, MAX_RUN));
+    }
+
+    #[test]
+    fn gilbert_elliott_matches_its_mean_loss() {
+        let model = LossModel::GilbertElliott { to_bad: 0.05, to_good: 0.3, bad_loss: 0.6 };
+        let lost = lost(&model, 2);
+        // Bursts make the count far more variable than a binomial's, so compare means over
+        // independent stretches instead, each long enough to forget its start
+        let stretches: Vec<f64> = lost.chunks(2000).map(|c| c.iter().filter(|&&l| l).count() as f64 / c.len() as f64).collect();
+        let mean = stretches.iter().sum::<f64>() / stretches.len() as f64;
+        let sd = (stretches.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (stretches.len() - 1) as f64).sqrt();
+        let z = (mean - model.mean_loss()) / (sd / (stretches.len() as f64).sqrt());
+        assert!(z.abs() < 3.3, "mean loss {:.4} is {:.1} standard errors from {:.4}", mean, z, model.mean_loss());
+    }
+
+    #[test]
+    fn gilbert_elliott_states_last_geometrically() {
+        // A bad state that loses everything makes each state's sojourn directly visible
+        let (to_bad, to_good) = (0.08, 0.25);
+        let lost = lost(&LossModel::GilbertElliott { to_bad, to_good, bad_loss: 1.0 }, 3);
+        assert_fits("bad-state bursts", &histogram(&run_lengths(&lost, true), 1, MAX_RUN), &geometric(to_good, MAX_RUN));
+        assert_fits("good-state gaps", &histogram(&run_lengths(&lost, false), 1, MAX_RUN * 4), &geometric(to_bad, MAX_RUN * 4));
+    }
+
+    #[test]
+    fn wrong_parameters_are_caught() {
+        let lost = lost(&LossModel::GilbertElliott { to_bad: 0.08, to_good: 0.25, bad_loss: 1.0 }, 4);
+        let bursts = histogram(&run_lengths(&lost, true), 1, MAX_RUN);
+        let result = std::panic::catch_unwind(|| assert_fits("bursts", &bursts, &geometric(0.3, MAX_RUN)));
+        assert!(result.is_err(), "a 20% error in to_good went unnoticed");
+    }
+
+    #[test]
+    fn traces_parse_both_forms() {
+        let flags = LossTrace::parse("# capture\n1101\n\n10\n", "flags").unwrap();
+        assert_eq!(flags.lost, [false, false, true, false, false, true]);
+        assert_eq!(flags.bursts(), (2, 1));
+        let timed = LossTrace::parse("0.3,lost\n0.1 delivered\n0.2\tDROPPED\n", "timed").unwrap();
+        assert_eq!(timed.lost, [false, true, true]);
+        assert_eq!(timed.bursts(), (1, 2));
+        assert!(LossTrace::parse("1101\n0.1 lost\n", "mixed").is_err());
+        assert!(LossTrace::parse("0.1 maybe\n", "status").is_err());
+        assert!(LossTrace::parse("# nothing\n", "empty").is_err());
+    }
+
+    #[test]
+    fn traces_play_back_in_order() {
+        let model = LossModel::Trace(Rc::new(LossTrace::parse("1110010", "trace").unwrap()));
+        // Wherever playback starts, the pattern repeats with the trace's period
+        let lost = lost(&model, 5);
+        assert!(lost.iter().zip(&lost[7..]).all(|(a, b)| a == b));
+        assert_eq!(lost[..7].iter().filter(|&&l| l).count(), 3);
+        assert!((model.mean_loss() - 3.0 / 7.0).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,101 @@
+// Sweep results as one Arrow record batch with a row per decode run, written
+// as an Arrow IPC file or Parquet by the output's extension. The writers come
+// from the `arrow` feature, so a default build keeps its short dependency list
+// and says how to get them when asked for either format.
+
+use std::path::Path;
+
+use crate::failure::Failure;
+use crate::sweep::SweepCell;
+
+// Where a sweep's rows came from; every row carries the hash, and the schema the whole description
+#[cfg_attr(not(feature = "arrow"), allow(dead_code))]
+pub struct RunMetadata {
+    pub config: String,
+    pub config_hash: String,
+}
+
+pub const AVAILABLE: bool = cfg!(feature = "arrow");
+
+pub fn wants(path: &Path) -> bool {
+    matches!(path.extension().and_then(|e| e.to_str()), Some("arrow" | "parquet"))
+}
+
+#[cfg(feature = "arrow")]
+pub fn write_sweep(cells: &[SweepCell], meta: &RunMetadata, path: &Path) -> Result<usize, Failure> {
+    use std::collections::HashMap;
+    use std::fs::File;
+    use std::sync::Arc;
+
+    use arrow_array::{ArrayRef, BooleanArray, Float64Array, RecordBatch, StringArray, UInt32Array, UInt64Array};
+    use arrow_schema::{Field, Schema};
+
+    let runs: Vec<(&SweepCell, u64, Option<usize>)> =
+        cells.iter().flat_map(|c| c.outcomes.iter().enumerate().map(move |(seed, extra)| (c, seed as u64, *extra))).collect();
+    let runs = || runs.iter().copied();
+    let columns: Vec<(&str, ArrayRef, bool)> = vec![
+        ("loss_rate", Arc::new(Float64Array::from_iter_values(runs().map(|(c, _, _)| c.loss))), false),
+        ("repair_packets", Arc::new(UInt32Array::from_iter_values(runs().map(|(c, _, _)| c.repair))), false),
+        ("source_packets", Arc::new(UInt32Array::from_iter_values(runs().map(|(c, _, _)| c.source_packets as u32))), false),
+        ("seed", Arc::new(UInt64Array::from_iter_values(runs().map(|(_, seed, _)| seed))), false),
+        ("decoded", Arc::new(BooleanArray::from(runs().map(|(_, _, extra)| extra.is_some()).collect::<Vec<_>>())), false),
+        ("extra_packets", Arc::new(UInt32Array::from_iter(runs().map(|(_, _, extra)| extra.map(|e| e as u32)))), true),
+        ("config_hash", Arc::new(StringArray::from_iter_values(runs().map(|_| meta.config_hash.as_str()))), false),
+    ];
+    let fields: Vec<Field> = columns.iter().map(|(name, array, nullable)| Field::new(*name, array.data_type().clone(), *nullable)).collect();
+    let schema = Arc::new(Schema::new_with_metadata(
+        fields,
+        HashMap::from([
+            ("config".to_string(), meta.config.clone()),
+            ("config_hash".to_string(), meta.config_hash.clone()),
+            ("toy_fec_version".to_string(), env!("CARGO_PKG_VERSION").to_string()),
+        ]),
+    ));
+    let batch = RecordBatch::try_new(schema.clone(), columns.into_iter().map(|(_, array, _)| array).collect())
+        .map_err(|e| Failure::Io(format!("cannot build the record batch: {}", e)))?;
+
+    let failed = |e: &dyn std::fmt::Display| Failure::Io(format!("cannot write {}: {}", path.display(), e));
+    let file = File::create(path).map_err(|e| failed(&e))?;
+    if path.extension().is_some_and(|e| e == "parquet") {
+        let mut writer = parquet::arrow::ArrowWriter::try_new(file, schema, None).map_err(|e| failed(&e))?;
+        writer.write(&batch).map_err(|e| failed(&e))?;
+        writer.close().map_err(|e| failed(&e))?;
+    } else {
+        let mut writer = arrow_ipc::writer::FileWriter::try_new(file, &schema).map_err(|e| failed(&e))?;
+        writer.write(&batch).map_err(|e| failed(&e))?;
+        writer.finish().map_err(|e| failed(&e))?;
+    }
+    Ok(batch.num_rows())
+}
+
+#[cfg(not(feature = "arrow"))]
+pub fn write_sweep(_cells: &[SweepCell], _meta: &RunMetadata, path: &Path) -> Result<usize, Failure> {
+    Err(unavailable(path))
+}
+
+pub fn unavailable(path: &Path) -> Failure {
+    Failure::Config(format!("writing {} needs a build with `--features arrow`", path.display()))
+}
+
+#[cfg(all(test, feature = "arrow"))]
+mod tests {
+    use super::*;
+    use crate::fec::OverheadStats;
+
+    #[test]
+    fn parquet_keeps_a_row_per_run() {
+        let cell = |loss: f64, outcomes: Vec<Option<usize>>| SweepCell { loss, repair: 5, source_packets: 38, stats: OverheadStats::default(), outcomes };
+        let cells = [cell(0.1, vec![Some(0), Some(2), None]), cell(0.3, vec![None, Some(1)])];
+        let meta = RunMetadata { config: "test".into(), config_hash: "abcd".into() };
+        let path = std::env::temp_dir().join(format!("toy-fec-columnar-{}.parquet", std::process::id()));
+        assert_eq!(write_sweep(&cells, &meta, &path).unwrap(), 5);
+
+        let file = std::fs::File::open(&path).unwrap();
+        let reader = parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder::try_new(file).unwrap();
+        assert_eq!(reader.schema().metadata()["config_hash"], "abcd");
+        let batch = reader.build().unwrap().next().unwrap().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(batch.num_rows(), 5);
+        assert_eq!(batch.column_by_name("extra_packets").unwrap().null_count(), 2);
+    }
+}
@@ -0,0 +1,159 @@
+use clap::ValueEnum;
+use sha2::{Digest, Sha256};
+
+use crate::merkle::{self, Hash, MerkleProof, MerkleTree};
+
+// Binds an ordered list of items (block hashes, headers, ...) to a short value
+// that travels in the header packet. Openings prove a single item against it.
+pub trait Commitment {
+    fn name(&self) -> &'static str;
+
+    // Tag written into the header packet so receivers pick the matching verifier
+    fn id(&self) -> u8;
+
+    fn commit(&self, items: &[&[u8]]) -> Vec<u8>;
+
+    fn open(&self, items: &[&[u8]], index: usize) -> Vec<u8>;
+
+    fn verify_opening(&self, commitment: &[u8], index: usize, item: &[u8], proof: &[u8]) -> bool;
+
+    // Check a complete recovered item list against the commitment
+    fn verify_all(&self, commitment: &[u8], items: &[&[u8]]) -> bool {
+        self.commit(items) == commitment
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum CommitmentKind {
+    #[default]
+    Merkle,
+    HashList,
+}
+
+impl CommitmentKind {
+    pub fn scheme(self) -> Box<dyn Commitment> {
+        match self {
+            CommitmentKind::Merkle => Box::new(MerkleCommitment),
+            CommitmentKind::HashList => Box::new(HashListCommitment),
+        }
+    }
+}
+
+pub fn by_id(id: u8) -> Option<Box<dyn Commitment>> {
+    CommitmentKind::value_variants()
+        .iter()
+        .map(|kind| kind.scheme())
+        .find(|scheme| scheme.id() == id)
+}
+
+// Split a flat buffer of 32-byte block hashes into commitment items
+pub fn hash_items(block_hashes: &[u8]) -> Vec<&[u8]> {
+    block_hashes.chunks(32).collect()
+}
+
+// ====================== Merkle (default) ======================
+
+// Logarithmic openings; proof bytes are (side, sibling) pairs from leaf to root
+pub struct MerkleCommitment;
+
+// Leaves commit to their position too, so an opening for one index cannot pass for another
+fn leaf(index: usize, item: &[u8]) -> Hash {
+    merkle::leaf_hash(&[&(index as u64).to_be_bytes()[..], item].concat())
+}
+
+fn tree(items: &[&[u8]]) -> MerkleTree {
+    MerkleTree::new(items.iter().enumerate().map(|(i, item)| leaf(i, item)).collect())
+}
+
+impl Commitment for MerkleCommitment {
+    fn name(&self) -> &'static str {
+        "merkle"
+    }
+
+    fn id(&self) -> u8 {
+        0
+    }
+
+    fn commit(&self, items: &[&[u8]]) -> Vec<u8> {
+        tree(items).root().to_vec()
+    }
+
+    fn open(&self, items: &[&[u8]], index: usize) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for (sibling, is_left) in tree(items).proof(index).path {
+            bytes.push(is_left as u8);
+            bytes.extend_from_slice(&sibling);
+        }
+        bytes
+    }
+
+    fn verify_opening(&self, commitment: &[u8], index: usize, item: &[u8], proof: &[u8]) -> bool {
+        if !proof.len().is_multiple_of(33) {
+            return false;
+        }
+        let path = proof
+            .chunks(33)
+            .map(|step| (step[1..].try_into().unwrap(), step[0] == 1))
+            .collect();
+        MerkleProof { path }.root_from(leaf(index, item)).as_slice() == commitment
+    }
+}
+
+// ====================== Hash list ======================
+
+// Commitment is the hash of every item hash; an opening is the whole list.
+// Linear-size proofs, but trivially simple to audit.
+pub struct HashListCommitment;
+
+fn item_hashes(items: &[&[u8]]) -> Vec<u8> {
+    items.iter().flat_map(Sha256::digest).collect()
+}
+
+impl Commitment for HashListCommitment {
+    fn name(&self) -> &'static str {
+        "hash-list"
+    }
+
+    fn id(&self) -> u8 {
+        1
+    }
+
+    fn commit(&self, items: &[&[u8]]) -> Vec<u8> {
+        Sha256::digest(item_hashes(items)).to_vec()
+    }
+
+    fn open(&self, items: &[&[u8]], _index: usize) -> Vec<u8> {
+        item_hashes(items)
+    }
+
+    fn verify_opening(&self, commitment: &[u8], index: usize, item: &[u8], proof: &[u8]) -> bool {
+        let Some(expected) = proof.chunks(32).nth(index) else {
+            return false;
+        };
+        let item_hash: Hash = Sha256::digest(item).into();
+        Sha256::digest(proof).as_slice() == commitment && expected == item_hash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn openings_verify_only_at_their_own_index() {
+        let hashes: Vec<u8> = (0..7u8).flat_map(|i| [i; 32]).collect();
+        let items = hash_items(&hashes);
+        for kind in [CommitmentKind::Merkle, CommitmentKind::HashList] {
+            let scheme = kind.scheme();
+            let commitment = scheme.commit(&items);
+            for j in 0..items.len() {
+                let proof = scheme.open(&items, j);
+                assert!(scheme.verify_opening(&commitment, j, items[j], &proof), "{:?}: {}", kind, j);
+                // Block j's opening, offered as proof of block j under another position
+                for i in (0..items.len()).filter(|&i| i != j) {
+                    assert!(!scheme.verify_opening(&commitment, i, items[j], &proof), "{:?}: {} as {}", kind, j, i);
+                }
+            }
+        }
+    }
+}
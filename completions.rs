@@ -0,0 +1,58 @@
+use std::io::{self, Write};
+
+use clap::{Args, Command};
+use clap_complete::Shell;
+
+#[derive(Args, Debug)]
+pub struct CompletionsArgs {
+    /// Shell to generate the completion script for
+    #[arg(value_enum)]
+    pub shell: Shell,
+}
+
+// Completion scripts are generated from the same clap definitions that parse the command line
+pub fn script(shell: Shell, mut root: Command) -> Vec<u8> {
+    let name = root.get_name().to_string();
+    let mut out = Vec::new();
+    clap_complete::generate(shell, &mut root, name, &mut out);
+    out
+}
+
+pub fn run(args: &CompletionsArgs, root: Command) -> io::Result<()> {
+    io::stdout().lock().write_all(&script(args.shell, root))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::{Command as Process, Stdio};
+
+    use clap::CommandFactory;
+
+    use super::*;
+    use crate::Cli;
+
+    fn text(shell: Shell) -> String {
+        String::from_utf8(script(shell, Cli::command())).unwrap()
+    }
+
+    #[test]
+    fn bash_script_parses_and_names_every_subcommand() {
+        let bash = text(Shell::Bash);
+        let mut check = Process::new("bash").arg("-n").stdin(Stdio::piped()).spawn().expect("bash is installed");
+        check.stdin.take().unwrap().write_all(bash.as_bytes()).unwrap();
+        assert!(check.wait().unwrap().success(), "bash -n rejected the script");
+
+        // Each subcommand needs its own arm in the script's dispatch on the words typed so far
+        let root = Cli::command();
+        for sub in root.get_subcommands() {
+            assert!(bash.contains(&format!("toy__fec,{})", sub.get_name())), "{} missing", sub.get_name());
+        }
+    }
+
+    #[test]
+    fn every_shell_gets_a_script() {
+        for shell in [Shell::Bash, Shell::Zsh, Shell::Fish] {
+            assert!(text(shell).contains("multicast-send"), "{:?}", shell);
+        }
+    }
+}
@@ -0,0 +1,98 @@
+// Interval estimates and tests for experiment results, all at 95%. The
+// sweep decodes every cell under the same seeded loss patterns, so two cells
+// can be compared pattern by pattern rather than as independent samples.
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+const Z: f64 = 1.96;                    // Two-sided 95% quantile of the standard normal
+const RESAMPLES: usize = 2000;
+
+// Wilson score interval for a success probability. Unlike p ± z·√(p(1-p)/n)
+// it stays inside [0, 1] and does not collapse to a point at 0 or n successes.
+pub fn wilson(successes: usize, runs: usize) -> (f64, f64) {
+    if runs == 0 {
+        return (0.0, 1.0);
+    }
+    let n = runs as f64;
+    let p = successes as f64 / n;
+    let z2 = Z * Z;
+    let centre = (p + z2 / (2.0 * n)) / (1.0 + z2 / n);
+    let spread = Z * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt() / (1.0 + z2 / n);
+    ((centre - spread).max(0.0), (centre + spread).min(1.0))
+}
+
+// Percentile bootstrap interval for a mean, resampled with a fixed seed so
+// reruns print the same interval
+pub fn bootstrap_mean(samples: &[f64]) -> (f64, f64) {
+    if samples.is_empty() {
+        return (0.0, 0.0);
+    }
+    let mut rng = StdRng::seed_from_u64(0);
+    let mut means: Vec<f64> = (0..RESAMPLES)
+        .map(|_| (0..samples.len()).map(|_| samples[rng.gen_range(0..samples.len())]).sum::<f64>() / samples.len() as f64)
+        .collect();
+    means.sort_by(f64::total_cmp);
+    let at = |q: f64| means[((RESAMPLES - 1) as f64 * q).round() as usize];
+    (at(0.025), at(0.975))
+}
+
+// McNemar's exact test on paired outcomes: only the patterns where exactly
+// one side succeeded say anything about which is better, and under the null
+// hypothesis each of those goes either way with chance one half
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PairedTest {
+    pub only_first: usize,
+    pub only_second: usize,
+    pub p_value: f64,                   // Two-sided
+}
+
+pub fn mcnemar(first: &[bool], second: &[bool]) -> PairedTest {
+    let only_first = first.iter().zip(second).filter(|&(&a, &b)| a && !b).count();
+    let only_second = first.iter().zip(second).filter(|&(&a, &b)| !a && b).count();
+    let n = only_first + only_second;
+    // P(X <= fewer) for X ~ Binomial(n, 1/2), summed in logs so large n does not underflow
+    let ln_half = 0.5f64.ln() * n as f64;
+    let mut ln_choose = 0.0;
+    let mut tail = 0.0;
+    for i in 0..=only_first.min(only_second) {
+        if i > 0 {
+            ln_choose += ((n - i + 1) as f64 / i as f64).ln();
+        }
+        tail += (ln_choose + ln_half).exp();
+    }
+    PairedTest { only_first, only_second, p_value: (2.0 * tail).min(1.0) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wilson_matches_published_values() {
+        let close = |(lo, hi): (f64, f64), (a, b): (f64, f64)| (lo - a).abs() < 5e-4 && (hi - b).abs() < 5e-4;
+        assert!(close(wilson(95, 100), (0.8883, 0.9785)), "{:?}", wilson(95, 100));
+        assert!(close(wilson(0, 10), (0.0, 0.2775)), "{:?}", wilson(0, 10));
+        assert!(close(wilson(10, 10), (0.7225, 1.0)), "{:?}", wilson(10, 10));
+    }
+
+    #[test]
+    fn mcnemar_counts_only_discordant_pairs() {
+        let first = [true; 20];
+        let mut second = [true; 20];
+        second[..6].fill(false);
+        // Six discordant pairs all one way: 2 · 0.5^6
+        let test = mcnemar(&first, &second);
+        assert_eq!((test.only_first, test.only_second), (6, 0));
+        assert!((test.p_value - 0.03125).abs() < 1e-12, "{}", test.p_value);
+        assert_eq!(mcnemar(&first, &first).p_value, 1.0);
+    }
+
+    #[test]
+    fn bootstrap_brackets_the_mean() {
+        let samples: Vec<f64> = (0..200).map(|i| (i % 7) as f64).collect();
+        let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+        let (lo, hi) = bootstrap_mean(&samples);
+        assert!(lo < mean && mean < hi && hi - lo < 1.0, "{} not in ({}, {})", mean, lo, hi);
+    }
+}
@@ -0,0 +1,273 @@
+use std::fs;
+use std::path::PathBuf;
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use raptorq::{Encoder, EncodingPacket, ObjectTransmissionInformation};
+
+use crate::auth::PacketAuth;
+use crate::bodies::{self, BodyArgs};
+use crate::commitment::CommitmentKind;
+use crate::erasure::{CodeKind, CodedPacket};
+use crate::feedback::{Ack, Nack};
+use crate::frame::{self, FrameHeader};
+use crate::handshake::{Hello, RejectReason, Reply};
+use crate::headers::{self, BlockHeader};
+use crate::lightclient::{FullNode, SyncObject};
+use crate::manifest::{self, TransmissionManifest};
+use crate::rng::{SimRng, SimStream};
+use crate::simulate::{self, Fanout, MiningArgs, OutputArgs, SimArgs};
+use crate::store::EncodedBlockStore;
+use crate::transfer::{self, PacketsArgs};
+use crate::{grow_dag, Block, ConsensusParams, ToyDag};
+
+// Every format the crate writes and reads back, each round-tripped and
+// compared field by field against what went in. Graph exports, CSV and the
+// explanation JSON are output only and have no reader to round-trip through.
+
+// DAGs covering what a format could lose: an empty DAG, wide merges with red
+// blocks, and non-default parameters, genesis, bootstrap blocks, truncated
+// parent lists and timestamps off the honest clock
+fn dags() -> Vec<(&'static str, ToyDag)> {
+    let simulated = SimArgs {
+        blocks: 60,
+        mining: MiningArgs { miners: 4, fanout: Fanout::Uniform, ..MiningArgs::default() },
+        consensus: ConsensusParams { k: 2, ..ConsensusParams::default() },
+        output: OutputArgs { quiet: true, verbose: 0 },
+        ..SimArgs::default()
+    };
+
+    let mut unusual = ToyDag::with_params(ConsensusParams {
+        k: 1,
+        max_parents: 3,
+        mtp_window: 5,
+        genesis: Some([0x5a; 32]),
+        bootstrap: 4,
+    });
+    for i in 0..30u64 {
+        let mut tips: Vec<u64> = unusual.tips.iter().copied().collect();
+        tips.sort();
+        if i % 3 == 0 {
+            tips.truncate(1);
+        }
+        unusual.create_block_at(tips, 100 + 7 * i).unwrap();
+    }
+
+    vec![
+        ("genesis only", ToyDag::new()),
+        ("grown", grow_dag(80, &mut StdRng::seed_from_u64(1))),
+        ("simulated", simulate::grow(&simulated, &SimStream::seeded(2))),
+        ("unusual", unusual),
+    ]
+}
+
+// Everything a block caches, so a format that loses an input shows up in what replay derives from it
+fn fields(b: &Block) -> impl std::fmt::Debug + PartialEq + '_ {
+    (&b.parents, &b.color, b.hash, b.selected_parent, b.blue_score, &b.mergeset, b.depth, b.daa_score, b.blue_work, b.timestamp)
+}
+
+// Bootstrap blocks travel as ordinary blocks, so only the parameter counting them may differ
+fn assert_same_dag(what: &str, a: &ToyDag, b: &ToyDag) {
+    let params = |d: &ToyDag| (d.params.k, d.params.max_parents, d.params.mtp_window, d.params.genesis.unwrap_or([0; 32]));
+    assert_eq!(params(a), params(b), "{}: consensus parameters", what);
+    assert_eq!(a.next_id, b.next_id, "{}: block count", what);
+    for id in 0..a.next_id {
+        assert_eq!(fields(&a.blocks[&id]), fields(&b.blocks[&id]), "{}: block {}", what, id);
+    }
+    assert_eq!(a.tips, b.tips, "{}: tips", what);
+    assert_eq!(a.children, b.children, "{}: children", what);
+    assert_eq!(a.selected_parent, b.selected_parent, "{}: selected parent", what);
+    assert_eq!(a.reorgs, b.reorgs, "{}: reorg history", what);
+}
+
+#[test]
+fn snapshots_round_trip() {
+    for (name, dag) in dags() {
+        let mut text = Vec::new();
+        crate::snapshot::write(&dag, &mut text).unwrap();
+        let reloaded = crate::snapshot::parse(&String::from_utf8(text).unwrap(), name).unwrap();
+        assert_same_dag(name, &dag, &reloaded);
+    }
+}
+
+#[test]
+fn header_objects_round_trip() {
+    let body = BodyArgs::default();
+    for (name, dag) in dags() {
+        let set = headers::parse(&headers::serialize(&dag, &body).0).unwrap();
+        let sent: Vec<BlockHeader> = (0..dag.next_id).map(|id| BlockHeader::new(&dag.blocks[&id], &body)).collect();
+        assert_eq!(set.headers, sent, "{}", name);
+        assert_same_dag(name, &dag, &headers::rebuild(&set).unwrap());
+    }
+}
+
+#[test]
+fn header_counts_the_object_cannot_hold_are_refused() {
+    let (mut bytes, _) = headers::serialize(&grow_dag(12, &mut StdRng::seed_from_u64(4)), &BodyArgs::default());
+    // The header count is the preamble's last field
+    let at = headers::PREAMBLE_LEN - 4;
+    bytes[at..at + 4].copy_from_slice(&u32::MAX.to_be_bytes());
+    assert!(headers::parse(&bytes).unwrap_err().contains("headers claimed"));
+}
+
+// A receiver that kept up until halfway asks for everything outside its checkpoint's past
+#[test]
+fn header_deltas_complete_an_earlier_copy() {
+    let body = BodyArgs::default();
+    for (name, dag) in dags() {
+        let held: Vec<u64> = (0..dag.next_id.div_ceil(2)).collect();
+        let (prefix, _) = headers::serialize_blocks(&dag, 0, &held, &body);
+        let mut copy = headers::rebuild(&headers::parse(&prefix).unwrap()).unwrap();
+        let delta = headers::parse(&headers::serialize_delta(&dag, copy.selected_parent, &body)).unwrap();
+        assert_eq!(headers::apply(&mut copy, &delta).unwrap() as u64, dag.next_id - held.len() as u64, "{}", name);
+        assert_same_dag(name, &dag, &copy);
+    }
+}
+
+#[test]
+fn body_objects_round_trip() {
+    let body = BodyArgs { txs: 5, tx_size: 33, ..BodyArgs::default() };
+    for (name, dag) in dags() {
+        let ids: Vec<u64> = (0..dag.next_id).collect();
+        let parsed = bodies::parse(&bodies::serialize(&dag, &ids, &body)).unwrap();
+        let sent: Vec<(u64, bodies::Body)> = ids.iter().map(|&id| (id, bodies::transactions(&dag.blocks[&id], &body))).collect();
+        assert_eq!(parsed, sent, "{}", name);
+    }
+}
+
+#[test]
+fn sync_objects_round_trip() {
+    for (name, dag) in dags() {
+        for kind in [CommitmentKind::Merkle, CommitmentKind::HashList] {
+            let sent = FullNode::new(&dag, kind.scheme()).sync_object();
+            let received = SyncObject::from_bytes(&sent.to_bytes()).unwrap();
+            let summary = |o: &SyncObject| (o.commitment_scheme, o.data_commitment.clone(), o.block_count, o.headers.clone());
+            assert_eq!(summary(&received), summary(&sent), "{} under {:?}", name, kind);
+        }
+    }
+}
+
+// The grown DAG's header object, split over two source blocks, with its packets
+fn object() -> (Vec<u8>, ObjectTransmissionInformation, Vec<EncodingPacket>) {
+    let dag = grow_dag(80, &mut StdRng::seed_from_u64(1));
+    let data = headers::serialize(&dag, &BodyArgs::default()).0;
+    let config = ObjectTransmissionInformation::new(data.len() as u64, 64, 2, 1, 8);
+    let packets = Encoder::new(&data, config).get_encoded_packets(6);
+    (data, config, packets)
+}
+
+// A fresh directory under the system temp dir, removed when the test is done with it
+fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("toy-fec-conformance-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn frames_and_records_round_trip() {
+    let (_, config, packets) = object();
+    let header = FrameHeader { session: 0xfec0_0001, object: 42, config };
+    let frames: Vec<Vec<u8>> = packets.iter().map(|p| frame::encode_frame(&header, p)).collect();
+    for (sent, bytes) in packets.iter().zip(&frames) {
+        assert_eq!(frame::decode_frame(bytes), Some((header, sent.clone())));
+    }
+
+    let mut stream = Vec::new();
+    for f in &frames {
+        frame::write_record(&mut stream, f).unwrap();
+    }
+    assert_eq!(frame::read_records(&mut stream.as_slice()).unwrap(), frames);
+}
+
+#[test]
+fn feedback_messages_round_trip() {
+    for done in [false, true] {
+        let ack = Ack { session: 0xfec0_0001, object: 42, block: 3, esi: 70_000, received: 19, done };
+        assert_eq!(Ack::from_bytes(&ack.to_bytes()), Some(ack));
+        assert_eq!(Nack::from_bytes(&ack.to_bytes()), None);
+    }
+    let nack = Nack { session: 0xfec0_0001, object: 42, block: 1, needed: 6, received: 30 };
+    assert_eq!(Nack::from_bytes(&nack.to_bytes()), Some(nack));
+    assert_eq!(Ack::from_bytes(&nack.to_bytes()), None);
+}
+
+#[test]
+fn handshakes_round_trip() {
+    let hello = Hello { session: 0xfec0_0001, versions: 1..=3, codes: vec![CodeKind::Raptorq, CodeKind::Ldpc], symbol_size: 1140 };
+    assert_eq!(Hello::from_bytes(&hello.to_bytes()), Some(hello));
+    for reply in [
+        Reply::Accept { session: 9, version: 2, code: CodeKind::Ldpc, symbol_size: 512 },
+        Reply::Reject { session: 9, reason: RejectReason::Version },
+        Reply::Reject { session: 9, reason: RejectReason::SymbolSize },
+    ] {
+        assert_eq!(Reply::from_bytes(&reply.to_bytes()), Some(reply));
+    }
+}
+
+#[test]
+fn packet_files_round_trip() {
+    let (_, config, packets) = object();
+    let auth = PacketAuth::new(&[7; 32], &config);
+    for authenticated in [false, true] {
+        let dir = scratch(&format!("dir-{}", authenticated));
+        let stream = scratch(&format!("stream-{}", authenticated));
+        for target in [PacketsArgs { dir: Some(dir.clone()), stream: None }, PacketsArgs { dir: None, stream: Some(stream.clone()) }] {
+            let auth = authenticated.then_some(&auth);
+            transfer::write_packets(&target, config, &packets, auth).unwrap();
+            let (read_config, frames) = transfer::read_packets(&target).unwrap();
+            assert_eq!(read_config, config);
+            let mut received: Vec<EncodingPacket> = frames
+                .iter()
+                .map(|f| match auth {
+                    Some(auth) => auth.open(f).expect("tag verifies"),
+                    None => EncodingPacket::deserialize(f),
+                })
+                .collect();
+            let mut sent = packets.clone();
+            let key = |p: &EncodingPacket| (p.payload_id().source_block_number(), p.payload_id().encoding_symbol_id());
+            received.sort_by_key(key);
+            sent.sort_by_key(key);
+            assert_eq!(received, sent, "{:?}", target);
+        }
+        let _ = fs::remove_dir_all(&dir);
+        let _ = fs::remove_file(&stream);
+    }
+}
+
+#[test]
+fn manifests_round_trip() {
+    let (data, config, packets) = object();
+    for kind in [CommitmentKind::Merkle, CommitmentKind::HashList] {
+        let hashes = &data[..data.len() / 32 * 32];
+        let (sent, proven) = manifest::seal(config, kind.scheme().as_ref(), hashes, packets.clone());
+        let bytes = sent.to_header_packet(&[7; 32]);
+        let received = TransmissionManifest::from_header_packet(&bytes, &[7; 32]).unwrap();
+        let summary = |m: &TransmissionManifest| (m.config, m.packet_count, m.packet_root, m.commitment_scheme, m.data_commitment.clone());
+        assert_eq!(summary(&received), summary(&sent), "{:?}", kind);
+        assert_eq!(received.to_header_packet(&[7; 32]), bytes);
+        assert!(received.verify_recovered(hashes), "{:?}: commitment no longer checks out", kind);
+        assert!(proven.iter().all(|p| received.verify(p)), "{:?}: proofs no longer check out", kind);
+    }
+}
+
+#[test]
+fn coded_packets_and_store_files_round_trip() {
+    let (data, _, _) = object();
+    for kind in [CodeKind::Raptorq, CodeKind::Ldpc] {
+        for packet in kind.code(64).encode(&data, 8) {
+            assert_eq!(CodedPacket::from_bytes(&packet.to_bytes()), Some(packet), "{:?}", kind);
+        }
+
+        let dir = scratch(&format!("store-{:?}", kind));
+        let mut written = EncodedBlockStore::new(kind.code(64), 8).persist_to(&dir).unwrap();
+        let mut read = EncodedBlockStore::new(kind.code(64), 8).persist_to(&dir).unwrap();
+        for (block, chunk) in data.chunks(700).enumerate() {
+            let root = written.put(block as u64, chunk).unwrap();
+            assert_eq!(read.load(block as u64).unwrap(), root, "{:?} block {}", kind, block);
+            assert_eq!(read.symbol_count(block as u64), written.symbol_count(block as u64));
+            assert_eq!(read.data_len(block as u64), chunk.len());
+            assert_eq!(read.reconstruct(block as u64).as_deref(), Some(chunk));
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
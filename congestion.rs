@@ -0,0 +1,284 @@
+use std::collections::VecDeque;
+
+use clap::{Args, ValueEnum};
+use raptorq::{Encoder, EncodingPacket};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::bodies::BodyArgs;
+use crate::failure::Failure;
+use crate::fec::{DecodeSession, SymbolArgs};
+use crate::{frame, grow_dag, headers, PAYLOAD_ID_LEN};
+
+#[derive(Args, Debug, Clone)]
+pub struct CongestionArgs {
+    /// Blocks in the DAG whose headers are sent
+    #[arg(long, default_value_t = 2000)]
+    pub blocks: u64,
+
+    /// How the sender sizes its window
+    #[arg(long, value_enum, default_value_t = Controller::Aimd)]
+    pub controller: Controller,
+
+    /// Packets in flight: the fixed window, or AIMD's first window before slow start grows it
+    #[arg(long, default_value_t = 4)]
+    pub window: usize,
+
+    /// Bottleneck capacity in packets per second, shared with the background traffic
+    #[arg(long, default_value_t = 10_000)]
+    pub capacity: u64,
+
+    /// Packets the bottleneck queue holds; arrivals beyond it are dropped
+    #[arg(long, default_value_t = 40)]
+    pub queue: usize,
+
+    /// Background traffic as a fraction of the bottleneck capacity
+    #[arg(long, default_value_t = 0.7)]
+    pub background: f64,
+
+    /// Mean length of a background burst in packets; 1 spreads the background evenly
+    #[arg(long, default_value_t = 8.0)]
+    pub background_burst: f64,
+
+    /// Round-trip propagation delay, not counting time spent queued
+    #[arg(long, default_value_t = 40.0)]
+    pub rtt_ms: f64,
+
+    /// Repair sent up front with the source packets, as fractions of them; one row each
+    #[arg(long, value_delimiter = ',', default_values_t = vec![0.0, 0.1, 0.25, 0.5, 1.0])]
+    pub redundancy: Vec<f64>,
+
+    /// Runs per row with different background traffic (seeds 0..runs)
+    #[arg(long, default_value_t = 10)]
+    pub runs: u64,
+
+    #[command(flatten)]
+    pub symbols: SymbolArgs,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Controller {
+    /// Slow start, then a packet more per window acknowledged; halve the window at most once per RTT on loss
+    Aimd,
+    /// Keep --window packets in flight whatever happens
+    Fixed,
+}
+
+// What the sender learns, one propagation RTT after the bottleneck served or dropped a packet
+enum Feedback {
+    Delivered,
+    Lost,
+}
+
+// One transfer through the bottleneck
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Transfer {
+    pub completed: bool,
+    pub secs: f64,                      // Until the receiver decoded
+    pub sent: usize,                    // Including what went out before the sender heard the object was done
+    pub lost: usize,                    // Our packets dropped at the queue
+    pub rounds: usize,                  // Batches sent; every one after the first answered a report of what was missing
+    pub background_sent: usize,
+    pub background_lost: usize,
+    pub mean_window: f64,
+}
+
+// The link in time slots of one packet's service at the bottleneck. Each slot
+// background packets may arrive (in bursts, by a two-state source), the
+// sender may put packets in flight while its window allows, and the queue
+// head is served. The receiver reports what it still needs once a batch has
+// all been accounted for; FEC repair sent up front saves those round trips
+// but adds to the load that causes the drops.
+pub fn transfer(args: &CongestionArgs, data: &[u8], symbol_size: u16, redundancy: f64, seed: u64) -> Transfer {
+    let encoder = Encoder::with_defaults(data, symbol_size);
+    let blocks = encoder.get_block_encoders();
+    let mut packets: VecDeque<EncodingPacket> = blocks.iter().flat_map(|b| b.source_packets()).collect();
+    let source = packets.len();
+    let mut next_repair = vec![0u32; blocks.len()];
+    let mut repair = |count: usize, packets: &mut VecDeque<EncodingPacket>| {
+        for i in 0..count {
+            let b = i % blocks.len();
+            packets.extend(blocks[b].repair_packets(next_repair[b], 1));
+            next_repair[b] += 1;
+        }
+    };
+    repair((source as f64 * redundancy.max(0.0)).ceil() as usize, &mut packets);
+
+    let slot_secs = 1.0 / args.capacity.max(1) as f64;
+    let rtt = ((args.rtt_ms / 1000.0) / slot_secs).round().max(2.0) as usize;
+    let mut rng = StdRng::seed_from_u64(seed);
+    // A background source alternating between bursts at full rate and silence,
+    // averaging --background of the capacity
+    let load = args.background.clamp(0.0, 0.99);
+    let to_off = 1.0 / args.background_burst.max(1.0);
+    let to_on = (load * to_off / (1.0 - load)).min(1.0);
+    let mut on = rng.gen_bool(load);
+
+    let mut session = DecodeSession::new(encoder.get_config());
+    let mut queue: VecDeque<Option<EncodingPacket>> = VecDeque::new(); // None is a background packet
+    let mut feedback: VecDeque<(usize, Feedback)> = VecDeque::new();
+    let mut window = args.window.max(1) as f64;
+    let mut threshold = f64::INFINITY;  // Slow start until the first loss
+    let (mut in_flight, mut last_cut, mut window_sum, mut received) = (0usize, 0usize, 0.0, 0usize);
+    let mut result = Transfer { rounds: 1, ..Transfer::default() };
+    let limit = rtt * 2000 + source * 1000; // Slots before the transfer counts as stuck
+    let mut heard_done = limit;         // When news of the decode reaches the sender
+    for slot in 0..limit {
+        if slot >= heard_done {
+            result.mean_window = window_sum / slot as f64;
+            return result;
+        }
+        on = match (args.background_burst <= 1.0, on) {
+            (true, _) => rng.gen_bool(load),
+            (false, true) => !rng.gen_bool(to_off),
+            (false, false) => rng.gen_bool(to_on),
+        };
+        // Whichever arrives first in a slot gets the last places in the queue, so take turns at random
+        let background_first = rng.gen_bool(0.5);
+        if on && background_first {
+            result.background_sent += 1;
+            result.background_lost += !offer(&mut queue, args.queue, None) as usize;
+        }
+
+        while feedback.front().is_some_and(|(due, _)| *due <= slot) {
+            let (_, event) = feedback.pop_front().unwrap();
+            in_flight -= 1;
+            match (event, args.controller) {
+                (Feedback::Delivered, Controller::Aimd) => window += if window < threshold { 1.0 } else { 1.0 / window },
+                (Feedback::Lost, Controller::Aimd) if slot - last_cut >= rtt => {
+                    threshold = (window / 2.0).max(2.0);
+                    window = threshold;
+                    last_cut = slot;
+                }
+                _ => {}
+            }
+        }
+        // A batch fully accounted for and the object still short: the receiver
+        // asks for the difference, and the answer goes out from here
+        if packets.is_empty() && in_flight == 0 && !result.completed {
+            repair(source.saturating_sub(received).max(1), &mut packets);
+            result.rounds += 1;
+        }
+        // The sender's own link is twice the bottleneck's speed
+        for _ in 0..2 {
+            if (in_flight as f64) >= window.floor() {
+                break;
+            }
+            let Some(packet) = packets.pop_front() else { break };
+            result.sent += 1;
+            in_flight += 1;
+            if !offer(&mut queue, args.queue, Some(packet)) {
+                result.lost += 1;
+                feedback.push_back((slot + rtt, Feedback::Lost));
+            }
+        }
+        if on && !background_first {
+            result.background_sent += 1;
+            result.background_lost += !offer(&mut queue, args.queue, None) as usize;
+        }
+        window_sum += window;
+
+        if let Some(Some(packet)) = queue.pop_front() {
+            feedback.push_back((slot + rtt, Feedback::Delivered));
+            received += 1;
+            if !result.completed && session.push(packet) {
+                result.completed = true;
+                result.secs = (slot + rtt / 2) as f64 * slot_secs;
+                heard_done = slot + rtt;
+            }
+        }
+    }
+    result.secs = limit as f64 * slot_secs;
+    result.mean_window = window_sum / limit as f64;
+    result
+}
+
+// Drop-tail: join the queue if there is room
+fn offer<T>(queue: &mut VecDeque<T>, limit: usize, item: T) -> bool {
+    let room = queue.len() < limit;
+    if room {
+        queue.push_back(item);
+    }
+    room
+}
+
+pub fn run(args: &CongestionArgs) -> Result<(), Failure> {
+    let dag = grow_dag(args.blocks, &mut StdRng::seed_from_u64(0));
+    let (data, _) = headers::serialize(&dag, &BodyArgs::default());
+    let symbols = args.symbols.choose(data.len(), frame::UDP_IPV4_LEN + frame::HEADER_LEN + PAYLOAD_ID_LEN);
+
+    println!(
+        "=== Congestion: {} bytes of headers over a {} packet/s bottleneck, {:.0}% background in bursts of {}, queue {}, RTT {}ms ===",
+        data.len(),
+        args.capacity,
+        args.background * 100.0,
+        args.background_burst,
+        args.queue,
+        args.rtt_ms
+    );
+    println!("{}; {:?} window of {}; {} runs per row", symbols.describe(), args.controller, args.window, args.runs);
+    println!(
+        "{:>10} {:>9} {:>10} {:>8} {:>9} {:>7} {:>10} {:>8}",
+        "redundancy", "completed", "mean time", "sent", "our loss", "rounds", "bg loss", "window"
+    );
+    for &redundancy in &args.redundancy {
+        let runs: Vec<Transfer> = (0..args.runs).map(|seed| transfer(args, &data, symbols.size, redundancy, seed)).collect();
+        let n = runs.len().max(1) as f64;
+        let mean = |f: fn(&Transfer) -> f64| runs.iter().map(f).sum::<f64>() / n;
+        let done: Vec<&Transfer> = runs.iter().filter(|t| t.completed).collect();
+        println!(
+            "{:>9.0}% {:>8.0}% {:>8.1}ms {:>8.1} {:>8.1}% {:>7.2} {:>9.1}% {:>8.1}",
+            redundancy * 100.0,
+            done.len() as f64 * 100.0 / n,
+            done.iter().map(|t| t.secs).sum::<f64>() * 1000.0 / done.len().max(1) as f64,
+            mean(|t| t.sent as f64),
+            mean(|t| t.lost as f64 / t.sent.max(1) as f64) * 100.0,
+            mean(|t| t.rounds as f64),
+            mean(|t| t.background_lost as f64 / t.background_sent.max(1) as f64) * 100.0,
+            mean(|t| t.mean_window)
+        );
+    }
+    println!("Our loss and the background's are both drops at the shared queue; times cover completed runs only.");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(controller: Controller, window: usize, background: f64, queue: usize) -> CongestionArgs {
+        CongestionArgs {
+            blocks: 300,
+            controller,
+            window,
+            capacity: 10_000,
+            queue,
+            background,
+            background_burst: 8.0,
+            rtt_ms: 20.0,
+            redundancy: vec![0.0],
+            runs: 1,
+            symbols: SymbolArgs::default(),
+        }
+    }
+
+    fn payload() -> Vec<u8> {
+        headers::serialize(&grow_dag(300, &mut StdRng::seed_from_u64(0)), &BodyArgs::default()).0
+    }
+
+    #[test]
+    fn an_idle_bottleneck_loses_nothing() {
+        let data = payload();
+        let t = transfer(&args(Controller::Aimd, 4, 0.0, 1000), &data, 256, 0.0, 0);
+        assert!(t.completed && t.lost == 0 && t.rounds == 1, "{:?}", t);
+        assert_eq!(t.sent, data.len().div_ceil(256));
+    }
+
+    #[test]
+    fn aimd_backs_off_where_a_fixed_window_keeps_losing() {
+        let data = payload();
+        let loss = |a: &CongestionArgs| (0..3).map(|seed| transfer(a, &data, 256, 0.25, seed)).map(|t| t.lost as f64 / t.sent as f64).sum::<f64>();
+        let (aimd, fixed) = (loss(&args(Controller::Aimd, 4, 0.7, 20)), loss(&args(Controller::Fixed, 200, 0.7, 20)));
+        assert!(aimd < fixed, "AIMD lost {:.3}, the fixed window {:.3}", aimd / 3.0, fixed / 3.0);
+    }
+}
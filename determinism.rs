@@ -0,0 +1,96 @@
+use rand::seq::SliceRandom;
+use raptorq::{Encoder, EncodingPacket};
+use sha2::{Digest, Sha256};
+
+use crate::failure::Failure;
+use crate::fec::{self, OverheadStats};
+use crate::rng::{self, SimRng, SimStream};
+use crate::simulate::{self, OutputArgs, SimArgs};
+use crate::{headers, Color, DemoArgs, PAYLOAD_ID_LEN};
+
+// One seeded run of the demo pipeline, reduced to values two runs can be compared on
+#[derive(Debug, PartialEq, Eq)]
+struct Fingerprint {
+    dag: [u8; 32],                      // Every block's hash, color, scores and selected parent, in ID order
+    order: Vec<u64>,                    // Consensus order
+    packets: [u8; 32],                  // Every encoded packet, in the order the encoder emitted them
+    decode: (Option<usize>, usize, usize, Option<[u8; 32]>),    // Overhead, packets used, symbols missing, recovered data
+    trials: (usize, usize, usize, u64), // Successes, failures, max overhead, mean overhead bits
+}
+
+// Same stages as the demo, minus the narration, with every random draw from one seeded stream
+fn fingerprint(args: &DemoArgs, seed: u64) -> Fingerprint {
+    let root = SimStream::seeded(seed);
+    let sim = SimArgs { output: OutputArgs { quiet: true, verbose: 0 }, save_dag: None, ..args.sim.clone() };
+    let dag = simulate::grow(&sim, &root);
+    let mut rng = root.fork(rng::CHANNEL);
+
+    let mut hasher = Sha256::new();
+    for id in 0..dag.next_id {
+        let block = &dag.blocks[&id];
+        hasher.update(block.hash);
+        hasher.update([(block.color == Color::Blue) as u8]);
+        hasher.update((block.blue_score as u64).to_be_bytes());
+        hasher.update(block.blue_work.to_be_bytes());
+        hasher.update(block.selected_parent.map_or(u64::MAX, |sp| sp).to_be_bytes());
+    }
+    hasher.update(dag.selected_parent.to_be_bytes());
+
+    let tx = &args.transmission;
+    let (data, _) = headers::serialize(&dag, &args.bodies);
+    let encoder = Encoder::with_defaults(&data, tx.symbols.choose(data.len(), PAYLOAD_ID_LEN).size);
+    let config = encoder.get_config();
+    let packets: Vec<EncodingPacket> = encoder.get_encoded_packets(tx.repair);
+    let packet_hash = packets.iter().fold(Sha256::new(), |h, p| h.chain_update(p.serialize())).finalize().into();
+
+    let mut received = packets.clone();
+    received.shuffle(&mut rng);
+    received.truncate(received.len().saturating_sub(tx.loss));
+    let outcome = fec::decode_packets(config, received);
+    let decode = (
+        outcome.overhead(),
+        outcome.packets_used,
+        outcome.missing.len(),
+        outcome.data.as_ref().map(|d| Sha256::digest(d).into()),
+    );
+
+    let mut stats = OverheadStats::default();
+    for _ in 0..tx.trials {
+        let mut trial = packets.clone();
+        trial.shuffle(&mut rng);
+        trial.truncate(trial.len().saturating_sub(tx.loss));
+        stats.record(&fec::decode_packets(config, trial));
+    }
+
+    Fingerprint {
+        dag: hasher.finalize().into(),
+        order: dag.consensus_order(),
+        packets: packet_hash,
+        decode,
+        trials: (stats.successes(), stats.failures(), stats.max_overhead(), stats.mean_overhead().to_bits()),
+    }
+}
+
+// Run the seeded scenario twice in this process and compare every stage. Anything
+// that differs leaked state the seed does not control, such as HashMap iteration order.
+pub fn check(args: &DemoArgs, seed: u64) -> Result<(), Failure> {
+    let first = fingerprint(args, seed);
+    let second = fingerprint(args, seed);
+    let stages = [
+        ("DAG hashes and colors", first.dag == second.dag),
+        ("Consensus order", first.order == second.order),
+        ("Encoded packets", first.packets == second.packets),
+        ("Decode report", first.decode == second.decode),
+        ("Overhead trials", first.trials == second.trials),
+    ];
+
+    println!("=== Determinism check (seed {}, two runs in one process) ===", seed);
+    for (stage, same) in stages {
+        println!("{:<22} {}", stage, if same { "identical" } else { "DIFFERS" });
+    }
+    let differing: Vec<&str> = stages.iter().filter(|(_, same)| !same).map(|(stage, _)| *stage).collect();
+    if !differing.is_empty() {
+        return Err(Failure::Mismatch(format!("seed {} gave different results: {}", seed, differing.join(", "))));
+    }
+    Ok(())
+}
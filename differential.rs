@@ -0,0 +1,194 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::{Color, ConsensusParams, ToyDag};
+
+// Straight-from-the-paper GHOSTDAG over nothing but parent lists: no caches, no
+// incremental state, every past recomputed by walking parents. The crate only
+// bounds a candidate's own blue anticone; with `k_cluster` set this also keeps
+// every blue already in that anticone within k, the paper's full rule.
+struct Reference {
+    k: usize,
+    k_cluster: bool,
+    parents: Vec<Vec<u64>>,
+    selected_parent: Vec<Option<u64>>,
+    mergeset: Vec<Vec<(u64, bool)>>,    // Beyond the selected parent's past, in (blue score, ID) order, with colors
+    blue_score: Vec<usize>,
+}
+
+impl Reference {
+    fn new(k: usize, k_cluster: bool) -> Self {
+        Reference { k, k_cluster, parents: vec![Vec::new()], selected_parent: vec![None], mergeset: vec![Vec::new()], blue_score: vec![0] }
+    }
+
+    // Past of the given blocks, the blocks themselves included
+    fn past(&self, from: &[u64]) -> BTreeSet<u64> {
+        let mut past: BTreeSet<u64> = from.iter().copied().collect();
+        let mut stack = from.to_vec();
+        while let Some(b) = stack.pop() {
+            for &p in &self.parents[b as usize] {
+                if past.insert(p) {
+                    stack.push(p);
+                }
+            }
+        }
+        past
+    }
+
+    fn blues(&self, block: u64) -> BTreeSet<u64> {
+        let mut blues = BTreeSet::new();
+        let mut current = Some(block);
+        while let Some(b) = current {
+            blues.extend(self.mergeset[b as usize].iter().filter(|m| m.1).map(|m| m.0));
+            blues.insert(b);
+            current = self.selected_parent[b as usize];
+        }
+        blues
+    }
+
+    // Anticone of `block` within `scope`: neither ancestor nor descendant
+    fn anticone(&self, block: u64, scope: &BTreeSet<u64>) -> BTreeSet<u64> {
+        let past = self.past(&[block]);
+        scope.iter().copied().filter(|&b| !past.contains(&b) && !self.past(&[b]).contains(&block)).collect()
+    }
+
+    fn color(&self, parents: &[u64]) -> (u64, Vec<(u64, bool)>) {
+        let sp = *parents.iter().max_by_key(|&&p| (self.blue_score[p as usize], std::cmp::Reverse(p))).unwrap();
+        let sp_past = self.past(&[sp]);
+        let mut merged: Vec<u64> = self.past(parents).difference(&sp_past).copied().collect();
+        merged.sort_by_key(|&b| (self.blue_score[b as usize], b));
+
+        let scope = self.past(parents);
+        let mut blues = self.blues(sp);
+        let mut colored = Vec::new();
+        for c in merged {
+            let anticone = self.anticone(c, &scope);
+            let blue_anticone: Vec<u64> = blues.intersection(&anticone).copied().collect();
+            let blue = blue_anticone.len() <= self.k
+                && (!self.k_cluster
+                    || blue_anticone.iter().all(|&b| self.anticone(b, &scope).intersection(&blues).count() < self.k));
+            if blue {
+                blues.insert(c);
+            }
+            colored.push((c, blue));
+        }
+        (sp, colored)
+    }
+
+    fn add(&mut self, parents: Vec<u64>) {
+        let (sp, mergeset) = self.color(&parents);
+        self.blue_score.push(self.blue_score[sp as usize] + 1 + mergeset.iter().filter(|m| m.1).count());
+        self.selected_parent.push(Some(sp));
+        self.mergeset.push(mergeset);
+        self.parents.push(parents);
+    }
+
+    fn tips(&self) -> Vec<u64> {
+        let referenced: BTreeSet<u64> = self.parents.iter().flatten().copied().collect();
+        (0..self.parents.len() as u64).filter(|b| !referenced.contains(b)).collect()
+    }
+
+    // Blue set as the virtual block over every tip sees it, and the consensus order
+    fn virtual_view(&self) -> (BTreeSet<u64>, Vec<u64>) {
+        let (sp, merged) = self.color(&self.tips());
+        let mut blues = self.blues(sp);
+        blues.extend(merged.iter().filter(|m| m.1).map(|m| m.0));
+
+        let mut chain = vec![sp];
+        while let Some(p) = self.selected_parent[*chain.last().unwrap() as usize] {
+            chain.push(p);
+        }
+        let order = chain
+            .iter()
+            .rev()
+            .flat_map(|&c| self.mergeset[c as usize].iter().map(|m| m.0).chain([c]))
+            .chain(merged.iter().map(|m| m.0))
+            .collect();
+        (blues, order)
+    }
+}
+
+// A block-arrival trace: each block's parents, drawn from the recent tips
+// with an occasional stale parent, the way a lagging miner would see the DAG
+fn trace(seed: u64, blocks: usize) -> Vec<Vec<u64>> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut links = Reference::new(0, false); // Only its parent links are used, to drop redundant parents
+    let mut tips: BTreeSet<u64> = BTreeSet::from([0]);
+    for id in 1..=blocks as u64 {
+        let mut parents: BTreeSet<u64> = tips.iter().copied().filter(|_| rng.gen_bool(0.6)).collect();
+        if parents.is_empty() || rng.gen_bool(0.2) {
+            parents.insert(rng.gen_range(0..id));
+        }
+        let covered: BTreeSet<u64> = parents.iter().flat_map(|&p| links.past(&links.parents[p as usize])).collect();
+        let parents: Vec<u64> = parents.into_iter().filter(|p| !covered.contains(p)).collect();
+        for p in &parents {
+            tips.remove(p);
+        }
+        tips.insert(id);
+        links.parents.push(parents);
+    }
+    links.parents.split_off(1)
+}
+
+// Where the crate and the reference disagree after replaying a trace
+fn diff(k: usize, k_cluster: bool, trace: &[Vec<u64>]) -> Vec<String> {
+    let mut dag = ToyDag::with_params(ConsensusParams { k, max_parents: usize::MAX, ..ConsensusParams::default() });
+    let mut reference = Reference::new(k, k_cluster);
+    for parents in trace {
+        dag.create_block(parents.clone());
+        reference.add(parents.clone());
+    }
+
+    let (ref_blues, ref_order) = reference.virtual_view();
+    let blues: BTreeSet<u64> = dag.blocks.values().filter(|b| b.color == Color::Blue).map(|b| b.id).collect();
+    let mut diffs = Vec::new();
+    let only: BTreeMap<&str, Vec<u64>> = BTreeMap::from([
+        ("blue here, red in the reference", blues.difference(&ref_blues).copied().collect()),
+        ("red here, blue in the reference", ref_blues.difference(&blues).copied().collect()),
+    ]);
+    for (what, ids) in only.into_iter().filter(|(_, ids)| !ids.is_empty()) {
+        diffs.push(format!("{}: {:?}", what, ids));
+    }
+    let order = dag.consensus_order();
+    if let Some(at) = order.iter().zip(&ref_order).position(|(a, b)| a != b) {
+        diffs.push(format!("orders part at position {}: {} here, {} in the reference", at, order[at], ref_order[at]));
+    }
+    diffs
+}
+
+// Seeds per k, and blocks per trace
+const SEEDS: u64 = 40;
+const BLOCKS: usize = 40;
+
+fn diff_all(k_cluster: bool) -> Vec<String> {
+    let mut failures = Vec::new();
+    for k in [1, 2, 3, 5, BLOCKS] {
+        for seed in 0..SEEDS {
+            let diffs = diff(k, k_cluster, &trace(seed, BLOCKS));
+            if !diffs.is_empty() {
+                failures.push(format!("k {} seed {}: {}", k, seed, diffs.join("; ")));
+            }
+        }
+    }
+    failures
+}
+
+// Same coloring rule, independent bookkeeping: selected parents, mergesets,
+// blue sets and the order built from them must all agree
+#[test]
+fn matches_reference_under_own_rule() {
+    let failures = diff_all(false);
+    assert!(failures.is_empty(), "{} traces differ:\n{}", failures.len(), failures.join("\n"));
+}
+
+// Against the full k-cluster rule. Known to disagree until the crate also checks
+// the blues already in a candidate's anticone, so it only runs on request:
+// cargo test differential -- --ignored
+#[test]
+#[ignore]
+fn matches_reference_k_cluster() {
+    let failures = diff_all(true);
+    assert!(failures.is_empty(), "{} traces differ:\n{}", failures.len(), failures.join("\n"));
+}
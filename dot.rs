@@ -0,0 +1,72 @@
+use std::io::{self, Write};
+
+use crate::graph::{self, Annotation};
+use crate::{Color, ToyDag};
+
+// Graphviz rendering: genesis on the left, edges point from child to parent.
+// From `colors` up blocks are filled by color and the selected-parent edge is
+// drawn bold; at `full` every chain block's mergeset sits in a dashed box
+// labelled with the chain block that merged it.
+pub fn write_dot<W: Write + ?Sized>(dag: &ToyDag, annotation: Annotation, out: &mut W) -> io::Result<()> {
+    writeln!(out, "digraph toy_dag {{")?;
+    writeln!(out, "  rankdir=RL;")?;
+    writeln!(out, "  label=\"{} blocks, k = {}\"; labelloc=t; fontname=\"Helvetica\";", dag.blocks.len(), dag.params.k)?;
+    writeln!(out, "  node [shape=box, style=\"rounded,filled\", fontname=\"Helvetica\", fontsize=10];")?;
+    writeln!(out, "  edge [arrowsize=0.6];")?;
+
+    if annotation == Annotation::Full {
+        for step in dag.ordering_steps() {
+            if let Some(c) = step.chain_block {
+                node(dag, c, true, annotation, "  ", out)?;
+            }
+            if step.merged.is_empty() {
+                continue;
+            }
+            let (name, label) = match step.chain_block {
+                Some(c) => (c.to_string(), format!("mergeset of {}", c)),
+                None => ("virtual".to_string(), "not merged yet".to_string()),
+            };
+            writeln!(out, "  subgraph cluster_{} {{", name)?;
+            writeln!(out, "    label=\"{}\"; style=dashed; color=gray50; fontsize=9;", label)?;
+            for c in &step.merged {
+                node(dag, c.id, false, annotation, "    ", out)?;
+            }
+            writeln!(out, "  }}")?;
+        }
+    } else {
+        let chain = dag.selected_chain();
+        for id in 0..dag.next_id {
+            node(dag, id, chain.contains(&id), annotation, "  ", out)?;
+        }
+    }
+
+    for id in 0..dag.next_id {
+        let block = &dag.blocks[&id];
+        for &p in &block.parents {
+            let style = match annotation {
+                Annotation::Ids => "",
+                _ if block.selected_parent == Some(p) => " [penwidth=2.5]",
+                _ => " [color=gray60]",
+            };
+            writeln!(out, "  b{} -> b{}{};", id, p, style)?;
+        }
+    }
+    writeln!(out, "}}")
+}
+
+fn node<W: Write + ?Sized>(dag: &ToyDag, id: u64, chain: bool, annotation: Annotation, indent: &str, out: &mut W) -> io::Result<()> {
+    let fill = match (annotation, &dag.blocks[&id].color) {
+        (Annotation::Ids, _) => "white",
+        (_, Color::Blue) => "lightblue",
+        (_, Color::Red) => "salmon",
+    };
+    writeln!(
+        out,
+        "{}b{} [label=\"{}\", fillcolor={}{}];",
+        indent,
+        id,
+        graph::label(dag, id, annotation).join("\\n"),
+        fill,
+        if chain && annotation != Annotation::Ids { ", penwidth=2" } else { "" }
+    )
+}
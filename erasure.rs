@@ -0,0 +1,237 @@
+use std::time::{Duration, Instant};
+use clap::ValueEnum;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use raptorq::{Encoder, EncodingPacket, ObjectTransmissionInformation, PayloadId};
+
+use crate::fec::{self, DecodeOutcome, OverheadStats, PacketRef};
+use crate::ldpc::LdpcStaircaseCode;
+
+// One encoded symbol as it travels over the channel, independent of code family
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CodedPacket {
+    pub block: u8,                      // Source block number (always 0 for single-block codes)
+    pub esi: u32,                       // Encoding symbol ID: < source symbols means a source packet
+    pub data: Vec<u8>,
+}
+
+impl CodedPacket {
+    // Same layout as a serialized RaptorQ packet: block | 24-bit ESI | data
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(4 + self.data.len());
+        bytes.push(self.block);
+        bytes.extend_from_slice(&self.esi.to_be_bytes()[1..]);
+        bytes.extend_from_slice(&self.data);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < 4 {
+            return None;
+        }
+        Some(CodedPacket {
+            block: bytes[0],
+            esi: u32::from_be_bytes([0, bytes[1], bytes[2], bytes[3]]),
+            data: bytes[4..].to_vec(),
+        })
+    }
+}
+
+// A code family that can protect an object against packet erasures
+pub trait ErasureCode {
+    fn name(&self) -> &'static str;
+
+    // Source packets followed by `repair` repair packets (per source block)
+    fn encode(&self, data: &[u8], repair: u32) -> Vec<CodedPacket>;
+
+    // Fresh decoder for an object of `data_len` bytes encoded with `repair` repair packets
+    fn decoder(&self, data_len: usize, repair: u32) -> Box<dyn ErasureDecoder>;
+}
+
+pub trait ErasureDecoder {
+    // Returns the object once enough packets have arrived
+    fn push(&mut self, packet: CodedPacket) -> Option<Vec<u8>>;
+
+    fn source_symbols(&self) -> usize;
+}
+
+// Code family picked on the command line
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum CodeKind {
+    /// RaptorQ (RFC 6330) fountain code
+    #[default]
+    Raptorq,
+    /// LDPC-staircase (RFC 5170) with peeling decoding
+    Ldpc,
+}
+
+impl CodeKind {
+    pub fn code(self, symbol_size: u16) -> Box<dyn ErasureCode> {
+        match self {
+            CodeKind::Raptorq => Box::new(RaptorQCode { symbol_size }),
+            CodeKind::Ldpc => Box::new(LdpcStaircaseCode::new(symbol_size as usize)),
+        }
+    }
+}
+
+// ====================== RaptorQ ======================
+
+pub struct RaptorQCode {
+    pub symbol_size: u16,
+}
+
+impl RaptorQCode {
+    pub fn config(&self, data_len: usize) -> ObjectTransmissionInformation {
+        ObjectTransmissionInformation::with_defaults(data_len as u64, self.symbol_size)
+    }
+}
+
+impl ErasureCode for RaptorQCode {
+    fn name(&self) -> &'static str {
+        "raptorq"
+    }
+
+    fn encode(&self, data: &[u8], repair: u32) -> Vec<CodedPacket> {
+        Encoder::new(data, self.config(data.len()))
+            .get_encoded_packets(repair)
+            .into_iter()
+            .map(CodedPacket::from)
+            .collect()
+    }
+
+    fn decoder(&self, data_len: usize, _repair: u32) -> Box<dyn ErasureDecoder> {
+        Box::new(fec::DecodeSession::new(self.config(data_len)))
+    }
+}
+
+impl ErasureDecoder for fec::DecodeSession {
+    fn push(&mut self, packet: CodedPacket) -> Option<Vec<u8>> {
+        if fec::DecodeSession::push(self, packet.into()) {
+            self.result().map(<[u8]>::to_vec)
+        } else {
+            None
+        }
+    }
+
+    fn source_symbols(&self) -> usize {
+        fec::source_symbol_count(&self.config())
+    }
+}
+
+impl From<EncodingPacket> for CodedPacket {
+    fn from(packet: EncodingPacket) -> Self {
+        let (id, data) = packet.split();
+        CodedPacket {
+            block: id.source_block_number(),
+            esi: id.encoding_symbol_id(),
+            data,
+        }
+    }
+}
+
+impl From<CodedPacket> for EncodingPacket {
+    fn from(packet: CodedPacket) -> Self {
+        EncodingPacket::new(PayloadId::new(packet.block, packet.esi), packet.data)
+    }
+}
+
+// ====================== Comparison trials ======================
+
+// Overhead and CPU cost of one code over repeated lossy transmissions
+pub struct TrialReport {
+    pub code: &'static str,
+    pub packets: usize,
+    pub overhead: OverheadStats,
+    pub encode_time: Duration,
+    pub decode_time: Duration,          // Summed over all trials
+}
+
+pub fn decode_with(code: &dyn ErasureCode, data_len: usize, repair: u32, packets: Vec<CodedPacket>) -> DecodeOutcome {
+    let mut decoder = code.decoder(data_len, repair);
+    let source_symbols = decoder.source_symbols();
+    let mut data = None;
+    let mut consumed = Vec::new();
+    let mut redundant = Vec::new();
+
+    for packet in packets {
+        let used = PacketRef { source_block: 0, esi: packet.esi, repair: packet.esi as usize >= source_symbols };
+        if data.is_some() {
+            redundant.push(used);
+            continue;
+        }
+        consumed.push(used);
+        data = decoder.push(packet);
+    }
+
+    DecodeOutcome {
+        data,
+        packets_used: consumed.len(),
+        source_symbols,
+        missing: Vec::new(),
+        consumed,
+        redundant,
+    }
+}
+
+pub fn run_trials<R: Rng>(
+    code: &dyn ErasureCode,
+    data: &[u8],
+    repair: u32,
+    loss: usize,
+    trials: usize,
+    rng: &mut R,
+) -> TrialReport {
+    let start = Instant::now();
+    let packets = code.encode(data, repair);
+    let encode_time = start.elapsed();
+
+    let mut overhead = OverheadStats::default();
+    let mut decode_time = Duration::ZERO;
+
+    for _ in 0..trials {
+        let mut received = packets.clone();
+        received.shuffle(rng);
+        received.truncate(received.len().saturating_sub(loss));
+
+        let start = Instant::now();
+        let outcome = decode_with(code, data.len(), repair, received);
+        decode_time += start.elapsed();
+
+        // A "success" with the wrong bytes is a decoder bug, not a lucky run
+        if outcome.data.as_deref().is_some_and(|d| d != data) {
+            panic!("{} decoder returned corrupted data", code.name());
+        }
+        overhead.record(&outcome);
+    }
+
+    TrialReport {
+        code: code.name(),
+        packets: packets.len(),
+        overhead,
+        encode_time,
+        decode_time,
+    }
+}
+
+pub fn print_comparison(reports: &[TrialReport]) {
+    println!("=== Erasure code comparison ===");
+    println!(
+        "{:<10} {:>8} {:>10} {:>14} {:>10} {:>12} {:>14}",
+        "code", "packets", "success", "mean overhead", "max", "encode", "decode/run"
+    );
+    for r in reports {
+        let runs = r.overhead.successes() + r.overhead.failures();
+        println!(
+            "{:<10} {:>8} {:>6}/{:<3} {:>14.3} {:>10} {:>12?} {:>14?}",
+            r.code,
+            r.packets,
+            r.overhead.successes(),
+            runs,
+            r.overhead.mean_overhead(),
+            r.overhead.max_overhead(),
+            r.encode_time,
+            r.decode_time / runs.max(1) as u32
+        );
+    }
+    println!("===============================\n");
+}
@@ -0,0 +1,39 @@
+use std::fmt;
+
+// Why a command failed, and the exit code a wrapping script sees for it.
+// 2 is also what clap exits with for a bad command line.
+#[derive(Debug)]
+pub enum Failure {
+    Config(String),                     // Invalid flags, input or DAG file: exit 2
+    Decode(String),                     // Not enough (intact) packets to recover the object: exit 3
+    Mismatch(String),                   // Recovered data contradicts its commitment: exit 4
+    Io(String),                         // Reading or writing a file or stream failed: exit 5
+}
+
+pub const EXIT_CODES: &str = "Exit codes: 0 success, 2 invalid config or input, 3 decode failure, \
+                              4 verification mismatch, 5 I/O error";
+
+impl Failure {
+    pub fn code(&self) -> i32 {
+        match self {
+            Failure::Config(_) => 2,
+            Failure::Decode(_) => 3,
+            Failure::Mismatch(_) => 4,
+            Failure::Io(_) => 5,
+        }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Failure::Config(m) | Failure::Decode(m) | Failure::Mismatch(m) | Failure::Io(m) => f.write_str(m),
+        }
+    }
+}
+
+// Report a failed command on stderr and leave with its exit code
+pub fn exit(command: &str, failure: Failure) -> ! {
+    eprintln!("{} failed: {}", command, failure);
+    std::process::exit(failure.code())
+}
@@ -0,0 +1,152 @@
+use clap::Args;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+// Places a failure can be injected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FaultPoint {
+    StoreWrite,                         // EncodedBlockStore::put fails as if the disk did
+    DroppedEvent,                       // DecodeManager loses an event before the caller sees it
+    DecoderRefusal,                     // DecodeManager's decoder turns a packet away
+}
+
+impl FaultPoint {
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+// Probability of failing at each point; all zero injects nothing
+#[derive(Args, Debug, Clone, Copy, Default)]
+pub struct FaultArgs {
+    /// Chance each block store write fails with an injected I/O error
+    #[arg(long, default_value_t = 0.0)]
+    pub fail_store_writes: f64,
+
+    /// Chance each decode event (opened, completed, evicted) is silently dropped
+    #[arg(long, default_value_t = 0.0)]
+    pub drop_events: f64,
+
+    /// Chance the decoder refuses a packet it was handed
+    #[arg(long, default_value_t = 0.0)]
+    pub refuse_packets: f64,
+
+    /// Seed for the injected failures, so a failing run can be repeated
+    #[arg(long, default_value_t = 0)]
+    pub fault_seed: u64,
+}
+
+// Draws failures for one component. Each component gets its own, so adding
+// faults to one leaves the others' draws unchanged.
+pub struct Faults {
+    rates: [f64; 3],
+    rng: StdRng,
+    injected: [usize; 3],
+}
+
+impl Faults {
+    pub fn new(args: &FaultArgs) -> Self {
+        Faults {
+            rates: [args.fail_store_writes, args.drop_events, args.refuse_packets].map(|p| p.clamp(0.0, 1.0)),
+            rng: StdRng::seed_from_u64(args.fault_seed),
+            injected: [0; 3],
+        }
+    }
+
+    pub fn none() -> Self {
+        Self::new(&FaultArgs::default())
+    }
+
+    // Whether to fail here this time. Points set to zero never draw, so
+    // enabling one point does not shift another's failures.
+    pub fn strike(&mut self, point: FaultPoint) -> bool {
+        let rate = self.rates[point.index()];
+        let strike = rate > 0.0 && self.rng.gen_bool(rate);
+        self.injected[point.index()] += strike as usize;
+        strike
+    }
+
+    pub fn injected(&self, point: FaultPoint) -> usize {
+        self.injected[point.index()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use raptorq::Encoder;
+
+    use super::*;
+    use crate::erasure::RaptorQCode;
+    use crate::frame::{self, FrameHeader};
+    use crate::manager::{DecodeManager, Event};
+    use crate::store::EncodedBlockStore;
+
+    fn faults(store: f64, events: f64, refuse: f64) -> Faults {
+        Faults::new(&FaultArgs { fail_store_writes: store, drop_events: events, refuse_packets: refuse, fault_seed: 7 })
+    }
+
+    // Frames for `objects` objects of one session, with `repair` repair packets each
+    fn frames(objects: u32, repair: u32) -> Vec<Vec<u8>> {
+        let data: Vec<u8> = (0..2000u32).map(|i| i as u8).collect();
+        let encoder = Encoder::with_defaults(&data, 128);
+        (0..objects)
+            .flat_map(|object| {
+                let header = FrameHeader { session: 1, object, config: encoder.get_config() };
+                encoder.get_encoded_packets(repair).iter().map(|p| frame::encode_frame(&header, p)).collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    fn route(mut manager: DecodeManager, frames: &[Vec<u8>]) -> (Vec<Event>, DecodeManager) {
+        let events = frames.iter().flat_map(|f| manager.push_frame(f)).collect();
+        (events, manager)
+    }
+
+    #[test]
+    fn store_write_failures_surface_as_io_errors() {
+        let mut store = EncodedBlockStore::new(Box::new(RaptorQCode { symbol_size: 64 }), 4).inject(faults(1.0, 0.0, 0.0));
+        let err = store.put(3, &[1; 500]).expect_err("every write fails");
+        assert!(err.to_string().contains("injected"));
+        assert_eq!(store.root(3), None, "a failed write must not leave the block half stored");
+        assert_eq!(store.reconstruct(3), None);
+    }
+
+    #[test]
+    fn store_keeps_working_between_failures() {
+        let mut store = EncodedBlockStore::new(Box::new(RaptorQCode { symbol_size: 64 }), 4).inject(faults(0.5, 0.0, 0.0));
+        let results: Vec<bool> = (0..40).map(|b| store.put(b, &[b as u8; 300]).is_ok()).collect();
+        assert!(results.iter().any(|&ok| ok) && results.iter().any(|&ok| !ok));
+        for (block, ok) in results.into_iter().enumerate() {
+            assert_eq!(store.reconstruct(block as u64).is_some(), ok, "block {}", block);
+        }
+    }
+
+    #[test]
+    fn dropped_events_are_counted_not_invented() {
+        let manager = DecodeManager::new(Some(1), 8, None).inject(faults(0.0, 0.5, 0.0));
+        let (mut events, manager) = route(manager, &frames(8, 4));
+        let dropped = manager.injected(FaultPoint::DroppedEvent);
+        events.extend(manager.finish());
+        // Every object opens once and completes once; whatever is missing was dropped
+        assert_eq!(events.len() + dropped, 16);
+        assert!(dropped > 0);
+    }
+
+    #[test]
+    fn refused_packets_can_starve_an_object() {
+        let manager = DecodeManager::new(Some(1), 8, None).inject(faults(0.0, 0.0, 1.0));
+        let (events, manager) = route(manager, &frames(2, 4));
+        assert!(events.iter().all(|e| !matches!(e, Event::Completed { .. })));
+        assert_eq!(manager.stats().refused, manager.stats().frames);
+        let evicted = manager.finish();
+        assert!(evicted.iter().all(|e| matches!(e, Event::Evicted { .. })));
+        assert_eq!(evicted.len(), 2);
+    }
+
+    #[test]
+    fn repair_covers_some_refusals() {
+        let manager = DecodeManager::new(Some(1), 8, None).inject(faults(0.0, 0.0, 0.2));
+        let (events, _) = route(manager, &frames(4, 16));
+        assert_eq!(events.iter().filter(|e| matches!(e, Event::Completed { .. })).count(), 4);
+    }
+}
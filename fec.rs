@@ -0,0 +1,557 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+use clap::Args;
+use raptorq::{partition, Decoder, EncodingPacket, ObjectTransmissionInformation};
+
+use crate::{MTU, TARGET_SYMBOLS};
+
+const ALIGNMENT: usize = 8;             // RaptorQ rounds symbol sizes down to this
+const MIN_SYMBOL: usize = 64;           // Smallest size raptorq can split into sub-blocks
+
+// How the symbol size is picked: given outright, or sized so the object splits
+// into about --target-symbols source symbols without a packet outgrowing --mtu
+#[derive(Args, Debug, Clone, Copy)]
+pub struct SymbolArgs {
+    /// Bytes per symbol; chosen from the object size, --mtu and --target-symbols when omitted
+    #[arg(long)]
+    pub symbol_size: Option<u16>,
+
+    /// Path MTU: largest packet the link carries unfragmented; a symbol plus its per-packet headers (IP and UDP too, for datagrams) must fit
+    #[arg(long, default_value_t = MTU)]
+    pub mtu: usize,
+
+    /// Source symbols to aim for when choosing the symbol size (more means finer-grained loss, more packets)
+    #[arg(long, default_value_t = TARGET_SYMBOLS)]
+    pub target_symbols: usize,
+}
+
+impl Default for SymbolArgs {
+    fn default() -> Self {
+        SymbolArgs { symbol_size: None, mtu: MTU, target_symbols: TARGET_SYMBOLS }
+    }
+}
+
+// The symbol size settled on, and what decided it
+#[derive(Debug, Clone, Copy)]
+pub struct SymbolChoice {
+    pub size: u16,
+    pub source_symbols: usize,
+    pub reason: &'static str,
+    pub packet_len: usize,              // Symbol plus every header in front of it
+    pub mtu: usize,
+}
+
+impl SymbolArgs {
+    // `packet_overhead` is what every packet carries besides the symbol: payload ID, tags, framing
+    pub fn choose(&self, object_len: usize, packet_overhead: usize) -> SymbolChoice {
+        let largest = (self.mtu.saturating_sub(packet_overhead) / ALIGNMENT * ALIGNMENT).clamp(MIN_SYMBOL, u16::MAX as usize);
+        let (size, reason) = match self.symbol_size {
+            Some(size) => (size as usize, "--symbol-size"),
+            None => {
+                let wanted = object_len.div_ceil(self.target_symbols.max(1)).next_multiple_of(ALIGNMENT);
+                if wanted > largest {
+                    (largest, "capped by --mtu")
+                } else if wanted < MIN_SYMBOL {
+                    (MIN_SYMBOL, "raised to the minimum")
+                } else {
+                    (wanted, "sized for --target-symbols")
+                }
+            }
+        };
+        let aligned = (size / ALIGNMENT * ALIGNMENT).max(ALIGNMENT);
+        let choice = SymbolChoice {
+            size: size as u16,
+            source_symbols: object_len.div_ceil(aligned),
+            reason,
+            packet_len: size + packet_overhead,
+            mtu: self.mtu,
+        };
+        // Only a forced --symbol-size or an MTU below the minimum symbol gets here
+        if choice.fragments() {
+            eprintln!(
+                "warning: {} byte symbols plus {} bytes of headers exceed the {} byte MTU; every packet will be fragmented",
+                choice.size, packet_overhead, self.mtu
+            );
+        }
+        choice
+    }
+}
+
+impl SymbolChoice {
+    pub fn fragments(&self) -> bool {
+        self.packet_len > self.mtu
+    }
+
+    pub fn describe(&self) -> String {
+        let fit = if self.fragments() { "over" } else { "within" };
+        format!(
+            "{} byte symbols, {} source symbols, {}; {} byte packets {} the {} byte MTU",
+            self.size, self.source_symbols, self.reason, self.packet_len, fit, self.mtu
+        )
+    }
+}
+
+// Result of feeding a stream of packets into a fresh decoder
+pub struct DecodeOutcome {
+    pub data: Option<Vec<u8>>,
+    pub packets_used: usize,            // Packets fed in before decode() returned (or all of them on failure)
+    pub source_symbols: usize,          // Minimum number of packets any decoder could need
+    pub missing: Vec<MissingSymbol>,    // Source symbols never received (only filled on failure)
+    pub consumed: Vec<PacketRef>,       // Packets the decoder took in, in arrival order
+    pub redundant: Vec<PacketRef>,      // Packets that arrived after decoding had finished
+}
+
+// A received packet and the kind of symbol it carried
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PacketRef {
+    pub source_block: u8,
+    pub esi: u32,
+    pub repair: bool,
+}
+
+// A source symbol that was neither received nor recovered
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingSymbol {
+    pub source_block: u8,
+    pub esi: u32,
+    pub byte_range: Range<usize>,       // Position in the original object, clamped to its length
+}
+
+// Why a decode gave up, in numbers a sender can act on
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecodeError {
+    InsufficientSymbols {
+        received: usize,                // Distinct symbols the decoder took in
+        estimated_needed: usize,        // The source count, or one past what was received if that was not enough
+        loss_observed: f64,             // Share of the source packets that never arrived; every one of them was sent
+    },
+}
+
+impl DecodeError {
+    // Repair packets that would also have had to be sent for the missing
+    // symbols to get through at the loss observed; None if nothing got through
+    pub fn more_repair_needed(&self) -> Option<usize> {
+        let DecodeError::InsufficientSymbols { received, estimated_needed, loss_observed } = *self;
+        (loss_observed < 1.0).then(|| (estimated_needed.saturating_sub(received) as f64 / (1.0 - loss_observed)).ceil() as usize)
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let DecodeError::InsufficientSymbols { received, estimated_needed, loss_observed } = *self;
+        write!(f, "{} symbols received, about {} needed, {:.1}% of source packets lost", received, estimated_needed, loss_observed * 100.0)?;
+        match self.more_repair_needed() {
+            Some(more) => write!(f, "; about {} more repair packets would have recovered the object", more),
+            None => write!(f, "; no packet got through, so no amount of repair would have helped"),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+impl DecodeOutcome {
+    // Reception overhead: packets needed beyond the source symbol count
+    pub fn overhead(&self) -> Option<usize> {
+        self.data
+            .as_ref()
+            .map(|_| self.packets_used.saturating_sub(self.source_symbols))
+    }
+
+    // The object, or how far the packets fell short of it
+    pub fn result(&self) -> Result<&[u8], DecodeError> {
+        if let Some(data) = &self.data {
+            return Ok(data);
+        }
+        let received: HashSet<(u8, u32)> = self.consumed.iter().map(|p| (p.source_block, p.esi)).collect();
+        let source_received = self.consumed.iter().filter(|p| !p.repair).map(|p| (p.source_block, p.esi)).collect::<HashSet<_>>().len();
+        Err(DecodeError::InsufficientSymbols {
+            received: received.len(),
+            estimated_needed: self.source_symbols.max(received.len() + 1),
+            loss_observed: 1.0 - source_received as f64 / self.source_symbols.max(1) as f64,
+        })
+    }
+
+    // Which received packets the decoder actually needed, by symbol class
+    pub fn print_packet_usage(&self) {
+        let esis = |packets: &[PacketRef], repair: bool| -> Vec<u32> {
+            let mut esis: Vec<u32> = packets.iter().filter(|p| p.repair == repair).map(|p| p.esi).collect();
+            esis.sort();
+            esis
+        };
+        let (source, repair) = (esis(&self.consumed, false), esis(&self.consumed, true));
+        let (late_source, late_repair) = (esis(&self.redundant, false), esis(&self.redundant, true));
+
+        println!("=== Which packets mattered ===");
+        println!(
+            "Consumed {} packets: {} source + {} repair (any decoder needs at least {})",
+            self.consumed.len(),
+            source.len(),
+            repair.len(),
+            self.source_symbols
+        );
+        println!("  source ESIs: {}", esi_ranges(&source));
+        println!("  repair ESIs: {}", esi_ranges(&repair));
+        println!(
+            "Redundant, arrived after decoding finished: {} packets ({} source + {} repair)",
+            self.redundant.len(),
+            late_source.len(),
+            late_repair.len()
+        );
+        if self.data.is_some() {
+            println!(
+                "Repair packets stood in for {} source symbols not yet received; {} consumed packets were overhead beyond the minimum",
+                self.source_symbols.saturating_sub(source.len()),
+                self.consumed.len().saturating_sub(self.source_symbols)
+            );
+        }
+        println!("==============================\n");
+    }
+}
+
+// Compact ESI list: 0-3, 5, 7-19
+fn esi_ranges(esis: &[u32]) -> String {
+    let mut ranges: Vec<(u32, u32)> = Vec::new();
+    for &esi in esis {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == esi => *end = esi,
+            _ => ranges.push((esi, esi)),
+        }
+    }
+    if ranges.is_empty() {
+        return "none".to_string();
+    }
+    ranges
+        .iter()
+        .map(|&(start, end)| if start == end { start.to_string() } else { format!("{}-{}", start, end) })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// Number of source symbols the object is split into (across all source blocks)
+pub fn source_symbol_count(config: &ObjectTransmissionInformation) -> usize {
+    (config.transfer_length() as usize).div_ceil(config.symbol_size() as usize)
+}
+
+// Source symbol count of each source block, as laid out by RFC 6330 partitioning
+pub fn source_block_symbols(config: &ObjectTransmissionInformation) -> Vec<u32> {
+    let (kl, ks, zl, zs) = partition(source_symbol_count(config) as u32, config.source_blocks());
+    let mut sizes = vec![kl; zl as usize];
+    sizes.extend(std::iter::repeat_n(ks, zs as usize));
+    sizes
+}
+
+// When a live receiver stops waiting for an object, counted from its first packet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Deadline {
+    After(Duration),                    // Wall-clock time since the first packet
+    Packets(usize),                     // Packets taken in without the object decoding
+}
+
+#[derive(Args, Debug, Clone, Copy, Default)]
+pub struct DeadlineArgs {
+    /// Give up on an object this many seconds after its first packet, and report what did arrive (before any packet, only --timeout applies)
+    #[arg(long, conflicts_with = "deadline_packets")]
+    pub deadline: Option<f64>,
+
+    /// Give up on an object once this many of its packets arrived without decoding it (0 gives up on the first)
+    #[arg(long)]
+    pub deadline_packets: Option<usize>,
+}
+
+impl DeadlineArgs {
+    pub fn deadline(&self) -> Option<Deadline> {
+        match (self.deadline, self.deadline_packets) {
+            (Some(secs), _) => Some(Deadline::After(Duration::try_from_secs_f64(secs.max(0.0)).unwrap_or(Duration::MAX))),
+            (None, Some(packets)) => Some(Deadline::Packets(packets)),
+            (None, None) => None,
+        }
+    }
+}
+
+// Wraps a raptorq decoder and remembers which symbols arrived, so a failed
+// decode can say exactly what is missing.
+pub struct DecodeSession {
+    config: ObjectTransmissionInformation,
+    decoder: Decoder,
+    received: HashSet<(u8, u32)>,
+    consumed: Vec<(u8, u32)>,
+    redundant: Vec<(u8, u32)>,
+    packets_used: usize,
+    result: Option<Vec<u8>>,
+    deadline: Option<Deadline>,
+    started: Option<Instant>,           // When the first packet arrived
+}
+
+impl DecodeSession {
+    pub fn new(config: ObjectTransmissionInformation) -> Self {
+        DecodeSession {
+            config,
+            decoder: Decoder::new(config),
+            received: HashSet::new(),
+            consumed: Vec::new(),
+            redundant: Vec::new(),
+            packets_used: 0,
+            result: None,
+            deadline: None,
+            started: None,
+        }
+    }
+
+    pub fn with_deadline(mut self, deadline: Option<Deadline>) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    // Whether the deadline passed with the object still undecoded. The session
+    // keeps taking packets regardless; finish() is the partial report. Both
+    // kinds count from the first packet, so a session that has none never
+    // expires, not even with --deadline-packets 0.
+    pub fn expired(&self) -> bool {
+        let Some(started) = self.started.filter(|_| self.result.is_none()) else { return false };
+        match self.deadline {
+            None => false,
+            Some(Deadline::After(limit)) => started.elapsed() >= limit,
+            Some(Deadline::Packets(limit)) => self.packets_used >= limit,
+        }
+    }
+
+    // Returns true once the object has been reconstructed
+    pub fn push(&mut self, packet: EncodingPacket) -> bool {
+        let id = packet.payload_id();
+        let key = (id.source_block_number(), id.encoding_symbol_id());
+        if self.result.is_some() {
+            self.redundant.push(key);
+            return true;
+        }
+        self.packets_used += 1;
+        self.started.get_or_insert_with(Instant::now);
+        self.received.insert(key);
+        self.consumed.push(key);
+        self.result = self.decoder.decode(packet);
+        self.result.is_some()
+    }
+
+    pub fn config(&self) -> ObjectTransmissionInformation {
+        self.config
+    }
+
+    pub fn result(&self) -> Option<&[u8]> {
+        self.result.as_deref()
+    }
+
+    // Source symbols not yet received, in object order.
+    // Assumes a single sub-block, which holds for objects of this toy's size.
+    pub fn missing_symbols(&self) -> Vec<MissingSymbol> {
+        if self.result.is_some() {
+            return Vec::new();
+        }
+        let symbol_size = self.config.symbol_size() as usize;
+        let transfer_length = self.config.transfer_length() as usize;
+        let mut missing = Vec::new();
+        let mut offset = 0;
+
+        for (sbn, &symbols) in source_block_symbols(&self.config).iter().enumerate() {
+            for esi in 0..symbols {
+                let start = offset + esi as usize * symbol_size;
+                if start < transfer_length && !self.received.contains(&(sbn as u8, esi)) {
+                    missing.push(MissingSymbol {
+                        source_block: sbn as u8,
+                        esi,
+                        byte_range: start..(start + symbol_size).min(transfer_length),
+                    });
+                }
+            }
+            offset += symbols as usize * symbol_size;
+        }
+        missing
+    }
+
+    pub fn finish(self) -> DecodeOutcome {
+        let missing = self.missing_symbols();
+        let block_symbols = source_block_symbols(&self.config);
+        let classify = |&(source_block, esi): &(u8, u32)| PacketRef {
+            source_block,
+            esi,
+            repair: block_symbols.get(source_block as usize).is_none_or(|&k| esi >= k),
+        };
+        DecodeOutcome {
+            data: self.result,
+            packets_used: self.packets_used,
+            source_symbols: source_symbol_count(&self.config),
+            missing,
+            consumed: self.consumed.iter().map(classify).collect(),
+            redundant: self.redundant.iter().map(classify).collect(),
+        }
+    }
+}
+
+pub fn decode_packets(config: ObjectTransmissionInformation, packets: Vec<EncodingPacket>) -> DecodeOutcome {
+    let mut session = DecodeSession::new(config);
+    for packet in packets {
+        session.push(packet);       // Packets after success are only counted as redundant
+    }
+    session.finish()
+}
+
+// Reception overhead aggregated across many decode runs
+#[derive(Debug, Default)]
+pub struct OverheadStats {
+    runs: usize,
+    failures: usize,
+    total_overhead: usize,
+    max_overhead: usize,
+    histogram: Vec<usize>,              // histogram[n] = runs that needed exactly n extra packets
+}
+
+impl OverheadStats {
+    pub fn record(&mut self, outcome: &DecodeOutcome) {
+        self.runs += 1;
+        match outcome.overhead() {
+            Some(extra) => {
+                self.total_overhead += extra;
+                self.max_overhead = self.max_overhead.max(extra);
+                if self.histogram.len() <= extra {
+                    self.histogram.resize(extra + 1, 0);
+                }
+                self.histogram[extra] += 1;
+            }
+            None => self.failures += 1,
+        }
+    }
+
+    pub fn failures(&self) -> usize {
+        self.failures
+    }
+
+    pub fn runs(&self) -> usize {
+        self.runs
+    }
+
+    pub fn histogram(&self) -> &[usize] {
+        &self.histogram
+    }
+
+    pub fn max_overhead(&self) -> usize {
+        self.max_overhead
+    }
+
+    pub fn successes(&self) -> usize {
+        self.runs - self.failures
+    }
+
+    pub fn mean_overhead(&self) -> f64 {
+        if self.successes() == 0 {
+            return 0.0;
+        }
+        self.total_overhead as f64 / self.successes() as f64
+    }
+
+    pub fn print_summary(&self) {
+        println!("=== Decoder overhead over {} runs ===", self.runs);
+        println!(
+            "Successes: {} | Failures: {} | Mean overhead: {:.3} packets | Max overhead: {}",
+            self.successes(),
+            self.failures,
+            self.mean_overhead(),
+            self.max_overhead
+        );
+        for (extra, &count) in self.histogram.iter().enumerate() {
+            if count > 0 {
+                println!("  +{:<2} packets: {} runs", extra, count);
+            }
+        }
+        println!("=====================================\n");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use raptorq::Encoder;
+
+    use super::*;
+
+    // 1024 bytes in 128 byte symbols: eight source packets (ESIs 0-7), then repair
+    fn packets(repair: u32) -> (ObjectTransmissionInformation, Vec<EncodingPacket>) {
+        let data: Vec<u8> = (0..1024u32).map(|i| (i * 7 % 256) as u8).collect();
+        let encoder = Encoder::with_defaults(&data, 128);
+        (encoder.get_config(), encoder.get_encoded_packets(repair))
+    }
+
+    #[test]
+    fn insufficient_symbols_counts_what_actually_arrived() {
+        let (config, packets) = packets(4);
+        assert_eq!(source_symbol_count(&config), 8);
+
+        // Lose source ESIs 0-2 and three of the four repair packets: five source and one repair get through
+        let kept: Vec<EncodingPacket> = packets[3..9].to_vec();
+        let error = decode_packets(config, kept).result().unwrap_err();
+        assert_eq!(error, DecodeError::InsufficientSymbols { received: 6, estimated_needed: 8, loss_observed: 0.375 });
+        // Two symbols short at 37.5% loss: ceil(2 / 0.625) more repair packets
+        assert_eq!(error.more_repair_needed(), Some(4));
+
+        // A repeated packet is one symbol, however often it arrives
+        let repeated = vec![packets[3].clone(), packets[3].clone(), packets[8].clone()];
+        let error = decode_packets(config, repeated).result().unwrap_err();
+        assert_eq!(error, DecodeError::InsufficientSymbols { received: 2, estimated_needed: 8, loss_observed: 0.875 });
+    }
+
+    #[test]
+    fn nothing_received_reports_total_loss() {
+        let (config, _) = packets(0);
+        let error = decode_packets(config, Vec::new()).result().unwrap_err();
+        assert_eq!(error, DecodeError::InsufficientSymbols { received: 0, estimated_needed: 8, loss_observed: 1.0 });
+        assert_eq!(error.more_repair_needed(), None);
+        assert!(error.to_string().contains("no amount of repair"), "{}", error);
+    }
+
+    #[test]
+    fn packet_deadlines_count_from_the_first_packet() {
+        let (config, packets) = packets(4);
+        assert_eq!(DeadlineArgs { deadline: None, deadline_packets: Some(0) }.deadline(), Some(Deadline::Packets(0)));
+
+        // --deadline-packets 0: nothing to give up on yet, then the first packet is the last
+        let mut session = DecodeSession::new(config).with_deadline(Some(Deadline::Packets(0)));
+        assert!(!session.expired());
+        session.push(packets[0].clone());
+        assert!(session.expired());
+
+        let mut session = DecodeSession::new(config).with_deadline(Some(Deadline::Packets(3)));
+        for packet in &packets[..2] {
+            session.push(packet.clone());
+            assert!(!session.expired());
+        }
+        session.push(packets[2].clone());
+        assert!(session.expired());
+
+        // The partial report: what arrived, and exactly which source symbols did not
+        let outcome = session.finish();
+        assert_eq!(outcome.packets_used, 3);
+        assert_eq!(outcome.missing.iter().map(|m| m.esi).collect::<Vec<_>>(), vec![3, 4, 5, 6, 7]);
+        assert_eq!(outcome.missing[0].byte_range, 384..512);
+        assert_eq!(outcome.result().unwrap_err(), DecodeError::InsufficientSymbols { received: 3, estimated_needed: 8, loss_observed: 0.625 });
+    }
+
+    #[test]
+    fn wall_clock_deadlines_count_from_the_first_packet() {
+        let (config, packets) = packets(4);
+        let mut session = DecodeSession::new(config).with_deadline(Some(Deadline::After(Duration::from_millis(20))));
+        std::thread::sleep(Duration::from_millis(40));
+        assert!(!session.expired(), "the clock started before any packet");
+        session.push(packets[0].clone());
+        assert!(!session.expired());
+        std::thread::sleep(Duration::from_millis(40));
+        assert!(session.expired());
+        assert_eq!(session.finish().missing.len(), 7);
+    }
+
+    #[test]
+    fn decoded_sessions_never_expire() {
+        let (config, packets) = packets(4);
+        let mut session = DecodeSession::new(config).with_deadline(Some(Deadline::Packets(8)));
+        let decoded = packets.into_iter().any(|p| session.push(p));
+        assert!(decoded && !session.expired());
+    }
+}
@@ -0,0 +1,660 @@
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fs;
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use clap::{Args, ValueEnum};
+use raptorq::{Encoder, EncodingPacket, ObjectTransmissionInformation};
+use rand::rngs::OsRng;
+use rand::Rng;
+
+use crate::channel::LossModel;
+use crate::rng::{self, SimRng, SimStream};
+use crate::erasure::CodeKind;
+use crate::failure::Failure;
+use crate::fec::{self, SymbolArgs};
+use crate::frame::{self, FrameHeader};
+use crate::handshake::{self, Capabilities, Hello, Reply};
+use crate::manager::{DecodeManager, Event, ObjectKey};
+use crate::pacing::{PacingArgs, TokenBucket};
+use crate::transfer::{self, InputArgs};
+use crate::PAYLOAD_ID_LEN;
+
+const MAGIC: [u8; 2] = *b"TA";
+const VERSION: u8 = 1;
+const MESSAGE_LEN: usize = 2 + 1 + 1 + 4 + 4 + 1 + 4 + 4;
+const TICK: Duration = Duration::from_millis(1); // How long the sender waits for feedback between scheduling decisions
+const HELLO_TRIES: usize = 4;
+const HELLO_WAIT: Duration = Duration::from_millis(250);
+
+// Every feedback message: "TA" | version | kind | session | object | block | two u32 fields.
+// Kinds are 1 ack, 2 done and 3 NACK.
+fn message(kind: u8, session: u32, object: u32, block: u8, first: u32, second: u32) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(MESSAGE_LEN);
+    bytes.extend_from_slice(&MAGIC);
+    bytes.push(VERSION);
+    bytes.push(kind);
+    bytes.extend_from_slice(&session.to_be_bytes());
+    bytes.extend_from_slice(&object.to_be_bytes());
+    bytes.push(block);
+    bytes.extend_from_slice(&first.to_be_bytes());
+    bytes.extend_from_slice(&second.to_be_bytes());
+    bytes
+}
+
+// The kind and fields of a well-formed message
+fn parse(bytes: &[u8]) -> Option<(u8, u32, u32, u8, u32, u32)> {
+    if bytes.len() != MESSAGE_LEN || bytes[..2] != MAGIC || bytes[2] != VERSION {
+        return None;
+    }
+    let u32_at = |at: usize| u32::from_be_bytes(bytes[at..at + 4].try_into().unwrap());
+    Some((bytes[3], u32_at(4), u32_at(8), bytes[12], u32_at(13), u32_at(17)))
+}
+
+// Receiver to sender, once per packet that arrives: which packet it was, so
+// the sender can time the round trip, and how many the object has had
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ack {
+    pub session: u32,
+    pub object: u32,
+    pub block: u8,                      // Payload ID of the packet being acknowledged
+    pub esi: u32,
+    pub received: u32,                  // Packets the object has had so far
+    pub done: bool,                     // The object decoded; stop sending
+}
+
+impl Ack {
+    pub fn to_bytes(self) -> Vec<u8> {
+        message(if self.done { 2 } else { 1 }, self.session, self.object, self.block, self.esi, self.received)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        match parse(bytes)? {
+            (kind @ (1 | 2), session, object, block, esi, received) => Some(Ack { session, object, block, esi, received, done: kind == 2 }),
+            _ => None,
+        }
+    }
+}
+
+// Receiver to sender when an object goes quiet short of decoding: how many
+// more symbols one source block needs, going by the distinct ones it holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Nack {
+    pub session: u32,
+    pub object: u32,
+    pub block: u8,
+    pub needed: u32,
+    pub received: u32,                  // Distinct symbols the block holds
+}
+
+impl Nack {
+    pub fn to_bytes(self) -> Vec<u8> {
+        message(3, self.session, self.object, self.block, self.needed, self.received)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        match parse(bytes)? {
+            (3, session, object, block, needed, received) => Some(Nack { session, object, block, needed, received }),
+            _ => None,
+        }
+    }
+}
+
+// Feedback as the sender hears it
+enum Heard {
+    Ack(Ack),
+    Nack(Nack),
+}
+
+// Smoothed RTT and its variation, as TCP keeps them (RFC 6298)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RttEstimator {
+    pub srtt: Option<Duration>,
+    pub rttvar: Duration,
+    pub samples: usize,
+}
+
+impl RttEstimator {
+    pub fn sample(&mut self, rtt: Duration) {
+        self.samples += 1;
+        match self.srtt {
+            None => {
+                self.srtt = Some(rtt);
+                self.rttvar = rtt / 2;
+            }
+            Some(srtt) => {
+                self.rttvar = (self.rttvar * 3 + srtt.abs_diff(rtt)) / 4;
+                self.srtt = Some((srtt * 7 + rtt) / 8);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Schedule {
+    /// A fixed batch up front, then the same batch again every --retry-ms until the receiver is done
+    Naive,
+    /// A batch for the expected loss up front, then once per measured RTT whatever the feedback says is still missing
+    Rtt,
+    /// A batch for the expected loss up front, then exactly the repair the receiver's NACKs ask for
+    Nack,
+}
+
+#[derive(Args, Debug)]
+pub struct FeedbackSendArgs {
+    #[command(flatten)]
+    pub input: InputArgs,
+
+    /// Receiver to send to
+    #[arg(long, default_value = "127.0.0.1:7037")]
+    pub to: SocketAddr,
+
+    /// When repair goes out beyond the source symbols
+    #[arg(long, value_enum, default_value_t = Schedule::Rtt)]
+    pub schedule: Schedule,
+
+    /// Run every schedule one after the other against the same receiver and compare them
+    #[arg(long)]
+    pub compare: bool,
+
+    /// Naive schedule: repair packets per batch
+    #[arg(long, default_value_t = 8)]
+    pub repair: u32,
+
+    /// Naive schedule: time between batches
+    #[arg(long, default_value_t = 250)]
+    pub retry_ms: u64,
+
+    /// RTT schedule: loss assumed for the first batch, before any feedback
+    #[arg(long, default_value_t = 0.05)]
+    pub expect_loss: f64,
+
+    /// Chance each datagram is dropped before it leaves, to make loopback lossy
+    #[arg(long, default_value_t = 0.0)]
+    pub loss: f64,
+
+    /// Delay added to every ACK's way back, to stand in for a longer path
+    #[arg(long, default_value_t = 0)]
+    pub ack_delay_ms: u64,
+
+    /// Give up after this many seconds without the receiver finishing
+    #[arg(long, default_value_t = 10)]
+    pub timeout: u64,
+
+    #[command(flatten)]
+    pub symbols: SymbolArgs,
+
+    #[command(flatten)]
+    pub pacing: PacingArgs,
+
+    /// Send frames straight away without negotiating, for receivers that predate the handshake
+    #[arg(long)]
+    pub no_handshake: bool,
+}
+
+#[derive(Args, Debug)]
+pub struct FeedbackRecvArgs {
+    /// Address to receive on
+    #[arg(long, default_value = "127.0.0.1:7037")]
+    pub listen: SocketAddr,
+
+    /// Objects to decode before exiting
+    #[arg(long, default_value_t = 1)]
+    pub count: usize,
+
+    /// Give up after this many seconds without a datagram
+    #[arg(long, default_value_t = 30)]
+    pub timeout: u64,
+
+    /// Write each recovered object here as s<session>-o<object>.bin
+    #[arg(long)]
+    pub out_dir: Option<PathBuf>,
+
+    /// NACK an undecoded object once no packet for it has arrived for this long, and again each time as long again passes (0 never NACKs)
+    #[arg(long, default_value_t = 50)]
+    pub nack_ms: u64,
+
+    /// Refuse senders whose symbols are larger than this at the handshake
+    #[arg(long, default_value_t = u16::MAX)]
+    pub max_symbol_size: u16,
+}
+
+fn io_failure(what: String) -> impl FnOnce(io::Error) -> Failure {
+    move |e| Failure::Io(format!("{}: {}", what, e))
+}
+
+fn timed_out(e: &io::Error) -> bool {
+    matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut)
+}
+
+// What one run of a schedule achieved
+struct Delivery {
+    schedule: Schedule,
+    recovered_after: Duration,
+    sent: usize,
+    dropped: usize,                     // Sent but dropped by --loss before leaving
+    repair: usize,
+    bursts: usize,                      // Repair batches after the first, NACK answers included
+    rtt: RttEstimator,
+    loss: f64,                          // Loss as the feedback showed it
+}
+
+// One object's packets and the state of sending them
+struct Sender<'a> {
+    socket: &'a UdpSocket,
+    header: FrameHeader,
+    encoder: &'a Encoder,
+    next_repair: Vec<u32>,              // Next unsent repair symbol, per source block
+    sent: HashMap<(u8, u32), (usize, Instant)>, // Send order and time per payload ID
+    count: usize,
+    dropped: usize,
+    repair: usize,
+    channel: LossModel,
+    losses: SimStream,                  // The channel's own stream
+    pacer: Option<TokenBucket>,
+}
+
+impl Sender<'_> {
+    fn transmit(&mut self, packet: &EncodingPacket) -> Result<(), Failure> {
+        let id = packet.payload_id();
+        self.sent.insert((id.source_block_number(), id.encoding_symbol_id()), (self.count, Instant::now()));
+        self.count += 1;
+        if self.channel.transmit(vec![()], &mut self.losses).is_empty() {
+            self.dropped += 1;
+            return Ok(());
+        }
+        let frame = frame::encode_frame(&self.header, packet);
+        if let Some(pacer) = &mut self.pacer {
+            pacer.wait(frame::UDP_IPV4_LEN + frame.len());
+        }
+        self.socket.send(&frame).map(drop).map_err(io_failure("cannot send".into()))
+    }
+
+    // `count` fresh repair packets, spread over the source blocks in turn
+    fn send_repair(&mut self, count: usize) -> Result<(), Failure> {
+        let blocks = self.next_repair.len();
+        for i in 0..count {
+            self.send_block_repair(i % blocks, 1)?;
+        }
+        Ok(())
+    }
+
+    fn send_block_repair(&mut self, block: usize, count: u32) -> Result<(), Failure> {
+        let packets = self.encoder.get_block_encoders()[block].repair_packets(self.next_repair[block], count);
+        self.next_repair[block] += count;
+        self.repair += count as usize;
+        for packet in &packets {
+            self.transmit(packet)?;
+        }
+        Ok(())
+    }
+}
+
+// Offer the receiver this session and wait for it to agree. Replies to
+// another session are stale ones from an earlier run and are skipped.
+fn handshake(socket: &UdpSocket, to: SocketAddr, session: u32, symbol_size: u16) -> Result<u8, Failure> {
+    let hello = Hello { session, versions: handshake::VERSIONS, codes: vec![CodeKind::Raptorq], symbol_size };
+    let mut buf = [0u8; 64];
+    for _ in 0..HELLO_TRIES {
+        socket.send(&hello.to_bytes()).map_err(io_failure(format!("cannot greet {}", to)))?;
+        let asked = Instant::now();
+        while asked.elapsed() < HELLO_WAIT {
+            let len = match socket.recv(&mut buf) {
+                Ok(len) => len,
+                Err(e) if timed_out(&e) => continue,
+                Err(e) if e.kind() == io::ErrorKind::ConnectionRefused => return Err(Failure::Io(format!("nothing is listening on {}", to))),
+                Err(e) => return Err(io_failure("cannot receive the handshake".into())(e)),
+            };
+            match Reply::from_bytes(&buf[..len]).filter(|r| r.session() == session) {
+                Some(Reply::Accept { version, code: CodeKind::Raptorq, symbol_size: agreed, .. }) if agreed == symbol_size => return Ok(version),
+                Some(Reply::Accept { code, symbol_size: agreed, .. }) => {
+                    return Err(Failure::Config(format!("{} agreed to {:?} with {} byte symbols, not what was offered", to, code, agreed)))
+                }
+                Some(Reply::Reject { reason, .. }) => return Err(Failure::Config(format!("{} refused the session: {}", to, reason.describe()))),
+                None => {}
+            }
+        }
+    }
+    Err(Failure::Io(format!("no handshake reply from {} after {} tries (--no-handshake for an older receiver)", to, HELLO_TRIES)))
+}
+
+fn deliver(args: &FeedbackSendArgs, schedule: Schedule, data: &[u8], socket: &UdpSocket) -> Result<Delivery, Failure> {
+    let symbols = args.symbols.choose(data.len(), frame::UDP_IPV4_LEN + frame::HEADER_LEN + PAYLOAD_ID_LEN);
+    let encoder = Encoder::with_defaults(data, symbols.size);
+    let header = FrameHeader { session: OsRng.r#gen(), object: 0, config: encoder.get_config() };
+    let source: usize = encoder.get_block_encoders().iter().map(|b| b.source_packets().len()).sum();
+    if !args.no_handshake {
+        handshake(socket, args.to, header.session, symbols.size)?;
+    }
+    let mut sender = Sender {
+        socket,
+        header,
+        encoder: &encoder,
+        next_repair: vec![0; encoder.get_block_encoders().len()],
+        sent: HashMap::new(),
+        count: 0,
+        dropped: 0,
+        repair: 0,
+        channel: LossModel::Uniform { rate: args.loss.clamp(0.0, 1.0) },
+        losses: SimStream::unseeded().fork(rng::CHANNEL),
+        pacer: args.pacing.bucket(),
+    };
+    let mut rtt = RttEstimator::default();
+    let mut incoming: VecDeque<(Instant, Heard)> = VecDeque::new(); // Feedback held back by --ack-delay-ms
+    let mut answered: Vec<Option<Instant>> = vec![None; sender.next_repair.len()]; // Last NACK answer per source block
+    let ack_delay = Duration::from_millis(args.ack_delay_ms);
+    let retry = Duration::from_millis(args.retry_ms);
+    let mut buf = [0u8; 64];
+
+    let started = Instant::now();
+    for block in encoder.get_block_encoders() {
+        for packet in block.source_packets() {
+            sender.transmit(&packet)?;
+        }
+    }
+    let upfront = match schedule {
+        Schedule::Naive => args.repair as usize,
+        Schedule::Rtt | Schedule::Nack => {
+            let p = args.expect_loss.clamp(0.0, 0.9);
+            (source as f64 * p / (1.0 - p)).ceil() as usize
+        }
+    };
+    sender.send_repair(upfront)?;
+
+    let (mut received, mut latest) = (0usize, None::<usize>);
+    let mut last_burst = Instant::now();
+    let mut bursts = 0;
+    loop {
+        if started.elapsed() > Duration::from_secs(args.timeout) {
+            return Err(Failure::Decode(format!("{:?} schedule: receiver not done after {}s", schedule, args.timeout)));
+        }
+        match socket.recv(&mut buf) {
+            Ok(len) => {
+                let heard = match (Ack::from_bytes(&buf[..len]), Nack::from_bytes(&buf[..len])) {
+                    (Some(ack), _) if ack.session == header.session => Heard::Ack(ack),
+                    (_, Some(nack)) if nack.session == header.session => Heard::Nack(nack),
+                    _ => continue,
+                };
+                incoming.push_back((Instant::now() + ack_delay, heard));
+            }
+            Err(e) if timed_out(&e) => {}
+            Err(e) => return Err(io_failure("cannot receive feedback".into())(e)),
+        }
+
+        let now = Instant::now();
+        while incoming.front().is_some_and(|(due, _)| *due <= now) {
+            let ack = match incoming.pop_front().unwrap().1 {
+                Heard::Ack(ack) => ack,
+                // A NACK repeats every quiet interval until its answer lands, so
+                // answer each block at most once per RTT
+                Heard::Nack(nack) => {
+                    let block = nack.block as usize;
+                    let holdoff = rtt.srtt.unwrap_or(retry);
+                    if schedule == Schedule::Nack && block < answered.len() && answered[block].is_none_or(|t| now - t >= holdoff) {
+                        answered[block] = Some(now);
+                        bursts += 1;
+                        sender.send_block_repair(block, nack.needed)?;
+                    }
+                    continue;
+                }
+            };
+            if ack.done {
+                let loss = latest.map_or(0.0, |l| 1.0 - received as f64 / (l + 1) as f64);
+                return Ok(Delivery {
+                    schedule,
+                    recovered_after: now - started,
+                    sent: sender.count,
+                    dropped: sender.dropped,
+                    repair: sender.repair,
+                    bursts,
+                    rtt,
+                    loss,
+                });
+            }
+            if let Some(&(order, sent_at)) = sender.sent.get(&(ack.block, ack.esi)) {
+                rtt.sample(now - sent_at);
+                latest = Some(latest.map_or(order, |l| l.max(order)));
+            }
+            received = received.max(ack.received as usize);
+        }
+
+        let burst = match (schedule, rtt.srtt, latest) {
+            (Schedule::Naive, _, _) if now - last_burst >= retry => args.repair as usize,
+            // Once per RTT: what is still missing, less what is already on its way,
+            // grossed up for the loss the feedback shows so far
+            (Schedule::Rtt, Some(srtt), Some(latest)) if now - last_burst >= srtt => {
+                let p = (1.0 - received as f64 / (latest + 1) as f64).clamp(0.0, 0.9);
+                let in_flight = (sender.count - latest - 1) as f64 * (1.0 - p);
+                let missing = source as f64 - received as f64 - in_flight;
+                if missing > 0.0 { (missing / (1.0 - p)).ceil() as usize + 1 } else { 0 }
+            }
+            // No feedback at all yet: fall back to the naive timer
+            (Schedule::Rtt, None, _) if now - last_burst >= retry => upfront.max(1),
+            _ => 0,
+        };
+        if burst > 0 || (schedule == Schedule::Rtt && rtt.srtt.is_some_and(|s| now - last_burst >= s)) {
+            last_burst = now;
+        }
+        if burst > 0 {
+            bursts += 1;
+            sender.send_repair(burst)?;
+        }
+    }
+}
+
+// Send one object to a receiver that acknowledges every packet, scheduling
+// repair from its feedback; with --compare, race every schedule
+pub fn run_send(args: &FeedbackSendArgs) -> Result<(), Failure> {
+    let data = transfer::read_input(&args.input, None)?;
+    if data.is_empty() {
+        return Err(Failure::Config("nothing to send: the input is empty".into()));
+    }
+    let socket = UdpSocket::bind(if args.to.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" })
+        .and_then(|s| s.connect(args.to).map(|_| s))
+        .map_err(io_failure(format!("cannot open a UDP socket to {}", args.to)))?;
+    socket.set_read_timeout(Some(TICK)).map_err(io_failure("cannot set a timeout".into()))?;
+
+    let schedules = if args.compare { vec![Schedule::Naive, Schedule::Rtt, Schedule::Nack] } else { vec![args.schedule] };
+    println!(
+        "=== Sending {} bytes to {} ({:.0}% loss, {} ms added to each ACK, {}) ===",
+        data.len(),
+        args.to,
+        args.loss * 100.0,
+        args.ack_delay_ms,
+        args.pacing.describe()
+    );
+    println!("{:<8} {:>10} {:>6} {:>7} {:>7} {:>7} {:>9} {:>9}", "schedule", "recovered", "sent", "dropped", "repair", "bursts", "srtt", "seen loss");
+    let mut recovered = Vec::new();
+    for schedule in schedules {
+        let d = deliver(args, schedule, &data, &socket)?;
+        recovered.push((schedule, d.recovered_after));
+        println!(
+            "{:<8} {:>8.1}ms {:>6} {:>7} {:>7} {:>7} {:>9} {:>8.1}%",
+            format!("{:?}", d.schedule).to_lowercase(),
+            d.recovered_after.as_secs_f64() * 1000.0,
+            d.sent,
+            d.dropped,
+            d.repair,
+            d.bursts,
+            d.rtt.srtt.map_or("-".to_string(), |s| format!("{:.1}ms", s.as_secs_f64() * 1000.0)),
+            d.loss * 100.0
+        );
+    }
+    if let [(Schedule::Naive, naive), rest @ ..] = &recovered[..] {
+        for (schedule, time) in rest {
+            let name = format!("{:?}", schedule).to_lowercase();
+            println!("The {} schedule recovered in {:.0}% of the naive schedule's time", name, time.as_secs_f64() * 100.0 / naive.as_secs_f64().max(1e-9));
+        }
+    }
+    Ok(())
+}
+
+// An object the receiver is still decoding, as far as NACKs need to know
+struct Short {
+    from: SocketAddr,
+    config: ObjectTransmissionInformation,
+    esis: BTreeMap<u8, HashSet<u32>>,   // Distinct symbols held, per source block
+    last_frame: Instant,
+    last_nack: Option<Instant>,
+    nacks: usize,
+}
+
+// (block, needed, held) for each source block short of its source symbol
+// count, given the distinct symbols held per block. A block holding that many
+// has almost surely decoded, so if every block does and the object still has
+// not, each asks for one more.
+pub fn block_needs(config: &ObjectTransmissionInformation, esis: &BTreeMap<u8, HashSet<u32>>) -> Vec<(u8, u32, u32)> {
+    let held: Vec<(u8, u32, u32)> = fec::source_block_symbols(config)
+        .iter()
+        .enumerate()
+        .map(|(b, &k)| (b as u8, k, esis.get(&(b as u8)).map_or(0, |e| e.len() as u32)))
+        .collect();
+    let short: Vec<(u8, u32, u32)> = held.iter().filter(|&&(_, k, h)| h < k).map(|&(b, k, h)| (b, k - h, h)).collect();
+    if short.is_empty() { held.iter().map(|&(b, _, h)| (b, 1, h)).collect() } else { short }
+}
+
+// NACK every object that has gone quiet short of decoding, at most once per quiet interval
+fn nack_quiet(socket: &UdpSocket, short: &mut HashMap<ObjectKey, Short>, quiet: Duration) -> Result<(), Failure> {
+    let now = Instant::now();
+    for (&(session, object), s) in short.iter_mut() {
+        if now - s.last_frame < quiet || s.last_nack.is_some_and(|t| now - t < quiet) {
+            continue;
+        }
+        for (block, needed, received) in block_needs(&s.config, &s.esis) {
+            let nack = Nack { session, object, block, needed, received };
+            socket.send_to(&nack.to_bytes(), s.from).map_err(io_failure(format!("cannot NACK to {}", s.from)))?;
+        }
+        s.last_nack = Some(now);
+        s.nacks += 1;
+    }
+    Ok(())
+}
+
+// Decode whatever arrives and acknowledge every packet; objects already done
+// get a done ACK for each straggler, in case the first one was lost. An object
+// that goes quiet before decoding is NACKed with what each block still needs.
+pub fn run_recv(args: &FeedbackRecvArgs) -> Result<(), Failure> {
+    let socket = UdpSocket::bind(args.listen).map_err(io_failure(format!("cannot listen on {}", args.listen)))?;
+    let timeout = Duration::from_secs(args.timeout.max(1));
+    let quiet = Duration::from_millis(args.nack_ms);
+    let tick = if quiet.is_zero() { timeout } else { quiet.min(timeout) };
+    socket.set_read_timeout(Some(tick)).map_err(io_failure("cannot set a timeout".into()))?;
+    println!("=== Receiving on {} ===", args.listen);
+
+    let capabilities = Capabilities { versions: handshake::VERSIONS, codes: vec![CodeKind::Raptorq], max_symbol_size: args.max_symbol_size };
+    let mut refused: HashSet<u32> = HashSet::new();
+    let mut manager = DecodeManager::new(None, 16, None);
+    let mut received: HashMap<ObjectKey, u32> = HashMap::new();
+    let mut short: HashMap<ObjectKey, Short> = HashMap::new();
+    let mut done: HashSet<ObjectKey> = HashSet::new();
+    let mut buf = vec![0; 65_536];
+    let mut heard_at = Instant::now();
+    while done.len() < args.count {
+        let (len, from) = match socket.recv_from(&mut buf) {
+            Ok(r) => r,
+            Err(e) if timed_out(&e) && heard_at.elapsed() < timeout => {
+                if !quiet.is_zero() {
+                    nack_quiet(&socket, &mut short, quiet)?;
+                }
+                continue;
+            }
+            Err(e) if timed_out(&e) => {
+                return Err(Failure::Decode(format!("nothing heard for {}s with {} of {} objects decoded", args.timeout, done.len(), args.count)))
+            }
+            Err(e) => return Err(io_failure("cannot receive".into())(e)),
+        };
+        heard_at = Instant::now();
+        if let Some(hello) = Hello::from_bytes(&buf[..len]) {
+            let reply = capabilities.negotiate(&hello);
+            match reply {
+                Reply::Accept { version, code, symbol_size, .. } => {
+                    println!("  s{} from {}: protocol v{}, {}, {} byte symbols", hello.session, from, version, format!("{:?}", code).to_lowercase(), symbol_size)
+                }
+                Reply::Reject { reason, .. } => {
+                    println!("  s{} from {}: refused, {}", hello.session, from, reason.describe());
+                    refused.insert(hello.session);
+                }
+            }
+            socket.send_to(&reply.to_bytes(), from).map_err(io_failure(format!("cannot answer {}", from)))?;
+            continue;
+        }
+        let Some((header, packet)) = frame::decode_frame(&buf[..len]) else { continue };
+        if refused.contains(&header.session) {
+            continue;
+        }
+        let key = header.key();
+        let id = packet.payload_id();
+        let count = received.entry(key).or_default();
+        *count += 1;
+        let mut ack = Ack {
+            session: key.0,
+            object: key.1,
+            block: id.source_block_number(),
+            esi: id.encoding_symbol_id(),
+            received: *count,
+            done: done.contains(&key),
+        };
+        if !ack.done {
+            let s = short.entry(key).or_insert_with(|| Short {
+                from,
+                config: header.config,
+                esis: BTreeMap::new(),
+                last_frame: heard_at,
+                last_nack: None,
+                nacks: 0,
+            });
+            s.from = from;
+            s.last_frame = heard_at;
+            s.esis.entry(id.source_block_number()).or_default().insert(id.encoding_symbol_id());
+        }
+        for event in manager.push_frame(&buf[..len]) {
+            if let Event::Completed { key, outcome } = event {
+                let data = outcome.data.expect("completed objects carry their data");
+                let nacks = short.remove(&key).map_or(0, |s| s.nacks);
+                println!("  s{}/o{}: {} bytes after {} packets and {} NACKs", key.0, key.1, data.len(), outcome.packets_used, nacks);
+                if let Some(dir) = &args.out_dir {
+                    let path = dir.join(format!("s{}-o{}.bin", key.0, key.1));
+                    fs::create_dir_all(dir)
+                        .and_then(|_| fs::write(&path, &data))
+                        .map_err(io_failure(format!("cannot write {}", path.display())))?;
+                }
+                done.insert(key);
+                ack.done = ack.done || key == header.key();
+            }
+        }
+        socket.send_to(&ack.to_bytes(), from).map_err(io_failure(format!("cannot acknowledge to {}", from)))?;
+        if !quiet.is_zero() {
+            nack_quiet(&socket, &mut short, quiet)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_sample_sets_the_estimate() {
+        let mut rtt = RttEstimator::default();
+        rtt.sample(Duration::from_millis(80));
+        assert_eq!(rtt.srtt, Some(Duration::from_millis(80)));
+        assert_eq!(rtt.rttvar, Duration::from_millis(40));
+    }
+
+    #[test]
+    fn later_samples_are_smoothed() {
+        let mut rtt = RttEstimator::default();
+        rtt.sample(Duration::from_millis(80));
+        rtt.sample(Duration::from_millis(160));
+        assert_eq!(rtt.srtt, Some(Duration::from_millis(90)));
+        assert_eq!(rtt.rttvar, Duration::from_millis(50));
+        for _ in 0..200 {
+            rtt.sample(Duration::from_millis(20));
+        }
+        let srtt = rtt.srtt.unwrap().as_secs_f64() * 1000.0;
+        assert!((srtt - 20.0).abs() < 0.01, "srtt {}ms", srtt);
+    }
+}
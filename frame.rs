@@ -0,0 +1,181 @@
+use std::io::{self, Read, Write};
+
+use raptorq::{Encoder, EncodingPacket, ObjectTransmissionInformation, SourceBlockEncoder};
+
+const MAGIC: [u8; 2] = *b"TF";
+const VERSION: u8 = 1;
+pub const HEADER_LEN: usize = 2 + 1 + 4 + 4 + 12;
+pub const UDP_IPV4_LEN: usize = 20 + 8;     // IPv4 and UDP headers in front of every datagram, counted against the MTU
+pub const MAX_OBJECT_LEN: u64 = 1 << 26;    // Bigger objects are refused rather than allocated for
+const MAX_BLOCK_SYMBOLS: u64 = 56403;       // K'max, the most source symbols RFC 6330 allows per block
+const MAX_REPAIR_ROUNDS: u32 = (1 << 24) - MAX_BLOCK_SYMBOLS as u32; // Repair ESIs start past K' and must fit 24 bits
+
+// Which object a packet belongs to. Every frame repeats the object config, so
+// a receiver can start decoding from whichever packet of an object it sees first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameHeader {
+    pub session: u32,                   // One sender's run; receivers drop sessions they did not ask for
+    pub object: u32,                    // Object within the session
+    pub config: ObjectTransmissionInformation,
+}
+
+impl FrameHeader {
+    pub fn key(&self) -> (u32, u32) {
+        (self.session, self.object)
+    }
+}
+
+// Frame: "TF" | version | session | object | OTI | serialized packet
+pub fn encode_frame(header: &FrameHeader, packet: &EncodingPacket) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(HEADER_LEN + 4 + packet.data().len());
+    bytes.extend_from_slice(&MAGIC);
+    bytes.push(VERSION);
+    bytes.extend_from_slice(&header.session.to_be_bytes());
+    bytes.extend_from_slice(&header.object.to_be_bytes());
+    bytes.extend_from_slice(&header.config.serialize());
+    bytes.extend_from_slice(&packet.serialize());
+    bytes
+}
+
+// A packet and the header of the object it belongs to, ready for the wire
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FramedPacket {
+    pub header: FrameHeader,
+    pub packet: EncodingPacket,
+}
+
+impl FramedPacket {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encode_frame(&self.header, &self.packet)
+    }
+}
+
+// An object's packets, made only as they are asked for: every source packet,
+// block by block, then repair packets taking turns across the source blocks
+// until the 24-bit ESI space runs out. A sender takes as many as its budget
+// allows, and taking more later continues with repair nobody has seen yet.
+pub struct Frames {
+    header: FrameHeader,
+    blocks: Vec<SourceBlockEncoder>,
+    source: std::vec::IntoIter<EncodingPacket>,
+    next_source_block: usize,
+    repair_round: u32,
+    repair_block: usize,
+}
+
+impl Frames {
+    pub fn new(encoder: &Encoder, session: u32, object: u32) -> Frames {
+        Frames {
+            header: FrameHeader { session, object, config: encoder.get_config() },
+            blocks: encoder.get_block_encoders().clone(),
+            source: Vec::new().into_iter(),
+            next_source_block: 0,
+            repair_round: 0,
+            repair_block: 0,
+        }
+    }
+
+    pub fn header(&self) -> FrameHeader {
+        self.header
+    }
+}
+
+impl Iterator for Frames {
+    type Item = FramedPacket;
+
+    fn next(&mut self) -> Option<FramedPacket> {
+        let packet = loop {
+            if let Some(packet) = self.source.next() {
+                break packet;
+            }
+            if let Some(block) = self.blocks.get(self.next_source_block) {
+                self.source = block.source_packets().into_iter();
+                self.next_source_block += 1;
+                continue;
+            }
+            if self.blocks.is_empty() || self.repair_round >= MAX_REPAIR_ROUNDS {
+                return None;
+            }
+            let packet = self.blocks[self.repair_block].repair_packets(self.repair_round, 1).pop()?;
+            self.repair_block += 1;
+            if self.repair_block == self.blocks.len() {
+                self.repair_block = 0;
+                self.repair_round += 1;
+            }
+            break packet;
+        };
+        Some(FramedPacket { header: self.header, packet })
+    }
+}
+
+// The same packets for async senders; each one is ready as soon as it is polled
+#[cfg(feature = "stream")]
+impl futures_core::Stream for Frames {
+    type Item = FramedPacket;
+
+    fn poll_next(self: std::pin::Pin<&mut Self>, _: &mut std::task::Context<'_>) -> std::task::Poll<Option<FramedPacket>> {
+        std::task::Poll::Ready(self.get_mut().next())
+    }
+}
+
+// Whether a decoder can be built for this config without tripping raptorq's
+// asserts or allocating for an absurd object; every field comes off the wire
+pub fn valid_config(config: &ObjectTransmissionInformation) -> bool {
+    let symbol_size = config.symbol_size() as u64;
+    let alignment = config.symbol_alignment() as u64;
+    let blocks = config.source_blocks() as u64;
+    if symbol_size == 0 || alignment == 0 || !symbol_size.is_multiple_of(alignment) || blocks == 0 {
+        return false;
+    }
+    let symbols = config.transfer_length().div_ceil(symbol_size);
+    (1..=MAX_OBJECT_LEN).contains(&config.transfer_length())
+        && blocks <= symbols
+        && symbols.div_ceil(blocks) <= MAX_BLOCK_SYMBOLS
+        && (1..=symbol_size / alignment).contains(&(config.sub_blocks() as u64))
+}
+
+// Anything that is not a well-formed frame comes back as None, never a panic:
+// the packet must also fit the config it claims to belong to
+pub fn decode_frame(bytes: &[u8]) -> Option<(FrameHeader, EncodingPacket)> {
+    if bytes.len() < HEADER_LEN + 4 || bytes[..2] != MAGIC || bytes[2] != VERSION {
+        return None;
+    }
+    let header = FrameHeader {
+        session: u32::from_be_bytes(bytes[3..7].try_into().ok()?),
+        object: u32::from_be_bytes(bytes[7..11].try_into().ok()?),
+        config: ObjectTransmissionInformation::deserialize(bytes[11..HEADER_LEN].try_into().ok()?),
+    };
+    let packet = EncodingPacket::deserialize(&bytes[HEADER_LEN..]);
+    if !valid_config(&header.config) || !fits(&header.config, &packet) {
+        return None;
+    }
+    Some((header, packet))
+}
+
+// Whether a packet is laid out for this config; the decoder indexes by both without checking
+pub fn fits(config: &ObjectTransmissionInformation, packet: &EncodingPacket) -> bool {
+    packet.data().len() == config.symbol_size() as usize && packet.payload_id().source_block_number() < config.source_blocks()
+}
+
+// Records on a byte stream, each behind a 4-byte big-endian length
+pub fn write_record<W: Write + ?Sized>(out: &mut W, record: &[u8]) -> io::Result<()> {
+    out.write_all(&(record.len() as u32).to_be_bytes())?;
+    out.write_all(record)
+}
+
+// Every complete record; a truncated tail is dropped like any other loss
+pub fn read_records<R: Read + ?Sized>(input: &mut R) -> io::Result<Vec<Vec<u8>>> {
+    let mut bytes = Vec::new();
+    input.read_to_end(&mut bytes)?;
+    let mut rest = &bytes[..];
+    let mut records = Vec::new();
+    while rest.len() >= 4 {
+        let len = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+        if rest.len() < 4 + len {
+            break;
+        }
+        records.push(rest[4..4 + len].to_vec());
+        rest = &rest[4 + len..];
+    }
+    Ok(records)
+}
@@ -0,0 +1,113 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use crate::failure::Failure;
+use crate::simulate::Fanout;
+use crate::{snapshot, Color, ConsensusParams, ToyDag};
+
+#[derive(Args, Debug, Clone)]
+pub struct GenerateArgs {
+    /// Layers after genesis (longest parent path from genesis to the newest blocks)
+    #[arg(long, default_value_t = 50)]
+    pub depth: usize,
+
+    /// Mean blocks per layer; fractional widths alternate between the two nearest sizes
+    #[arg(long, default_value_t = 3.0)]
+    pub width: f64,
+
+    /// Layer sizes vary by up to this many blocks either side of --width
+    #[arg(long, default_value_t = 0)]
+    pub width_jitter: usize,
+
+    /// Distribution of the number of parents per block
+    #[arg(long, value_enum, default_value_t = Fanout::Uniform)]
+    pub fanout: Fanout,
+
+    /// Most parents per block (capped at --max-parents)
+    #[arg(long, default_value_t = 3)]
+    pub max_fanout: usize,
+
+    /// Layers back a block may reach for parents beyond the one it must take from the layer below
+    #[arg(long, default_value_t = 2)]
+    pub reach: usize,
+
+    /// Seed for layer sizes and parent choices
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+
+    /// Save the DAG here in the snapshot format
+    #[arg(long)]
+    pub out: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub consensus: ConsensusParams,
+}
+
+// Build a DAG layer by layer to the requested shape; only GHOSTDAG runs on it,
+// no miners, tip selection or StitchBot. Every block takes one parent from the
+// layer right below it, so layer L sits exactly L steps from genesis.
+pub fn generate<R: Rng>(args: &GenerateArgs, rng: &mut R) -> ToyDag {
+    let mut dag = ToyDag::with_params(args.consensus);
+    let max_fanout = args.max_fanout.clamp(1, args.consensus.max_parents.max(1));
+    let mut layers = dag.layers();
+
+    for _ in 0..args.depth {
+        let width = args.width.max(1.0);
+        let base = width.floor() as usize + usize::from(rng.gen_bool(width.fract()));
+        let size = match args.width_jitter {
+            0 => base,
+            j => rng.gen_range(base.saturating_sub(j).max(1)..=base + j),
+        };
+
+        let below = layers.last().unwrap();
+        let older: Vec<u64> = layers.iter().rev().take(args.reach.max(1)).flatten().copied().collect();
+        let mut layer = Vec::with_capacity(size);
+        for _ in 0..size {
+            let mut parents = vec![*below.choose(rng).unwrap()];
+            let wanted = args.fanout.draw(max_fanout, rng);
+            let extra: Vec<u64> = older.iter().copied().filter(|p| !parents.contains(p)).collect();
+            parents.extend(extra.choose_multiple(rng, wanted - 1).copied());
+            layer.push(dag.create_block(parents));
+        }
+        layers.push(layer);
+    }
+    dag
+}
+
+// Shape the generator aimed for next to what came out
+pub fn run(args: &GenerateArgs) -> Result<(), Failure> {
+    let dag = generate(args, &mut StdRng::seed_from_u64(args.seed));
+    let blocks = dag.blocks.len() - 1;
+    let parents: Vec<usize> = (1..dag.next_id).map(|id| dag.blocks[&id].parents.len()).collect();
+    let mut histogram = vec![0usize; parents.iter().copied().max().unwrap_or(0) + 1];
+    for &n in &parents {
+        histogram[n] += 1;
+    }
+    let reds = dag.blocks.values().filter(|b| b.color == Color::Red).count();
+
+    println!("=== Synthetic DAG (seed {}) ===", args.seed);
+    println!("{:<22} {:>10} {:>10}", "", "target", "actual");
+    println!("{:<22} {:>10} {:>10}", "depth", args.depth, dag.layers().len() - 1);
+    println!("{:<22} {:>10.2} {:>10.2}", "mean width", args.width.max(1.0), blocks as f64 / args.depth.max(1) as f64);
+    println!(
+        "{:<22} {:>10} {:>10.2}",
+        "mean parents",
+        format!("{:?}", args.fanout).to_lowercase(),
+        parents.iter().sum::<usize>() as f64 / parents.len().max(1) as f64
+    );
+    for (n, &count) in histogram.iter().enumerate().skip(1) {
+        println!("{:<22} {:>10} {:>10}", format!("  {} parent(s)", n), "", count);
+    }
+    println!("Blocks: {}   selected chain: {}   red: {}", dag.blocks.len(), dag.selected_chain().len(), reds);
+    println!("================================");
+
+    if let Some(path) = &args.out {
+        snapshot::save(&dag, path).map_err(|e| Failure::Io(format!("cannot write {}: {}", path.display(), e)))?;
+        println!("Saved to {}", path.display());
+    }
+    Ok(())
+}
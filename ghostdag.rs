@@ -0,0 +1,289 @@
+use std::io::{self, Write};
+
+use clap::Args;
+
+use crate::json::{obj, Json};
+use crate::rng::{self, SimRng, SimStream};
+use crate::{grow_dag, ToyDag};
+
+// One mergeset block as the merging block colored it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    pub id: u64,
+    pub blue_anticone: usize,           // Blues (so far) in its anticone when it was considered
+    pub blue: bool,
+}
+
+// One step of the consensus ordering: a selected-chain block (None for the
+// virtual block), preceded by the blocks it merged
+#[derive(Debug, Clone)]
+pub struct OrderStep {
+    pub chain_block: Option<u64>,
+    pub merged: Vec<Candidate>,         // Ordered by (blue score, ID); colors here are final
+}
+
+// The selected chain switching branches: the last `depth` blocks of the old
+// chain are no longer on it. A chain that merely grows is not a reorg.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reorg {
+    pub depth: usize,
+    pub old_tip: u64,
+    pub new_tip: u64,
+}
+
+// How many reorgs went how deep: entry d counts reorgs of depth d
+pub fn reorg_histogram(reorgs: &[Reorg]) -> Vec<usize> {
+    let mut histogram = vec![0; reorgs.iter().map(|r| r.depth + 1).max().unwrap_or(0)];
+    for r in reorgs {
+        histogram[r.depth] += 1;
+    }
+    histogram
+}
+
+// "2×1 1×3" for two depth-1 reorgs and one of depth 3
+pub fn describe_reorgs(reorgs: &[Reorg]) -> String {
+    if reorgs.is_empty() {
+        return "none".to_string();
+    }
+    reorg_histogram(reorgs)
+        .iter()
+        .enumerate()
+        .filter(|&(_, &n)| n > 0)
+        .map(|(depth, n)| format!("{}×{}", n, depth))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// Everything a block's coloring decision depended on, small enough to ship
+// around and check without the DAG.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColoringWitness {
+    pub block: u64,
+    pub k: usize,
+    pub selected_parent: Option<u64>,
+    pub selected_parent_blue_score: usize,
+    pub blue_score: usize,
+    pub mergeset: Vec<Candidate>,       // In the order the candidates were considered
+}
+
+impl ColoringWitness {
+    pub fn summary(&self) -> String {
+        let candidates: Vec<String> = self
+            .mergeset
+            .iter()
+            .map(|c| format!("{}:{}({})", c.id, if c.blue { "B" } else { "R" }, c.blue_anticone))
+            .collect();
+        format!(
+            "Block {:3} | sp {:>4} (score {:3}) | mergeset [{}] | blue score {}",
+            self.block,
+            self.selected_parent.map_or("-".to_string(), |sp| sp.to_string()),
+            self.selected_parent_blue_score,
+            candidates.join(", "),
+            self.blue_score
+        )
+    }
+}
+
+// Checks the witness is internally consistent: every color follows from its
+// anticone count and K, and the blue score adds up. The counts themselves are
+// taken on trust — recomputing them needs the DAG.
+pub fn verify_witness(w: &ColoringWitness) -> Result<(), String> {
+    let Some(sp) = w.selected_parent else {
+        return if w.mergeset.is_empty() && w.blue_score == 0 {
+            Ok(())
+        } else {
+            Err(format!("block {} has no selected parent but is not a bare genesis", w.block))
+        };
+    };
+
+    let mut seen = std::collections::HashSet::new();
+    for c in &w.mergeset {
+        if c.id == sp || c.id == w.block || !seen.insert(c.id) {
+            return Err(format!("block {} lists candidate {} more than once or illegally", w.block, c.id));
+        }
+        if c.blue != (c.blue_anticone <= w.k) {
+            return Err(format!(
+                "block {} colored {} {} with {} blues in its anticone (k = {})",
+                w.block,
+                c.id,
+                if c.blue { "Blue" } else { "Red" },
+                c.blue_anticone,
+                w.k
+            ));
+        }
+    }
+
+    let expected = w.selected_parent_blue_score + 1 + w.mergeset.iter().filter(|c| c.blue).count();
+    if w.blue_score != expected {
+        return Err(format!("block {} claims blue score {} but its witness gives {}", w.block, w.blue_score, expected));
+    }
+    Ok(())
+}
+
+// Step-by-step account of how a block colored its mergeset, for --explain
+pub fn explain(dag: &ToyDag, block_id: u64) -> String {
+    let block = &dag.blocks[&block_id];
+    let k = dag.params.k;
+    let Some(sp) = block.selected_parent else {
+        return format!("Block {} is genesis: blue by definition, blue score 0", block_id);
+    };
+
+    let parents: Vec<String> = block
+        .parents
+        .iter()
+        .map(|p| format!("{} (score {})", p, dag.blocks[p].blue_score))
+        .collect();
+    let sp_score = dag.blocks[&sp].blue_score;
+    let mut lines = vec![
+        format!("Block {} | parents {}", block_id, parents.join(", ")),
+        format!(
+            "  selected parent {}: highest blue score among the parents (ties go to the lower ID), inherits score {}",
+            sp, sp_score
+        ),
+    ];
+
+    if block.mergeset.is_empty() {
+        lines.push("  mergeset empty: every other parent is already in the selected parent's past".to_string());
+    } else {
+        lines.push(format!(
+            "  mergeset of {} considered in (blue score, ID) order against k = {}:",
+            block.mergeset.len(),
+            k
+        ));
+        for c in &block.mergeset {
+            let verdict = if c.blue {
+                format!("{} ≤ k → Blue, joins the blue set", c.blue_anticone)
+            } else {
+                format!("{} > k → Red, too many blues cannot see it", c.blue_anticone)
+            };
+            lines.push(format!("    {:>4}: blues in anticone {}", c.id, verdict));
+        }
+    }
+
+    let blues = block.mergeset.iter().filter(|c| c.blue).count();
+    lines.push(format!(
+        "  blue score = {} (selected parent) + 1 (selected parent itself) + {} (blue mergeset) = {}",
+        sp_score, blues, block.blue_score
+    ));
+    lines.join("\n")
+}
+
+// The same decisions as `explain`, structured for teaching frontends
+pub fn explain_json(dag: &ToyDag, block_id: u64) -> Json {
+    let block = &dag.blocks[&block_id];
+    let parents: Vec<Json> = block
+        .parents
+        .iter()
+        .map(|&p| obj([("id", p.into()), ("blue_score", dag.blocks[&p].blue_score.into())]))
+        .collect();
+    let mergeset: Vec<Json> = block
+        .mergeset
+        .iter()
+        .map(|c| {
+            obj([
+                ("id", c.id.into()),
+                ("blue_score", dag.blocks[&c.id].blue_score.into()),
+                ("blue_anticone", c.blue_anticone.into()),
+                ("threshold", dag.params.k.into()),
+                ("decision", if c.blue { "blue" } else { "red" }.into()),
+            ])
+        })
+        .collect();
+    let sp_score = block.selected_parent.map_or(0, |sp| dag.blocks[&sp].blue_score);
+    obj([
+        ("block", block_id.into()),
+        ("k", dag.params.k.into()),
+        ("parents", Json::Arr(parents)),
+        ("selected_parent", block.selected_parent.into()),
+        ("selected_parent_rule", "highest blue score, ties to the lower ID".into()),
+        ("mergeset_order", "blue score, then ID".into()),
+        ("mergeset", Json::Arr(mergeset)),
+        (
+            "blue_score",
+            obj([
+                ("selected_parent", sp_score.into()),
+                ("selected_parent_itself", usize::from(block.selected_parent.is_some()).into()),
+                ("blue_mergeset", block.mergeset.iter().filter(|c| c.blue).count().into()),
+                ("total", block.blue_score.into()),
+            ]),
+        ),
+        ("color", format!("{:?}", block.color).to_lowercase().into()),
+    ])
+}
+
+// The ordering as it is built, one line per decision, stable across runs so two
+// traces (or this toy and a reference implementation) can be diffed directly
+pub fn write_order_trace<W: Write + ?Sized>(dag: &ToyDag, out: &mut W) -> io::Result<()> {
+    writeln!(out, "# toy-fec consensus ordering: {} blocks, k = {}", dag.blocks.len(), dag.params.k)?;
+    let mut position = 0;
+    for (index, step) in dag.ordering_steps().iter().enumerate() {
+        match step.chain_block {
+            Some(c) => writeln!(
+                out,
+                "step {} chain {} blue_score {} mergeset {}",
+                index,
+                c,
+                dag.blocks[&c].blue_score,
+                step.merged.len()
+            )?,
+            None => writeln!(out, "step {} virtual mergeset {}", index, step.merged.len())?,
+        }
+        for c in &step.merged {
+            writeln!(
+                out,
+                "  {:>5} merged {} blue_score {} blue_anticone {} {}",
+                position,
+                c.id,
+                dag.blocks[&c.id].blue_score,
+                c.blue_anticone,
+                if c.blue { "blue" } else { "red" }
+            )?;
+            position += 1;
+        }
+        if let Some(c) = step.chain_block {
+            writeln!(out, "  {:>5} chain {}", position, c)?;
+            position += 1;
+        }
+    }
+    Ok(())
+}
+
+#[derive(Args, Debug)]
+pub struct WitnessArgs {
+    /// Blocks to grow before emitting witnesses
+    #[arg(long, default_value_t = 150)]
+    pub blocks: u64,
+
+    /// Witnesses to print (all are verified)
+    #[arg(long, default_value_t = 20)]
+    pub show: usize,
+}
+
+pub fn run(args: &WitnessArgs) {
+    let dag = grow_dag(args.blocks, &mut SimStream::unseeded().fork(rng::MINERS));
+    let mut ids: Vec<u64> = dag.blocks.keys().copied().collect();
+    ids.sort();
+
+    println!("=== GHOSTDAG coloring witnesses ===");
+    let witnesses: Vec<ColoringWitness> = ids.iter().map(|&id| dag.coloring_witness(id)).collect();
+    for w in witnesses.iter().rev().take(args.show).rev() {
+        println!("{}", w.summary());
+    }
+
+    let failures: Vec<String> = witnesses.iter().filter_map(|w| verify_witness(w).err()).collect();
+    println!("\nVerified {} witnesses: {} failed", witnesses.len(), failures.len());
+    for reason in &failures {
+        println!("  {}", reason);
+    }
+
+    // Flip one decision and make sure the verifier notices
+    if let Some(mut forged) = witnesses.iter().find(|w| !w.mergeset.is_empty()).cloned() {
+        forged.mergeset[0].blue = !forged.mergeset[0].blue;
+        println!("Tampered witness for block {}: {:?}", forged.block, verify_witness(&forged));
+    } else {
+        let mut forged = witnesses.last().unwrap().clone();
+        forged.blue_score += 1;
+        println!("Tampered witness for block {}: {:?}", forged.block, verify_witness(&forged));
+    }
+    println!("===================================");
+}
@@ -0,0 +1,126 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+use raptorq::Encoder;
+
+use crate::bodies::BodyArgs;
+use crate::failure::{self, Failure};
+use crate::frame::{self, FrameHeader};
+use crate::rng::{SimRng, SimStream};
+use crate::simulate::{self, Fanout, MiningArgs, OutputArgs, SimArgs};
+use crate::{headers, snapshot, Color, ConsensusParams, ToyDag, SYMBOL_SIZE};
+
+const SEED: u64 = 0x70FEC;              // Seed the golden DAG is grown from
+const BLOCKS: u64 = 60;
+const MINERS: usize = 4;                // Parallel miners, so the DAG has merges
+const K: usize = 2;                     // Low enough that some merged blocks come out red
+const REPAIR: u32 = 8;                  // Repair packets in the golden packet set
+
+/// Regenerate the golden fixtures, or check that this build still reproduces them
+#[derive(Parser, Debug)]
+#[command(name = "golden")]
+pub struct GoldenArgs {
+    /// Directory holding the fixtures
+    #[arg(long, default_value = "golden")]
+    pub dir: PathBuf,
+
+    /// Compare against the fixtures instead of overwriting them
+    #[arg(long)]
+    pub check: bool,
+}
+
+// The seeded DAG every fixture is derived from
+fn dag() -> ToyDag {
+    let sim = SimArgs {
+        blocks: BLOCKS,
+        mining: MiningArgs { miners: MINERS, fanout: Fanout::Uniform, ..MiningArgs::default() },
+        consensus: ConsensusParams { k: K, ..ConsensusParams::default() },
+        output: OutputArgs { quiet: true, verbose: 0 },
+        ..SimArgs::default()
+    };
+    simulate::grow(&sim, &SimStream::seeded(SEED))
+}
+
+// Every fixture by file name: the DAG snapshot, its consensus order with each
+// block's color and hash, and the framed packets of its header object
+pub fn fixtures() -> Vec<(&'static str, Vec<u8>)> {
+    let dag = dag();
+
+    let mut snapshot = Vec::new();
+    snapshot::write(&dag, &mut snapshot).expect("writing to memory cannot fail");
+
+    let order: String = dag
+        .consensus_order()
+        .iter()
+        .map(|id| {
+            let block = &dag.blocks[id];
+            let color = if block.color == Color::Blue { "blue" } else { "red" };
+            format!("{} {} {}\n", id, color, hex::encode(block.hash))
+        })
+        .collect();
+
+    let (data, _) = headers::serialize(&dag, &BodyArgs::default());
+    let encoder = Encoder::with_defaults(&data, SYMBOL_SIZE);
+    let header = FrameHeader { session: 0, object: 0, config: encoder.get_config() };
+    let mut packets = Vec::new();
+    for packet in encoder.get_encoded_packets(REPAIR) {
+        frame::write_record(&mut packets, &frame::encode_frame(&header, &packet)).expect("writing to memory cannot fail");
+    }
+
+    vec![("dag.snapshot", snapshot), ("order.txt", order.into_bytes()), ("packets.bin", packets)]
+}
+
+// Fixture names whose file is missing or differs from what this build produces
+fn mismatches(dir: &Path) -> Vec<&'static str> {
+    fixtures()
+        .into_iter()
+        .filter(|(name, bytes)| fs::read(dir.join(name)).ok().as_ref() != Some(bytes))
+        .map(|(name, _)| name)
+        .collect()
+}
+
+fn run(args: &GoldenArgs) -> Result<(), Failure> {
+    if args.check {
+        let stale = mismatches(&args.dir);
+        if !stale.is_empty() {
+            return Err(Failure::Mismatch(format!("{} no longer reproduced: {}", args.dir.display(), stale.join(", "))));
+        }
+        println!("All golden fixtures in {} reproduced byte for byte", args.dir.display());
+        return Ok(());
+    }
+    fs::create_dir_all(&args.dir).map_err(|e| Failure::Io(format!("cannot create {}: {}", args.dir.display(), e)))?;
+    for (name, bytes) in fixtures() {
+        let path = args.dir.join(name);
+        fs::write(&path, &bytes).map_err(|e| Failure::Io(format!("cannot write {}: {}", path.display(), e)))?;
+        println!("Wrote {} ({} bytes)", path.display(), bytes.len());
+    }
+    Ok(())
+}
+
+// The `golden` binary
+pub fn run_cli() {
+    run(&GoldenArgs::parse()).unwrap_or_else(|f| failure::exit("golden", f))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fixtures;
+
+    // Regenerate with `cargo run --bin golden` after an intended change to hashing, ordering or encoding
+    const GOLDEN: [(&str, &[u8]); 3] = [
+        ("dag.snapshot", include_bytes!("golden/dag.snapshot")),
+        ("order.txt", include_bytes!("golden/order.txt")),
+        ("packets.bin", include_bytes!("golden/packets.bin")),
+    ];
+
+    #[test]
+    fn fixtures_are_reproduced() {
+        let produced = fixtures();
+        assert_eq!(produced.len(), GOLDEN.len());
+        for ((name, bytes), (golden_name, golden)) in produced.iter().zip(GOLDEN) {
+            assert_eq!(*name, golden_name);
+            assert!(bytes.as_slice() == golden, "{} differs from its golden copy", name);
+        }
+    }
+}
@@ -0,0 +1,327 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use clap::{Args, ValueEnum};
+
+use crate::agent::AgentParams;
+use crate::failure::Failure;
+use crate::json::{obj, Json};
+use crate::rng::SimStream;
+use crate::simulate::{self, MiningArgs, OutputArgs, SimArgs};
+use crate::stitch::StitchArgs;
+use crate::{dot, snapshot, Color, ConsensusParams, ToyDag};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum GraphFormat {
+    /// Graphviz source
+    #[default]
+    Dot,
+    /// Mermaid flowchart, for Markdown renderers
+    Mermaid,
+    /// Standalone SVG, laid out without Graphviz
+    Svg,
+    /// One JSON document with every block and edge
+    Json,
+    /// Plain text, one line per layer
+    Ascii,
+}
+
+// How much each block carries beyond its ID and parent edges
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum Annotation {
+    /// Block IDs and parent edges only
+    Ids,
+    /// Plus blue/red coloring, the selected chain and selected-parent edges
+    #[default]
+    Colors,
+    /// Plus blue score, mergeset and anticone sizes, and mergeset groupings
+    Full,
+}
+
+#[derive(Args, Debug)]
+pub struct GraphArgs {
+    /// Saved DAG to render; without it a fresh DAG is grown from the flags below
+    #[arg(long)]
+    pub dag: Option<PathBuf>,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t)]
+    pub format: GraphFormat,
+
+    /// How much each block is labelled with
+    #[arg(long, value_enum, default_value_t)]
+    pub annotate: Annotation,
+
+    /// Output file (stdout when omitted)
+    #[arg(long)]
+    pub out: Option<PathBuf>,
+
+    /// Blocks mined for a fresh DAG
+    #[arg(long, default_value_t = 40)]
+    pub blocks: u64,
+
+    #[command(flatten)]
+    pub mining: MiningArgs,
+
+    #[command(flatten)]
+    pub stitch: StitchArgs,
+
+    #[command(flatten)]
+    pub agent: AgentParams,
+
+    #[command(flatten)]
+    pub consensus: ConsensusParams,
+}
+
+// Every earlier block not in its past; nothing was in its future yet
+pub fn anticone_at_insertion(dag: &ToyDag, id: u64) -> usize {
+    id as usize + 1 - dag.past_set(id).len()
+}
+
+// Lines of a block's label at the given annotation level
+pub fn label(dag: &ToyDag, id: u64, annotation: Annotation) -> Vec<String> {
+    let block = &dag.blocks[&id];
+    let mut lines = vec![id.to_string()];
+    if annotation == Annotation::Full {
+        lines.push(format!(
+            "bs {}  ms {}  ac {}",
+            block.blue_score,
+            block.mergeset.len(),
+            anticone_at_insertion(dag, id)
+        ));
+    }
+    lines
+}
+
+fn color_name(color: &Color) -> &'static str {
+    match color {
+        Color::Blue => "blue",
+        Color::Red => "red",
+    }
+}
+
+fn write_mermaid<W: Write + ?Sized>(dag: &ToyDag, annotation: Annotation, out: &mut W) -> io::Result<()> {
+    writeln!(out, "flowchart RL")?;
+    let node = |id: u64| format!("b{}[\"{}\"]", id, label(dag, id, annotation).join("<br/>"));
+
+    if annotation == Annotation::Full {
+        for step in dag.ordering_steps() {
+            if let Some(c) = step.chain_block {
+                writeln!(out, "  {}", node(c))?;
+            }
+            if step.merged.is_empty() {
+                continue;
+            }
+            let (name, title) = match step.chain_block {
+                Some(c) => (c.to_string(), format!("mergeset of {}", c)),
+                None => ("virtual".to_string(), "not merged yet".to_string()),
+            };
+            writeln!(out, "  subgraph ms_{} [\"{}\"]", name, title)?;
+            for c in &step.merged {
+                writeln!(out, "    {}", node(c.id))?;
+            }
+            writeln!(out, "  end")?;
+        }
+    } else {
+        for id in 0..dag.next_id {
+            writeln!(out, "  {}", node(id))?;
+        }
+    }
+
+    for id in 0..dag.next_id {
+        let block = &dag.blocks[&id];
+        for &p in &block.parents {
+            let arrow = if annotation > Annotation::Ids && block.selected_parent == Some(p) { "==>" } else { "-->" };
+            writeln!(out, "  b{} {} b{}", id, arrow, p)?;
+        }
+    }
+
+    if annotation > Annotation::Ids {
+        writeln!(out, "  classDef blue fill:#add8e6,stroke:#333")?;
+        writeln!(out, "  classDef red fill:#fa8072,stroke:#333")?;
+        writeln!(out, "  classDef chain stroke-width:3px")?;
+        for color in [Color::Blue, Color::Red] {
+            let ids: Vec<String> = (0..dag.next_id).filter(|id| dag.blocks[id].color == color).map(|id| format!("b{}", id)).collect();
+            if !ids.is_empty() {
+                writeln!(out, "  class {} {}", ids.join(","), color_name(&color))?;
+            }
+        }
+        let chain: Vec<String> = dag.selected_chain().iter().map(|id| format!("b{}", id)).collect();
+        writeln!(out, "  class {} chain", chain.join(","))?;
+    }
+    Ok(())
+}
+
+// Columns are the DAG's layers, so every edge points left
+fn layout(dag: &ToyDag) -> HashMap<u64, (usize, usize)> {
+    let mut place = HashMap::new();
+    for (column, layer) in dag.layers().into_iter().enumerate() {
+        for (row, id) in layer.into_iter().enumerate() {
+            place.insert(id, (column, row));
+        }
+    }
+    place
+}
+
+// One line per layer, genesis first: red blocks in parentheses, chain blocks in brackets
+fn write_ascii<W: Write + ?Sized>(dag: &ToyDag, annotation: Annotation, out: &mut W) -> io::Result<()> {
+    let chain: HashSet<u64> = dag.selected_chain().into_iter().collect();
+    let layers = dag.layers();
+    writeln!(out, "{} blocks in {} layers, k = {}", dag.blocks.len(), layers.len(), dag.params.k)?;
+    for (depth, layer) in layers.iter().enumerate() {
+        let cells: Vec<String> = layer
+            .iter()
+            .map(|&id| {
+                let text = label(dag, id, annotation).join(" ");
+                match (annotation, &dag.blocks[&id].color) {
+                    (Annotation::Ids, _) => text,
+                    _ if chain.contains(&id) => format!("[{}]", text),
+                    (_, Color::Red) => format!("({})", text),
+                    (_, Color::Blue) => text,
+                }
+            })
+            .collect();
+        writeln!(out, "{:>4} | {}", depth, cells.join("  "))?;
+    }
+    Ok(())
+}
+
+fn write_svg<W: Write + ?Sized>(dag: &ToyDag, annotation: Annotation, out: &mut W) -> io::Result<()> {
+    const COL: usize = 110;
+    const ROW: usize = 56;
+    const W_BOX: usize = 84;
+    const H_BOX: usize = 36;
+
+    let place = layout(dag);
+    let columns = place.values().map(|&(c, _)| c + 1).max().unwrap_or(1);
+    let rows = place.values().map(|&(_, r)| r + 1).max().unwrap_or(1);
+    let center = |id: u64| {
+        let (c, r) = place[&id];
+        (20 + c * COL + W_BOX / 2, 40 + r * ROW + H_BOX / 2)
+    };
+    let chain: HashSet<u64> = dag.selected_chain().into_iter().collect();
+
+    writeln!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" font-family=\"Helvetica\" font-size=\"11\">",
+        40 + columns * COL,
+        60 + rows * ROW
+    )?;
+    writeln!(out, "  <text x=\"20\" y=\"22\" font-size=\"13\">{} blocks, k = {}</text>", dag.blocks.len(), dag.params.k)?;
+    for id in 0..dag.next_id {
+        let block = &dag.blocks[&id];
+        let (x1, y1) = center(id);
+        for &p in &block.parents {
+            let (x2, y2) = center(p);
+            let style = if annotation > Annotation::Ids && block.selected_parent == Some(p) {
+                "stroke=\"#222\" stroke-width=\"2.5\""
+            } else {
+                "stroke=\"#999\" stroke-width=\"1\""
+            };
+            writeln!(
+                out,
+                "  <line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" {}/>",
+                x1 - W_BOX / 2,
+                y1,
+                x2 + W_BOX / 2,
+                y2,
+                style
+            )?;
+        }
+    }
+    for id in 0..dag.next_id {
+        let (x, y) = center(id);
+        let fill = match (annotation, &dag.blocks[&id].color) {
+            (Annotation::Ids, _) => "white",
+            (_, Color::Blue) => "#add8e6",
+            (_, Color::Red) => "#fa8072",
+        };
+        let stroke = if annotation > Annotation::Ids && chain.contains(&id) { 2.5 } else { 1.0 };
+        writeln!(
+            out,
+            "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"6\" fill=\"{}\" stroke=\"#333\" stroke-width=\"{}\"/>",
+            x - W_BOX / 2,
+            y - H_BOX / 2,
+            W_BOX,
+            H_BOX,
+            fill,
+            stroke
+        )?;
+        let lines = label(dag, id, annotation);
+        for (i, line) in lines.iter().enumerate() {
+            let dy = (2 * i + 1) as f64 * 7.0 - lines.len() as f64 * 7.0 + 4.0;
+            writeln!(out, "  <text x=\"{}\" y=\"{:.0}\" text-anchor=\"middle\">{}</text>", x, y as f64 + dy, line)?;
+        }
+    }
+    writeln!(out, "</svg>")
+}
+
+fn write_json<W: Write + ?Sized>(dag: &ToyDag, annotation: Annotation, out: &mut W) -> io::Result<()> {
+    let chain: HashSet<u64> = dag.selected_chain().into_iter().collect();
+    let blocks: Vec<Json> = (0..dag.next_id)
+        .map(|id| {
+            let block = &dag.blocks[&id];
+            let mut fields = vec![("id", id.into()), ("parents", block.parents.clone().into())];
+            if annotation > Annotation::Ids {
+                fields.push(("color", color_name(&block.color).into()));
+                fields.push(("selected_parent", block.selected_parent.into()));
+                fields.push(("chain", chain.contains(&id).into()));
+            }
+            if annotation == Annotation::Full {
+                fields.push(("blue_score", block.blue_score.into()));
+                fields.push(("mergeset", block.mergeset.iter().map(|c| c.id).collect::<Vec<u64>>().into()));
+                fields.push(("anticone_at_insertion", anticone_at_insertion(dag, id).into()));
+                fields.push(("depth", block.depth.into()));
+                fields.push(("daa_score", block.daa_score.into()));
+                fields.push(("blue_work", block.blue_work.into()));
+            }
+            Json::Obj(fields)
+        })
+        .collect();
+    let graph = obj([
+        ("k", dag.params.k.into()),
+        ("max_parents", dag.params.max_parents.into()),
+        ("blocks", Json::Arr(blocks)),
+    ]);
+    writeln!(out, "{}", graph)
+}
+
+pub fn write_graph<W: Write + ?Sized>(dag: &ToyDag, format: GraphFormat, annotation: Annotation, out: &mut W) -> io::Result<()> {
+    match format {
+        GraphFormat::Dot => dot::write_dot(dag, annotation, out),
+        GraphFormat::Mermaid => write_mermaid(dag, annotation, out),
+        GraphFormat::Svg => write_svg(dag, annotation, out),
+        GraphFormat::Json => write_json(dag, annotation, out),
+        GraphFormat::Ascii => write_ascii(dag, annotation, out),
+    }
+}
+
+pub fn run(args: &GraphArgs) -> Result<(), Failure> {
+    let dag = match &args.dag {
+        Some(path) => snapshot::load(path)?,
+        None => {
+            let sim = SimArgs {
+                blocks: args.blocks,
+                mining: args.mining,
+                stitch: args.stitch.clone(),
+                agent: args.agent,
+                consensus: args.consensus,
+                output: OutputArgs { quiet: true, verbose: 0 },
+                ..SimArgs::default()
+            };
+            simulate::grow(&sim, &SimStream::unseeded())
+        }
+    };
+
+    let written = match &args.out {
+        Some(path) => File::create(path).and_then(|mut f| write_graph(&dag, args.format, args.annotate, &mut f)),
+        None => write_graph(&dag, args.format, args.annotate, &mut io::stdout().lock()),
+    };
+    written.map_err(|e| Failure::Io(format!("cannot write graph: {}", e)))?;
+    if let Some(path) = &args.out {
+        println!("Wrote {} blocks as {} to {}", dag.blocks.len(), format!("{:?}", args.format).to_lowercase(), path.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,289 @@
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+use clap::{Args, CommandFactory, FromArgMatches, Parser};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::failure::Failure;
+use crate::network::{self, NetworkArgs};
+use crate::Color;
+
+#[derive(Args, Debug)]
+pub struct GridArgs {
+    /// A parameter and the values to try, e.g. nodes=4,8,16 or mine_chance=0.05..0.4 (ranges only with --random); repeat per parameter
+    #[arg(long = "param", value_name = "NAME=VALUES", required = true)]
+    pub params: Vec<String>,
+
+    /// Draw this many configs at random from the values instead of trying every combination
+    #[arg(long)]
+    pub random: Option<usize>,
+
+    /// Seed for --random's draws
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+
+    /// Worker threads (default: one per core)
+    #[arg(long)]
+    pub threads: Option<usize>,
+
+    /// CSV dataset to add rows to; configs already in it are skipped, so an interrupted search picks up where it stopped
+    #[arg(long)]
+    pub output: PathBuf,
+}
+
+// What each config can set, in the network simulation's terms and CSV column order
+const PARAMS: [&str; 12] = [
+    "nodes", "blocks", "mine_chance", "min_latency", "max_latency", "jitter", "min_loss", "max_loss", "block_symbols", "repair", "k",
+    "seed",
+];
+const FRACTIONAL: [&str; 3] = ["mine_chance", "min_loss", "max_loss"];
+const METRICS: [&str; 7] = ["rounds", "red_ratio", "mean_tips", "mean_delay", "p90_delay", "packet_overhead", "reorgs"];
+
+// Values one parameter takes
+#[derive(Debug, Clone, PartialEq)]
+pub enum Axis {
+    Values(Vec<f64>),
+    Range(f64, f64),
+}
+
+pub fn parse_param(text: &str) -> Result<(usize, Axis), Failure> {
+    let bad = |what: String| Failure::Config(format!("--param {}: {}", text, what));
+    let (name, values) = text.split_once('=').ok_or_else(|| bad("expected NAME=VALUES".into()))?;
+    let index = PARAMS.iter().position(|p| *p == name.trim()).ok_or_else(|| bad(format!("unknown parameter; one of {}", PARAMS.join(", "))))?;
+    let number = |s: &str| s.trim().parse::<f64>().map_err(|_| bad(format!("'{}' is not a number", s)));
+    let axis = match values.split_once("..") {
+        Some((lo, hi)) => Axis::Range(number(lo)?, number(hi)?),
+        None => Axis::Values(values.split(',').map(number).collect::<Result<_, _>>()?),
+    };
+    let whole = !FRACTIONAL.contains(&PARAMS[index]);
+    if let Axis::Values(values) = &axis
+        && whole
+        && values.iter().any(|v| v.fract() != 0.0 || *v < 0.0)
+    {
+        return Err(bad("takes whole numbers".into()));
+    }
+    Ok((index, axis))
+}
+
+// One config: a value for every parameter, the simulation's defaults where the search sets none
+#[derive(Debug, Clone, PartialEq)]
+pub struct Config(pub [f64; PARAMS.len()]);
+
+impl Config {
+    fn defaults() -> Config {
+        let args = network_args();
+        Config([
+            args.nodes as f64,
+            args.blocks as f64,
+            args.mine_chance,
+            args.min_latency as f64,
+            args.max_latency as f64,
+            args.jitter as f64,
+            args.min_loss,
+            args.max_loss,
+            args.block_symbols as f64,
+            args.repair as f64,
+            args.consensus.k as f64,
+            args.seed as f64,
+        ])
+    }
+
+    // The CSV's parameter columns, which double as the key duplicates are found by
+    pub fn key(&self) -> String {
+        self.0.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(",")
+    }
+
+    fn args(&self) -> NetworkArgs {
+        let v = |name: &str| self.0[PARAMS.iter().position(|p| *p == name).unwrap()];
+        let mut args = network_args();
+        args.nodes = v("nodes") as usize;
+        args.blocks = v("blocks") as u64;
+        args.mine_chance = v("mine_chance");
+        args.min_latency = v("min_latency") as u64;
+        args.max_latency = v("max_latency") as u64;
+        args.jitter = v("jitter") as u64;
+        args.min_loss = v("min_loss");
+        args.max_loss = v("max_loss");
+        args.block_symbols = v("block_symbols") as usize;
+        args.repair = v("repair") as usize;
+        args.consensus.k = v("k") as usize;
+        args.seed = v("seed") as u64;
+        args
+    }
+}
+
+// The network command's own defaults, so a search and a hand run agree on what is left unset
+fn network_args() -> NetworkArgs {
+    #[derive(Parser)]
+    struct Network {
+        #[command(flatten)]
+        args: NetworkArgs,
+    }
+    let matches = Network::command().get_matches_from(["network"]);
+    Network::from_arg_matches(&matches).expect("the defaults parse").args
+}
+
+// Every combination, or `random` draws; duplicates and configs in `done` dropped.
+// Returns the configs to run and how many were dropped as duplicates.
+pub fn plan(axes: &[(usize, Axis)], random: Option<usize>, seed: u64, done: &HashSet<String>) -> Result<(Vec<Config>, usize), Failure> {
+    let base = Config::defaults();
+    let mut configs = Vec::new();
+    match random {
+        Some(count) => {
+            let mut rng = StdRng::seed_from_u64(seed);
+            for _ in 0..count {
+                let mut config = base.clone();
+                for (index, axis) in axes {
+                    config.0[*index] = match axis {
+                        Axis::Values(values) => values[rng.gen_range(0..values.len())],
+                        // Four decimals keep the keys readable and let near-identical draws dedupe
+                        Axis::Range(lo, hi) if FRACTIONAL.contains(&PARAMS[*index]) => {
+                            (rng.gen_range(lo.min(*hi)..=hi.max(*lo)) * 1e4).round() / 1e4
+                        }
+                        Axis::Range(lo, hi) => rng.gen_range(lo.min(*hi).ceil() as u64..=hi.max(*lo).floor() as u64) as f64,
+                    };
+                }
+                configs.push(config);
+            }
+        }
+        None => {
+            configs.push(base);
+            for (index, axis) in axes {
+                let Axis::Values(values) = axis else {
+                    return Err(Failure::Config(format!("{} is a range; ranges need --random", PARAMS[*index])));
+                };
+                configs = configs
+                    .into_iter()
+                    .flat_map(|c| {
+                        values.iter().map(move |&v| {
+                            let mut c = c.clone();
+                            c.0[*index] = v;
+                            c
+                        })
+                    })
+                    .collect();
+            }
+        }
+    }
+    let total = configs.len();
+    let mut seen = done.clone();
+    configs.retain(|c| seen.insert(c.key()));
+    let dropped = total - configs.len();
+    Ok((configs, dropped))
+}
+
+fn evaluate(config: &Config) -> Vec<f64> {
+    let args = config.args();
+    let run = network::simulate(&args);
+    let reds = run.dag.blocks.values().filter(|b| b.color == Color::Red).count();
+    let mut delays: Vec<u64> = run.blocks[run.known..]
+        .iter()
+        .flat_map(|b| (0..b.arrived_at.len()).filter(move |&node| node != b.miner).map(move |node| b.delay(node)))
+        .collect();
+    delays.sort();
+    let (sent, relays) = run.links.iter().flatten().fold((0, 0), |(s, r), l| (s + l.sent, r + l.relays));
+    vec![
+        run.rounds as f64,
+        reds as f64 / run.dag.blocks.len() as f64,
+        run.mean_tips,
+        delays.iter().sum::<u64>() as f64 / delays.len().max(1) as f64,
+        delays.get((delays.len().saturating_sub(1) as f64 * 0.9).round() as usize).copied().unwrap_or(0) as f64,
+        sent as f64 / (relays * args.block_symbols.max(1)).max(1) as f64 - 1.0,
+        run.dag.reorgs.len() as f64,
+    ]
+}
+
+pub fn run(args: &GridArgs) -> Result<(), Failure> {
+    let axes = args.params.iter().map(|p| parse_param(p)).collect::<Result<Vec<_>, _>>()?;
+    let header = format!("{},{}", PARAMS.join(","), METRICS.join(","));
+    let io = |e: std::io::Error| Failure::Io(format!("{}: {}", args.output.display(), e));
+
+    // Rows already in the dataset, found by their parameter columns
+    let existing = match fs::read_to_string(&args.output) {
+        Ok(text) => text,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(io(e)),
+    };
+    let mut lines = existing.lines();
+    if let Some(first) = lines.next()
+        && first != header
+    {
+        return Err(Failure::Config(format!("{} holds other columns than a grid search writes", args.output.display())));
+    }
+    let done: HashSet<String> = lines.map(|l| l.split(',').take(PARAMS.len()).collect::<Vec<_>>().join(",")).collect();
+    let (configs, duplicates) = plan(&axes, args.random, args.seed, &done)?;
+
+    let threads = args.threads.unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get())).max(1);
+    println!(
+        "=== Grid search: {} configs to run ({} dropped as duplicates or already in {}), {} threads ===",
+        configs.len(),
+        duplicates,
+        args.output.display(),
+        threads
+    );
+    let mut out = OpenOptions::new().create(true).append(true).open(&args.output).map_err(io)?;
+    if existing.is_empty() {
+        writeln!(out, "{}", header).map_err(io)?;
+    }
+
+    // Workers take the next config off a shared counter; rows are written as
+    // they finish, so an interrupted search loses only what was in flight
+    let next = AtomicUsize::new(0);
+    let (sender, results) = mpsc::channel();
+    thread::scope(|scope| -> Result<(), Failure> {
+        for _ in 0..threads.min(configs.len()) {
+            let sender = sender.clone();
+            let (next, configs) = (&next, &configs);
+            scope.spawn(move || {
+                while let Some(config) = configs.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    if sender.send((config, evaluate(config))).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+        for (finished, (config, metrics)) in results.iter().enumerate() {
+            let metrics: Vec<String> = metrics.iter().map(|m| format!("{:.4}", m)).collect();
+            writeln!(out, "{},{}", config.key(), metrics.join(",")).map_err(io)?;
+            out.flush().map_err(io)?;
+            println!("  [{}/{}] {} -> red ratio {}, mean delay {}", finished + 1, configs.len(), config.key(), metrics[1], metrics[3]);
+        }
+        Ok(())
+    })?;
+    println!("{} now holds {} rows", args.output.display(), done.len() + configs.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_grid_covers_every_combination_once() {
+        let axes = vec![parse_param("nodes=4,8,4").unwrap(), parse_param("k=2,3").unwrap()];
+        let (configs, dropped) = plan(&axes, None, 0, &HashSet::new()).unwrap();
+        assert_eq!((configs.len(), dropped), (4, 2));
+        let done: HashSet<String> = configs[..3].iter().map(Config::key).collect();
+        let (rest, dropped) = plan(&axes, None, 0, &done).unwrap();
+        assert_eq!(rest, configs[3..]);
+        assert_eq!(dropped, 5);
+    }
+
+    #[test]
+    fn parameters_are_checked() {
+        assert!(parse_param("nodes=2.5").is_err());
+        assert!(parse_param("speed=3").is_err());
+        assert_eq!(parse_param("mine_chance=0.1..0.3").unwrap(), (2, Axis::Range(0.1, 0.3)));
+        let axes = vec![parse_param("mine_chance=0.1..0.3").unwrap()];
+        assert!(plan(&axes, None, 0, &HashSet::new()).is_err());
+        let (configs, _) = plan(&axes, Some(5), 0, &HashSet::new()).unwrap();
+        assert!(configs.iter().all(|c| (0.1..=0.3).contains(&c.0[2])));
+    }
+}
@@ -0,0 +1,264 @@
+// A DAG that several threads or tasks can drive at once, for applications
+// embedding the toy. One RwLock guards the whole DAG: inserting a block holds
+// it for writing until the block is colored and announced, so insertions run
+// one at a time and queries wait for them; queries share it for reading and
+// run side by side. Subscribers each get their own unbounded channel,
+// so one that stops reading never holds up the DAG. Events are sent while the
+// write lock is still held, so every subscriber sees blocks in insertion order.
+
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, RwLock};
+
+use crate::hooks::Hooks;
+use crate::inclusion::InclusionProof;
+use crate::{ConsensusParams, ToyDag};
+
+// A block as the DAG sees it when asked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockInfo {
+    pub id: u64,
+    pub parents: Vec<u64>,
+    pub blue: bool,                     // From the virtual block's point of view, so it can change later
+    pub blue_score: usize,
+    pub hash: [u8; 32],
+    pub selected_parent: Option<u64>,   // None for genesis
+}
+
+// What a subscriber hears for each new block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockEvent {
+    pub block: BlockInfo,
+    pub selected_tip: u64,              // The DAG's selected tip once the block was in
+    pub reorg_depth: Option<usize>,     // Chain blocks dropped, if the block switched the selected chain
+}
+
+// Read access to the DAG for the duration of a query
+pub struct DagView<'a>(&'a ToyDag);
+
+impl DagView<'_> {
+    pub fn len(&self) -> usize {
+        self.0.blocks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.blocks.is_empty()
+    }
+
+    pub fn block(&self, id: u64) -> Option<BlockInfo> {
+        self.0.block_info(id)
+    }
+
+    // Blocks that list this one as a parent, in creation order
+    pub fn children(&self, id: u64) -> &[u64] {
+        self.0.children(id)
+    }
+
+    pub fn is_tip(&self, id: u64) -> bool {
+        self.0.is_tip(id)
+    }
+
+    // Sorted by ID
+    pub fn tips(&self) -> Vec<u64> {
+        let mut tips: Vec<u64> = self.0.tips.iter().copied().collect();
+        tips.sort();
+        tips
+    }
+
+    pub fn selected_tip(&self) -> u64 {
+        self.0.selected_parent
+    }
+
+    pub fn selected_chain(&self) -> Vec<u64> {
+        self.0.selected_chain()
+    }
+
+    pub fn consensus_order(&self) -> Vec<u64> {
+        self.0.consensus_order()
+    }
+
+    // Parent path from the chain block that merged this block; check it with inclusion::verify_inclusion
+    pub fn prove_inclusion(&self, id: u64) -> Option<InclusionProof> {
+        self.0.prove_inclusion(id)
+    }
+}
+
+struct Shared {
+    dag: RwLock<ToyDag>,
+    subscribers: Mutex<Vec<Sender<BlockEvent>>>,
+}
+
+// Cheap to clone; every clone drives the same DAG
+#[derive(Clone)]
+pub struct DagHandle {
+    shared: Arc<Shared>,
+}
+
+impl Default for DagHandle {
+    fn default() -> Self {
+        Self::from_dag(ToyDag::new())
+    }
+}
+
+impl DagHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // A DAG whose GHOSTDAG k is not the default
+    pub fn with_k(k: usize) -> Self {
+        Self::from_dag(ToyDag::with_params(ConsensusParams { k, ..ConsensusParams::default() }))
+    }
+
+    fn from_dag(dag: ToyDag) -> Self {
+        DagHandle { shared: Arc::new(Shared { dag: RwLock::new(dag), subscribers: Mutex::new(Vec::new()) }) }
+    }
+
+    // Add a block over these parents, stamped by the honest clock. Unknown or
+    // missing parents are refused rather than left to panic inside the DAG.
+    pub fn add_block(&self, parents: &[u64]) -> Result<BlockInfo, String> {
+        let mut parents = parents.to_vec();
+        parents.sort();
+        parents.dedup();
+        let mut dag = self.shared.dag.write().unwrap();
+        if parents.is_empty() {
+            return Err("a block needs at least one parent".into());
+        }
+        if let Some(unknown) = parents.iter().find(|p| !dag.blocks.contains_key(p)) {
+            return Err(format!("parent {} is not in the DAG", unknown));
+        }
+        let reorgs = dag.reorgs.len();
+        let now = dag.next_id;
+        let id = dag.create_block_at(parents, now)?;
+
+        let view = DagView(&dag);
+        let block = view.block(id).expect("just added");
+        let event = BlockEvent {
+            block: block.clone(),
+            selected_tip: view.selected_tip(),
+            reorg_depth: dag.reorgs[reorgs..].last().map(|r| r.depth),
+        };
+        // Subscribers whose receiver is gone are dropped here
+        self.shared.subscribers.lock().unwrap().retain(|s| s.send(event.clone()).is_ok());
+        Ok(block)
+    }
+
+    // Replaces any hooks set before; see Hooks for when they run
+    pub fn set_hooks(&self, hooks: Hooks) {
+        self.shared.dag.write().unwrap().set_hooks(hooks);
+    }
+
+    pub fn query<T>(&self, f: impl FnOnce(&DagView) -> T) -> T {
+        f(&DagView(&self.shared.dag.read().unwrap()))
+    }
+
+    // Every block added from now on, in insertion order
+    pub fn subscribe(&self) -> Receiver<BlockEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.shared.subscribers.lock().unwrap().push(sender);
+        receiver
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+
+    #[test]
+    fn threads_share_one_dag() {
+        fn send_sync<T: Send + Sync>() {}
+        send_sync::<DagHandle>();
+
+        let handle = DagHandle::with_k(3);
+        let events = handle.subscribe();
+        thread::scope(|scope| {
+            for _ in 0..4 {
+                let handle = handle.clone();
+                scope.spawn(move || {
+                    for _ in 0..25 {
+                        let tips = handle.query(|view| view.tips());
+                        handle.add_block(&tips).unwrap();
+                    }
+                });
+            }
+        });
+        let ids: Vec<u64> = events.try_iter().map(|e| e.block.id).collect();
+        assert_eq!(ids, (1..=100).collect::<Vec<_>>());
+        assert_eq!(handle.query(|view| view.len()), 101);
+        handle.shared.dag.read().unwrap().check_invariants().unwrap();
+        assert!(handle.add_block(&[500]).is_err());
+        assert!(handle.add_block(&[]).is_err());
+    }
+
+    #[test]
+    fn readers_never_see_a_half_inserted_block() {
+        let handle = DagHandle::with_k(3);
+        let done = AtomicBool::new(false);
+        thread::scope(|scope| {
+            let readers: Vec<_> = (0..4)
+                .map(|_| {
+                    let (handle, done) = (handle.clone(), &done);
+                    scope.spawn(move || {
+                        let (mut reads, mut last_len) = (0, 0);
+                        while !done.load(Ordering::Acquire) || reads == 0 {
+                            let len = handle.query(|view| {
+                                // Everything a query sees belongs to one consistent DAG
+                                let tips = view.tips();
+                                assert!(tips.iter().all(|&t| view.block(t).is_some() && view.children(t).is_empty()));
+                                assert!(view.block(view.selected_tip()).is_some());
+                                assert_eq!(view.selected_chain().first(), Some(&0));
+                                assert!(view.block(view.len() as u64).is_none());
+                                view.len()
+                            });
+                            assert!(len >= last_len, "the DAG shrank from {} to {}", last_len, len);
+                            (reads, last_len) = (reads + 1, len);
+                        }
+                        reads
+                    })
+                })
+                .collect();
+            scope.spawn(|| {
+                for _ in 0..200 {
+                    let tips = handle.query(|view| view.tips());
+                    handle.add_block(&tips).unwrap();
+                }
+                done.store(true, Ordering::Release);
+            });
+            assert!(readers.into_iter().all(|r| r.join().unwrap() > 0));
+        });
+        assert_eq!(handle.query(|view| view.len()), 201);
+        handle.shared.dag.read().unwrap().check_invariants().unwrap();
+    }
+
+    #[test]
+    fn children_and_tips_come_from_the_maintained_index() {
+        let handle = DagHandle::new();
+        let a = handle.add_block(&[0]).unwrap().id;
+        let b = handle.add_block(&[0]).unwrap().id;
+        let c = handle.add_block(&[a, b]).unwrap().id;
+        handle.query(|view| {
+            assert_eq!(view.children(0), [a, b]);
+            assert_eq!(view.children(a), [c]);
+            assert!(view.children(c).is_empty() && view.children(99).is_empty());
+            assert!(view.is_tip(c) && !view.is_tip(a) && !view.is_tip(0));
+        });
+    }
+
+    #[test]
+    fn inclusion_proofs_verify_outside_the_dag() {
+        let handle = DagHandle::with_k(0);
+        let a = handle.add_block(&[0]).unwrap();
+        let b = handle.add_block(&[0]).unwrap();
+        let c = handle.add_block(&[a.id, b.id]).unwrap();
+        // Only hashes cross over: the verifier trusts the chain block's hash and the genesis hash
+        let chain = handle.query(|view| view.selected_chain());
+        for block in [&a, &b, &c] {
+            let proof = handle.query(|view| view.prove_inclusion(block.id)).unwrap();
+            assert!(chain.contains(&proof.chain_block()));
+            let trusted = handle.query(|view| view.block(proof.chain_block()).unwrap().hash);
+            assert_eq!(crate::inclusion::verify_inclusion(&proof, &trusted, &[0; 32]), Ok(block.hash));
+        }
+        assert!(handle.query(|view| view.prove_inclusion(99)).is_none());
+    }
+}
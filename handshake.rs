@@ -0,0 +1,192 @@
+use std::ops::RangeInclusive;
+
+use crate::erasure::CodeKind;
+
+// Before its first frame a sender says which protocol versions and code
+// backends it speaks and the symbol size it means to use; the receiver
+// answers with what they have in common or a refusal. Frames from senders
+// that skip the handshake are still taken as version 1, so older peers keep
+// working both ways.
+//
+//   hello:  "TH" | 1 | session | lowest version | highest version | code bits | symbol size
+//   accept: "TH" | 2 | session | version | code | symbol size
+//   reject: "TH" | 3 | session | reason
+
+const MAGIC: [u8; 2] = *b"TH";
+pub const VERSIONS: RangeInclusive<u8> = 1..=1; // Protocol versions this build speaks
+
+fn code_bit(code: CodeKind) -> u8 {
+    match code {
+        CodeKind::Raptorq => 1,
+        CodeKind::Ldpc => 2,
+    }
+}
+
+const CODES: [CodeKind; 2] = [CodeKind::Raptorq, CodeKind::Ldpc];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hello {
+    pub session: u32,
+    pub versions: RangeInclusive<u8>,
+    pub codes: Vec<CodeKind>,           // In the sender's order of preference
+    pub symbol_size: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejectReason {
+    Version,                            // No protocol version in common
+    Code,                               // No code backend in common
+    SymbolSize,                         // Symbols bigger than the receiver takes
+}
+
+impl RejectReason {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            RejectReason::Version => "no protocol version in common",
+            RejectReason::Code => "no code backend in common",
+            RejectReason::SymbolSize => "symbol size larger than the receiver accepts",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reply {
+    Accept { session: u32, version: u8, code: CodeKind, symbol_size: u16 },
+    Reject { session: u32, reason: RejectReason },
+}
+
+// What a receiver can take
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capabilities {
+    pub versions: RangeInclusive<u8>,
+    pub codes: Vec<CodeKind>,
+    pub max_symbol_size: u16,
+}
+
+impl Capabilities {
+    // The highest version both speak and the sender's first choice of code the receiver has
+    pub fn negotiate(&self, hello: &Hello) -> Reply {
+        let session = hello.session;
+        let high = (*hello.versions.end()).min(*self.versions.end());
+        let low = (*hello.versions.start()).max(*self.versions.start());
+        if low > high {
+            return Reply::Reject { session, reason: RejectReason::Version };
+        }
+        let Some(&code) = hello.codes.iter().find(|c| self.codes.contains(c)) else {
+            return Reply::Reject { session, reason: RejectReason::Code };
+        };
+        if hello.symbol_size > self.max_symbol_size {
+            return Reply::Reject { session, reason: RejectReason::SymbolSize };
+        }
+        Reply::Accept { session, version: high, code, symbol_size: hello.symbol_size }
+    }
+}
+
+impl Hello {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(1);
+        bytes.extend_from_slice(&self.session.to_be_bytes());
+        bytes.extend_from_slice(&[*self.versions.start(), *self.versions.end()]);
+        bytes.push(self.codes.iter().fold(0, |bits, &c| bits | code_bit(c)));
+        bytes.extend_from_slice(&self.symbol_size.to_be_bytes());
+        bytes
+    }
+
+    // The preference order does not travel; a receiver sees the codes in bit order
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() != 12 || bytes[..2] != MAGIC || bytes[2] != 1 {
+            return None;
+        }
+        Some(Hello {
+            session: u32::from_be_bytes(bytes[3..7].try_into().ok()?),
+            versions: bytes[7]..=bytes[8],
+            codes: CODES.into_iter().filter(|&c| bytes[9] & code_bit(c) != 0).collect(),
+            symbol_size: u16::from_be_bytes(bytes[10..12].try_into().ok()?),
+        })
+    }
+}
+
+impl Reply {
+    pub fn to_bytes(self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        match self {
+            Reply::Accept { session, version, code, symbol_size } => {
+                bytes.push(2);
+                bytes.extend_from_slice(&session.to_be_bytes());
+                bytes.extend_from_slice(&[version, code_bit(code)]);
+                bytes.extend_from_slice(&symbol_size.to_be_bytes());
+            }
+            Reply::Reject { session, reason } => {
+                bytes.push(3);
+                bytes.extend_from_slice(&session.to_be_bytes());
+                bytes.push(reason as u8);
+            }
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < 7 || bytes[..2] != MAGIC {
+            return None;
+        }
+        let session = u32::from_be_bytes(bytes[3..7].try_into().ok()?);
+        match (bytes[2], &bytes[7..]) {
+            (2, &[version, code, hi, lo]) => Some(Reply::Accept {
+                session,
+                version,
+                code: CODES.into_iter().find(|&c| code_bit(c) == code)?,
+                symbol_size: u16::from_be_bytes([hi, lo]),
+            }),
+            (3, &[reason]) => Some(Reply::Reject {
+                session,
+                reason: [RejectReason::Version, RejectReason::Code, RejectReason::SymbolSize].into_iter().find(|&r| r as u8 == reason)?,
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn session(&self) -> u32 {
+        match *self {
+            Reply::Accept { session, .. } | Reply::Reject { session, .. } => session,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hello(versions: RangeInclusive<u8>, codes: Vec<CodeKind>, symbol_size: u16) -> Hello {
+        Hello { session: 7, versions, codes, symbol_size }
+    }
+
+    fn receiver(versions: RangeInclusive<u8>, codes: Vec<CodeKind>) -> Capabilities {
+        Capabilities { versions, codes, max_symbol_size: 1024 }
+    }
+
+    #[test]
+    fn peers_settle_on_the_highest_common_version() {
+        let reply = receiver(1..=3, vec![CodeKind::Raptorq]).negotiate(&hello(2..=5, vec![CodeKind::Raptorq], 512));
+        assert_eq!(reply, Reply::Accept { session: 7, version: 3, code: CodeKind::Raptorq, symbol_size: 512 });
+    }
+
+    #[test]
+    fn the_senders_preferred_code_wins_when_both_have_it() {
+        let both = receiver(1..=1, vec![CodeKind::Raptorq, CodeKind::Ldpc]);
+        let reply = both.negotiate(&hello(1..=1, vec![CodeKind::Ldpc, CodeKind::Raptorq], 512));
+        assert!(matches!(reply, Reply::Accept { code: CodeKind::Ldpc, .. }), "{:?}", reply);
+    }
+
+    #[test]
+    fn incompatible_peers_are_refused_with_the_reason() {
+        let old = receiver(1..=1, vec![CodeKind::Raptorq]);
+        let refused = |h: Hello| match old.negotiate(&h) {
+            Reply::Reject { reason, .. } => Some(reason),
+            Reply::Accept { .. } => None,
+        };
+        assert_eq!(refused(hello(2..=4, vec![CodeKind::Raptorq], 512)), Some(RejectReason::Version));
+        assert_eq!(refused(hello(1..=1, vec![CodeKind::Ldpc], 512)), Some(RejectReason::Code));
+        assert_eq!(refused(hello(1..=1, vec![CodeKind::Raptorq], 2048)), Some(RejectReason::SymbolSize));
+    }
+}
@@ -0,0 +1,209 @@
+use std::ops::Range;
+
+use crate::bodies::{self, BodyArgs};
+use crate::{Block, ConsensusParams, ToyDag};
+
+const MAGIC: &[u8; 4] = b"TFHD";
+pub const PREAMBLE_LEN: usize = 4 + 3 * 4 + 32 + 8 + 32 + 4;
+const FIXED_LEN: usize = 8 + 8 + 8 + 2 + 32 + 32;   // id, timestamp, blue score, parent count, tx root, hash
+
+// One block as it goes over the wire: enough to replay it, plus the values the
+// replay has to reproduce. Colors and mergesets are left out; GHOSTDAG recomputes them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockHeader {
+    pub id: u64,
+    pub parents: Vec<u64>,
+    pub timestamp: u64,
+    pub blue_score: u64,
+    pub tx_root: [u8; 32],              // Merkle root of the block's body, checked once the body arrives
+    pub hash: [u8; 32],
+}
+
+impl BlockHeader {
+    pub fn new(block: &Block, body: &BodyArgs) -> Self {
+        BlockHeader {
+            id: block.id,
+            parents: block.parents.clone(),
+            timestamp: block.timestamp,
+            blue_score: block.blue_score as u64,
+            tx_root: bodies::tx_root(&bodies::transactions(block, body)),
+            hash: block.hash,
+        }
+    }
+}
+
+// A decoded header object: every block since `checkpoint`, or the whole DAG
+// when the checkpoint is genesis and the genesis header leads
+#[derive(Debug, Clone)]
+pub struct HeaderSet {
+    pub params: ConsensusParams,
+    pub checkpoint: u64,                // Selected-chain block the receiver must already have
+    pub checkpoint_hash: [u8; 32],
+    pub headers: Vec<BlockHeader>,
+}
+
+// The whole DAG, with each header's byte range indexed by ID
+pub fn serialize(dag: &ToyDag, body: &BodyArgs) -> (Vec<u8>, Vec<Range<usize>>) {
+    let ids: Vec<u64> = (0..dag.next_id).collect();
+    serialize_blocks(dag, 0, &ids, body)
+}
+
+// Only what a receiver holding the checkpoint lacks: every block outside its
+// past, i.e. its future plus whatever sits in its anticone, which the future may reference
+pub fn serialize_delta(dag: &ToyDag, checkpoint: u64, body: &BodyArgs) -> Vec<u8> {
+    let past = dag.past_set(checkpoint);
+    let ids: Vec<u64> = (0..dag.next_id).filter(|id| !past.contains(id)).collect();
+    serialize_blocks(dag, checkpoint, &ids, body).0
+}
+
+// Object layout: "TFHD" | k | max_parents | mtp_window | genesis hash | checkpoint ID |
+// checkpoint hash | block count, then each header in ID order. Spans follow `ids`.
+pub fn serialize_blocks(dag: &ToyDag, checkpoint: u64, ids: &[u64], body: &BodyArgs) -> (Vec<u8>, Vec<Range<usize>>) {
+    let mut bytes = Vec::with_capacity(PREAMBLE_LEN + ids.len() * (FIXED_LEN + 16));
+    bytes.extend_from_slice(MAGIC);
+    for value in [dag.params.k, dag.params.max_parents, dag.params.mtp_window] {
+        bytes.extend_from_slice(&(value as u32).to_be_bytes());
+    }
+    bytes.extend_from_slice(&dag.blocks[&0].hash);
+    bytes.extend_from_slice(&checkpoint.to_be_bytes());
+    bytes.extend_from_slice(&dag.blocks[&checkpoint].hash);
+    bytes.extend_from_slice(&(ids.len() as u32).to_be_bytes());
+
+    let mut spans = Vec::with_capacity(ids.len());
+    for id in ids {
+        let header = BlockHeader::new(&dag.blocks[id], body);
+        let start = bytes.len();
+        bytes.extend_from_slice(&header.id.to_be_bytes());
+        bytes.extend_from_slice(&header.timestamp.to_be_bytes());
+        bytes.extend_from_slice(&header.blue_score.to_be_bytes());
+        bytes.extend_from_slice(&(header.parents.len() as u16).to_be_bytes());
+        for p in &header.parents {
+            bytes.extend_from_slice(&p.to_be_bytes());
+        }
+        bytes.extend_from_slice(&header.tx_root);
+        bytes.extend_from_slice(&header.hash);
+        spans.push(start..bytes.len());
+    }
+    (bytes, spans)
+}
+
+// Reads big-endian fields off the front of a byte slice
+struct Cursor<'a>(&'a [u8]);
+
+impl Cursor<'_> {
+    fn take(&mut self, n: usize) -> Result<&[u8], String> {
+        if self.0.len() < n {
+            return Err("header data ends mid-field".into());
+        }
+        let (head, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Ok(head)
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        Ok(u16::from_be_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_be_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn hash(&mut self) -> Result<[u8; 32], String> {
+        Ok(self.take(32)?.try_into().unwrap())
+    }
+}
+
+pub fn parse(bytes: &[u8]) -> Result<HeaderSet, String> {
+    let mut cursor = Cursor(bytes);
+    if cursor.take(4)? != MAGIC {
+        return Err("not a block header object".into());
+    }
+    let params = ConsensusParams {
+        k: cursor.u32()? as usize,
+        max_parents: cursor.u32()? as usize,
+        mtp_window: cursor.u32()? as usize,
+        genesis: Some(cursor.hash()?).filter(|h| *h != [0; 32]),
+        bootstrap: 0,                   // Bootstrap blocks travel as ordinary headers
+    };
+    let checkpoint = cursor.u64()?;
+    let checkpoint_hash = cursor.hash()?;
+    let count = cursor.u32()? as usize;
+    // Checked before allocating: the count comes off the wire
+    if count > cursor.0.len() / FIXED_LEN {
+        return Err(format!("{} headers claimed, but only {} bytes follow", count, cursor.0.len()));
+    }
+    let mut headers = Vec::with_capacity(count);
+    for _ in 0..count {
+        let id = cursor.u64()?;
+        let timestamp = cursor.u64()?;
+        let blue_score = cursor.u64()?;
+        let parents = (0..cursor.u16()?).map(|_| cursor.u64()).collect::<Result<Vec<_>, _>>()?;
+        let tx_root = cursor.hash()?;
+        let hash = cursor.hash()?;
+        headers.push(BlockHeader { id, parents, timestamp, blue_score, tx_root, hash });
+    }
+    if !cursor.0.is_empty() {
+        return Err(format!("{} trailing bytes after {} headers", cursor.0.len(), count));
+    }
+    Ok(HeaderSet { params, checkpoint, checkpoint_hash, headers })
+}
+
+// Replay a full header set into a fresh DAG
+pub fn rebuild(set: &HeaderSet) -> Result<ToyDag, String> {
+    if set.headers.first().is_none_or(|h| h.id != 0) {
+        return Err("no genesis header".into());
+    }
+    let mut dag = ToyDag::with_params(set.params);
+    apply(&mut dag, set)?;
+    Ok(dag)
+}
+
+// Replay headers on top of a DAG the receiver already has, returning how many
+// blocks were new. Known blocks must match exactly; every new block has to land
+// under its own ID with the hash and blue score its sender claimed.
+pub fn apply(dag: &mut ToyDag, set: &HeaderSet) -> Result<usize, String> {
+    let p = &set.params;
+    if (p.k, p.max_parents, p.mtp_window) != (dag.params.k, dag.params.max_parents, dag.params.mtp_window)
+        || p.genesis_hash() != dag.blocks[&0].hash
+    {
+        return Err("headers were built under different consensus parameters or genesis".into());
+    }
+    if dag.blocks.get(&set.checkpoint).is_none_or(|b| b.hash != set.checkpoint_hash) {
+        return Err(format!("checkpoint block {} is missing or differs here", set.checkpoint));
+    }
+
+    let mut added = 0;
+    for header in &set.headers {
+        if let Some(known) = dag.blocks.get(&header.id) {
+            if known.hash != header.hash {
+                return Err(format!("block {} conflicts with the local copy", header.id));
+            }
+            continue;
+        }
+        if header.id != dag.next_id {
+            return Err(format!("expected block {}, found {}", dag.next_id, header.id));
+        }
+        if header.parents.is_empty() || header.parents.iter().any(|p| !dag.blocks.contains_key(p)) {
+            return Err(format!("block {} references unknown parents", header.id));
+        }
+        if header.parents.len() > p.max_parents {
+            return Err(format!("block {} has {} parents, more than {}", header.id, header.parents.len(), p.max_parents));
+        }
+        let id = dag
+            .create_block_at(header.parents.clone(), header.timestamp)
+            .map_err(|e| format!("block {}: {}", header.id, e))?;
+        let block = &dag.blocks[&id];
+        if block.hash != header.hash {
+            return Err(format!("block {} hash does not match its parents", id));
+        }
+        if block.blue_score as u64 != header.blue_score {
+            return Err(format!("block {} claims blue score {}, replay gives {}", id, header.blue_score, block.blue_score));
+        }
+        added += 1;
+    }
+    Ok(added)
+}
@@ -0,0 +1,106 @@
+// Callbacks a DAG runs as it changes, so an embedding application can persist
+// blocks or raise alerts without forking the insertion loop. They run inside
+// the insertion, in the order below, after the DAG is consistent again: the
+// new block, a reorg it caused, then every older block whose color flipped.
+// Through a DagHandle they run under its write lock, so a hook must not call
+// back into the same handle.
+
+use crate::handle::BlockInfo;
+
+pub type BlockAdded = dyn Fn(&BlockInfo) + Send + Sync;
+pub type Reorged = dyn Fn(u64, u64, usize) + Send + Sync;   // Old tip, new tip, chain blocks dropped
+pub type ColorChanged = dyn Fn(u64, bool) + Send + Sync;    // Block, whether it is blue now
+pub type Stitched = dyn Fn(&[u64]) + Send + Sync;           // Merge blocks one stitch created
+
+#[derive(Default)]
+pub struct Hooks {
+    pub on_block_added: Option<Box<BlockAdded>>,
+    pub on_reorg: Option<Box<Reorged>>,
+    pub on_color_changed: Option<Box<ColorChanged>>,
+    pub on_stitch: Option<Box<Stitched>>,
+}
+
+impl Hooks {
+    pub fn is_empty(&self) -> bool {
+        self.on_block_added.is_none() && self.on_reorg.is_none() && self.on_color_changed.is_none() && self.on_stitch.is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    use crate::handle::DagHandle;
+    use crate::stitch::TipThreshold;
+    use crate::{ConsensusParams, ToyDag};
+
+    // k = 0 siblings on genesis, then a block on whichever sibling lost, so the chain switches to it
+    fn overtake(handle: &DagHandle) -> (u64, u64) {
+        let (a, b) = (handle.add_block(&[0]).unwrap().id, handle.add_block(&[0]).unwrap().id);
+        let (tip, loser) = if handle.query(|view| view.selected_tip()) == a { (a, b) } else { (b, a) };
+        (tip, handle.add_block(&[loser]).unwrap().id)
+    }
+
+    #[test]
+    fn hooks_see_every_mutation() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let hooks = {
+            let (added, reorg, color, stitch) = (log.clone(), log.clone(), log.clone(), log.clone());
+            Hooks {
+                on_block_added: Some(Box::new(move |b: &BlockInfo| added.lock().unwrap().push(format!("added {}", b.id)))),
+                on_reorg: Some(Box::new(move |old, new, depth| reorg.lock().unwrap().push(format!("reorg {}->{} {}", old, new, depth)))),
+                on_color_changed: Some(Box::new(move |id, blue| color.lock().unwrap().push(format!("{} {}", id, if blue { "blue" } else { "red" })))),
+                on_stitch: Some(Box::new(move |merges: &[u64]| stitch.lock().unwrap().push(format!("stitch {:?}", merges)))),
+            }
+        };
+        // k = 0: of two siblings only one can be blue, and a longer branch takes the chain over
+        let mut dag = ToyDag::with_params(ConsensusParams { k: 0, ..ConsensusParams::default() });
+        dag.set_hooks(hooks);
+        let a = dag.create_block(vec![0]);
+        let b = dag.create_block(vec![0]);
+        let c = dag.create_block(vec![b]);
+        dag.stitch(&mut TipThreshold { threshold: 1 });
+        let log = log.lock().unwrap();
+        assert_eq!(log[..2], ["added 1", "added 2"]);
+        assert!(log.iter().any(|l| l.starts_with("reorg ") && l.ends_with(" 1")), "{:?}", log);
+        assert!(log.contains(&format!("{} red", a)) && dag.blocks[&c].selected_parent == Some(b), "{:?}", log);
+        assert_eq!(log.last().unwrap(), "stitch [4]");
+    }
+
+    #[test]
+    fn reorg_hook_reports_both_tips_and_the_depth() {
+        let reorgs = Arc::new(Mutex::new(Vec::new()));
+        let handle = DagHandle::with_k(0);
+        let seen = reorgs.clone();
+        handle.set_hooks(Hooks {
+            on_reorg: Some(Box::new(move |old, new, depth| seen.lock().unwrap().push((old, new, depth)))),
+            ..Hooks::default()
+        });
+        let (old_tip, new_tip) = overtake(&handle);
+        let reorgs = reorgs.lock().unwrap();
+        assert_eq!(reorgs.last(), Some(&(old_tip, new_tip, 1)), "{:?}", reorgs);
+        assert_eq!(handle.query(|view| view.selected_tip()), new_tip);
+    }
+
+    #[test]
+    fn color_hook_reports_flips_of_older_blocks_only() {
+        let flips = Arc::new(Mutex::new(Vec::new()));
+        let handle = DagHandle::with_k(0);
+        let seen = flips.clone();
+        handle.set_hooks(Hooks {
+            on_color_changed: Some(Box::new(move |id, blue| seen.lock().unwrap().push((id, blue)))),
+            ..Hooks::default()
+        });
+        let (old_tip, new_tip) = overtake(&handle);
+        let flips = flips.lock().unwrap();
+        // The overtaken chain tip turns red once the other branch carries the chain
+        assert!(flips.contains(&(old_tip, false)), "{:?}", flips);
+        assert!(flips.iter().all(|&(id, _)| id != new_tip), "{:?}", flips);
+        // The last report for each block is the color the DAG settled on
+        let last: std::collections::HashMap<u64, bool> = flips.iter().copied().collect();
+        for (id, blue) in last {
+            assert_eq!(handle.query(|view| view.block(id).unwrap().blue), blue, "block {}", id);
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use crate::block_hash;
+
+// One block along an inclusion path; its hash is recomputed, never trusted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PathStep {
+    pub id: u64,
+    pub parents: Vec<u64>,
+}
+
+// Proof that a block sits in the past of a selected-chain block.
+// path[0] is the chain block, each next step is a parent of the previous one,
+// and the last step is the block being proven.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InclusionProof {
+    pub path: Vec<PathStep>,
+    pub mergeset_position: Option<usize>,   // Index in the chain block's ordered mergeset; None for chain blocks
+}
+
+impl InclusionProof {
+    pub fn chain_block(&self) -> u64 {
+        self.path[0].id
+    }
+}
+
+// Checks a proof against the hash of a chain block the verifier already trusts
+// (e.g. from a synced header chain) and the genesis hash of its network.
+// Returns the proven block's hash. The mergeset position is reported as-is:
+// checking it needs the whole mergeset.
+pub fn verify_inclusion(proof: &InclusionProof, trusted_chain_hash: &[u8; 32], genesis: &[u8; 32]) -> Result<[u8; 32], String> {
+    let first = proof.path.first().ok_or("empty inclusion path")?;
+    if step_hash(first, genesis) != *trusted_chain_hash {
+        return Err(format!("chain block {} does not match the trusted hash", first.id));
+    }
+
+    for pair in proof.path.windows(2) {
+        let (child, parent) = (&pair[0], &pair[1]);
+        if !child.parents.contains(&parent.id) {
+            return Err(format!("block {} is not a parent of block {}", parent.id, child.id));
+        }
+    }
+
+    let last = &proof.path[proof.path.len() - 1];
+    if proof.path.len() == 1 && proof.mergeset_position.is_some() {
+        return Err("a chain block cannot sit in its own mergeset".into());
+    }
+    Ok(step_hash(last, genesis))
+}
+
+// Genesis has no parents to hash; it carries whatever hash its network chose
+fn step_hash(step: &PathStep, genesis: &[u8; 32]) -> [u8; 32] {
+    if step.parents.is_empty() {
+        *genesis
+    } else {
+        block_hash(step.id, &step.parents)
+    }
+}
@@ -0,0 +1,351 @@
+use clap::Args;
+use hex::encode;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use raptorq::Encoder;
+
+use crate::commitment::{self, Commitment, CommitmentKind};
+use crate::inclusion::{self, InclusionProof};
+use crate::rng::{self, SimRng, SimStream};
+use crate::{block_hash, fec, grow_dag_with, ConsensusParams, ToyDag};
+
+#[derive(Args, Debug)]
+pub struct LightSyncArgs {
+    /// Blocks the full node grows before the client syncs
+    #[arg(long, default_value_t = 150)]
+    pub blocks: u64,
+
+    /// Bytes per encoded symbol
+    #[arg(long, default_value_t = 64)]
+    pub symbol_size: u16,
+
+    /// Repair packets sent with the header object
+    #[arg(long, default_value_t = 20)]
+    pub repair: u32,
+
+    /// Packets dropped on the way to the client
+    #[arg(long, default_value_t = 10)]
+    pub loss: usize,
+
+    /// Random blocks the client asks inclusion proofs for after syncing
+    #[arg(long, default_value_t = 5)]
+    pub proofs: usize,
+
+    /// How the full node commits to every block hash
+    #[arg(long, value_enum, default_value_t)]
+    pub commitment: CommitmentKind,
+
+    #[command(flatten)]
+    pub consensus: ConsensusParams,
+}
+
+// What a light client keeps per selected-chain block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainHeader {
+    pub id: u64,
+    pub parents: Vec<u64>,
+    pub selected_parent: Option<u64>,
+    pub blue_score: u64,
+    pub hash: [u8; 32],
+}
+
+impl ChainHeader {
+    // id | blue score | selected parent (u64::MAX for none) | parent count | parents | hash
+    fn write(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&self.id.to_be_bytes());
+        out.extend_from_slice(&self.blue_score.to_be_bytes());
+        out.extend_from_slice(&self.selected_parent.unwrap_or(u64::MAX).to_be_bytes());
+        out.extend_from_slice(&(self.parents.len() as u16).to_be_bytes());
+        for p in &self.parents {
+            out.extend_from_slice(&p.to_be_bytes());
+        }
+        out.extend_from_slice(&self.hash);
+    }
+
+    fn read(bytes: &mut &[u8]) -> Option<Self> {
+        let id = take_u64(bytes)?;
+        let blue_score = take_u64(bytes)?;
+        let selected_parent = Some(take_u64(bytes)?).filter(|&p| p != u64::MAX);
+        let count = u16::from_be_bytes(take(bytes, 2)?.try_into().ok()?);
+        let parents = (0..count).map(|_| take_u64(bytes)).collect::<Option<Vec<_>>>()?;
+        let hash = take(bytes, 32)?.try_into().ok()?;
+        Some(ChainHeader { id, parents, selected_parent, blue_score, hash })
+    }
+}
+
+fn take<'a>(bytes: &mut &'a [u8], n: usize) -> Option<&'a [u8]> {
+    if bytes.len() < n {
+        return None;
+    }
+    let (head, tail) = bytes.split_at(n);
+    *bytes = tail;
+    Some(head)
+}
+
+fn take_u64(bytes: &mut &[u8]) -> Option<u64> {
+    Some(u64::from_be_bytes(take(bytes, 8)?.try_into().ok()?))
+}
+
+// Everything the client syncs in one FEC-protected object
+pub struct SyncObject {
+    pub commitment_scheme: u8,
+    pub data_commitment: Vec<u8>,
+    pub block_count: u64,
+    pub headers: Vec<ChainHeader>,
+}
+
+impl SyncObject {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = vec![self.commitment_scheme];
+        out.extend_from_slice(&(self.data_commitment.len() as u16).to_be_bytes());
+        out.extend_from_slice(&self.data_commitment);
+        out.extend_from_slice(&self.block_count.to_be_bytes());
+        out.extend_from_slice(&(self.headers.len() as u32).to_be_bytes());
+        for header in &self.headers {
+            header.write(&mut out);
+        }
+        out
+    }
+
+    pub fn from_bytes(mut bytes: &[u8]) -> Option<Self> {
+        let bytes = &mut bytes;
+        let commitment_scheme = take(bytes, 1)?[0];
+        let len = u16::from_be_bytes(take(bytes, 2)?.try_into().ok()?) as usize;
+        let data_commitment = take(bytes, len)?.to_vec();
+        let block_count = take_u64(bytes)?;
+        let count = u32::from_be_bytes(take(bytes, 4)?.try_into().ok()?);
+        let headers = (0..count).map(|_| ChainHeader::read(bytes)).collect::<Option<Vec<_>>>()?;
+        bytes.is_empty().then_some(SyncObject { commitment_scheme, data_commitment, block_count, headers })
+    }
+}
+
+// A block plus the opening that ties its hash to the synced commitment
+#[derive(Debug, Clone)]
+pub struct BlockProof {
+    pub id: u64,
+    pub parents: Vec<u64>,
+    pub hash: [u8; 32],
+    pub opening: Vec<u8>,
+}
+
+// ====================== Full node side ======================
+
+pub struct FullNode<'a> {
+    dag: &'a ToyDag,
+    scheme: Box<dyn Commitment>,
+    hashes: Vec<u8>,                    // Every block hash in ID order; commitment index = block ID
+}
+
+impl<'a> FullNode<'a> {
+    pub fn new(dag: &'a ToyDag, scheme: Box<dyn Commitment>) -> Self {
+        let mut blocks: Vec<_> = dag.blocks.values().collect();
+        blocks.sort_by_key(|b| b.id);
+        let hashes = blocks.iter().flat_map(|b| b.hash).collect();
+        FullNode { dag, scheme, hashes }
+    }
+
+    pub fn sync_object(&self) -> SyncObject {
+        let headers = self
+            .dag
+            .selected_chain()
+            .iter()
+            .map(|id| {
+                let block = &self.dag.blocks[id];
+                ChainHeader {
+                    id: block.id,
+                    parents: block.parents.clone(),
+                    selected_parent: block.selected_parent,
+                    blue_score: block.blue_score as u64,
+                    hash: block.hash,
+                }
+            })
+            .collect();
+
+        SyncObject {
+            commitment_scheme: self.scheme.id(),
+            data_commitment: self.scheme.commit(&commitment::hash_items(&self.hashes)),
+            block_count: self.dag.blocks.len() as u64,
+            headers,
+        }
+    }
+
+    pub fn serve_proof(&self, id: u64) -> Option<BlockProof> {
+        let block = self.dag.blocks.get(&id)?;
+        Some(BlockProof {
+            id,
+            parents: block.parents.clone(),
+            hash: block.hash,
+            opening: self.scheme.open(&commitment::hash_items(&self.hashes), id as usize),
+        })
+    }
+}
+
+// ====================== Light client side ======================
+
+pub struct LightClient {
+    genesis: [u8; 32],                  // Trusted up front, like a node's built-in genesis
+    chain: Vec<ChainHeader>,
+    scheme: Box<dyn Commitment>,
+    data_commitment: Vec<u8>,
+    block_count: u64,
+}
+
+impl LightClient {
+    // Accept a synced object only if the selected chain links up from the genesis the client trusts
+    pub fn sync(object: SyncObject, genesis: [u8; 32]) -> Result<Self, String> {
+        let scheme = commitment::by_id(object.commitment_scheme)
+            .ok_or_else(|| format!("unknown commitment scheme {}", object.commitment_scheme))?;
+
+        let first = object.headers.first().ok_or("empty chain")?;
+        if !first.parents.is_empty() || first.hash != genesis || first.selected_parent.is_some() {
+            return Err("chain does not start at genesis".into());
+        }
+
+        for pair in object.headers.windows(2) {
+            let (prev, header) = (&pair[0], &pair[1]);
+            if header.hash != block_hash(header.id, &header.parents) {
+                return Err(format!("header {} hash does not match its contents", header.id));
+            }
+            if header.selected_parent != Some(prev.id) || !header.parents.contains(&prev.id) {
+                return Err(format!("header {} does not extend header {}", header.id, prev.id));
+            }
+            if header.blue_score <= prev.blue_score {
+                return Err(format!("blue score does not increase at header {}", header.id));
+            }
+        }
+
+        Ok(LightClient {
+            genesis,
+            chain: object.headers,
+            scheme,
+            data_commitment: object.data_commitment,
+            block_count: object.block_count,
+        })
+    }
+
+    pub fn tip(&self) -> &ChainHeader {
+        self.chain.last().unwrap()
+    }
+
+    // Structural proof: the block hangs below a chain block this client synced
+    pub fn verify_inclusion(&self, proof: &InclusionProof) -> Result<[u8; 32], String> {
+        let chain_header = self
+            .chain
+            .iter()
+            .find(|h| h.id == proof.chain_block())
+            .ok_or_else(|| format!("block {} is not on the synced chain", proof.chain_block()))?;
+        inclusion::verify_inclusion(proof, &chain_header.hash, &self.genesis)
+    }
+
+    pub fn verify_block(&self, proof: &BlockProof) -> bool {
+        let hash = if proof.parents.is_empty() { self.genesis } else { block_hash(proof.id, &proof.parents) };
+        proof.id < self.block_count
+            && proof.hash == hash
+            && self
+                .scheme
+                .verify_opening(&self.data_commitment, proof.id as usize, &proof.hash, &proof.opening)
+    }
+}
+
+pub fn run(args: &LightSyncArgs) {
+    let root = SimStream::unseeded();
+    let dag = grow_dag_with(args.consensus, args.blocks, &mut root.fork(rng::MINERS));
+    let node = FullNode::new(&dag, args.commitment.scheme());
+
+    println!("=== Light-client header sync ===");
+    let object = node.sync_object();
+    let bytes = object.to_bytes();
+    println!(
+        "Full node: {} blocks, selected chain of {} headers ({} bytes)",
+        dag.blocks.len(),
+        object.headers.len(),
+        bytes.len()
+    );
+
+    // Only the chain headers and the commitment travel, FEC-protected
+    let encoder = Encoder::with_defaults(&bytes, args.symbol_size);
+    let mut packets = encoder.get_encoded_packets(args.repair);
+    let sent = packets.len();
+    packets.shuffle(&mut root.fork(rng::CHANNEL));
+    packets.truncate(sent.saturating_sub(args.loss));
+    let outcome = fec::decode_packets(encoder.get_config(), packets);
+    println!(
+        "FEC: {} packets sent, {} lost, decoded: {} (after {} packets)",
+        sent,
+        args.loss.min(sent),
+        outcome.data.is_some(),
+        outcome.packets_used
+    );
+
+    let Some(received) = outcome.data else {
+        println!("Sync failed — {} source symbols missing.", outcome.missing.len());
+        return;
+    };
+    let synced = SyncObject::from_bytes(&received).ok_or("malformed sync object".to_string());
+    let client = match synced.and_then(|object| LightClient::sync(object, args.consensus.genesis_hash())) {
+        Ok(client) => client,
+        Err(reason) => {
+            println!("Sync rejected: {}", reason);
+            return;
+        }
+    };
+    println!(
+        "Chain validated: {} headers, tip {} (blue score {}, hash {})\n",
+        client.chain.len(),
+        client.tip().id,
+        client.tip().blue_score,
+        &encode(client.tip().hash)[..16]
+    );
+
+    // Later on the client asks the full node about individual blocks
+    let mut queries = root.fork("queries");
+    for _ in 0..args.proofs {
+        let id = queries.gen_range(0..dag.blocks.len() as u64);
+        let proof = node.serve_proof(id).unwrap();
+        println!("Proof for block {:3}: {} byte opening, valid: {}", id, proof.opening.len(), client.verify_block(&proof));
+
+        match dag.prove_inclusion(id).map(|p| (client.verify_inclusion(&p), p)) {
+            Some((Ok(hash), p)) => println!(
+                "  Included via chain block {} ({} hops, mergeset position {:?}), hash matches: {}",
+                p.chain_block(),
+                p.path.len() - 1,
+                p.mergeset_position,
+                hash == proof.hash
+            ),
+            Some((Err(reason), _)) => println!("  Inclusion proof rejected: {}", reason),
+            None => println!("  Not in the past of the selected chain (no inclusion proof)"),
+        }
+    }
+
+    // A full node lying about a block's parents must be caught
+    let mut forged = node.serve_proof(dag.selected_parent).unwrap();
+    forged.parents.push(0);
+    println!("Forged proof for block {:3}: valid: {}", forged.id, client.verify_block(&forged));
+    println!("================================");
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn a_custom_genesis_syncs_and_proves() {
+        let params = ConsensusParams { genesis: Some([0xab; 32]), ..ConsensusParams::default() };
+        let dag = grow_dag_with(params, 40, &mut StdRng::seed_from_u64(3));
+        let node = FullNode::new(&dag, CommitmentKind::default().scheme());
+        let client = LightClient::sync(node.sync_object(), [0xab; 32]).unwrap();
+
+        // Genesis sits at the bottom of every path, so its proofs exercise the parentless step
+        for id in [0, 1, dag.next_id - 1] {
+            let proof = dag.prove_inclusion(id).unwrap();
+            assert_eq!(client.verify_inclusion(&proof), Ok(dag.blocks[&id].hash), "block {}", id);
+            assert!(client.verify_block(&node.serve_proof(id).unwrap()), "block {}", id);
+        }
+
+        // A client expecting another network's genesis refuses the chain
+        assert!(LightClient::sync(node.sync_object(), [0; 32]).is_err());
+    }
+}
@@ -0,0 +1,300 @@
+use std::collections::{HashMap, HashSet};
+
+use raptorq::ObjectTransmissionInformation;
+
+use crate::faults::{FaultPoint, Faults};
+use crate::fec::{self, Deadline, DecodeOutcome, DecodeSession};
+use crate::frame;
+
+pub type ObjectKey = (u32, u32);        // (session, object)
+
+// Something that happened to an object while frames were routed
+pub enum Event {
+    Opened { key: ObjectKey, config: ObjectTransmissionInformation },
+    Completed { key: ObjectKey, outcome: DecodeOutcome },
+    Evicted { key: ObjectKey, outcome: DecodeOutcome, reason: EvictReason },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvictReason {
+    Full,                               // Too many objects in flight; the least recently fed one goes
+    Idle,                               // No frame for this object within the idle limit
+    Deadline,                           // Its decode session's deadline passed
+}
+
+impl EvictReason {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EvictReason::Full => "full",
+            EvictReason::Idle => "idle",
+            EvictReason::Deadline => "deadline",
+        }
+    }
+}
+
+// How far along one in-flight object is
+#[derive(Debug, Clone, Copy)]
+pub struct Progress {
+    pub key: ObjectKey,
+    pub received: usize,
+    pub needed: usize,                  // Source symbols, the least any decoder could do with
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RouteStats {
+    pub frames: usize,
+    pub malformed: usize,
+    pub foreign: usize,                 // Frames from sessions the manager was not asked for
+    pub late: usize,                    // Frames for objects already completed or evicted
+    pub refused: usize,                 // Packets the decoder turned away (injected faults only)
+}
+
+struct Slot {
+    session: DecodeSession,
+    received: usize,
+    last_seen: usize,                   // Frame count when this object last got a packet
+}
+
+// Routes framed packets to one decoder per (session, object). Finished and
+// evicted objects are remembered, so their stragglers never reopen them.
+pub struct DecodeManager {
+    session: Option<u32>,
+    max_objects: usize,
+    max_idle: Option<usize>,
+    deadline: Option<Deadline>,
+    slots: HashMap<ObjectKey, Slot>,
+    closed: HashSet<ObjectKey>,
+    stats: RouteStats,
+    faults: Faults,
+}
+
+impl DecodeManager {
+    pub fn new(session: Option<u32>, max_objects: usize, max_idle: Option<usize>) -> Self {
+        DecodeManager {
+            session,
+            max_objects: max_objects.max(1),
+            max_idle,
+            deadline: None,
+            slots: HashMap::new(),
+            closed: HashSet::new(),
+            stats: RouteStats::default(),
+            faults: Faults::none(),
+        }
+    }
+
+    // Drop events and refuse packets at the given rates, to exercise callers' error paths
+    pub fn inject(mut self, faults: Faults) -> Self {
+        self.faults = faults;
+        self
+    }
+
+    // Give every object opened from now on this deadline
+    pub fn with_deadline(mut self, deadline: Option<Deadline>) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    pub fn injected(&self, point: FaultPoint) -> usize {
+        self.faults.injected(point)
+    }
+
+    pub fn stats(&self) -> RouteStats {
+        self.stats
+    }
+
+    // In-flight objects, in key order
+    pub fn pending(&self) -> Vec<Progress> {
+        let mut pending: Vec<Progress> = self
+            .slots
+            .iter()
+            .map(|(&key, slot)| Progress {
+                key,
+                received: slot.received,
+                needed: fec::source_symbol_count(&slot.session.config()),
+            })
+            .collect();
+        pending.sort_by_key(|p| p.key);
+        pending
+    }
+
+    pub fn push_frame(&mut self, bytes: &[u8]) -> Vec<Event> {
+        let events = self.route(bytes);
+        self.deliver(events)
+    }
+
+    fn route(&mut self, bytes: &[u8]) -> Vec<Event> {
+        self.stats.frames += 1;
+        let now = self.stats.frames;
+        let mut events = self.expire(now);
+
+        let Some((header, packet)) = frame::decode_frame(bytes) else {
+            self.stats.malformed += 1;
+            return events;
+        };
+        if self.session.is_some_and(|s| s != header.session) {
+            self.stats.foreign += 1;
+            return events;
+        }
+        let key = header.key();
+        if self.closed.contains(&key) {
+            self.stats.late += 1;
+            return events;
+        }
+
+        if !self.slots.contains_key(&key) {
+            if self.slots.len() >= self.max_objects {
+                let oldest = self.slots.iter().min_by_key(|(k, s)| (s.last_seen, **k)).map(|(&k, _)| k);
+                events.extend(oldest.map(|k| self.evict(k, EvictReason::Full)));
+            }
+            let slot = Slot { session: DecodeSession::new(header.config).with_deadline(self.deadline), received: 0, last_seen: now };
+            self.slots.insert(key, slot);
+            events.push(Event::Opened { key, config: header.config });
+        }
+
+        let slot = self.slots.get_mut(&key).expect("slot was just opened");
+        // The decoder was built for the first config heard; a packet laid out for another would crash it
+        if slot.session.config() != header.config {
+            self.stats.malformed += 1;
+            return events;
+        }
+        slot.received += 1;
+        slot.last_seen = now;
+        if self.faults.strike(FaultPoint::DecoderRefusal) {
+            self.stats.refused += 1;
+            return events;
+        }
+        if slot.session.push(packet) {
+            let slot = self.slots.remove(&key).expect("slot exists");
+            self.closed.insert(key);
+            events.push(Event::Completed { key, outcome: slot.session.finish() });
+        } else if slot.session.expired() {
+            events.push(self.evict(key, EvictReason::Deadline));
+        }
+        events
+    }
+
+    // Objects whose wall-clock deadline passed; frames only check this as
+    // they arrive, so a receiver hearing nothing calls it while it waits
+    pub fn expire_deadlines(&mut self) -> Vec<Event> {
+        let mut expired: Vec<ObjectKey> = self.slots.iter().filter(|(_, s)| s.session.expired()).map(|(&k, _)| k).collect();
+        expired.sort();
+        let events = expired.into_iter().map(|k| self.evict(k, EvictReason::Deadline)).collect();
+        self.deliver(events)
+    }
+
+    // Give up on everything still in flight, e.g. once the stream ends
+    pub fn finish(mut self) -> Vec<Event> {
+        let mut keys: Vec<ObjectKey> = self.slots.keys().copied().collect();
+        keys.sort();
+        let events = keys.into_iter().map(|k| self.evict(k, EvictReason::Idle)).collect();
+        self.deliver(events)
+    }
+
+    // Hand events to the caller, minus any an injected fault swallows
+    fn deliver(&mut self, events: Vec<Event>) -> Vec<Event> {
+        events.into_iter().filter(|_| !self.faults.strike(FaultPoint::DroppedEvent)).collect()
+    }
+
+    fn expire(&mut self, now: usize) -> Vec<Event> {
+        let mut stale: Vec<(ObjectKey, EvictReason)> = self
+            .slots
+            .iter()
+            .filter_map(|(&k, s)| {
+                if self.max_idle.is_some_and(|limit| now - s.last_seen > limit) {
+                    Some((k, EvictReason::Idle))
+                } else {
+                    s.session.expired().then_some((k, EvictReason::Deadline))
+                }
+            })
+            .collect();
+        stale.sort_by_key(|&(k, _)| k);
+        stale.into_iter().map(|(k, reason)| self.evict(k, reason)).collect()
+    }
+
+    fn evict(&mut self, key: ObjectKey, reason: EvictReason) -> Event {
+        let slot = self.slots.remove(&key).expect("evicting a known object");
+        self.closed.insert(key);
+        Event::Evicted { key, outcome: slot.session.finish(), reason }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    use raptorq::Encoder;
+
+    use crate::fec::DecodeError;
+    use crate::frame::Frames;
+
+    fn route_all(manager: &mut DecodeManager, frames: &[Vec<u8>]) -> Vec<Event> {
+        frames.iter().flat_map(|f| manager.push_frame(f)).collect()
+    }
+
+    #[test]
+    fn deadlines_end_sessions_with_a_partial_report() {
+        let data = vec![7u8; 1000];
+        let frames: Vec<Vec<u8>> = Frames::new(&Encoder::with_defaults(&data, 128), 1, 0).take(12).map(|f| f.to_bytes()).collect();
+
+        // Three packets of the eight needed, then the count runs out; the rest arrive late
+        let mut manager = DecodeManager::new(Some(1), 4, None).with_deadline(Some(Deadline::Packets(3)));
+        let events = route_all(&mut manager, &frames[..5]);
+        let Some(Event::Evicted { outcome, reason: EvictReason::Deadline, .. }) = events.last() else { panic!("no deadline eviction") };
+        assert_eq!(outcome.packets_used, 3);
+        assert!(matches!(outcome.result(), Err(DecodeError::InsufficientSymbols { received: 3, estimated_needed: 8, .. })));
+        assert_eq!(manager.stats().late, 2);
+
+        // A zero deadline passes with the first packet; a loose one leaves room to decode
+        let mut manager = DecodeManager::new(Some(1), 4, None).with_deadline(Some(Deadline::After(Duration::ZERO)));
+        assert!(matches!(route_all(&mut manager, &frames[..1]).last(), Some(Event::Evicted { reason: EvictReason::Deadline, .. })));
+        let mut manager = DecodeManager::new(Some(1), 4, None).with_deadline(Some(Deadline::After(Duration::from_secs(60))));
+        assert!(route_all(&mut manager, &frames).iter().any(|e| matches!(e, Event::Completed { .. })));
+        assert!(manager.expire_deadlines().is_empty());
+    }
+
+    #[test]
+    fn a_silent_object_expires_on_the_wall_clock() {
+        let data = vec![7u8; 1000];
+        let frames: Vec<Vec<u8>> = Frames::new(&Encoder::with_defaults(&data, 128), 1, 0).take(2).map(|f| f.to_bytes()).collect();
+
+        // --deadline-packets 0 gives up on the first packet, and the report still carries it
+        let mut manager = DecodeManager::new(Some(1), 4, None).with_deadline(Some(Deadline::Packets(0)));
+        let events = route_all(&mut manager, &frames[..1]);
+        let [Event::Opened { .. }, Event::Evicted { outcome, reason: EvictReason::Deadline, .. }] = &events[..] else { panic!("no deadline eviction") };
+        assert_eq!(outcome.packets_used, 1);
+
+        // Nothing heard yet: no object is open, so no deadline runs and the caller's timeout is all there is
+        let mut manager = DecodeManager::new(Some(1), 4, None).with_deadline(Some(Deadline::After(Duration::from_millis(20))));
+        std::thread::sleep(Duration::from_millis(40));
+        assert!(manager.expire_deadlines().is_empty());
+
+        // Two packets, then silence: only expire_deadlines notices the time running out
+        assert!(route_all(&mut manager, &frames).iter().all(|e| matches!(e, Event::Opened { .. })));
+        assert!(manager.expire_deadlines().is_empty());
+        std::thread::sleep(Duration::from_millis(40));
+        let events = manager.expire_deadlines();
+        let [Event::Evicted { key: (1, _), outcome, reason: EvictReason::Deadline }] = &events[..] else { panic!("no deadline eviction") };
+        assert_eq!((outcome.packets_used, outcome.missing.len()), (2, 6));
+        assert!(matches!(outcome.result(), Err(DecodeError::InsufficientSymbols { received: 2, estimated_needed: 8, .. })));
+        assert!(manager.pending().is_empty());
+        assert!(manager.expire_deadlines().is_empty());
+    }
+
+    #[test]
+    fn a_conflicting_config_for_an_open_object_is_malformed() {
+        let encoder = Encoder::with_defaults(&vec![7u8; 1000], 128);
+        let frames: Vec<Vec<u8>> = Frames::new(&encoder, 1, 0).take(12).map(|f| f.to_bytes()).collect();
+        // Same session and object, but split in two source blocks: a block 1 packet indexes past the open decoder's one
+        let two_blocks = Encoder::new(&[1u8; 1024], ObjectTransmissionInformation::new(1024, 128, 2, 1, 8));
+        let spoof = Frames::new(&two_blocks, 1, 0).find(|f| f.packet.payload_id().source_block_number() == 1).unwrap().to_bytes();
+
+        let mut manager = DecodeManager::new(Some(1), 4, None);
+        let mut events = route_all(&mut manager, &frames[..1]);
+        events.extend(manager.push_frame(&spoof));
+        events.extend(route_all(&mut manager, &frames[1..]));
+        assert_eq!(manager.stats().malformed, 1);
+        let Some(Event::Completed { outcome, .. }) = events.last() else { panic!("the real object did not decode") };
+        assert_eq!(outcome.data.as_deref(), Some(&[7u8; 1000][..]));
+    }
+}
@@ -0,0 +1,184 @@
+use hkdf::Hkdf;
+use raptorq::{EncodingPacket, ObjectTransmissionInformation};
+use sha2::Sha256;
+
+use crate::auth::{PacketAuth, TAG_LEN};
+use crate::commitment::{self, Commitment};
+use crate::frame;
+use crate::merkle::{self, Hash, MerkleProof, MerkleTree};
+
+// Sent ahead of the packets (out of band or heavily repeated): everything a
+// receiver needs to set up a decoder and to authenticate every packet.
+#[derive(Debug, Clone)]
+pub struct TransmissionManifest {
+    pub config: ObjectTransmissionInformation,
+    pub packet_count: usize,
+    pub packet_root: Hash,              // Merkle root over every encoded packet, source and repair
+    pub commitment_scheme: u8,          // Which Commitment produced data_commitment
+    pub data_commitment: Vec<u8>,       // Binds the ordered block hashes being sent
+}
+
+const FIXED_HEADER_LEN: usize = 12 + 4 + 32 + 1 + 2;
+const HEADER_KEY_INFO: &[u8] = b"toy-fec manifest header v1";  // Keeps header tags apart from packet tags
+
+// An encoded packet carrying its inclusion proof against the manifest root
+#[derive(Debug, Clone)]
+pub struct ProvenPacket {
+    pub packet: EncodingPacket,
+    pub proof: MerkleProof,
+}
+
+// Leaves commit to the payload ID as well, so a valid symbol cannot be replayed under another ESI
+pub fn packet_leaf(packet: &EncodingPacket) -> Hash {
+    merkle::leaf_hash(&packet.serialize())
+}
+
+pub fn seal(
+    config: ObjectTransmissionInformation,
+    scheme: &dyn Commitment,
+    block_hashes: &[u8],
+    packets: Vec<EncodingPacket>,
+) -> (TransmissionManifest, Vec<ProvenPacket>) {
+    let tree = MerkleTree::new(packets.iter().map(packet_leaf).collect());
+    let manifest = TransmissionManifest {
+        config,
+        packet_count: packets.len(),
+        packet_root: tree.root(),
+        commitment_scheme: scheme.id(),
+        data_commitment: scheme.commit(&commitment::hash_items(block_hashes)),
+    };
+    let proven = packets
+        .into_iter()
+        .enumerate()
+        .map(|(i, packet)| ProvenPacket { proof: tree.proof(i), packet })
+        .collect();
+    (manifest, proven)
+}
+
+pub fn header_auth(session_key: &[u8], config: &ObjectTransmissionInformation) -> PacketAuth {
+    let mut key = [0u8; 32];
+    Hkdf::<Sha256>::new(None, session_key).expand(HEADER_KEY_INFO, &mut key).expect("32 bytes is a valid HKDF-SHA256 length");
+    PacketAuth::new(&key, config)
+}
+
+impl TransmissionManifest {
+    // Header packet: OTI | packet count | packet root | scheme | commitment length | commitment | tag.
+    // The tag is the packet MAC under a key derived from the session key, so
+    // only a holder of that key can produce a header the receiver accepts,
+    // and no packet tag doubles as a header tag or the other way round.
+    pub fn to_header_packet(&self, key: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(FIXED_HEADER_LEN + self.data_commitment.len());
+        bytes.extend_from_slice(&self.config.serialize());
+        bytes.extend_from_slice(&(self.packet_count as u32).to_be_bytes());
+        bytes.extend_from_slice(&self.packet_root);
+        bytes.push(self.commitment_scheme);
+        bytes.extend_from_slice(&(self.data_commitment.len() as u16).to_be_bytes());
+        bytes.extend_from_slice(&self.data_commitment);
+        header_auth(key, &self.config).seal_bytes(&bytes)
+    }
+
+    pub fn from_header_packet(bytes: &[u8], key: &[u8]) -> Option<Self> {
+        if bytes.len() < FIXED_HEADER_LEN + TAG_LEN {
+            return None;
+        }
+        let config = ObjectTransmissionInformation::deserialize(bytes[0..12].try_into().ok()?);
+        let body = header_auth(key, &config).open_bytes(bytes)?;
+        let commitment_len = u16::from_be_bytes(body[49..51].try_into().ok()?) as usize;
+        if body.len() != FIXED_HEADER_LEN + commitment_len {
+            return None;
+        }
+        if !frame::valid_config(&config) {
+            return None;
+        }
+        Some(TransmissionManifest {
+            config,
+            packet_count: u32::from_be_bytes(body[12..16].try_into().ok()?) as usize,
+            packet_root: body[16..48].try_into().ok()?,
+            commitment_scheme: body[48],
+            data_commitment: body[FIXED_HEADER_LEN..].to_vec(),
+        })
+    }
+
+    pub fn commitment(&self) -> Option<Box<dyn Commitment>> {
+        commitment::by_id(self.commitment_scheme)
+    }
+
+    // Re-derive the data commitment from recovered bytes alone
+    pub fn verify_recovered(&self, recovered: &[u8]) -> bool {
+        self.commitment()
+            .is_some_and(|scheme| scheme.verify_all(&self.data_commitment, &commitment::hash_items(recovered)))
+    }
+
+    pub fn verify(&self, packet: &ProvenPacket) -> bool {
+        packet.proof.verify(packet_leaf(&packet.packet), &self.packet_root)
+    }
+
+    // Split received packets into those that check out and the number rejected
+    pub fn filter_verified(&self, packets: Vec<ProvenPacket>) -> (Vec<EncodingPacket>, usize) {
+        let total = packets.len();
+        let accepted: Vec<EncodingPacket> = packets
+            .into_iter()
+            .filter(|p| self.verify(p))
+            .map(|p| p.packet)
+            .collect();
+        let rejected = total - accepted.len();
+        (accepted, rejected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use raptorq::Encoder;
+    use sha2::Digest;
+
+    use super::*;
+    use crate::commitment::CommitmentKind;
+
+    const KEY: [u8; 32] = [7; 32];
+
+    fn header() -> Vec<u8> {
+        let encoder = Encoder::with_defaults(&[5; 640], 64);
+        let hashes = [9u8; 64];
+        let (manifest, _) = seal(encoder.get_config(), CommitmentKind::Merkle.scheme().as_ref(), &hashes, encoder.get_encoded_packets(2));
+        manifest.to_header_packet(&KEY)
+    }
+
+    #[test]
+    fn forged_headers_are_rejected() {
+        let bytes = header();
+        let sent = TransmissionManifest::from_header_packet(&bytes, &KEY).unwrap();
+
+        // Swap in another packet root: without the key the tag cannot follow
+        let mut forged = sent.clone();
+        forged.packet_root = [0xee; 32];
+        let mut forged_bytes = forged.to_header_packet(&[8; 32]);
+        assert!(TransmissionManifest::from_header_packet(&forged_bytes, &KEY).is_none());
+        // Nor does an unkeyed digest over the new fields, which anyone could compute
+        forged_bytes.truncate(forged_bytes.len() - TAG_LEN);
+        forged_bytes.extend_from_slice(&Sha256::digest(&forged_bytes)[..TAG_LEN]);
+        assert!(TransmissionManifest::from_header_packet(&forged_bytes, &KEY).is_none());
+    }
+
+    #[test]
+    fn every_byte_of_the_header_is_covered() {
+        let bytes = header();
+        for i in 0..bytes.len() {
+            let mut tampered = bytes.clone();
+            tampered[i] ^= 1;
+            assert!(TransmissionManifest::from_header_packet(&tampered, &KEY).is_none(), "byte {} flipped", i);
+        }
+        assert!(TransmissionManifest::from_header_packet(&bytes[..bytes.len() - 1], &KEY).is_none());
+    }
+
+    #[test]
+    fn packet_and_header_tags_do_not_cross_over() {
+        let bytes = header();
+        let config = ObjectTransmissionInformation::deserialize(bytes[..12].try_into().unwrap());
+        let packets = PacketAuth::new(&KEY, &config);
+        // The header's fields sealed the way data packets are
+        let body = &bytes[..bytes.len() - TAG_LEN];
+        assert!(TransmissionManifest::from_header_packet(&packets.seal_bytes(body), &KEY).is_none());
+        // And the header itself offered as a data frame
+        assert!(packets.open_bytes(&bytes).is_none());
+    }
+}
@@ -0,0 +1,113 @@
+use sha2::{Digest, Sha256};
+
+pub type Hash = [u8; 32];
+
+// Domain-separated hashing (RFC 6962 style) so a leaf can never pose as an inner node
+pub fn leaf_hash(data: &[u8]) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([0x00]);
+    hasher.update(data);
+    hasher.finalize().into()
+}
+
+pub fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([0x01]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+// Root over already-hashed leaves; an odd node at the end of a level is carried up unchanged
+pub fn merkle_root(leaves: &[Hash]) -> Hash {
+    if leaves.is_empty() {
+        return Sha256::digest([]).into();
+    }
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => node_hash(left, right),
+                [single] => *single,
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+    level[0]
+}
+
+// Full tree kept level by level so inclusion proofs can be cut from it
+pub struct MerkleTree {
+    levels: Vec<Vec<Hash>>,             // levels[0] = leaves, last = [root]
+}
+
+// Sibling hashes from leaf to root; the flag is true when the sibling sits on the left
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerkleProof {
+    pub path: Vec<(Hash, bool)>,
+}
+
+impl MerkleTree {
+    pub fn new(leaves: Vec<Hash>) -> Self {
+        let mut levels = vec![leaves];
+        while levels.last().is_some_and(|level| level.len() > 1) {
+            let next = levels.last().unwrap().chunks(2).map(merkle_root).collect();
+            levels.push(next);
+        }
+        MerkleTree { levels }
+    }
+
+    pub fn root(&self) -> Hash {
+        merkle_root(self.levels.last().map(Vec::as_slice).unwrap_or_default())
+    }
+
+    pub fn proof(&self, index: usize) -> MerkleProof {
+        let mut path = Vec::new();
+        let mut i = index;
+        for level in &self.levels[..self.levels.len() - 1] {
+            let sibling = i ^ 1;
+            if sibling < level.len() {
+                path.push((level[sibling], sibling < i));
+            }
+            i /= 2;
+        }
+        MerkleProof { path }
+    }
+}
+
+impl MerkleProof {
+    pub fn root_from(&self, leaf: Hash) -> Hash {
+        self.path.iter().fold(leaf, |acc, (sibling, sibling_is_left)| {
+            if *sibling_is_left {
+                node_hash(sibling, &acc)
+            } else {
+                node_hash(&acc, sibling)
+            }
+        })
+    }
+
+    pub fn verify(&self, leaf: Hash, root: &Hash) -> bool {
+        self.root_from(leaf) == *root
+    }
+
+    // Which leaf of a `leaves`-leaf tree this path climbs from, read off the
+    // sibling sides from the root down. None when the path does not fit the tree.
+    pub fn leaf_index(&self, leaves: usize) -> Option<usize> {
+        let mut sizes = vec![leaves];
+        while *sizes.last().unwrap() > 1 {
+            sizes.push(sizes.last().unwrap().div_ceil(2));
+        }
+        let mut path = self.path.iter().rev();
+        let mut index = 0;
+        for &size in sizes[..sizes.len() - 1].iter().rev() {
+            index *= 2;
+            // The last node of an odd level is carried up without a sibling
+            if index + 1 < size {
+                let (_, sibling_is_left) = path.next()?;
+                index += *sibling_is_left as usize;
+            }
+        }
+        path.next().is_none().then_some(index)
+    }
+}
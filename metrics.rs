@@ -0,0 +1,65 @@
+// Prometheus text exposition, shared by the live server's /metrics page and
+// the network simulation's --metrics file (which suits node_exporter's
+// textfile collector). A family is one metric name; per-node series carry a
+// `node` label.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Gauge,
+    Counter,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Family {
+    pub name: &'static str,
+    pub help: &'static str,
+    pub kind: Kind,
+    pub samples: Vec<(Option<usize>, f64)>, // (node label, value)
+}
+
+impl Family {
+    pub fn single(name: &'static str, help: &'static str, kind: Kind, value: f64) -> Family {
+        Family { name, help, kind, samples: vec![(None, value)] }
+    }
+
+    pub fn per_node(name: &'static str, help: &'static str, kind: Kind, values: impl IntoIterator<Item = f64>) -> Family {
+        Family { name, help, kind, samples: values.into_iter().enumerate().map(|(node, v)| (Some(node), v)).collect() }
+    }
+}
+
+pub fn render(families: &[Family]) -> String {
+    let mut text = String::new();
+    for family in families {
+        let kind = match family.kind {
+            Kind::Gauge => "gauge",
+            Kind::Counter => "counter",
+        };
+        text += &format!("# HELP {} {}\n# TYPE {} {}\n", family.name, family.help, family.name, kind);
+        for (node, value) in &family.samples {
+            match node {
+                Some(node) => text += &format!("{}{{node=\"{}\"}} {}\n", family.name, node, value),
+                None => text += &format!("{} {}\n", family.name, value),
+            }
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn families_render_as_prometheus_text() {
+        let text = render(&[
+            Family::single("toyfec_blocks", "Blocks", Kind::Gauge, 12.0),
+            Family::per_node("toyfec_node_delay", "Mean delay", Kind::Gauge, [2.5, 3.0]),
+        ]);
+        assert_eq!(
+            text,
+            "# HELP toyfec_blocks Blocks\n# TYPE toyfec_blocks gauge\ntoyfec_blocks 12\n\
+             # HELP toyfec_node_delay Mean delay\n# TYPE toyfec_node_delay gauge\n\
+             toyfec_node_delay{node=\"0\"} 2.5\ntoyfec_node_delay{node=\"1\"} 3\n"
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use std::path::PathBuf;
+
+use clap::{Args, ValueEnum};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::stitch::StitchArgs;
+use crate::{snapshot, Color, ConsensusParams, ToyDag};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Phenomenon {
+    /// The first block the virtual block colors red
+    FirstRed,
+    /// The selected chain abandons its last --depth blocks for another branch
+    Reorg,
+    /// StitchBot creates a merge block
+    Stitch,
+}
+
+#[derive(Args, Debug)]
+pub struct MinimalArgs {
+    /// What the example has to show
+    #[arg(value_enum)]
+    pub phenomenon: Phenomenon,
+
+    /// Reorg depth to look for
+    #[arg(long, default_value_t = 2)]
+    pub depth: usize,
+
+    /// Seeds to search (0..seeds); the smallest example wins, ties to the lower seed
+    #[arg(long, default_value_t = 500)]
+    pub seeds: u64,
+
+    /// Give up on a seed after this many blocks
+    #[arg(long, default_value_t = 60)]
+    pub max_blocks: usize,
+
+    /// Most blocks mined concurrently in one round
+    #[arg(long, default_value_t = 3)]
+    pub miners: usize,
+
+    /// Save the example as a DAG file (for `analyze` or as a test fixture)
+    #[arg(long)]
+    pub out: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub stitch: StitchArgs,
+
+    #[command(flatten)]
+    pub consensus: ConsensusParams,
+}
+
+// Grow one seeded DAG until it shows the phenomenon; None if it never does within max_blocks.
+// Each round some miners build concurrently on random subsets of the tips they all saw.
+fn search(args: &MinimalArgs, seed: u64) -> Option<(ToyDag, String)> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut dag = ToyDag::with_params(args.consensus);
+    let mut stitcher = args.stitch.policy();
+
+    while dag.blocks.len() < args.max_blocks {
+        let mut seen: Vec<u64> = dag.tips.iter().copied().collect();
+        seen.sort();
+        for _ in 0..rng.gen_range(1..=args.miners.max(1)) {
+            let mut parents: Vec<u64> = seen.iter().copied().filter(|_| rng.gen_bool(0.5)).collect();
+            if parents.is_empty() {
+                parents.push(seen[rng.gen_range(0..seen.len())]);
+            }
+            let reorgs = dag.reorgs.len();
+            let id = dag.create_block(parents);
+
+            let found = match args.phenomenon {
+                Phenomenon::FirstRed => {
+                    let red = dag.blocks.values().filter(|b| b.color == Color::Red).map(|b| b.id).min();
+                    red.map(|r| {
+                        if r == id {
+                            format!("block {} is red as soon as it arrives", r)
+                        } else {
+                            format!("block {} turns red once block {} arrives", r, id)
+                        }
+                    })
+                }
+                Phenomenon::Reorg => dag.reorgs[reorgs..].iter().find(|r| r.depth >= args.depth).map(|r| {
+                    format!(
+                        "block {} moves the selected tip from {} to {}, dropping {} chain blocks",
+                        id, r.old_tip, r.new_tip, r.depth
+                    )
+                }),
+                Phenomenon::Stitch => None,
+            };
+            if let Some(what) = found {
+                return Some((dag, what));
+            }
+        }
+
+        if args.phenomenon == Phenomenon::Stitch {
+            let tips = dag.tips.len();
+            if let Some(&merge) = dag.stitch(stitcher.as_mut()).first() {
+                let what = format!("StitchBot ({}) merges {} tips into block {}", stitcher.name(), tips, merge);
+                return Some((dag, what));
+            }
+        }
+    }
+    None
+}
+
+pub fn run(args: &MinimalArgs) {
+    let best = (0..args.seeds)
+        .filter_map(|seed| search(args, seed).map(|(dag, what)| (seed, dag, what)))
+        .min_by_key(|(seed, dag, _)| (dag.blocks.len(), *seed));
+
+    let Some((seed, dag, what)) = best else {
+        println!(
+            "No {:?} within {} blocks for any of {} seeds; raise --max-blocks or --seeds",
+            args.phenomenon, args.max_blocks, args.seeds
+        );
+        return;
+    };
+
+    println!("=== Minimal example: {:?} ===", args.phenomenon);
+    println!("Seed {} of {} gives the smallest DAG: {} blocks, k = {}", seed, args.seeds, dag.blocks.len(), dag.params.k);
+    println!("What happens: {}\n", what);
+    for id in 1..dag.next_id {
+        println!("  {}: {:?}", id, dag.blocks[&id].parents);
+    }
+    println!();
+    dag.print_dag();
+
+    if let Some(path) = &args.out {
+        match snapshot::save(&dag, path) {
+            Ok(()) => println!("Saved to {}", path.display()),
+            Err(e) => println!("Could not save to {}: {}", path.display(), e),
+        }
+    }
+}
@@ -0,0 +1,325 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::net::{Ipv4Addr, SocketAddrV4, UdpSocket};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use clap::Args;
+use hex::encode;
+use raptorq::Encoder;
+use rand::rngs::OsRng;
+use rand::Rng;
+use sha2::{Digest, Sha256};
+
+use crate::aead::CipherArgs;
+use crate::failure::Failure;
+use crate::fec::{self, DeadlineArgs, DecodeOutcome, SymbolArgs};
+use crate::frame::{self, Frames};
+use crate::manager::{DecodeManager, EvictReason, Event, ObjectKey};
+use crate::pacing::PacingArgs;
+use crate::transfer::{self, InputArgs};
+use crate::{headers, snapshot, PAYLOAD_ID_LEN, REPAIR_PACKETS};
+
+const GROUP: &str = "239.255.70.236:7036"; // Administratively scoped, so routers keep it inside the site
+const MAX_DATAGRAM: usize = 65_507;     // Largest UDP payload IPv4 can carry
+const DEADLINE_POLL: Duration = Duration::from_millis(200); // How often a quiet socket wakes to check deadlines
+
+#[derive(Args, Debug)]
+pub struct MulticastSendArgs {
+    #[command(flatten)]
+    pub input: InputArgs,
+
+    /// Multicast group and port to send to
+    #[arg(long, default_value = GROUP)]
+    pub group: SocketAddrV4,
+
+    /// Routers the datagrams may cross (1 keeps them on the local network)
+    #[arg(long, default_value_t = 1)]
+    pub ttl: u32,
+
+    /// Session ID stamped on every frame (random when omitted)
+    #[arg(long)]
+    pub session: Option<u32>,
+
+    #[command(flatten)]
+    pub symbols: SymbolArgs,
+
+    /// Repair packets per source block in each carousel
+    #[arg(long, default_value_t = REPAIR_PACKETS)]
+    pub repair: u32,
+
+    /// Passes over the object; each one after the first sends only repair symbols not sent before, so late joiners catch up
+    #[arg(long, default_value_t = 3)]
+    pub carousels: u32,
+
+    #[command(flatten)]
+    pub pacing: PacingArgs,
+
+    #[command(flatten)]
+    pub cipher: CipherArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct MulticastRecvArgs {
+    /// Multicast group and port to join
+    #[arg(long, default_value = GROUP)]
+    pub group: SocketAddrV4,
+
+    /// Local interface address to join the group on (the system picks one when unspecified)
+    #[arg(long, default_value_t = Ipv4Addr::UNSPECIFIED)]
+    pub interface: Ipv4Addr,
+
+    /// Only accept frames from this session (the first object to decode wins otherwise)
+    #[arg(long)]
+    pub session: Option<u32>,
+
+    /// Give up after this many seconds without a datagram, including before the first one (deadlines only start then)
+    #[arg(long, default_value_t = 10)]
+    pub timeout: u64,
+
+    #[command(flatten)]
+    pub deadline: DeadlineArgs,
+
+    /// Keep listening after decoding until the sender goes quiet, so the loss report covers everything sent
+    #[arg(long)]
+    pub drain: bool,
+
+    /// Write the recovered object here
+    #[arg(long)]
+    pub out: Option<PathBuf>,
+
+    /// Treat the object as block headers sent with --dag: rebuild and validate the DAG, then save it here
+    #[arg(long)]
+    pub dag_out: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub cipher: CipherArgs,
+}
+
+// Losses seen by one receiver. Each source block's ESIs go out in increasing
+// order, so a gap below the highest ESI heard is a loss, and a run of
+// consecutive gaps is one burst.
+#[derive(Debug, Default)]
+struct LossPattern {
+    sent: usize,                        // Packets up to the last one heard, per block
+    lost: usize,
+    bursts: usize,
+    longest: usize,
+}
+
+impl LossPattern {
+    fn from_esis<'a>(blocks: impl Iterator<Item = &'a BTreeSet<u32>>) -> Self {
+        let mut pattern = LossPattern::default();
+        for esis in blocks {
+            let mut next = 0;
+            for &esi in esis {
+                let gap = (esi - next) as usize;
+                if gap > 0 {
+                    pattern.lost += gap;
+                    pattern.bursts += 1;
+                    pattern.longest = pattern.longest.max(gap);
+                }
+                next = esi + 1;
+            }
+            pattern.sent += next as usize;
+        }
+        pattern
+    }
+}
+
+fn io_failure(what: String) -> impl FnOnce(io::Error) -> Failure {
+    move |e| Failure::Io(format!("{}: {}", what, e))
+}
+
+// Report what arrived for an object whose deadline passed, and stop waiting
+// unless some other object has decoded already
+fn missed_deadline(key: ObjectKey, outcome: &DecodeOutcome, decoded: bool) -> Result<(), Failure> {
+    let error = outcome.result().expect_err("only undecoded objects pass their deadline");
+    println!("  deadline passed for s{}/o{}: {}", key.0, key.1, error);
+    outcome.print_packet_usage();
+    if decoded {
+        return Ok(());
+    }
+    Err(Failure::Decode(format!("deadline passed for s{}/o{}: {}", key.0, key.1, error)))
+}
+
+// One object to everyone listening: the sender keeps no per-receiver state and
+// hears nothing back. Every frame carries the object config, so a receiver can
+// join at any point and decode from whichever packets reach it.
+pub fn run_send(args: &MulticastSendArgs) -> Result<(), Failure> {
+    let mut data = transfer::read_input(&args.input, None)?;
+    if data.is_empty() {
+        return Err(Failure::Config("nothing to send: the input is empty".into()));
+    }
+    // Everyone in the group hears every frame, so only the key holders should be able to read them
+    let plain_len = data.len();
+    if let Some(sealed) = args.cipher.encrypt(&data)? {
+        data = sealed;
+    }
+    // The path MTU counts the IP and UDP headers as well as the frame's own
+    let symbols = args.symbols.choose(data.len(), frame::UDP_IPV4_LEN + frame::HEADER_LEN + PAYLOAD_ID_LEN);
+    let encoder = Encoder::with_defaults(&data, symbols.size);
+    let session = args.session.unwrap_or_else(|| OsRng.r#gen());
+    let mut frames = Frames::new(&encoder, session, 0);
+    let repair_per_pass = encoder.get_block_encoders().len() * args.repair as usize;
+
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).map_err(io_failure("cannot open a UDP socket".into()))?;
+    socket.set_multicast_ttl_v4(args.ttl).map_err(io_failure(format!("cannot set TTL {}", args.ttl)))?;
+
+    println!("=== Multicasting session {} to {} (TTL {}, {}) ===", session, args.group, args.ttl, args.pacing.describe());
+    println!(
+        "{} bytes{}, {}",
+        plain_len,
+        if data.len() > plain_len { format!(" ({} encrypted)", data.len()) } else { String::new() },
+        symbols.describe()
+    );
+    let mut pacer = args.pacing.bucket();
+    let started = Instant::now();
+    let (mut datagrams, mut bytes, mut largest) = (0, 0, 0);
+    for carousel in 0..args.carousels.max(1) {
+        // Source symbols go out once; every later pass adds repair symbols nobody has seen yet
+        let source = if carousel == 0 { fec::source_symbol_count(&frames.header().config) } else { 0 };
+        let mut packets = 0;
+        for framed in frames.by_ref().take(source + repair_per_pass) {
+            let frame = framed.to_bytes();
+            if let Some(pacer) = &mut pacer {
+                pacer.wait(frame::UDP_IPV4_LEN + frame.len());
+            }
+            socket.send_to(&frame, args.group).map_err(io_failure(format!("cannot send to {}", args.group)))?;
+            datagrams += 1;
+            bytes += frame.len();
+            largest = largest.max(frame::UDP_IPV4_LEN + frame.len());
+            packets += 1;
+        }
+        println!("  carousel {}: {} packets", carousel + 1, packets);
+    }
+    let secs = started.elapsed().as_secs_f64();
+    println!(
+        "Sent {} datagrams ({} bytes) in {:.2}s, {:.0} packets/s; largest {} bytes on the wire with IP and UDP headers, MTU {}",
+        datagrams,
+        bytes,
+        secs,
+        datagrams as f64 / secs.max(1e-9),
+        largest,
+        args.symbols.mtu
+    );
+    if let Some(pacer) = &pacer {
+        println!("Held back {:.2}s by pacing", pacer.waited().as_secs_f64());
+    }
+    Ok(())
+}
+
+// Join the group and feed every datagram to a DecodeManager until an object decodes
+pub fn run_recv(args: &MulticastRecvArgs) -> Result<(), Failure> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, args.group.port()))
+        .map_err(io_failure(format!("cannot listen on port {}", args.group.port())))?;
+    socket
+        .join_multicast_v4(args.group.ip(), &args.interface)
+        .map_err(io_failure(format!("cannot join {} on {}", args.group.ip(), args.interface)))?;
+    let quiet = Duration::from_secs(args.timeout.max(1));
+    let deadline = args.deadline.deadline();
+    // A wall-clock deadline has to pass on time even while nothing arrives
+    let wake = if deadline.is_some() { quiet.min(DEADLINE_POLL) } else { quiet };
+    socket.set_read_timeout(Some(wake)).map_err(io_failure("cannot set a timeout".into()))?;
+
+    println!("=== Listening on {} (interface {}) ===", args.group, args.interface);
+    let mut manager = DecodeManager::new(args.session, 16, None).with_deadline(deadline);
+    let mut buf = vec![0; MAX_DATAGRAM];
+    let mut first: Option<Instant> = None;
+    let mut last = Instant::now();
+    let mut heard: BTreeMap<(ObjectKey, u8), BTreeSet<u32>> = BTreeMap::new();
+    let mut done: Option<(Vec<u8>, ObjectKey, usize)> = None;
+    loop {
+        let len = match socket.recv_from(&mut buf) {
+            Ok((len, from)) => {
+                if first.is_none() {
+                    println!("First datagram from {}", from);
+                }
+                len
+            }
+            // Woken early to check deadlines; the sender is not quiet yet
+            Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) && last.elapsed() < quiet => {
+                for event in manager.expire_deadlines() {
+                    if let Event::Evicted { key, outcome, .. } = event {
+                        missed_deadline(key, &outcome, done.is_some())?;
+                    }
+                }
+                continue;
+            }
+            // Draining ends when the sender goes quiet
+            Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) && done.is_some() => break,
+            Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => {
+                let pending: Vec<String> =
+                    manager.pending().iter().map(|p| format!("s{}/o{} {}/{}", p.key.0, p.key.1, p.received, p.needed)).collect();
+                println!("Nothing heard for {}s; in flight: {}", args.timeout, if pending.is_empty() { "none".into() } else { pending.join(", ") });
+                return Err(Failure::Decode(format!("no object decoded from {} datagrams", manager.stats().frames)));
+            }
+            Err(e) => return Err(io_failure("cannot receive".into())(e)),
+        };
+        last = Instant::now();
+        first.get_or_insert(last);
+        if let Some((header, packet)) = frame::decode_frame(&buf[..len]) {
+            let id = packet.payload_id();
+            heard.entry((header.key(), id.source_block_number())).or_default().insert(id.encoding_symbol_id());
+        }
+        for event in manager.push_frame(&buf[..len]) {
+            match event {
+                Event::Opened { key, config } => println!("  opened s{}/o{}: {} bytes", key.0, key.1, config.transfer_length()),
+                Event::Completed { key, outcome } if done.is_none() => {
+                    let used = outcome.packets_used;
+                    done = outcome.data.map(|data| (data, key, used));
+                }
+                Event::Completed { key, .. } => println!("  also decoded s{}/o{}", key.0, key.1),
+                Event::Evicted { key, outcome, reason: EvictReason::Deadline } => missed_deadline(key, &outcome, done.is_some())?,
+                Event::Evicted { key, reason, .. } => println!("  evicted s{}/o{} ({})", key.0, key.1, reason.name()),
+            }
+        }
+        if done.is_some() && !args.drain {
+            break;
+        }
+    }
+    let (data, key, used) = done.expect("the loop only ends early once an object decoded");
+
+    let stats = manager.stats();
+    println!(
+        "Recovered s{}/o{}: {} bytes from {} packets, {} datagrams heard in {:.2}s ({} malformed, {} from other sessions)",
+        key.0,
+        key.1,
+        data.len(),
+        used,
+        stats.frames,
+        first.map_or(0.0, |t| (last - t).as_secs_f64()),
+        stats.malformed,
+        stats.foreign
+    );
+    let pattern = LossPattern::from_esis(heard.iter().filter(|((k, _), _)| *k == key).map(|(_, esis)| esis));
+    println!(
+        "Loss{}: {} of the {} packets sent up to the last one heard ({:.1}%), in {} bursts, longest {}",
+        if args.drain { "" } else { " until decoded (--drain to cover the rest)" },
+        pattern.lost,
+        pattern.sent,
+        pattern.lost as f64 * 100.0 / pattern.sent.max(1) as f64,
+        pattern.bursts,
+        pattern.longest
+    );
+    let data = match args.cipher.decrypt(&data)? {
+        Some(plain) => {
+            println!("Decrypted and authenticated {} bytes", plain.len());
+            plain
+        }
+        None => data,
+    };
+    println!("SHA256 {}", encode(Sha256::digest(&data)));
+    if let Some(path) = &args.dag_out {
+        let set = headers::parse(&data).map_err(|e| Failure::Mismatch(format!("recovered object is not block headers: {}", e)))?;
+        let dag = headers::rebuild(&set).map_err(|e| Failure::Mismatch(format!("recovered headers do not rebuild a valid DAG: {}", e)))?;
+        snapshot::save(&dag, path).map_err(io_failure(format!("cannot write {}", path.display())))?;
+        println!("Rebuilt {} blocks, saved to {}", dag.blocks.len(), path.display());
+    }
+    if let Some(path) = &args.out {
+        fs::write(path, &data).map_err(io_failure(format!("cannot write {}", path.display())))?;
+        println!("Wrote {}", path.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,266 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use raptorq::Encoder;
+use rand::rngs::OsRng;
+use rand::Rng;
+
+use crate::channel::LossModel;
+use crate::failure::Failure;
+use crate::faults::{FaultArgs, FaultPoint, Faults};
+use crate::frame::{self, Frames};
+use crate::manager::{DecodeManager, Event, ObjectKey};
+use crate::rng::{self, SimRng, SimStream};
+use crate::fec::{self, SymbolArgs};
+use crate::{PAYLOAD_ID_LEN, REPAIR_PACKETS};
+
+#[derive(Args, Debug)]
+pub struct MuxArgs {
+    /// Files to send, one object each (object IDs follow the order given)
+    #[arg(long = "file", required = true)]
+    pub files: Vec<PathBuf>,
+
+    /// Framed stream to write (`-` for stdout)
+    #[arg(long)]
+    pub stream: PathBuf,
+
+    /// Session ID stamped on every frame (random when omitted)
+    #[arg(long)]
+    pub session: Option<u32>,
+
+    #[command(flatten)]
+    pub symbols: SymbolArgs,
+
+    /// Repair packets per source block, for every object
+    #[arg(long, default_value_t = REPAIR_PACKETS)]
+    pub repair: u32,
+
+    /// Chance each frame is dropped on the way into the stream
+    #[arg(long, default_value_t = 0.0)]
+    pub loss: f64,
+}
+
+#[derive(Args, Debug)]
+pub struct DemuxArgs {
+    /// Framed stream to read (`-` for stdin)
+    #[arg(long)]
+    pub stream: PathBuf,
+
+    /// Write each recovered object here as s<session>-o<object>.bin
+    #[arg(long)]
+    pub out_dir: Option<PathBuf>,
+
+    /// Only accept frames from this session
+    #[arg(long)]
+    pub session: Option<u32>,
+
+    /// Objects decoded at once; a new object evicts the least recently fed one beyond this
+    #[arg(long, default_value_t = 64)]
+    pub max_objects: usize,
+
+    /// Give up on an object after this many frames without a packet for it
+    #[arg(long)]
+    pub max_idle: Option<usize>,
+
+    /// Print objects as they open, complete or are evicted, plus periodic progress
+    #[arg(short, long)]
+    pub verbose: bool,
+
+    #[command(flatten)]
+    pub faults: FaultArgs,
+}
+
+const PROGRESS_EVERY: usize = 50;      // Frames between progress lines at -v
+
+fn is_stdio(path: &Path) -> bool {
+    path.as_os_str() == "-"
+}
+
+// Encode every file and interleave their frames, so all objects travel at once
+pub fn run_mux(args: &MuxArgs) -> Result<(), Failure> {
+    let session = args.session.unwrap_or_else(|| OsRng.r#gen());
+    let mut queues: Vec<Vec<Vec<u8>>> = Vec::new();
+    let mut report = Vec::new();
+    for (object, path) in args.files.iter().enumerate() {
+        let data = fs::read(path).map_err(|e| Failure::Io(format!("cannot read {}: {}", path.display(), e)))?;
+        if data.is_empty() {
+            return Err(Failure::Config(format!("{} is empty", path.display())));
+        }
+        let symbols = args.symbols.choose(data.len(), frame::HEADER_LEN + PAYLOAD_ID_LEN);
+        let encoder = Encoder::with_defaults(&data, symbols.size);
+        let count = fec::source_symbol_count(&encoder.get_config()) + encoder.get_block_encoders().len() * args.repair as usize;
+        queues.push(Frames::new(&encoder, session, object as u32).take(count).map(|f| f.to_bytes()).collect());
+        report.push(format!("  object {}: {} ({} bytes), {}", object, path.display(), data.len(), symbols.describe()));
+    }
+
+    let longest = queues.iter().map(Vec::len).max().unwrap_or(0);
+    let interleaved: Vec<Vec<u8>> = (0..longest)
+        .flat_map(|i| queues.iter().filter_map(move |q| q.get(i).cloned()))
+        .collect();
+    let sent = interleaved.len();
+    let frames = LossModel::Uniform { rate: args.loss.clamp(0.0, 1.0) }.transmit(interleaved, &mut SimStream::unseeded().fork(rng::CHANNEL));
+
+    let mut bytes = Vec::new();
+    for f in &frames {
+        frame::write_record(&mut bytes, f).expect("writing to memory cannot fail");
+    }
+    let written = if is_stdio(&args.stream) { io::stdout().lock().write_all(&bytes) } else { fs::write(&args.stream, &bytes) };
+    written.map_err(|e| Failure::Io(format!("cannot write {}: {}", args.stream.display(), e)))?;
+
+    let summary = format!(
+        "Session {}: {} objects in {} interleaved frames, {} kept after {:.0}% loss\n{}",
+        session,
+        args.files.len(),
+        sent,
+        frames.len(),
+        args.loss * 100.0,
+        report.join("\n")
+    );
+    if is_stdio(&args.stream) {
+        eprintln!("{}", summary);
+    } else {
+        println!("{}", summary);
+    }
+    Ok(())
+}
+
+// Route frames through a DecodeManager, reporting each object as it completes or is given up on
+pub fn run_demux(args: &DemuxArgs) -> Result<(), Failure> {
+    let records = if is_stdio(&args.stream) {
+        frame::read_records(&mut io::stdin().lock())
+    } else {
+        File::open(&args.stream).and_then(|mut f| frame::read_records(&mut f))
+    }
+    .map_err(|e| Failure::Io(format!("cannot read {}: {}", args.stream.display(), e)))?;
+
+    let mut manager = DecodeManager::new(args.session, args.max_objects, args.max_idle).inject(Faults::new(&args.faults));
+    let mut sizes = BTreeMap::new();
+    let mut rows = Vec::new();
+    let mut failed = 0;
+    let mut handle = |event: Event| -> Result<(), Failure> {
+        match event {
+            Event::Opened { key, config } => {
+                sizes.insert(key, (config.transfer_length(), config.symbol_size()));
+                if args.verbose {
+                    println!("  opened s{}/o{}: {} bytes", key.0, key.1, config.transfer_length());
+                }
+            }
+            Event::Completed { key, outcome } => {
+                let data = outcome.data.as_deref().expect("completed objects carry their data");
+                let result = match &args.out_dir {
+                    None => "decoded".to_string(),
+                    Some(dir) => {
+                        let path = dir.join(format!("s{}-o{}.bin", key.0, key.1));
+                        fs::create_dir_all(dir)
+                            .and_then(|_| fs::write(&path, data))
+                            .map_err(|e| Failure::Io(format!("cannot write {}: {}", path.display(), e)))?;
+                        format!("wrote {}", path.display())
+                    }
+                };
+                if args.verbose {
+                    println!("  completed s{}/o{} after {} packets", key.0, key.1, outcome.packets_used);
+                }
+                rows.push((key, outcome.packets_used, outcome.source_symbols, result));
+            }
+            Event::Evicted { key, outcome, reason } => {
+                failed += 1;
+                if args.verbose {
+                    println!("  evicted s{}/o{} ({}) with {} packets", key.0, key.1, reason.name(), outcome.packets_used);
+                }
+                let result = format!("failed ({}), {} source symbols missing", reason.name(), outcome.missing.len());
+                rows.push((key, outcome.packets_used, outcome.source_symbols, result));
+            }
+        }
+        Ok(())
+    };
+
+    for (i, record) in records.iter().enumerate() {
+        for event in manager.push_frame(record) {
+            handle(event)?;
+        }
+        if args.verbose && (i + 1) % PROGRESS_EVERY == 0 {
+            let pending: Vec<String> =
+                manager.pending().iter().map(|p| format!("s{}/o{} {}/{}", p.key.0, p.key.1, p.received, p.needed)).collect();
+            println!("  after {} frames, in flight: {}", i + 1, if pending.is_empty() { "none".into() } else { pending.join(", ") });
+        }
+    }
+    let stats = manager.stats();
+    let dropped = manager.injected(FaultPoint::DroppedEvent);
+    for event in manager.finish() {
+        handle(event)?;
+    }
+    // An object whose outcome never arrived counts as lost
+    let reported: BTreeSet<ObjectKey> = rows.iter().map(|r| r.0).collect();
+    for &key in sizes.keys().filter(|k| !reported.contains(k)) {
+        failed += 1;
+        rows.push((key, 0, 0, "failed (no outcome reported)".to_string()));
+    }
+
+    println!(
+        "{} frames: {} objects, {} malformed, {} from other sessions, {} late",
+        stats.frames,
+        rows.len(),
+        stats.malformed,
+        stats.foreign,
+        stats.late
+    );
+    if stats.refused + dropped > 0 {
+        println!("Injected faults: {} packets refused, {} events dropped", stats.refused, dropped);
+    }
+    println!("{:>10} {:>7} {:>8} {:>8} {:>8} {:>7}  result", "session", "object", "packets", "needed", "bytes", "symbol");
+    rows.sort_by_key(|r| r.0);
+    for ((session, object), packets, needed, result) in rows {
+        let (bytes, symbol) = match sizes.get(&(session, object)) {
+            Some((bytes, symbol)) => (bytes.to_string(), symbol.to_string()),
+            None => ("?".to_string(), "?".to_string()),
+        };
+        println!("{:>10} {:>7} {:>8} {:>8} {:>8} {:>7}  {}", session, object, packets, needed, bytes, symbol, result);
+    }
+    if failed > 0 {
+        return Err(Failure::Decode(format!("{} objects could not be recovered", failed)));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use raptorq::ObjectTransmissionInformation;
+
+    use super::*;
+
+    #[test]
+    fn demux_survives_a_conflicting_config() {
+        let dir = std::env::temp_dir().join(format!("toy-fec-demux-{}", std::process::id()));
+        let data = vec![7u8; 1000];
+        let mut frames: Vec<Vec<u8>> = Frames::new(&Encoder::with_defaults(&data, 128), 1, 0).take(12).map(|f| f.to_bytes()).collect();
+        // A spoofed frame for the same object, from a two-block layout, right after the first real one
+        let two_blocks = Encoder::new(&[1u8; 1024], ObjectTransmissionInformation::new(1024, 128, 2, 1, 8));
+        let spoof = Frames::new(&two_blocks, 1, 0).find(|f| f.packet.payload_id().source_block_number() == 1).unwrap();
+        frames.insert(1, spoof.to_bytes());
+
+        let mut bytes = Vec::new();
+        for f in &frames {
+            frame::write_record(&mut bytes, f).unwrap();
+        }
+        fs::create_dir_all(&dir).unwrap();
+        let stream = dir.join("frames.bin");
+        fs::write(&stream, bytes).unwrap();
+        let args = DemuxArgs {
+            stream,
+            out_dir: Some(dir.join("out")),
+            session: Some(1),
+            max_objects: 4,
+            max_idle: None,
+            verbose: false,
+            faults: FaultArgs::default(),
+        };
+        let result = run_demux(&args);
+        let recovered = fs::read(dir.join("out").join("s1-o0.bin"));
+        let _ = fs::remove_dir_all(&dir);
+        assert!(result.is_ok(), "{:?}", result.err());
+        assert_eq!(recovered.unwrap(), data);
+    }
+}
@@ -0,0 +1,671 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use clap::Args;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::metrics::{render, Family, Kind};
+use crate::topology::{self, Topology};
+use crate::{ghostdag, Color, ConsensusParams, ToyDag};
+
+#[derive(Args, Debug, Clone)]
+pub struct NetworkArgs {
+    /// Nodes in the network, each mining on its own view of the DAG
+    #[arg(long, default_value_t = 8)]
+    pub nodes: usize,
+
+    /// Blocks to mine before stopping
+    #[arg(long, default_value_t = 300)]
+    pub blocks: u64,
+
+    /// Chance per round that a node finds a block
+    #[arg(long, default_value_t = 0.25)]
+    pub mine_chance: f64,
+
+    /// Fastest link latency in rounds (at least 1)
+    #[arg(long, default_value_t = 1)]
+    pub min_latency: u64,
+
+    /// Slowest link latency in rounds; each directed link draws its own between the two
+    #[arg(long, default_value_t = 4)]
+    pub max_latency: u64,
+
+    /// Extra rounds a single relay may take on top of its link latency
+    #[arg(long, default_value_t = 1)]
+    pub jitter: u64,
+
+    /// Who talks to whom: the miner straight to everyone, or hop by hop over a sparser graph
+    #[arg(long, value_enum, default_value_t)]
+    pub topology: Topology,
+
+    /// Mean peers per node in the random and scale-free topologies
+    #[arg(long, default_value_t = 4)]
+    pub degree: usize,
+
+    /// Lowest packet loss rate of a link; each directed link draws its own up to --max-loss
+    #[arg(long, default_value_t = 0.0)]
+    pub min_loss: f64,
+
+    /// Highest packet loss rate of a link
+    #[arg(long, default_value_t = 0.1)]
+    pub max_loss: f64,
+
+    /// Source symbols per block relay; a peer needs this many packets to rebuild the block
+    #[arg(long, default_value_t = 4)]
+    pub block_symbols: usize,
+
+    /// Repair packets per relay before any loss has been observed
+    #[arg(long, default_value_t = 1)]
+    pub repair: usize,
+
+    /// Keep --repair on every link instead of tuning it to the loss each link shows
+    #[arg(long)]
+    pub fixed_repair: bool,
+
+    /// Seed for link latencies, losses, jitter and mining luck
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+
+    /// Wall-clock length of a round, for the per-node throughput in blocks per second
+    #[arg(long, default_value_t = 1000)]
+    pub round_ms: u64,
+
+    /// Write every (block, node) arrival as CSV here
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+
+    /// Write per-node throughput, acceptance time and block requests here in Prometheus text format
+    #[arg(long)]
+    pub metrics: Option<PathBuf>,
+
+    /// Compare the anticone sizes the run produced with a Poisson model of its block rate and delay
+    #[arg(long)]
+    pub anticones: bool,
+
+    #[command(flatten)]
+    pub consensus: ConsensusParams,
+}
+
+// When a block was mined and when each node had it, in rounds
+#[derive(Debug, Clone)]
+pub struct Propagation {
+    pub miner: usize,
+    pub mined_at: u64,
+    pub arrived_at: Vec<u64>,           // Per node; the miner's own entry is mined_at
+}
+
+impl Propagation {
+    pub fn delay(&self, node: usize) -> u64 {
+        self.arrived_at[node] - self.mined_at
+    }
+}
+
+// What one directed link has seen of packet loss, and the repair it uses now
+#[derive(Debug, Clone)]
+pub struct LinkStats {
+    pub loss: f64,                      // True loss rate, hidden from the sender
+    pub sent: usize,
+    pub lost: usize,
+    pub relays: usize,
+    pub retries: usize,                 // Relays that fell short and had to be sent again
+    pub repair: usize,
+}
+
+impl LinkStats {
+    // Smoothed so a link that has lost nothing yet is not assumed perfect
+    pub fn observed_loss(&self) -> f64 {
+        (self.lost as f64 + 1.0) / (self.sent as f64 + 2.0)
+    }
+
+    // Smallest N whose expected arrivals, less two standard deviations, still cover
+    // `source`: N(1-p) - 2√(Np(1-p)) = S, solved as a quadratic in √N
+    fn tune(&mut self, source: usize) {
+        let p = self.observed_loss();
+        let q = 1.0 - p;
+        let spread = (p * q).sqrt();
+        let root = (spread + (p * q + q * source as f64).sqrt()) / q;
+        self.repair = ((root * root).ceil() as usize).saturating_sub(source);
+    }
+}
+
+// One node's view: the blocks that reached it so far and the tips among them
+struct Node {
+    tips: HashSet<u64>,
+    incoming: BinaryHeap<Reverse<(u64, u64)>>, // (arrival round, block ID) still on the wire
+}
+
+impl Node {
+    fn receive(&mut self, dag: &ToyDag, id: u64) {
+        for p in &dag.blocks[&id].parents {
+            self.tips.remove(p);
+        }
+        self.tips.insert(id);
+    }
+}
+
+pub struct NetworkRun {
+    pub dag: ToyDag,
+    pub blocks: Vec<Propagation>,       // Indexed by block ID; genesis and bootstrap blocks are known to everyone at round 0
+    pub known: usize,                   // Blocks every node starts with
+    pub latency: Vec<Vec<u64>>,         // latency[from][to] in rounds
+    pub links: Vec<Vec<LinkStats>>,     // links[from][to]
+    pub neighbors: Vec<Vec<usize>>,     // Peers each node relays to, sorted
+    pub rounds: u64,
+    pub mean_tips: f64,
+}
+
+// One relay over a link: its latency plus jitter, and another trip for every
+// attempt that lost too many packets to rebuild the block. Returns the rounds taken.
+fn relay<R: Rng>(args: &NetworkArgs, link: &mut LinkStats, latency: u64, rng: &mut R) -> u64 {
+    let source = args.block_symbols.max(1);
+    let mut rounds = latency + rng.gen_range(0..=args.jitter);
+    link.relays += 1;
+    loop {
+        let sent = source + link.repair;
+        let lost = (0..sent).filter(|_| rng.gen_bool(link.loss)).count();
+        link.sent += sent;
+        link.lost += lost;
+        if !args.fixed_repair {
+            link.tune(source);
+        }
+        if sent - lost >= source {
+            return rounds;
+        }
+        link.retries += 1;
+        rounds += latency;
+    }
+}
+
+pub fn simulate(args: &NetworkArgs) -> NetworkRun {
+    let mut rng = StdRng::seed_from_u64(args.seed);
+    let n = args.nodes.max(1);
+    let min = args.min_latency.max(1);
+    let max = args.max_latency.max(min);
+    let latency: Vec<Vec<u64>> = (0..n)
+        .map(|from| (0..n).map(|to| if from == to { 0 } else { rng.gen_range(min..=max) }).collect())
+        .collect();
+    let min_loss = args.min_loss.clamp(0.0, 0.99);
+    let max_loss = args.max_loss.clamp(min_loss, 0.99);
+    let mut links: Vec<Vec<LinkStats>> = (0..n)
+        .map(|_| {
+            (0..n)
+                .map(|_| LinkStats {
+                    loss: if max_loss > min_loss { rng.gen_range(min_loss..=max_loss) } else { min_loss },
+                    sent: 0,
+                    lost: 0,
+                    relays: 0,
+                    retries: 0,
+                    repair: args.repair,
+                })
+                .collect()
+        })
+        .collect();
+
+    let neighbors = topology::build(args.topology, n, args.degree, &mut rng);
+
+    let mut dag = ToyDag::with_params(args.consensus);
+    let mut blocks: Vec<Propagation> =
+        (0..dag.next_id).map(|_| Propagation { miner: 0, mined_at: 0, arrived_at: vec![0; n] }).collect();
+    let mut nodes: Vec<Node> = (0..n)
+        .map(|_| Node { tips: dag.tips.clone(), incoming: BinaryHeap::new() })
+        .collect();
+
+    let known = blocks.len();
+    let mut round = 0;
+    let mut tip_samples = 0;
+    while (dag.blocks.len() as u64) < args.blocks {
+        round += 1;
+        for node in nodes.iter_mut() {
+            while node.incoming.peek().is_some_and(|Reverse((arrival, _))| *arrival <= round) {
+                let Reverse((_, id)) = node.incoming.pop().unwrap();
+                node.receive(&dag, id);
+            }
+        }
+
+        for miner in 0..n {
+            if (dag.blocks.len() as u64) >= args.blocks || !rng.gen_bool(args.mine_chance.clamp(0.0, 1.0)) {
+                continue;
+            }
+            let mut parents: Vec<u64> = nodes[miner].tips.iter().copied().collect();
+            parents.sort();
+            let id = dag.create_block(parents);
+
+            // A node can only accept a block once it has every parent, so it never arrives before them.
+            // A relay that loses too many packets is asked for again, costing another trip.
+            let ready = |to: usize| dag.blocks[&id].parents.iter().map(|p| blocks[*p as usize].arrived_at[to]).max().unwrap_or(0);
+            let mut arrived_at = vec![round; n];
+            if args.topology == Topology::Mesh {
+                for to in (0..n).filter(|&to| to != miner) {
+                    arrived_at[to] = ready(to).max(round + relay(args, &mut links[miner][to], latency[miner][to], &mut rng));
+                }
+            } else {
+                // Hop by hop: each node passes the block on once it has accepted it, to
+                // every peer not yet known to have it. Pushes that race each other to
+                // the same peer all cost bandwidth; the first to land is the one that counts.
+                let mut accepted = vec![false; n];
+                let mut pushes = BinaryHeap::from([Reverse((round, miner))]);
+                while let Some(Reverse((at, node))) = pushes.pop() {
+                    if std::mem::replace(&mut accepted[node], true) {
+                        continue;
+                    }
+                    arrived_at[node] = at;
+                    for &to in neighbors[node].iter().filter(|&&to| !accepted[to]) {
+                        let took = relay(args, &mut links[node][to], latency[node][to], &mut rng);
+                        pushes.push(Reverse((ready(to).max(at + took), to)));
+                    }
+                }
+            }
+            for (to, node) in nodes.iter_mut().enumerate() {
+                if to != miner {
+                    node.incoming.push(Reverse((arrived_at[to], id)));
+                }
+            }
+            nodes[miner].receive(&dag, id);
+            blocks.push(Propagation { miner, mined_at: round, arrived_at });
+        }
+        tip_samples += dag.tips.len();
+    }
+
+    NetworkRun { dag, blocks, known, latency, links, neighbors, rounds: round, mean_tips: tip_samples as f64 / round.max(1) as f64 }
+}
+
+// What one node did with the blocks it was sent, up to the end of the run
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeMetrics {
+    pub accepted: usize,                // Blocks from other miners that had arrived by the last round
+    pub accepted_per_sec: f64,
+    pub mean_acceptance: f64,           // Rounds from a block's creation until this node accepted it
+    pub requests: usize,                // Relays it could not rebuild and asked for again
+}
+
+pub fn node_metrics(run: &NetworkRun, args: &NetworkArgs) -> Vec<NodeMetrics> {
+    let seconds = (run.rounds * args.round_ms) as f64 / 1000.0;
+    (0..run.latency.len())
+        .map(|node| {
+            let accepted: Vec<u64> = run.blocks[run.known..]
+                .iter()
+                .filter(|b| b.miner != node && b.arrived_at[node] <= run.rounds)
+                .map(|b| b.delay(node))
+                .collect();
+            NodeMetrics {
+                accepted: accepted.len(),
+                accepted_per_sec: if seconds > 0.0 { accepted.len() as f64 / seconds } else { 0.0 },
+                mean_acceptance: accepted.iter().sum::<u64>() as f64 / accepted.len().max(1) as f64,
+                requests: run.links.iter().map(|row| row[node].retries).sum(),
+            }
+        })
+        .collect()
+}
+
+pub fn prometheus(run: &NetworkRun, nodes: &[NodeMetrics]) -> String {
+    let reds = run.dag.blocks.values().filter(|b| b.color == Color::Red).count();
+    render(&[
+        Family::single("toyfec_network_rounds_total", "Rounds simulated", Kind::Counter, run.rounds as f64),
+        Family::single("toyfec_network_blocks", "Blocks in the DAG, genesis included", Kind::Gauge, run.dag.blocks.len() as f64),
+        Family::single("toyfec_network_red_blocks", "Blocks colored red", Kind::Gauge, reds as f64),
+        Family::per_node("toyfec_node_blocks_accepted_total", "Blocks from other miners the node accepted", Kind::Counter, nodes.iter().map(|m| m.accepted as f64)),
+        Family::per_node("toyfec_node_blocks_accepted_per_second", "Blocks from other miners accepted per second of simulated time", Kind::Gauge, nodes.iter().map(|m| m.accepted_per_sec)),
+        Family::per_node("toyfec_node_acceptance_rounds_mean", "Mean rounds from a block's creation to its acceptance", Kind::Gauge, nodes.iter().map(|m| m.mean_acceptance)),
+        Family::per_node("toyfec_node_block_requests_total", "Relays the node could not rebuild and requested again", Kind::Counter, nodes.iter().map(|m| m.requests as f64)),
+    ])
+}
+
+// Delays of the blocks a node heard about from others, sorted
+fn delays(run: &NetworkRun, node: usize) -> Vec<u64> {
+    let mut delays: Vec<u64> = run.blocks[run.known..].iter().filter(|b| b.miner != node).map(|b| b.delay(node)).collect();
+    delays.sort();
+    delays
+}
+
+pub fn percentile(sorted: &[u64], p: f64) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    sorted[((sorted.len() - 1) as f64 * p).round() as usize]
+}
+
+// What the model predicts for a block mined well inside the run
+pub struct AnticoneModel {
+    pub rate: f64,                      // Blocks per round across the network
+    pub delay: f64,                     // Mean relay time in rounds
+    pub mean: f64,
+    pub exact: Vec<f64>,                // P(anticone size = k) for independent trials
+    pub poisson: Vec<f64>,              // The Poisson law of the same mean
+}
+
+// Chance one relay takes longer than `rounds`, with the link latency and
+// jitter both uniform over their ranges
+fn slower_than(args: &NetworkArgs, rounds: u64) -> f64 {
+    let min = args.min_latency.max(1);
+    let max = args.max_latency.max(min);
+    let relays = (min..=max).flat_map(|l| (0..=args.jitter).map(move |j| l + j));
+    relays.clone().filter(|&d| d > rounds).count() as f64 / relays.count() as f64
+}
+
+// A block another node mined Δ rounds before or after B is in B's anticone
+// when the relay between their miners took longer than Δ: neither miner had
+// the other's block yet. Blocks from B's own round always are, and the
+// miner's own blocks never. Each other node mines each round with chance p,
+// so the anticone size is a sum of independent trials with chance p·P(d > Δ),
+// and tends to a Poisson law of the same mean as blocks get rarer. Retries
+// after loss and waiting for parents are left out, so the model reads the
+// configured delay as the whole delay.
+pub fn anticone_model(args: &NetworkArgs) -> AnticoneModel {
+    let p = args.mine_chance.clamp(0.0, 1.0);
+    let n = args.nodes.max(1);
+    let mut trials = vec![p; n - 1];
+    for offset in 1.. {
+        let slower = slower_than(args, offset);
+        if slower == 0.0 {
+            break;
+        }
+        trials.extend(std::iter::repeat_n(p * slower, 2 * (n - 1)));
+    }
+    let mut exact = vec![1.0];
+    for &t in &trials {
+        let mut next = vec![0.0; exact.len() + 1];
+        for (k, &pk) in exact.iter().enumerate() {
+            next[k] += pk * (1.0 - t);
+            next[k + 1] += pk * t;
+        }
+        exact = next;
+    }
+    let mean: f64 = trials.iter().sum();
+    let mut poisson = vec![(-mean).exp()];
+    for k in 1..exact.len() {
+        poisson.push(poisson[k - 1] * mean / k as f64);
+    }
+    let min = args.min_latency.max(1);
+    let delay = (min + args.max_latency.max(min)) as f64 / 2.0 + args.jitter as f64 / 2.0;
+    AnticoneModel { rate: n as f64 * p, delay, mean, exact, poisson }
+}
+
+// Anticone sizes of the blocks mined far enough from either end of the run
+// that every block which could share their anticone was mined too
+pub fn anticone_sizes(run: &NetworkRun, args: &NetworkArgs) -> Vec<(u64, usize)> {
+    let horizon = args.max_latency.max(args.min_latency.max(1)) + args.jitter;
+    run.blocks
+        .iter()
+        .enumerate()
+        .skip(run.known)
+        .filter(|(_, b)| b.mined_at >= horizon && b.mined_at + horizon < run.rounds)
+        .map(|(id, _)| (id as u64, run.dag.anticone_size(id as u64)))
+        .collect()
+}
+
+fn report_anticones(run: &NetworkRun, args: &NetworkArgs) {
+    let model = anticone_model(args);
+    let sizes = anticone_sizes(run, args);
+    let count = sizes.len().max(1) as f64;
+    let mut observed = vec![0.0; model.exact.len()];
+    for &(_, size) in &sizes {
+        observed[size.min(model.exact.len() - 1)] += 1.0 / count;
+    }
+    let shown = (0..observed.len()).rev().find(|&k| observed[k].max(model.exact[k]) >= 0.005).unwrap_or(0) + 1;
+    let tail = |dist: &[f64]| dist[shown.min(dist.len())..].iter().sum::<f64>();
+
+    println!();
+    println!(
+        "Anticone sizes of {} blocks mined away from the run's edges, against the model ({:.2} blocks/round, mean delay {:.1} rounds):",
+        sizes.len(),
+        model.rate,
+        model.delay
+    );
+    println!("{:>6} {:>10} {:>8} {:>8}", "size", "simulated", "model", "poisson");
+    for (k, ((o, e), p)) in observed.iter().zip(&model.exact).zip(&model.poisson).take(shown).enumerate() {
+        println!("{:>6} {:>10.3} {:>8.3} {:>8.3}", k, o, e, p);
+    }
+    if shown < observed.len() {
+        println!("{:>6} {:>10.3} {:>8.3} {:>8.3}", format!("{}+", shown), tail(&observed), tail(&model.exact), tail(&model.poisson));
+    }
+    let mean = sizes.iter().map(|&(_, s)| s as f64).sum::<f64>() / count;
+    println!("{:>6} {:>10.2} {:>8.2} {:>8.2}", "mean", mean, model.mean, model.mean);
+    let distance = |dist: &[f64]| observed.iter().zip(dist).map(|(o, e)| (o - e).abs()).sum::<f64>() / 2.0;
+    println!("Total variation distance from the simulation: model {:.3}, Poisson {:.3}", distance(&model.exact), distance(&model.poisson));
+
+    // A block whose anticone holds more than k blocks cannot have them all blue,
+    // so the share above k is a rough guide to how many end up red
+    let k = args.consensus.k;
+    let reds = sizes.iter().filter(|&&(id, _)| run.dag.blocks[&id].color == Color::Red).count();
+    println!(
+        "Anticone above k={}: simulated {:.3}, model {:.3}, Poisson {:.3}; red ratio of these blocks {:.3}",
+        k,
+        sizes.iter().filter(|&&(_, s)| s > k).count() as f64 / count,
+        model.exact.iter().skip(k + 1).sum::<f64>(),
+        model.poisson.iter().skip(k + 1).sum::<f64>(),
+        reds as f64 / count
+    );
+    println!("The model leaves out retries after loss and waiting for parents, which only lengthen delays.");
+}
+
+pub fn write_csv<W: Write + ?Sized>(run: &NetworkRun, out: &mut W) -> io::Result<()> {
+    writeln!(out, "block,miner,mined_at,node,arrived_at,delay")?;
+    for (id, block) in run.blocks.iter().enumerate().skip(run.known) {
+        for (node, arrived) in block.arrived_at.iter().enumerate() {
+            writeln!(out, "{},{},{},{},{},{}", id, block.miner, block.mined_at, node, arrived, block.delay(node))?;
+        }
+    }
+    Ok(())
+}
+
+pub fn run(args: &NetworkArgs) -> io::Result<()> {
+    let run = simulate(args);
+    let n = run.latency.len();
+    let reds = run.dag.blocks.values().filter(|b| b.color == Color::Red).count();
+    let merged = run.dag.blocks.values().map(|b| b.mergeset.len()).sum::<usize>();
+
+    println!(
+        "=== Multi-node propagation ({} nodes, {} topology, latency {}..={} + jitter {}, seed {}) ===",
+        n,
+        args.topology.name(),
+        args.min_latency.max(1),
+        args.max_latency.max(args.min_latency.max(1)),
+        args.jitter,
+        args.seed
+    );
+    println!("Blocks: {} over {} rounds", run.dag.blocks.len(), run.rounds);
+    println!(
+        "Mean tips: {:.2}   red ratio: {:.3}   mean mergeset: {:.2}",
+        run.mean_tips,
+        reds as f64 / run.dag.blocks.len() as f64,
+        merged as f64 / run.dag.blocks.len() as f64
+    );
+    println!("Reorgs: {} (count×depth: {})", run.dag.reorgs.len(), ghostdag::describe_reorgs(&run.dag.reorgs));
+    println!();
+    println!("Propagation delay (rounds from mining to arrival) of blocks mined elsewhere:");
+    println!("{:<6} {:>6} {:>11} {:>8} {:>6} {:>6} {:>6}", "node", "mined", "mean link", "mean", "p50", "p90", "max");
+    let mut all = Vec::new();
+    for node in 0..n {
+        let heard = delays(&run, node);
+        let inbound = (0..n).filter(|&from| from != node).map(|from| run.latency[from][node]).sum::<u64>();
+        println!(
+            "{:<6} {:>6} {:>11.2} {:>8.2} {:>6} {:>6} {:>6}",
+            node,
+            run.blocks[run.known..].iter().filter(|b| b.miner == node).count(),
+            inbound as f64 / (n - 1).max(1) as f64,
+            heard.iter().sum::<u64>() as f64 / heard.len().max(1) as f64,
+            percentile(&heard, 0.5),
+            percentile(&heard, 0.9),
+            heard.last().copied().unwrap_or(0)
+        );
+        all.extend(heard);
+    }
+    all.sort();
+    println!(
+        "{:<6} {:>6} {:>11} {:>8.2} {:>6} {:>6} {:>6}",
+        "all",
+        run.blocks.len() - run.known,
+        "",
+        all.iter().sum::<u64>() as f64 / all.len().max(1) as f64,
+        percentile(&all, 0.5),
+        percentile(&all, 0.9),
+        all.last().copied().unwrap_or(0)
+    );
+    println!();
+    let throughput = node_metrics(&run, args);
+    println!("Per-node throughput ({} ms rounds; acceptance counts blocks that arrived by the last round):", args.round_ms);
+    println!("{:<6} {:>9} {:>11} {:>15} {:>9}", "node", "accepted", "blocks/sec", "mean accept", "requests");
+    for (node, m) in throughput.iter().enumerate() {
+        println!("{:<6} {:>9} {:>11.3} {:>15.2} {:>9}", node, m.accepted, m.accepted_per_sec, m.mean_acceptance, m.requests);
+    }
+    println!();
+    println!(
+        "Per-link FEC ({} source symbols per relay, repair {}):",
+        args.block_symbols.max(1),
+        if args.fixed_repair { "fixed" } else { "tuned to observed loss" }
+    );
+    println!("{:<8} {:>6} {:>9} {:>7} {:>8} {:>8} {:>7}", "link", "loss", "observed", "relays", "packets", "retries", "repair");
+    let (mut sent, mut retries, mut relays) = (0, 0, 0);
+    for (from, row) in run.links.iter().enumerate() {
+        for (to, link) in row.iter().enumerate().filter(|&(to, _)| run.neighbors[from].contains(&to)) {
+            println!(
+                "{:<8} {:>6.3} {:>9.3} {:>7} {:>8} {:>8} {:>7}",
+                format!("{}->{}", from, to),
+                link.loss,
+                link.observed_loss(),
+                link.relays,
+                link.sent,
+                link.retries,
+                link.repair
+            );
+            sent += link.sent;
+            retries += link.retries;
+            relays += link.relays;
+        }
+    }
+    let minimum = relays * args.block_symbols.max(1);
+    println!(
+        "{} relays, {} packets ({:.1}% over the {} a lossless link needs), {} retries",
+        relays,
+        sent,
+        (sent as f64 / minimum.max(1) as f64 - 1.0) * 100.0,
+        minimum,
+        retries
+    );
+    if args.anticones {
+        report_anticones(&run, args);
+    }
+    println!("==================================================================");
+
+    if let Some(path) = &args.output {
+        write_csv(&run, &mut File::create(path)?)?;
+        println!("Wrote {} arrivals to {}", (run.blocks.len() - run.known) * n, path.display());
+    }
+    if let Some(path) = &args.metrics {
+        std::fs::write(path, prometheus(&run, &throughput))?;
+        println!("Wrote per-node metrics to {}", path.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::{anticone_model, anticone_sizes, node_metrics, simulate, NetworkArgs};
+    use crate::stattest::{assert_fits, assert_ks, histogram};
+    use crate::topology::Topology;
+    use crate::ConsensusParams;
+
+    pub(crate) fn args(nodes: usize, blocks: u64) -> NetworkArgs {
+        NetworkArgs {
+            nodes,
+            blocks,
+            mine_chance: 0.25,
+            min_latency: 2,
+            max_latency: 9,
+            jitter: 1,
+            topology: Topology::Mesh,
+            degree: 4,
+            min_loss: 0.05,
+            max_loss: 0.3,
+            block_symbols: 4,
+            repair: 1,
+            fixed_repair: false,
+            seed: 11,
+            round_ms: 1000,
+            output: None,
+            metrics: None,
+            anticones: false,
+            consensus: ConsensusParams::default(),
+        }
+    }
+
+    #[test]
+    fn link_latencies_are_uniform() {
+        let args = args(40, 20);
+        let run = simulate(&args);
+        let latencies: Vec<usize> = run
+            .latency
+            .iter()
+            .enumerate()
+            .flat_map(|(from, row)| row.iter().enumerate().filter(move |&(to, _)| to != from).map(|(_, &l)| l as usize))
+            .collect();
+        let (min, max) = (args.min_latency as usize, args.max_latency as usize);
+        let width = (max - min + 1) as f64;
+        assert_fits("latency", &histogram(&latencies, min, max), &vec![1.0 / width; max - min + 1]);
+    }
+
+    #[test]
+    fn link_loss_rates_are_uniform() {
+        let args = args(40, 20);
+        let run = simulate(&args);
+        let rates: Vec<f64> = run.links.iter().flatten().map(|l| l.loss).collect();
+        let (lo, hi) = (args.min_loss, args.max_loss);
+        assert_ks("link loss", rates, |x| ((x - lo) / (hi - lo)).clamp(0.0, 1.0));
+    }
+
+    #[test]
+    fn blocks_per_round_are_binomial() {
+        let args = args(8, 500);
+        let run = simulate(&args);
+        let mut per_round = vec![0; run.rounds as usize + 1];
+        for b in &run.blocks[run.known..] {
+            per_round[b.mined_at as usize] += 1;
+        }
+        // The last round stops early once the target is reached
+        let counts = &per_round[1..run.rounds as usize];
+        let p = args.mine_chance;
+        let n = args.nodes as i32;
+        let binomial: Vec<f64> = (0..=n)
+            .map(|k| (1..=k).map(|i| (n - k + i) as f64 / i as f64).product::<f64>() * p.powi(k) * (1.0 - p).powi(n - k))
+            .collect();
+        assert_fits("blocks per round", &histogram(counts, 0, args.nodes), &binomial);
+    }
+
+    #[test]
+    fn anticones_follow_the_model_on_fixed_lossless_links() {
+        let mut args = args(8, 600);
+        (args.min_latency, args.max_latency, args.jitter, args.max_loss, args.min_loss) = (3, 3, 0, 0.0, 0.0);
+        let run = simulate(&args);
+        let model = anticone_model(&args);
+        // Five rounds of seven other nodes at 0.25: binomial with mean 8.75
+        assert!((model.mean - 8.75).abs() < 1e-9, "{}", model.mean);
+        let sizes: Vec<usize> = anticone_sizes(&run, &args).into_iter().map(|(_, s)| s).collect();
+        let mut expected = model.exact.clone();
+        let tail: f64 = expected.drain(16..).sum();
+        expected.push(tail);
+        assert_fits("anticone size", &histogram(&sizes, 0, 16), &expected);
+    }
+
+    #[test]
+    fn node_metrics_count_what_each_node_accepted() {
+        let mut args = args(6, 200);
+        (args.min_latency, args.max_latency, args.jitter, args.round_ms) = (2, 2, 0, 500);
+        let run = simulate(&args);
+        let metrics = node_metrics(&run, &args);
+        let retries: usize = run.links.iter().flatten().map(|l| l.retries).sum();
+        assert_eq!(metrics.iter().map(|m| m.requests).sum::<usize>(), retries);
+        for (node, m) in metrics.iter().enumerate() {
+            // Blocks mined in the last rounds are still on the wire; everything else got through
+            let late = run.blocks[run.known..].iter().filter(|b| b.miner != node && b.arrived_at[node] > run.rounds).count();
+            let others = run.blocks[run.known..].iter().filter(|b| b.miner != node).count();
+            assert_eq!(m.accepted + late, others);
+            assert!((m.accepted_per_sec - m.accepted as f64 / (run.rounds as f64 / 2.0)).abs() < 1e-9);
+            // Two rounds on the wire, plus another two per request
+            assert!(m.mean_acceptance >= 2.0, "{:?}", m);
+        }
+    }
+}
@@ -0,0 +1,164 @@
+// How many symbols beyond the source count a decoder needs, as a
+// distribution over many trials rather than one mean. RaptorQ decodes from
+// exactly K received symbols most of the time and needs one or two more in
+// the rest, and that small tail is what sizing repair against is about.
+
+use std::fs;
+use std::path::PathBuf;
+
+use clap::Args;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::erasure::{self, CodeKind};
+use crate::failure::Failure;
+use crate::fec::OverheadStats;
+use crate::pipeline::FecPipeline;
+use crate::SYMBOL_SIZE;
+
+const BAR: usize = 50;                  // Characters in the longest histogram bar
+
+#[derive(Args, Debug)]
+pub struct OverheadArgs {
+    /// Code family to measure
+    #[arg(long, value_enum, default_value_t)]
+    pub code: CodeKind,
+
+    /// Object size in bytes (default: the demo's 151 block hashes)
+    #[arg(long, default_value_t = 151 * 32)]
+    pub size: usize,
+
+    /// Bytes per symbol
+    #[arg(long, default_value_t = SYMBOL_SIZE)]
+    pub symbol_size: u16,
+
+    /// Decode trials, each under its own loss pattern
+    #[arg(long, default_value_t = 5000)]
+    pub trials: usize,
+
+    /// Chance each packet is lost; at 0 the source packets alone always decode, so the default mixes in repair
+    #[arg(long, default_value_t = 0.5)]
+    pub loss: f64,
+
+    /// Seed for the loss patterns
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+
+    /// Write the histogram as CSV here
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+}
+
+// Enough repair that a trial practically never runs out of packets before it
+// decodes, so failures mean the code, not the budget
+fn repair_for(source: usize, loss: f64) -> u32 {
+    let delivered = (1.0 - loss).max(0.01);
+    ((source as f64 + 20.0) / delivered * 1.5).ceil() as u32
+}
+
+pub fn measure(args: &OverheadArgs) -> Result<OverheadStats, Failure> {
+    if !(0.0..1.0).contains(&args.loss) {
+        return Err(Failure::Config(format!("--loss {} is not in [0, 1)", args.loss)));
+    }
+    let pipeline = FecPipeline::builder().symbol_size(args.symbol_size).code(args.code).build().map_err(Failure::Config)?;
+    let code = pipeline.erasure_code();
+    let mut data = vec![0u8; args.size.max(1)];
+    StdRng::seed_from_u64(0).fill(&mut data[..]);
+    let source = pipeline.source_symbols(data.len());
+    let repair = repair_for(source, args.loss);
+    let packets = code.encode(&data, repair);
+
+    let mut rng = StdRng::seed_from_u64(args.seed);
+    let mut stats = OverheadStats::default();
+    for _ in 0..args.trials {
+        let received = packets.iter().filter(|_| !rng.gen_bool(args.loss)).cloned().collect();
+        stats.record(&erasure::decode_with(code.as_ref(), data.len(), repair, received));
+    }
+    Ok(stats)
+}
+
+pub fn write_csv(stats: &OverheadStats) -> String {
+    let runs = stats.runs().max(1) as f64;
+    let mut csv = String::from("extra_symbols,runs,fraction,cumulative\n");
+    let mut cumulative = 0;
+    for (extra, &count) in stats.histogram().iter().enumerate() {
+        cumulative += count;
+        csv += &format!("{},{},{:.6},{:.6}\n", extra, count, count as f64 / runs, cumulative as f64 / runs);
+    }
+    csv += &format!("failed,{},{:.6},1.000000\n", stats.failures(), stats.failures() as f64 / runs);
+    csv
+}
+
+// One row per extra-symbol count, bars scaled to the most common count; any
+// nonzero count gets at least one mark so the rare tail stays visible
+pub fn ascii_histogram(stats: &OverheadStats) -> Vec<String> {
+    let tallest = stats.histogram().iter().copied().chain([stats.failures()]).max().unwrap_or(0).max(1);
+    let runs = stats.runs().max(1) as f64;
+    let bar = |count: usize| "#".repeat(if count == 0 { 0 } else { (count * BAR).div_ceil(tallest) });
+    let mut rows: Vec<String> = stats
+        .histogram()
+        .iter()
+        .enumerate()
+        .map(|(extra, &count)| format!("{:>7} | {:<w$} {:>6} ({:.3}%)", format!("+{}", extra), bar(count), count, count as f64 / runs * 100.0, w = BAR))
+        .collect();
+    if stats.failures() > 0 {
+        let failures = stats.failures();
+        rows.push(format!("{:>7} | {:<w$} {:>6} ({:.3}%)", "failed", bar(failures), failures, failures as f64 / runs * 100.0, w = BAR));
+    }
+    rows
+}
+
+pub fn run(args: &OverheadArgs) -> Result<(), Failure> {
+    let stats = measure(args)?;
+    let source = args.size.max(1).div_ceil(args.symbol_size as usize);
+    println!(
+        "=== Decoding overhead: {} over {} trials ({} source symbols of {} bytes, {:.0}% loss, seed {}) ===",
+        args.code.code(args.symbol_size).name(),
+        stats.runs(),
+        source,
+        args.symbol_size,
+        args.loss * 100.0,
+        args.seed
+    );
+    println!("Extra symbols received beyond {} before the object decoded:", source);
+    for row in ascii_histogram(&stats) {
+        println!("{}", row);
+    }
+    // P(a decoder still fails after K + n symbols) is what a repair budget of n buys
+    let runs = stats.runs().max(1) as f64;
+    let mut needed_more = stats.runs();
+    let tail: Vec<String> = (0..3)
+        .map(|n| {
+            needed_more -= stats.histogram().get(n).copied().unwrap_or(0);
+            format!("K+{}: {:.3}%", n, needed_more as f64 / runs * 100.0)
+        })
+        .collect();
+    println!("Still undecoded after  {}", tail.join("   "));
+    println!("Mean overhead: {:.4} symbols   max: {}", stats.mean_overhead(), stats.max_overhead());
+    println!("==================================================================");
+
+    if let Some(path) = &args.output {
+        fs::write(path, write_csv(&stats)).map_err(|e| Failure::Io(format!("cannot write {}: {}", path.display(), e)))?;
+        println!("Wrote the histogram to {}", path.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raptorq_mostly_decodes_from_exactly_k_symbols() {
+        let args = OverheadArgs { code: CodeKind::Raptorq, size: 1000, symbol_size: 100, trials: 300, loss: 0.5, seed: 1, output: None };
+        let stats = measure(&args).unwrap();
+        assert_eq!(stats.histogram().iter().sum::<usize>() + stats.failures(), 300);
+        assert_eq!(stats.failures(), 0);
+        // RFC 6330 puts failure at zero overhead around 1%; allow plenty of slack for 300 trials
+        assert!(stats.histogram()[0] >= 280, "{:?}", stats.histogram());
+        let csv = write_csv(&stats);
+        assert!(csv.starts_with("extra_symbols,runs,fraction,cumulative\n0,"), "{}", csv);
+        assert!(csv.ends_with("failed,0,0.000000,1.000000\n"), "{}", csv);
+        assert_eq!(ascii_histogram(&stats).len(), stats.histogram().len());
+    }
+}
@@ -0,0 +1,136 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use clap::Args;
+
+// How fast a real-transport sender may put datagrams on the wire; unpaced when no rate is given
+#[derive(Args, Debug, Clone, Copy, Default)]
+pub struct PacingArgs {
+    /// Cap the send rate at this many packets per second
+    #[arg(long, conflicts_with = "bytes_per_sec")]
+    pub packets_per_sec: Option<f64>,
+
+    /// Cap the send rate at this many bytes per second, counting whole datagrams
+    #[arg(long)]
+    pub bytes_per_sec: Option<f64>,
+
+    /// Packets (or bytes, with --bytes-per-sec) that may go out back to back after a pause; one packet's worth when omitted
+    #[arg(long)]
+    pub burst: Option<f64>,
+}
+
+impl PacingArgs {
+    pub fn bucket(&self) -> Option<TokenBucket> {
+        let (rate, per_byte) = match (self.packets_per_sec, self.bytes_per_sec) {
+            (Some(rate), _) => (rate, false),
+            (None, Some(rate)) => (rate, true),
+            (None, None) => return None,
+        };
+        Some(TokenBucket {
+            rate: rate.max(f64::MIN_POSITIVE),
+            burst: self.burst,
+            per_byte,
+            tokens: self.burst.unwrap_or(f64::MAX),
+            last: None,
+            waited: Duration::ZERO,
+        })
+    }
+
+    pub fn describe(&self) -> String {
+        let burst = |unit: &str| self.burst.map_or(String::new(), |b| format!(", bursts of {} {}", b, unit));
+        match (self.packets_per_sec, self.bytes_per_sec) {
+            (Some(rate), _) => format!("paced at {} packets/s{}", rate, burst("packets")),
+            (None, Some(rate)) => format!("paced at {} bytes/s{}", rate, burst("bytes")),
+            (None, None) => "unpaced".into(),
+        }
+    }
+}
+
+// Tokens refill at `rate` up to the burst size and each datagram spends its
+// cost. A datagram may run the bucket into debt; the sender then sleeps until
+// the debt is paid back, so the long-run rate never exceeds `rate`.
+pub struct TokenBucket {
+    rate: f64,
+    burst: Option<f64>,
+    per_byte: bool,
+    tokens: f64,
+    last: Option<Instant>,
+    waited: Duration,                   // Total time spent sleeping for tokens
+}
+
+impl TokenBucket {
+    // How long to hold a `len` byte datagram that is ready at `now`
+    pub fn delay(&mut self, len: usize, now: Instant) -> Duration {
+        let cost = if self.per_byte { len as f64 } else { 1.0 };
+        let elapsed = self.last.map_or(0.0, |last| now.saturating_duration_since(last).as_secs_f64());
+        self.last = Some(now);
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.burst.unwrap_or(cost));
+        self.tokens -= cost;
+        if self.tokens >= 0.0 { Duration::ZERO } else { Duration::from_secs_f64(-self.tokens / self.rate) }
+    }
+
+    // Sleep until a `len` byte datagram may go out
+    pub fn wait(&mut self, len: usize) {
+        let delay = self.delay(len, Instant::now());
+        if !delay.is_zero() {
+            thread::sleep(delay);
+            self.waited += delay;
+        }
+    }
+
+    pub fn waited(&self) -> Duration {
+        self.waited
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bucket(packets_per_sec: Option<f64>, bytes_per_sec: Option<f64>, burst: Option<f64>) -> TokenBucket {
+        PacingArgs { packets_per_sec, bytes_per_sec, burst }.bucket().unwrap()
+    }
+
+    // Send `count` datagrams as fast as the bucket allows, returning when the last one left
+    fn drain(bucket: &mut TokenBucket, count: usize, len: usize, start: Instant) -> Duration {
+        let mut now = start;
+        for _ in 0..count {
+            now += bucket.delay(len, now);
+        }
+        now - start
+    }
+
+    #[test]
+    fn burst_goes_out_at_once_then_the_rate_holds() {
+        let mut b = bucket(Some(100.0), None, Some(10.0));
+        let start = Instant::now();
+        assert_eq!(drain(&mut b, 10, 1000, start), Duration::ZERO);
+        // Another 100 packets take a second at 100 per second
+        let took = drain(&mut b, 100, 1000, start).as_secs_f64();
+        assert!((took - 1.0).abs() < 1e-6, "took {}s", took);
+    }
+
+    #[test]
+    fn byte_rate_counts_datagram_sizes() {
+        let mut b = bucket(None, Some(10_000.0), None);
+        let start = Instant::now();
+        // 1 + 20 datagrams of 500 bytes: the first is free, the rest need a second of tokens
+        let took = drain(&mut b, 21, 500, start).as_secs_f64();
+        assert!((took - 1.0).abs() < 1e-6, "took {}s", took);
+    }
+
+    #[test]
+    fn idle_time_refills_only_up_to_the_burst() {
+        let mut b = bucket(Some(10.0), None, Some(3.0));
+        let start = Instant::now();
+        drain(&mut b, 3, 100, start);
+        let later = start + Duration::from_secs(60);
+        assert_eq!(drain(&mut b, 3, 100, later), Duration::ZERO);
+        assert_eq!(b.delay(100, later), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn no_rate_means_no_bucket() {
+        assert!(PacingArgs::default().bucket().is_none());
+    }
+}
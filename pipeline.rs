@@ -0,0 +1,181 @@
+// One validated FEC configuration: which code, how big a symbol, and how much
+// repair to send per source packet. The CLI commands and library callers build
+// one of these instead of pairing loose constants with a code by hand, so a
+// symbol size the code cannot take is refused up front rather than panicking
+// deep inside an encoder.
+
+use raptorq::Encoder;
+
+use crate::erasure::{CodedPacket, ErasureCode, ErasureDecoder, RaptorQCode};
+use crate::fec;
+use crate::{OVERHEAD_RATIO, SYMBOL_SIZE};
+
+pub use crate::erasure::CodeKind;
+pub use crate::frame::{FrameHeader, FramedPacket, Frames};
+
+const RAPTORQ_ALIGNMENT: u16 = 8;       // RaptorQ needs at least one aligned unit per symbol
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FecPipeline {
+    symbol_size: u16,
+    overhead_ratio: f64,
+    code: CodeKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FecPipelineBuilder {
+    symbol_size: u16,
+    overhead_ratio: f64,
+    code: CodeKind,
+}
+
+impl Default for FecPipelineBuilder {
+    fn default() -> Self {
+        FecPipelineBuilder { symbol_size: SYMBOL_SIZE, overhead_ratio: OVERHEAD_RATIO, code: CodeKind::default() }
+    }
+}
+
+impl FecPipelineBuilder {
+    pub fn symbol_size(mut self, symbol_size: u16) -> Self {
+        self.symbol_size = symbol_size;
+        self
+    }
+
+    // Repair packets per source packet (per source block, for RaptorQ)
+    pub fn overhead_ratio(mut self, overhead_ratio: f64) -> Self {
+        self.overhead_ratio = overhead_ratio;
+        self
+    }
+
+    pub fn code(mut self, code: CodeKind) -> Self {
+        self.code = code;
+        self
+    }
+
+    pub fn build(self) -> Result<FecPipeline, String> {
+        let smallest = match self.code {
+            CodeKind::Raptorq => RAPTORQ_ALIGNMENT,
+            CodeKind::Ldpc => 1,
+        };
+        if self.symbol_size < smallest {
+            return Err(format!("{} byte symbols are too small for {} (at least {})", self.symbol_size, self.code.code(self.symbol_size).name(), smallest));
+        }
+        if !self.overhead_ratio.is_finite() || self.overhead_ratio < 0.0 {
+            return Err(format!("overhead ratio {} is not a non-negative number", self.overhead_ratio));
+        }
+        Ok(FecPipeline { symbol_size: self.symbol_size, overhead_ratio: self.overhead_ratio, code: self.code })
+    }
+}
+
+impl Default for FecPipeline {
+    fn default() -> Self {
+        FecPipelineBuilder::default().build().expect("the defaults are valid")
+    }
+}
+
+impl FecPipeline {
+    pub fn builder() -> FecPipelineBuilder {
+        FecPipelineBuilder::default()
+    }
+
+    pub fn symbol_size(&self) -> u16 {
+        self.symbol_size
+    }
+
+    pub fn overhead_ratio(&self) -> f64 {
+        self.overhead_ratio
+    }
+
+    pub fn code(&self) -> CodeKind {
+        self.code
+    }
+
+    pub(crate) fn erasure_code(&self) -> Box<dyn ErasureCode> {
+        self.code.code(self.symbol_size)
+    }
+
+    // Source symbols the object splits into, across all source blocks
+    pub fn source_symbols(&self, data_len: usize) -> usize {
+        self.erasure_code().decoder(data_len, 0).source_symbols()
+    }
+
+    // Repair packets the ratio buys for an object this size. RaptorQ sends
+    // that many for every source block, so it is sized from the largest one.
+    pub fn repair_for(&self, data_len: usize) -> u32 {
+        let block = match self.code {
+            CodeKind::Raptorq => {
+                let config = RaptorQCode { symbol_size: self.symbol_size }.config(data_len);
+                fec::source_block_symbols(&config).into_iter().max().unwrap_or(0) as usize
+            }
+            CodeKind::Ldpc => self.source_symbols(data_len),
+        };
+        (block as f64 * self.overhead_ratio).ceil() as u32
+    }
+
+    // Source packets, then the repair packets the ratio calls for
+    pub fn encode(&self, data: &[u8]) -> Vec<CodedPacket> {
+        self.erasure_code().encode(data, self.repair_for(data.len()))
+    }
+
+    // A decoder for an object this pipeline encoded
+    pub fn decoder(&self, data_len: usize) -> Box<dyn ErasureDecoder> {
+        self.erasure_code().decoder(data_len, self.repair_for(data_len))
+    }
+
+    // The object's framed packets, made lazily and without end: take the
+    // source packets plus however much repair the channel budget allows.
+    // Frames carry a RaptorQ config, so only RaptorQ pipelines have them.
+    pub fn frames(&self, data: &[u8], session: u32, object: u32) -> Result<Frames, String> {
+        if self.code != CodeKind::Raptorq {
+            return Err(format!("{} has no frame format; frames carry a RaptorQ config", self.erasure_code().name()));
+        }
+        if data.is_empty() {
+            return Err("an empty object has no packets".into());
+        }
+        let config = RaptorQCode { symbol_size: self.symbol_size }.config(data.len());
+        Ok(Frames::new(&Encoder::new(data, config), session, object))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame;
+
+    #[test]
+    fn builder_validates_and_sizes_repair() {
+        let pipeline = FecPipeline::builder().symbol_size(128).overhead_ratio(0.3).code(CodeKind::Raptorq).build().unwrap();
+        assert_eq!(pipeline, FecPipeline::default());
+        assert_eq!(pipeline.source_symbols(1000), 8);
+        assert_eq!(pipeline.repair_for(1000), 3);
+        let data: Vec<u8> = (0..1000).map(|i| i as u8).collect();
+        let packets = pipeline.encode(&data);
+        assert_eq!(packets.len(), 11);
+        // Three source packets lost, three repair packets make up for them
+        let mut decoder = pipeline.decoder(data.len());
+        let recovered = packets[3..].iter().cloned().find_map(|p| decoder.push(p));
+        assert_eq!(recovered.as_deref(), Some(&data[..]));
+
+        assert!(FecPipeline::builder().symbol_size(4).build().is_err());
+        assert!(FecPipeline::builder().symbol_size(4).code(CodeKind::Ldpc).build().is_ok());
+        assert!(FecPipeline::builder().overhead_ratio(-0.1).build().is_err());
+        assert!(FecPipeline::builder().overhead_ratio(f64::NAN).build().is_err());
+    }
+
+    #[test]
+    fn frames_yield_source_then_fresh_repair() {
+        let pipeline = FecPipeline::default();
+        let data: Vec<u8> = (0..1000).map(|i| (i * 7) as u8).collect();
+        let mut frames = pipeline.frames(&data, 5, 9).unwrap();
+        let esis: Vec<u32> = frames.by_ref().take(8).map(|f| f.packet.payload_id().encoding_symbol_id()).collect();
+        assert_eq!(esis, (0..8).collect::<Vec<_>>());
+
+        // What was not taken before is still there, and it is repair the encoder also makes eagerly
+        let eager = Encoder::new(&data, frames.header().config).get_encoded_packets(4);
+        let repair: Vec<FramedPacket> = frames.take(4).collect();
+        assert!(repair.iter().all(|f| f.header.key() == (5, 9)));
+        assert_eq!(repair.iter().map(|f| f.packet.clone()).collect::<Vec<_>>(), eager[8..]);
+        assert!(frame::decode_frame(&repair[0].to_bytes()).is_some());
+        assert!(FecPipeline::builder().code(CodeKind::Ldpc).build().unwrap().frames(&data, 0, 0).is_err());
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::HashSet;
+
+use proptest::prelude::*;
+use proptest::sample::Index;
+
+use crate::{Color, ConsensusParams, ToyDag};
+
+// One block to add: parents picked from the current tips, or from any block
+// to grow deep side branches and late merges
+#[derive(Debug, Clone)]
+struct Step {
+    from_tips: bool,
+    parents: Vec<Index>,
+}
+
+fn step() -> impl Strategy<Value = Step> {
+    (any::<bool>(), prop::collection::vec(any::<Index>(), 1..5)).prop_map(|(from_tips, parents)| Step { from_tips, parents })
+}
+
+// Small k and parent limits so reds and dropped parents actually show up
+fn params() -> impl Strategy<Value = ConsensusParams> {
+    (0usize..5, 1usize..6)
+        .prop_map(|(k, max_parents)| ConsensusParams { k, max_parents, ..ConsensusParams::default() })
+}
+
+// Replay the steps, dropping any parent already in another chosen parent's past
+fn grow(params: ConsensusParams, steps: &[Step]) -> ToyDag {
+    let mut dag = ToyDag::with_params(params);
+    for step in steps {
+        let mut pool: Vec<u64> = if step.from_tips { dag.tips.iter().copied().collect() } else { (0..dag.next_id).collect() };
+        pool.sort();
+        let mut chosen: Vec<u64> = step.parents.iter().map(|i| *i.get(&pool)).collect();
+        chosen.sort();
+        chosen.dedup();
+        let pasts: Vec<HashSet<u64>> = chosen.iter().map(|&p| dag.past_set(p)).collect();
+        let parents: Vec<u64> = chosen
+            .iter()
+            .enumerate()
+            .filter(|&(i, p)| !pasts.iter().enumerate().any(|(j, past)| i != j && past.contains(p)))
+            .map(|(_, &p)| p)
+            .collect();
+        dag.create_block(parents);
+    }
+    dag
+}
+
+// Growth sequences rather than grown DAGs, so a failure shrinks to the fewest steps
+fn growth() -> impl Strategy<Value = (ConsensusParams, Vec<Step>)> {
+    (params(), prop::collection::vec(step(), 0..40))
+}
+
+proptest! {
+    #[test]
+    fn genesis_is_in_every_past((params, steps) in growth()) {
+        let dag = grow(params, &steps);
+        for id in 0..dag.next_id {
+            prop_assert!(dag.past_set(id).contains(&0), "block {} does not reach genesis", id);
+        }
+    }
+
+    #[test]
+    fn tips_are_exactly_the_childless_blocks((params, steps) in growth()) {
+        let dag = grow(params, &steps);
+        for id in 0..dag.next_id {
+            prop_assert_eq!(dag.is_tip(id), dag.children(id).is_empty(), "block {}", id);
+        }
+    }
+
+    #[test]
+    fn anticone_is_symmetric((params, steps) in growth()) {
+        let dag = grow(params, &steps);
+        let anticones: Vec<Vec<u64>> = (0..dag.next_id).map(|id| dag.anticone(id)).collect();
+        for (a, anticone) in anticones.iter().enumerate() {
+            prop_assert!(!anticone.contains(&(a as u64)), "block {} is in its own anticone", a);
+            for &b in anticone {
+                prop_assert!(anticones[b as usize].contains(&(a as u64)), "{} is in {}'s anticone but not the reverse", b, a);
+            }
+        }
+    }
+
+    #[test]
+    fn selected_parent_is_a_blue_tip((params, steps) in growth()) {
+        let dag = grow(params, &steps);
+        prop_assert!(dag.is_tip(dag.selected_parent));
+        prop_assert_eq!(&dag.blocks[&dag.selected_parent].color, &Color::Blue);
+        let best = dag.tips.iter().map(|t| dag.blocks[t].blue_score).max().unwrap();
+        prop_assert_eq!(dag.blocks[&dag.selected_parent].blue_score, best);
+    }
+
+    #[test]
+    fn selected_chain_is_monotonic((params, steps) in growth()) {
+        let dag = grow(params, &steps);
+        let chain = dag.selected_chain();
+        prop_assert_eq!(chain[0], 0);
+        for pair in chain.windows(2) {
+            let (parent, child) = (&dag.blocks[&pair[0]], &dag.blocks[&pair[1]]);
+            prop_assert_eq!(child.selected_parent, Some(parent.id));
+            prop_assert!(child.parents.contains(&parent.id));
+            prop_assert!(child.blue_score > parent.blue_score, "blue score falls from {} to {}", parent.id, child.id);
+            prop_assert!(child.blue_work >= parent.blue_work);
+            prop_assert_eq!(child.depth, parent.depth + 1);
+            prop_assert_eq!(&child.color, &Color::Blue);
+        }
+    }
+
+    #[test]
+    fn caches_match_a_rebuild((params, steps) in growth()) {
+        let dag = grow(params, &steps);
+        prop_assert_eq!(dag.check_invariants(), Ok(()));
+    }
+}
@@ -0,0 +1,204 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use clap::{ArgMatches, Args};
+use hex::encode;
+use sha2::{Digest, Sha256};
+
+use crate::failure::Failure;
+
+// What it takes to repeat a run: the arguments as given, and enough about
+// the build and the parsed config to tell whether a rerun really is the same
+// run. Plain `key value` lines, one `arg` line per argument so values with
+// spaces survive:
+//
+//   version 0.1.0
+//   git 45a1760…          (or "unknown" when the source tree is gone)
+//   started_unix 1760612345
+//   command sweep
+//   seed 0                (or "none" when the command takes no --seed)
+//   scenario_hash 3f2a…   (SHA-256 of the parsed config, so flag order does not matter)
+//   config Sweep(SweepArgs { … })
+//   arg sweep
+//   arg --loss
+//   arg 0.1
+
+const HEADER: &str = "# toy-fec run manifest; repeat it with `toy-fec rerun <this file>`";
+
+// Argument IDs naming something a command writes; directories get the manifest inside them
+const OUTPUT_FILES: [&str; 6] = ["output", "out", "dag_out", "save_dag", "table", "metrics"];
+const OUTPUT_DIRS: [&str; 3] = ["out_dir", "figures", "store_dir"];
+
+#[derive(Args, Debug)]
+pub struct RerunArgs {
+    /// Manifest written next to an earlier run's output
+    #[arg(value_name = "MANIFEST")]
+    pub file: PathBuf,
+
+    /// Rerun even if this build parses the recorded arguments into a different config
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunManifest {
+    pub version: String,
+    pub git: Option<String>,
+    pub started_unix: u64,
+    pub command: String,
+    pub seed: Option<u64>,
+    pub scenario_hash: String,
+    pub config: String,
+    pub args: Vec<String>,
+}
+
+pub fn scenario_hash(config: &str) -> String {
+    encode(&Sha256::digest(config.as_bytes())[..8])
+}
+
+// The commit the binary was built from, if its source tree is still a git checkout
+fn git_revision() -> Option<String> {
+    let git = |args: &[&str]| {
+        process::Command::new("git")
+            .arg("-C")
+            .arg(env!("CARGO_MANIFEST_DIR"))
+            .args(args)
+            .output()
+            .ok()
+            .filter(|o| o.status.success())
+            .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+    };
+    let head = git(&["rev-parse", "--short=12", "HEAD"]).filter(|h| !h.is_empty())?;
+    let dirty = git(&["status", "--porcelain", "--untracked-files=no"]).is_some_and(|s| !s.is_empty());
+    Some(if dirty { format!("{}-dirty", head) } else { head })
+}
+
+impl RunManifest {
+    // `config` is the parsed command's Debug form, defaults filled in
+    pub fn new(matches: &ArgMatches, config: String, args: Vec<String>) -> Self {
+        let (command, seed) = match matches.subcommand() {
+            Some((name, sub)) => (name.to_string(), sub.try_get_one::<u64>("seed").ok().flatten().copied()),
+            None => ("demo".to_string(), None),
+        };
+        RunManifest {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            git: git_revision(),
+            started_unix: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()),
+            command,
+            seed,
+            scenario_hash: scenario_hash(&config),
+            config,
+            args,
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut lines = vec![
+            HEADER.to_string(),
+            format!("version {}", self.version),
+            format!("git {}", self.git.as_deref().unwrap_or("unknown")),
+            format!("started_unix {}", self.started_unix),
+            format!("command {}", self.command),
+            format!("seed {}", self.seed.map_or("none".to_string(), |s| s.to_string())),
+            format!("scenario_hash {}", self.scenario_hash),
+            format!("config {}", self.config),
+        ];
+        lines.extend(self.args.iter().map(|a| format!("arg {}", a)));
+        lines.join("\n") + "\n"
+    }
+
+    pub fn parse(text: &str, origin: &str) -> Result<Self, Failure> {
+        let mut manifest =
+            RunManifest { version: String::new(), git: None, started_unix: 0, command: String::new(), seed: None, scenario_hash: String::new(), config: String::new(), args: Vec::new() };
+        for (n, line) in text.lines().enumerate() {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let bad = |what: &str| Failure::Config(format!("{} line {}: {}", origin, n + 1, what));
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            match key {
+                "version" => manifest.version = value.to_string(),
+                "git" => manifest.git = Some(value.to_string()).filter(|g| g != "unknown"),
+                "started_unix" => manifest.started_unix = value.parse().map_err(|_| bad("started_unix is not a number"))?,
+                "command" => manifest.command = value.to_string(),
+                "seed" if value == "none" => manifest.seed = None,
+                "seed" => manifest.seed = Some(value.parse().map_err(|_| bad("seed is not a number"))?),
+                "scenario_hash" => manifest.scenario_hash = value.to_string(),
+                "config" => manifest.config = value.to_string(),
+                "arg" => manifest.args.push(value.to_string()),
+                _ => return Err(bad(&format!("unknown key '{}'", key))),
+            }
+        }
+        if manifest.scenario_hash.is_empty() {
+            return Err(Failure::Config(format!("{}: no scenario_hash, so not a run manifest", origin)));
+        }
+        Ok(manifest)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, Failure> {
+        let text = fs::read_to_string(path).map_err(|e| Failure::Io(format!("cannot read {}: {}", path.display(), e)))?;
+        Self::parse(&text, &path.display().to_string())
+    }
+
+    // Next to every output the command was given, and wherever --manifest points
+    pub fn destinations(matches: &ArgMatches, explicit: Option<&Path>) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = explicit.map(Path::to_path_buf).into_iter().collect();
+        if let Some((_, sub)) = matches.subcommand() {
+            let given = |id: &str| sub.try_get_one::<PathBuf>(id).ok().flatten().filter(|p| p.as_os_str() != "-").cloned();
+            for path in OUTPUT_FILES.iter().filter_map(|id| given(id)) {
+                let mut name = path.into_os_string();
+                name.push(".manifest");
+                paths.push(name.into());
+            }
+            paths.extend(OUTPUT_DIRS.iter().filter_map(|id| given(id)).map(|dir| dir.join("run.manifest")));
+        }
+        paths
+    }
+
+    // Missing manifests are reported, not fatal: the run itself already succeeded
+    pub fn write_to(&self, paths: &[PathBuf]) {
+        let text = self.to_text();
+        for path in paths {
+            if let Err(e) = fs::write(path, &text) {
+                eprintln!("Could not write the run manifest {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    // Why a rerun from this build would not be the recorded run, if anything
+    pub fn differences(&self, rerun: &RunManifest) -> Vec<String> {
+        let mut differences = Vec::new();
+        if self.version != rerun.version {
+            differences.push(format!("recorded with version {}, this is {}", self.version, rerun.version));
+        }
+        if let (Some(then), Some(now)) = (&self.git, &rerun.git)
+            && then != now
+        {
+            differences.push(format!("recorded at commit {}, this build is {}", then, now));
+        }
+        differences
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifests_survive_a_text_round_trip() {
+        let manifest = RunManifest {
+            version: "0.1.0".into(),
+            git: Some("45a1760abcde-dirty".into()),
+            started_unix: 1_760_612_345,
+            command: "sweep".into(),
+            seed: None,
+            scenario_hash: scenario_hash("Sweep(SweepArgs { seeds: 5 })"),
+            config: "Sweep(SweepArgs { seeds: 5 })".into(),
+            args: vec!["sweep".into(), "--output".into(), "with space.csv".into()],
+        };
+        assert_eq!(RunManifest::parse(&manifest.to_text(), "test").unwrap(), manifest);
+        assert!(RunManifest::parse("version 0.1.0\n", "test").is_err());
+    }
+}
@@ -0,0 +1,232 @@
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+use clap::Args;
+use rand::seq::SliceRandom;
+use raptorq::{Encoder, EncodingPacket, ObjectTransmissionInformation};
+
+use crate::rng::{self, SimRng, SimStream};
+use crate::{fec, ghostdag, print_missing_blocks, snapshot, Block, ConsensusParams, ToyDag};
+
+const HELP: &str = "\
+Commands:
+  block [P ...] [@T]   add a block with parents P (default: every tip), claiming time T
+  tips                 list the current tips
+  dag                  print the whole DAG
+  color B              show B's color, blue score and how it was colored
+  mtp B                show B's timestamp and the median time past a child of B must beat
+  children B           list the blocks that reference B
+  anticone A [B]       list A's anticone, or say how A and B relate
+  chain                print the selected chain
+  encode [REPAIR]      FEC-encode every block hash (default 10 repair packets)
+  lose N | N%          drop N packets (or N percent) of what is still in flight
+  decode               try to recover the block hashes from the surviving packets
+  save FILE            write the DAG for `toy-fec analyze --dag FILE`
+  help | quit";
+
+#[derive(Args, Debug)]
+pub struct ReplArgs {
+    /// Bytes per encoded symbol
+    #[arg(long, default_value_t = 64)]
+    pub symbol_size: u16,
+
+    #[command(flatten)]
+    pub consensus: ConsensusParams,
+}
+
+struct Session {
+    dag: ToyDag,
+    symbol_size: u16,
+    config: Option<ObjectTransmissionInformation>,
+    in_flight: Vec<EncodingPacket>,     // Packets that survived every `lose` so far
+    channel: SimStream,                 // What `lose` drops
+    encoded_blocks: usize,              // DAG size at the time of the last `encode`
+}
+
+fn parse_id(dag: &ToyDag, word: &str) -> Result<u64, String> {
+    let id: u64 = word.parse().map_err(|_| format!("'{}' is not a block ID", word))?;
+    if dag.blocks.contains_key(&id) {
+        Ok(id)
+    } else {
+        Err(format!("no block {}", id))
+    }
+}
+
+impl Session {
+    fn sorted_blocks(&self) -> Vec<&Block> {
+        let mut blocks: Vec<&Block> = self.dag.blocks.values().collect();
+        blocks.sort_by_key(|b| b.id);
+        blocks
+    }
+
+    fn execute(&mut self, words: &[&str]) -> Result<(), String> {
+        match words {
+            ["block", args @ ..] => {
+                let (time, parents): (Vec<&str>, Vec<&str>) = args.iter().partition(|w| w.starts_with('@'));
+                let timestamp = match time.as_slice() {
+                    [] => self.dag.next_id,
+                    [t] => t[1..].parse().map_err(|_| format!("'{}' is not a timestamp", t))?,
+                    _ => return Err("give at most one @TIME".into()),
+                };
+                let parents = if parents.is_empty() {
+                    crate::tips::fresh_tips(&self.dag, None)
+                } else {
+                    parents.iter().map(|w| parse_id(&self.dag, w)).collect::<Result<Vec<_>, _>>()?
+                };
+                let id = self.dag.create_block_at(parents, timestamp)?;
+                println!("{}", ghostdag::explain(&self.dag, id));
+            }
+            ["tips"] => {
+                let tips: Vec<String> = crate::tips::fresh_tips(&self.dag, None)
+                    .iter()
+                    .map(|id| format!("{} (score {})", id, self.dag.blocks[id].blue_score))
+                    .collect();
+                println!("{} tips: {}", tips.len(), tips.join(", "));
+            }
+            ["dag"] => self.dag.print_dag(),
+            ["color", block] => {
+                let id = parse_id(&self.dag, block)?;
+                let b = &self.dag.blocks[&id];
+                println!("Block {} is {:?} from the virtual block's view (blue score {})", id, b.color, b.blue_score);
+                println!("{}", ghostdag::explain(&self.dag, id));
+            }
+            ["children", block] => {
+                let id = parse_id(&self.dag, block)?;
+                if self.dag.is_tip(id) {
+                    println!("{} is a tip: no block references it yet", id);
+                } else {
+                    println!("Children of {}: {:?}", id, self.dag.children(id));
+                }
+            }
+            ["mtp", block] => {
+                let id = parse_id(&self.dag, block)?;
+                println!(
+                    "Block {} claims time {}; median time past over {} chain blocks: {}",
+                    id,
+                    self.dag.blocks[&id].timestamp,
+                    self.dag.params.mtp_window,
+                    self.dag.median_time_past(id)
+                );
+            }
+            ["anticone", a] => {
+                let id = parse_id(&self.dag, a)?;
+                let anticone = self.dag.anticone(id);
+                println!("Anticone of {} ({} blocks): {:?}", id, anticone.len(), anticone);
+            }
+            ["anticone", a, b] => {
+                let (a, b) = (parse_id(&self.dag, a)?, parse_id(&self.dag, b)?);
+                if a == b {
+                    println!("{} is the same block", a);
+                } else if self.dag.past_set(b).contains(&a) {
+                    println!("{} is in the past of {}: not in each other's anticone", a, b);
+                } else if self.dag.past_set(a).contains(&b) {
+                    println!("{} is in the past of {}: not in each other's anticone", b, a);
+                } else {
+                    println!("{} and {} are in each other's anticone (neither saw the other)", a, b);
+                }
+            }
+            ["chain"] => {
+                let chain: Vec<String> = self.dag.selected_chain().iter().map(u64::to_string).collect();
+                println!("Selected chain ({} blocks): {}", chain.len(), chain.join(" → "));
+            }
+            ["encode", rest @ ..] => {
+                let repair = match rest {
+                    [] => 10,
+                    [n] => n.parse().map_err(|_| format!("'{}' is not a packet count", n))?,
+                    _ => return Err("usage: encode [REPAIR]".into()),
+                };
+                let data: Vec<u8> = self.sorted_blocks().iter().flat_map(|b| b.hash).collect();
+                let encoder = Encoder::with_defaults(&data, self.symbol_size);
+                self.in_flight = encoder.get_encoded_packets(repair);
+                self.config = Some(encoder.get_config());
+                self.encoded_blocks = self.dag.blocks.len();
+                println!(
+                    "Encoded {} block hashes ({} bytes) into {} packets ({} source + {} repair)",
+                    self.encoded_blocks,
+                    data.len(),
+                    self.in_flight.len(),
+                    fec::source_symbol_count(&encoder.get_config()),
+                    repair
+                );
+            }
+            ["lose", amount] => {
+                if self.config.is_none() {
+                    return Err("nothing encoded yet; run `encode` first".into());
+                }
+                let count = match amount.strip_suffix('%') {
+                    Some(percent) => {
+                        let p: f64 = percent.parse().map_err(|_| format!("'{}' is not a percentage", amount))?;
+                        (self.in_flight.len() as f64 * p / 100.0).round() as usize
+                    }
+                    None => amount.parse().map_err(|_| format!("'{}' is not a packet count", amount))?,
+                };
+                self.in_flight.shuffle(&mut self.channel);
+                let count = count.min(self.in_flight.len());
+                self.in_flight.truncate(self.in_flight.len() - count);
+                println!("Dropped {} packets, {} still in flight", count, self.in_flight.len());
+            }
+            ["decode"] => {
+                let config = self.config.ok_or("nothing encoded yet; run `encode` first")?;
+                let outcome = fec::decode_packets(config, self.in_flight.clone());
+                let blocks = self.sorted_blocks();
+                let encoded = &blocks[..self.encoded_blocks];
+                match &outcome.data {
+                    Some(data) => {
+                        let intact = data.chunks(32).zip(encoded).all(|(hash, b)| hash == b.hash);
+                        println!(
+                            "Recovered all {} block hashes from {} packets (overhead {}), intact: {}",
+                            encoded.len(),
+                            outcome.packets_used,
+                            outcome.packets_used as i64 - outcome.source_symbols as i64,
+                            intact
+                        );
+                        outcome.print_packet_usage();
+                    }
+                    None => {
+                        let spans: Vec<_> = (0..encoded.len()).map(|i| i * 32..(i + 1) * 32).collect();
+                        print_missing_blocks(&outcome.missing, &spans)
+                    }
+                }
+            }
+            ["save", path] => {
+                snapshot::save(&self.dag, Path::new(path)).map_err(|e| format!("cannot save to {}: {}", path, e))?;
+                println!("Saved {} blocks to {}", self.dag.blocks.len(), path);
+            }
+            ["help"] => println!("{}", HELP),
+            _ => return Err(format!("unknown command '{}' (try `help`)", words.join(" "))),
+        }
+        Ok(())
+    }
+}
+
+pub fn run(args: &ReplArgs) {
+    let mut session = Session {
+        dag: ToyDag::with_params(args.consensus),
+        symbol_size: args.symbol_size,
+        config: None,
+        in_flight: Vec::new(),
+        channel: SimStream::unseeded().fork(rng::CHANNEL),
+        encoded_blocks: 0,
+    };
+    println!("toy-fec REPL: genesis block 0 is ready (k = {}). Type `help` for commands.", args.consensus.k);
+
+    let stdin = io::stdin();
+    loop {
+        print!("> ");
+        io::stdout().flush().ok();
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).unwrap_or(0) == 0 {
+            break;
+        }
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            [] => continue,
+            ["quit"] | ["exit"] => break,
+            _ => {
+                if let Err(e) = session.execute(&words) {
+                    println!("error: {}", e);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,381 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::{Args, ValueEnum};
+
+use crate::erasure::CodeKind;
+use crate::failure::Failure;
+use crate::rng::{SimRng, SimStream};
+use crate::simulate::{self, MiningArgs, OutputArgs, SimArgs};
+use crate::sweep::{self, SweepArgs};
+use crate::{series, snapshot, Color, ConsensusParams, ToyDag, SYMBOL_SIZE};
+
+#[derive(Args, Debug)]
+pub struct ReportArgs {
+    /// Directory to write the figures (SVG) and the data behind each (CSV) into
+    #[arg(long)]
+    pub figures: PathBuf,
+
+    /// A directory an earlier report wrote; figures whose CSV is there are redrawn from it instead of rerun
+    #[arg(long)]
+    pub results: Option<PathBuf>,
+
+    /// Saved DAG to draw the red ratio and tips figures from, instead of growing one
+    #[arg(long)]
+    pub dag: Option<PathBuf>,
+
+    /// Blocks mined when growing the DAG
+    #[arg(long, default_value_t = 300)]
+    pub blocks: u64,
+
+    /// Miners per round when growing the DAG, all building on the same tips
+    #[arg(long, default_value_t = 6)]
+    pub miners: usize,
+
+    /// GHOSTDAG k values the red ratio is plotted against
+    #[arg(long, value_delimiter = ',', default_values_t = vec![0, 1, 2, 3, 4, 6, 8, 12, 18, 24])]
+    pub k: Vec<usize>,
+
+    /// Repair packet counts, one recovery curve each
+    #[arg(long, value_delimiter = ',', default_values_t = vec![0, 5, 10, 20, 40])]
+    pub repair: Vec<u32>,
+
+    /// Loss rate the overhead CDF is measured at
+    #[arg(long, default_value_t = 0.1)]
+    pub overhead_loss: f64,
+
+    /// Seeded loss patterns per point (seeds 0..seeds)
+    #[arg(long, default_value_t = 100)]
+    pub seeds: u64,
+
+    /// Seed for growing the DAG
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+}
+
+// The standard set, each written as <name>.svg and <name>.csv
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum FigureKind {
+    RedRatioVsK,
+    TipsOverTime,
+    RecoveryVsLoss,
+    OverheadCdf,
+}
+
+impl FigureKind {
+    fn name(self) -> String {
+        self.to_possible_value().expect("no variant is skipped").get_name().to_string()
+    }
+}
+
+// Named lines over shared axes; the CSV holds one row per point
+#[derive(Debug, Clone, PartialEq)]
+pub struct Figure {
+    pub title: String,
+    pub x_label: String,
+    pub y_label: String,
+    pub series: Vec<(String, Vec<(f64, f64)>)>,
+}
+
+const PALETTE: [&str; 6] = ["#1f77b4", "#d62728", "#2ca02c", "#9467bd", "#ff7f0e", "#17becf"];
+const WIDTH: f64 = 640.0;
+const HEIGHT: f64 = 420.0;
+const MARGIN: (f64, f64, f64, f64) = (70.0, 30.0, 50.0, 60.0); // Left, right, top, bottom
+
+impl Figure {
+    pub fn to_csv(&self) -> String {
+        let mut csv = format!("# {}\nseries,{},{}\n", self.title, self.x_label, self.y_label);
+        for (name, points) in &self.series {
+            for (x, y) in points {
+                writeln!(csv, "{},{},{}", name, x, y).unwrap();
+            }
+        }
+        csv
+    }
+
+    // The title comes from the comment line, the axis labels from the header
+    pub fn from_csv(text: &str, origin: &str) -> Result<Figure, Failure> {
+        let bad = |n: usize, what: &str| Failure::Config(format!("{} line {}: {}", origin, n + 1, what));
+        let mut lines = text.lines().enumerate();
+        let title = match lines.next() {
+            Some((_, line)) if line.starts_with("# ") => line[2..].to_string(),
+            _ => return Err(bad(0, "expected a '# title' line")),
+        };
+        let (x_label, y_label) = match lines.next().map(|(n, l)| (n, l.split(',').collect::<Vec<_>>())) {
+            Some((_, header)) if header.len() == 3 && header[0] == "series" => (header[1].to_string(), header[2].to_string()),
+            _ => return Err(bad(1, "expected a 'series,<x>,<y>' header")),
+        };
+        let mut series: Vec<(String, Vec<(f64, f64)>)> = Vec::new();
+        for (n, line) in lines.filter(|(_, l)| !l.trim().is_empty()) {
+            // Series names may hold commas; the numbers never do
+            let [y, x, name] = line.rsplitn(3, ',').collect::<Vec<_>>()[..] else {
+                return Err(bad(n, "expected series,x,y"));
+            };
+            let number = |s: &str| s.trim().parse::<f64>().map_err(|_| bad(n, &format!("'{}' is not a number", s)));
+            let point = (number(x)?, number(y)?);
+            match series.last_mut() {
+                Some((last, points)) if last == name => points.push(point),
+                _ => series.push((name.to_string(), vec![point])),
+            }
+        }
+        Ok(Figure { title, x_label, y_label, series })
+    }
+
+    pub fn to_svg(&self) -> String {
+        let (left, right, top, bottom) = MARGIN;
+        let points = || self.series.iter().flat_map(|(_, p)| p.iter());
+        let (x_min, x_max) = span(points().map(|p| p.0));
+        let (y_min, y_max) = span(points().map(|p| p.1).chain([0.0]));
+        let plot_w = WIDTH - left - right;
+        let plot_h = HEIGHT - top - bottom;
+        let px = |x: f64| left + (x - x_min) / (x_max - x_min) * plot_w;
+        let py = |y: f64| top + plot_h - (y - y_min) / (y_max - y_min) * plot_h;
+
+        let mut svg = String::new();
+        writeln!(
+            svg,
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" font-family=\"Helvetica\" font-size=\"12\">",
+            WIDTH, HEIGHT
+        )
+        .unwrap();
+        writeln!(svg, "  <rect width=\"100%\" height=\"100%\" fill=\"white\"/>").unwrap();
+        writeln!(svg, "  <text x=\"{}\" y=\"24\" font-size=\"15\" text-anchor=\"middle\">{}</text>", WIDTH / 2.0, escape(&self.title)).unwrap();
+        for i in 0..=5 {
+            let (x, y) = (x_min + (x_max - x_min) * i as f64 / 5.0, y_min + (y_max - y_min) * i as f64 / 5.0);
+            writeln!(svg, "  <line x1=\"{0:.1}\" y1=\"{1}\" x2=\"{0:.1}\" y2=\"{2}\" stroke=\"#eee\"/>", px(x), top, top + plot_h).unwrap();
+            writeln!(svg, "  <line x1=\"{1}\" y1=\"{0:.1}\" x2=\"{2}\" y2=\"{0:.1}\" stroke=\"#eee\"/>", py(y), left, left + plot_w).unwrap();
+            writeln!(svg, "  <text x=\"{:.1}\" y=\"{}\" text-anchor=\"middle\">{}</text>", px(x), top + plot_h + 18.0, tick(x)).unwrap();
+            writeln!(svg, "  <text x=\"{}\" y=\"{:.1}\" text-anchor=\"end\">{}</text>", left - 6.0, py(y) + 4.0, tick(y)).unwrap();
+        }
+        writeln!(
+            svg,
+            "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"none\" stroke=\"#333\"/>",
+            left, top, plot_w, plot_h
+        )
+        .unwrap();
+        writeln!(svg, "  <text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{}</text>", left + plot_w / 2.0, HEIGHT - 18.0, escape(&self.x_label)).unwrap();
+        writeln!(
+            svg,
+            "  <text x=\"18\" y=\"{0}\" text-anchor=\"middle\" transform=\"rotate(-90 18 {0})\">{1}</text>",
+            top + plot_h / 2.0,
+            escape(&self.y_label)
+        )
+        .unwrap();
+        for (i, (name, points)) in self.series.iter().enumerate() {
+            let colour = PALETTE[i % PALETTE.len()];
+            let path: Vec<String> = points.iter().map(|&(x, y)| format!("{:.1},{:.1}", px(x), py(y))).collect();
+            writeln!(svg, "  <polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"2\"/>", path.join(" "), colour).unwrap();
+            if self.series.len() > 1 {
+                let y = top + 14.0 + i as f64 * 16.0;
+                writeln!(
+                    svg,
+                    "  <line x1=\"{0}\" y1=\"{1}\" x2=\"{2}\" y2=\"{1}\" stroke=\"{3}\" stroke-width=\"2\"/>",
+                    left + plot_w - 110.0,
+                    y - 4.0,
+                    left + plot_w - 90.0,
+                    colour
+                )
+                .unwrap();
+                writeln!(svg, "  <text x=\"{}\" y=\"{}\">{}</text>", left + plot_w - 84.0, y, escape(name)).unwrap();
+            }
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+// An axis range with a little room, never empty
+fn span(values: impl Iterator<Item = f64>) -> (f64, f64) {
+    let (lo, hi) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| (lo.min(v), hi.max(v)));
+    match (lo.is_finite(), hi > lo) {
+        (true, true) => (lo, hi),
+        (true, false) => (lo - 0.5, lo + 0.5),
+        (false, _) => (0.0, 1.0),
+    }
+}
+
+fn tick(value: f64) -> String {
+    if value.fract().abs() < 1e-9 { format!("{:.0}", value) } else { format!("{:.2}", value) }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn red_ratio(dag: &ToyDag) -> f64 {
+    dag.blocks.values().filter(|b| b.color == Color::Red).count() as f64 / dag.blocks.len() as f64
+}
+
+// The same blocks and parents colored again under each k
+fn red_ratio_vs_k(dag: &ToyDag, ks: &[usize]) -> Result<Figure, Failure> {
+    let mut points = Vec::new();
+    for &k in ks {
+        let mut replay = ToyDag::with_params(ConsensusParams { k, ..dag.params });
+        for id in replay.next_id..dag.next_id {
+            let block = &dag.blocks[&id];
+            replay.create_block_at(block.parents.clone(), block.timestamp).map_err(|e| Failure::Config(format!("block {} under k={}: {}", id, k, e)))?;
+        }
+        points.push((k as f64, red_ratio(&replay)));
+    }
+    Ok(Figure {
+        title: format!("Red ratio against k ({} blocks)", dag.blocks.len()),
+        x_label: "k".into(),
+        y_label: "red ratio".into(),
+        series: vec![("red ratio".into(), points)],
+    })
+}
+
+fn tips_over_time(dag: &ToyDag) -> Figure {
+    let points = series::insertion_series(dag).iter().map(|s| (s.id as f64, s.tips as f64)).collect();
+    Figure {
+        title: format!("Tips as the DAG grows (k = {})", dag.params.k),
+        x_label: "block".into(),
+        y_label: "tips".into(),
+        series: vec![("tips".into(), points)],
+    }
+}
+
+fn sweep_args(args: &ReportArgs, code: CodeKind, loss: Vec<f64>, repair: Vec<u32>) -> SweepArgs {
+    SweepArgs {
+        code,
+        size: 151 * 32,
+        symbol_size: SYMBOL_SIZE,
+        loss,
+        trace: None,
+        repair,
+        seeds: args.seeds,
+        output: None,
+        plot: false,
+        target: None,
+        compare: Vec::new(),
+    }
+}
+
+fn recovery_vs_loss(args: &ReportArgs) -> Result<Figure, Failure> {
+    let losses: Vec<f64> = (0..=10).map(|i| i as f64 * 0.05).collect();
+    let cells = sweep::sweep(&sweep_args(args, CodeKind::Raptorq, losses, args.repair.clone()))?;
+    let series = args
+        .repair
+        .iter()
+        .map(|&repair| {
+            let points = cells
+                .iter()
+                .filter(|c| c.repair == repair)
+                .map(|c| (c.loss, c.stats.successes() as f64 / c.outcomes.len().max(1) as f64))
+                .collect();
+            (format!("{} repair", repair), points)
+        })
+        .collect();
+    Ok(Figure {
+        title: format!("Recovery probability against loss ({} source packets, {} seeds)", cells[0].source_packets, args.seeds),
+        x_label: "loss rate".into(),
+        y_label: "P(recovered)".into(),
+        series,
+    })
+}
+
+// Extra packets each code needed beyond the source count, with enough repair
+// that decoding never runs short
+fn overhead_cdf(args: &ReportArgs) -> Result<Figure, Failure> {
+    let source = (151 * 32usize).div_ceil(SYMBOL_SIZE as usize) as u32;
+    let mut extras = Vec::new();
+    for &code in CodeKind::value_variants() {
+        let cells = sweep::sweep(&sweep_args(args, code, vec![args.overhead_loss], vec![source * 2]))?;
+        let mut extra: Vec<usize> = cells[0].outcomes.iter().flatten().copied().collect();
+        extra.sort();
+        extras.push((code, extra));
+    }
+    // Every curve runs to the same right edge, so a code that never needed extra still draws a line
+    let max = extras.iter().filter_map(|(_, e)| e.last().copied()).max().unwrap_or(0).max(1);
+    let series = extras
+        .into_iter()
+        .map(|(code, extra)| {
+            let n = extra.len().max(1) as f64;
+            let points = (0..=max).map(|x| (x as f64, extra.partition_point(|&e| e <= x) as f64 / n)).collect();
+            (code.to_possible_value().expect("no variant is skipped").get_name().to_string(), points)
+        })
+        .collect();
+    Ok(Figure {
+        title: format!("Reception overhead CDF at {:.0}% loss", args.overhead_loss * 100.0),
+        x_label: "extra packets".into(),
+        y_label: "P(overhead <= x)".into(),
+        series,
+    })
+}
+
+fn read_saved(dir: &Path, kind: FigureKind) -> Result<Option<Figure>, Failure> {
+    let path = dir.join(format!("{}.csv", kind.name()));
+    match fs::read_to_string(&path) {
+        Ok(text) => Figure::from_csv(&text, &path.display().to_string()).map(Some),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(Failure::Io(format!("cannot read {}: {}", path.display(), e))),
+    }
+}
+
+// Grown or loaded the first time a figure needs it
+fn dag<'a>(cached: &'a mut Option<ToyDag>, args: &ReportArgs) -> Result<&'a ToyDag, Failure> {
+    if cached.is_none() {
+        *cached = Some(match &args.dag {
+            Some(path) => snapshot::load(path)?,
+            None => {
+                let sim = SimArgs {
+                    blocks: args.blocks,
+                    mining: MiningArgs { miners: args.miners, ..MiningArgs::default() },
+                    output: OutputArgs { quiet: true, verbose: 0 },
+                    ..SimArgs::default()
+                };
+                simulate::grow(&sim, &SimStream::seeded(args.seed))
+            }
+        });
+    }
+    Ok(cached.as_ref().expect("filled above"))
+}
+
+pub fn run(args: &ReportArgs) -> Result<(), Failure> {
+    fs::create_dir_all(&args.figures).map_err(|e| Failure::Io(format!("cannot create {}: {}", args.figures.display(), e)))?;
+    let mut cached: Option<ToyDag> = None;
+
+    println!("=== Figures into {} ===", args.figures.display());
+    for &kind in FigureKind::value_variants() {
+        let saved = match &args.results {
+            Some(dir) => read_saved(dir, kind)?,
+            None => None,
+        };
+        let source = if saved.is_some() { "redrawn from saved results" } else { "computed" };
+        let figure = match saved {
+            Some(figure) => figure,
+            None => match kind {
+                FigureKind::RedRatioVsK => red_ratio_vs_k(dag(&mut cached, args)?, &args.k)?,
+                FigureKind::TipsOverTime => tips_over_time(dag(&mut cached, args)?),
+                FigureKind::RecoveryVsLoss => recovery_vs_loss(args)?,
+                FigureKind::OverheadCdf => overhead_cdf(args)?,
+            },
+        };
+        for (extension, contents) in [("svg", figure.to_svg()), ("csv", figure.to_csv())] {
+            let path = args.figures.join(format!("{}.{}", kind.name(), extension));
+            fs::write(&path, contents).map_err(|e| Failure::Io(format!("cannot write {}: {}", path.display(), e)))?;
+        }
+        println!("  {:<18} {} ({})", kind.name(), figure.title, source);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn figures_survive_a_csv_round_trip() {
+        let figure = Figure {
+            title: "Recovery, by repair".into(),
+            x_label: "loss rate".into(),
+            y_label: "P(recovered)".into(),
+            series: vec![("0 repair".into(), vec![(0.0, 1.0), (0.25, 0.5)]), ("5 repair".into(), vec![(0.0, 1.0), (0.5, 0.125)])],
+        };
+        assert_eq!(Figure::from_csv(&figure.to_csv(), "test").unwrap(), figure);
+        let svg = figure.to_svg();
+        assert_eq!(svg.matches("<polyline").count(), 2);
+        assert!(svg.contains("Recovery, by repair"));
+    }
+}
@@ -0,0 +1,95 @@
+// Randomness for the simulations. Every stochastic component (the miners,
+// the channel, StitchBot, ...) draws from its own stream, forked by name from
+// one root. A fork depends only on the root's seed and the component's name,
+// never on how much anyone has drawn so far, so adding a component or
+// changing the order they run in leaves every other component's draws as
+// they were, and one seed still reproduces the whole experiment.
+
+use rand::rngs::{OsRng, StdRng};
+use rand::{RngCore, SeedableRng};
+use sha2::{Digest, Sha256};
+
+// Component names shared across commands, so the same part of the system
+// draws from the same stream wherever it runs
+pub const MINERS: &str = "miners";
+pub const STITCHBOT: &str = "stitchbot";
+pub const CHANNEL: &str = "channel";
+
+pub trait SimRng: RngCore + Sized {
+    fn seeded(seed: u64) -> Self;
+
+    fn seed(&self) -> u64;
+
+    // An independent stream for `component`; draws nothing from this one
+    fn fork(&self, component: &str) -> Self {
+        let digest = Sha256::new().chain_update(self.seed().to_be_bytes()).chain_update(component.as_bytes()).finalize();
+        Self::seeded(u64::from_be_bytes(digest[..8].try_into().unwrap()))
+    }
+}
+
+// StdRng that remembers the seed it started from, which forking needs
+#[derive(Debug, Clone)]
+pub struct SimStream {
+    seed: u64,
+    inner: StdRng,
+}
+
+impl SimStream {
+    // A root for runs that were given no seed: the seed comes from the OS,
+    // and the components still get independent streams from it
+    pub fn unseeded() -> Self {
+        Self::seeded(OsRng.next_u64())
+    }
+
+    pub fn root(seed: Option<u64>) -> Self {
+        seed.map_or_else(Self::unseeded, Self::seeded)
+    }
+}
+
+impl SimRng for SimStream {
+    fn seeded(seed: u64) -> Self {
+        SimStream { seed, inner: StdRng::seed_from_u64(seed) }
+    }
+
+    fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl RngCore for SimStream {
+    fn next_u32(&mut self) -> u32 {
+        self.inner.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.inner.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.inner.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.inner.try_fill_bytes(dest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn forks_ignore_what_was_drawn_before() {
+        let mut root = SimStream::seeded(7);
+        let before: u64 = root.fork(MINERS).r#gen();
+        let _: [u64; 10] = root.r#gen();
+        let mut channel = root.fork(CHANNEL);
+        let _: u64 = channel.r#gen();
+        assert_eq!(root.fork(MINERS).r#gen::<u64>(), before);
+        assert_ne!(root.fork(CHANNEL).r#gen::<u64>(), before);
+        assert_ne!(SimStream::seeded(8).fork(MINERS).r#gen::<u64>(), before);
+        // Forks of forks are streams of their own too
+        assert_ne!(root.fork(MINERS).fork(CHANNEL).seed(), root.fork(CHANNEL).seed());
+    }
+}
@@ -0,0 +1,134 @@
+use reed_solomon_erasure::galois_8::ReedSolomon;
+
+use crate::merkle::{self, Hash};
+
+// Celestia-style extended data square: the original k×k shares sit in the
+// top-left quadrant, every row is RS-extended to 2k, then every column is.
+pub struct ExtendedSquare {
+    pub k: usize,
+    shares: Vec<Vec<u8>>,               // 2k × 2k, row-major
+    pub row_roots: Vec<Hash>,
+    pub col_roots: Vec<Hash>,
+}
+
+pub const MAX_K: usize = 128;           // GF(2^8) RS caps 2k at 256 shards
+
+// Smallest k whose k×k square holds the data
+pub fn square_size(data_len: usize, share_size: usize) -> Result<usize, String> {
+    if share_size == 0 {
+        return Err("shares must hold at least one byte".into());
+    }
+    let shares = data_len.div_ceil(share_size).max(1);
+    let k = (shares as f64).sqrt().ceil() as usize;
+    if k > MAX_K {
+        return Err(format!(
+            "{} bytes in {} byte shares need a {}×{} square, more than the {}×{} a GF(256) extended square allows",
+            data_len, share_size, k, k, MAX_K, MAX_K
+        ));
+    }
+    Ok(k)
+}
+
+fn axis_root<'a>(shares: impl Iterator<Item = &'a [u8]>) -> Hash {
+    let leaves: Vec<Hash> = shares.map(merkle::leaf_hash).collect();
+    merkle::merkle_root(&leaves)
+}
+
+impl ExtendedSquare {
+    pub fn extend(data: &[u8], share_size: usize) -> Result<Self, String> {
+        let k = square_size(data.len(), share_size)?;
+        let width = 2 * k;
+        let rs = ReedSolomon::new(k, k).expect("valid RS parameters");
+
+        let mut shares = vec![vec![0u8; share_size]; width * width];
+        for (i, chunk) in data.chunks(share_size).enumerate() {
+            shares[(i / k) * width + i % k][..chunk.len()].copy_from_slice(chunk);
+        }
+
+        // Extend the k original rows horizontally
+        for row in 0..k {
+            let line = &mut shares[row * width..(row + 1) * width];
+            rs.encode(line).expect("row extension");
+        }
+        // Then every one of the 2k columns vertically
+        for col in 0..width {
+            let mut line: Vec<Vec<u8>> = (0..width).map(|row| shares[row * width + col].clone()).collect();
+            rs.encode(&mut line).expect("column extension");
+            for (row, share) in line.into_iter().enumerate().skip(k) {
+                shares[row * width + col] = share;
+            }
+        }
+
+        let mut square = ExtendedSquare {
+            k,
+            shares,
+            row_roots: Vec::new(),
+            col_roots: Vec::new(),
+        };
+        square.row_roots = (0..width).map(|r| axis_root((0..width).map(|c| square.share(r, c)))).collect();
+        square.col_roots = (0..width).map(|c| axis_root((0..width).map(|r| square.share(r, c)))).collect();
+        Ok(square)
+    }
+
+    pub fn width(&self) -> usize {
+        2 * self.k
+    }
+
+    pub fn share(&self, row: usize, col: usize) -> &[u8] {
+        &self.shares[row * self.width() + col]
+    }
+
+    pub fn shares(&self) -> &[Vec<u8>] {
+        &self.shares
+    }
+
+    // Commitment to the whole square: root over all row roots followed by all column roots
+    pub fn data_root(&self) -> Hash {
+        let roots: Vec<Hash> = self.row_roots.iter().chain(&self.col_roots).copied().collect();
+        merkle::merkle_root(&roots)
+    }
+}
+
+// Crossword decoding: keep rebuilding any row or column that has at least k
+// known shares until nothing changes. Returns true if every share is known.
+pub fn repair(k: usize, shares: &mut [Option<Vec<u8>>]) -> bool {
+    let width = 2 * k;
+    let rs = ReedSolomon::new(k, k).expect("valid RS parameters");
+
+    loop {
+        let mut progress = false;
+        for line in 0..width {
+            for by_row in [true, false] {
+                let index = |i: usize| if by_row { line * width + i } else { i * width + line };
+                let known = (0..width).filter(|&i| shares[index(i)].is_some()).count();
+                if known < k || known == width {
+                    continue;
+                }
+
+                let mut slots: Vec<Option<Vec<u8>>> = (0..width).map(|i| shares[index(i)].clone()).collect();
+                if rs.reconstruct(&mut slots).is_ok() {
+                    for (i, share) in slots.into_iter().enumerate() {
+                        shares[index(i)] = share;
+                    }
+                    progress = true;
+                }
+            }
+        }
+        if !progress {
+            return shares.iter().all(Option::is_some);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oversized_squares_are_refused_not_asserted() {
+        assert_eq!(square_size(MAX_K * MAX_K * 64, 64), Ok(MAX_K));
+        assert!(square_size(MAX_K * MAX_K * 64 + 1, 64).is_err());
+        assert!(square_size(2048, 0).is_err());
+        assert!(ExtendedSquare::extend(&vec![0; MAX_K * MAX_K + 1], 1).is_err());
+    }
+}
@@ -0,0 +1,87 @@
+use std::cmp::Reverse;
+use std::io::{self, Write};
+
+use crate::analyze::ExportFormat;
+use crate::json::obj;
+use crate::ToyDag;
+
+// The DAG's shape right after one block went in. Rebuilt from the finished
+// DAG, so saved DAGs get the same series as live ones.
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    pub id: u64,
+    pub tips: usize,
+    pub layer: usize,                   // Layer the new block landed in
+    pub newest_layer: usize,            // Highest layer reached so far
+    pub newest_layer_width: usize,      // Blocks in that layer so far
+    pub selected_tip: u64,
+    pub chain_mergeset: usize,          // Mergeset size of the selected tip
+}
+
+pub fn insertion_series(dag: &ToyDag) -> Vec<Sample> {
+    let mut level = vec![0usize];
+    let mut widths = vec![1usize];
+    let mut referenced = vec![false; dag.next_id as usize];
+    let mut tips = 1;
+    let mut best = (0, Reverse(0));
+    let mut series = Vec::with_capacity(dag.next_id as usize);
+
+    for id in 1..dag.next_id {
+        let block = &dag.blocks[&id];
+        let layer = block.parents.iter().map(|&p| level[p as usize] + 1).max().unwrap_or(0);
+        level.push(layer);
+        if widths.len() <= layer {
+            widths.resize(layer + 1, 0);
+        }
+        widths[layer] += 1;
+
+        tips += 1;
+        for &p in &block.parents {
+            if !std::mem::replace(&mut referenced[p as usize], true) {
+                tips -= 1;
+            }
+        }
+        // Blue score grows along every edge, so the best block overall is always a tip
+        best = best.max((block.blue_score, Reverse(id)));
+        let selected_tip = best.1 .0;
+
+        series.push(Sample {
+            id,
+            tips,
+            layer,
+            newest_layer: widths.len() - 1,
+            newest_layer_width: widths[widths.len() - 1],
+            selected_tip,
+            chain_mergeset: dag.blocks[&selected_tip].mergeset.len(),
+        });
+    }
+    series
+}
+
+pub fn write_series<W: Write + ?Sized>(dag: &ToyDag, format: ExportFormat, out: &mut W) -> io::Result<()> {
+    if format == ExportFormat::Csv {
+        writeln!(out, "id,tips,layer,newest_layer,newest_layer_width,selected_tip,chain_mergeset")?;
+    }
+    for s in insertion_series(dag) {
+        match format {
+            ExportFormat::Csv => writeln!(
+                out,
+                "{},{},{},{},{},{},{}",
+                s.id, s.tips, s.layer, s.newest_layer, s.newest_layer_width, s.selected_tip, s.chain_mergeset
+            )?,
+            ExportFormat::Json => {
+                let row = obj([
+                    ("id", s.id.into()),
+                    ("tips", s.tips.into()),
+                    ("layer", s.layer.into()),
+                    ("newest_layer", s.newest_layer.into()),
+                    ("newest_layer_width", s.newest_layer_width.into()),
+                    ("selected_tip", s.selected_tip.into()),
+                    ("chain_mergeset", s.chain_mergeset.into()),
+                ]);
+                writeln!(out, "{}", row)?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,219 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use clap::Args;
+
+use crate::agent::{AgentParams, StitchBot};
+use crate::analyze::{self, DagStats, ExportFormat};
+use crate::json::{obj, Json};
+use crate::metrics::{render, Family, Kind};
+use crate::rng::{self, SimRng, SimStream};
+use crate::simulate::{self, MiningArgs};
+use crate::stitch::StitchArgs;
+use crate::{ghostdag, series, Color, ConsensusParams, ToyDag};
+
+const INDEX: &str = "\
+toy-fec live simulation
+GET /metrics          Prometheus metrics
+GET /api/stats        DAG statistics (JSON)
+GET /api/chain        selected chain, genesis first (JSON)
+GET /api/blocks/ID    why block ID got its color and score (JSON)
+GET /api/dag          every block, one JSON object per line
+GET /api/series       tips, newest layer width and chain mergeset after each insertion (CSV)
+GET /events           server-sent events, one per new block
+";
+
+#[derive(Args, Debug)]
+pub struct ServeArgs {
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1:8080")]
+    pub addr: SocketAddr,
+
+    /// Mining rounds per second
+    #[arg(long, default_value_t = 2.0)]
+    pub rate: f64,
+
+    /// Stop growing the DAG after this many blocks and keep serving it (0 = never)
+    #[arg(long, default_value_t = 0)]
+    pub max_blocks: u64,
+
+    #[command(flatten)]
+    pub mining: MiningArgs,
+
+    #[command(flatten)]
+    pub stitch: StitchArgs,
+
+    #[command(flatten)]
+    pub agent: AgentParams,
+
+    #[command(flatten)]
+    pub consensus: ConsensusParams,
+}
+
+// The DAG as the simulation leaves it after each round, shared with every connection
+struct Live {
+    dag: ToyDag,
+    rounds: u64,
+    merges: u64,                        // StitchBot merge blocks so far
+}
+
+type Shared = Arc<(Mutex<Live>, Condvar)>;
+
+fn simulate(args: &ServeArgs, shared: Shared) {
+    let root = SimStream::unseeded();
+    let (mut miners, mut stitchbot) = (root.fork(rng::MINERS), root.fork(rng::STITCHBOT));
+    let mut bot = StitchBot::new(args.stitch.policy(), args.agent, &shared.0.lock().unwrap().dag);
+    let pause = Duration::from_secs_f64(1.0 / args.rate.max(0.001));
+    loop {
+        thread::sleep(pause);
+        let (lock, changed) = &*shared;
+        let mut live = lock.lock().unwrap();
+        let limit = match args.max_blocks {
+            0 => u64::MAX,
+            max => max.saturating_sub(live.dag.blocks.len() as u64),
+        };
+        if limit == 0 {
+            return;
+        }
+        live.rounds += 1;
+        let round = live.rounds;
+        let added = simulate::mine_round(&args.mining, &mut live.dag, &mut bot, round, limit, &mut miners, &mut stitchbot);
+        live.merges += added.merges.len() as u64;
+        changed.notify_all();
+    }
+}
+
+fn metrics(live: &Live) -> String {
+    let dag = &live.dag;
+    let reds = dag.blocks.values().filter(|b| b.color == Color::Red).count();
+    let last = series::insertion_series(dag).last().copied();
+    render(&[
+        Family::single("toyfec_blocks", "Blocks in the DAG, genesis included", Kind::Gauge, dag.blocks.len() as f64),
+        Family::single("toyfec_tips", "Blocks nothing references yet", Kind::Gauge, dag.tips.len() as f64),
+        Family::single("toyfec_red_blocks", "Blocks colored red", Kind::Gauge, reds as f64),
+        Family::single("toyfec_chain_length", "Blocks on the selected chain", Kind::Gauge, dag.selected_chain().len() as f64),
+        Family::single("toyfec_selected_blue_score", "Blue score of the selected tip", Kind::Gauge, dag.blocks[&dag.selected_parent].blue_score as f64),
+        Family::single("toyfec_newest_layer_width", "Blocks in the highest layer so far", Kind::Gauge, last.map_or(1, |s| s.newest_layer_width) as f64),
+        Family::single("toyfec_chain_mergeset", "Mergeset size of the selected tip", Kind::Gauge, last.map_or(0, |s| s.chain_mergeset) as f64),
+        Family::single("toyfec_reorgs_total", "Times the selected chain switched branches", Kind::Counter, dag.reorgs.len() as f64),
+        Family::single("toyfec_max_reorg_depth", "Most chain blocks one reorg dropped", Kind::Gauge, dag.reorgs.iter().map(|r| r.depth).max().unwrap_or(0) as f64),
+        Family::single("toyfec_rounds_total", "Mining rounds simulated", Kind::Counter, live.rounds as f64),
+        Family::single("toyfec_stitch_merges_total", "Merge blocks created by StitchBot", Kind::Counter, live.merges as f64),
+    ])
+}
+
+fn block_event(dag: &ToyDag, id: u64) -> String {
+    let block = &dag.blocks[&id];
+    let event = obj([
+        ("id", id.into()),
+        ("parents", block.parents.clone().into()),
+        ("color", format!("{:?}", block.color).to_lowercase().into()),
+        ("blue_score", block.blue_score.into()),
+        ("selected_parent", block.selected_parent.into()),
+    ]);
+    format!("data: {}\n\n", event)
+}
+
+fn respond(stream: &mut TcpStream, status: &str, content_type: &str, body: &str) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )
+}
+
+// Push every block mined after the client connected until it goes away
+fn stream_events(stream: &mut TcpStream, shared: &Shared) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nAccess-Control-Allow-Origin: *\r\n\r\n"
+    )?;
+    let (lock, changed) = &**shared;
+    let mut sent = lock.lock().unwrap().dag.next_id;
+    loop {
+        let events: String = {
+            let live = changed.wait_while(lock.lock().unwrap(), |live| live.dag.next_id == sent).unwrap();
+            let events = (sent..live.dag.next_id).map(|id| block_event(&live.dag, id)).collect();
+            sent = live.dag.next_id;
+            events
+        };
+        stream.write_all(events.as_bytes())?;
+        stream.flush()?;
+    }
+}
+
+fn handle(mut stream: TcpStream, shared: Shared) -> io::Result<()> {
+    let mut request = String::new();
+    let mut reader = BufReader::new(stream.try_clone()?);
+    reader.read_line(&mut request)?;
+    // Headers carry nothing we use, but must be read before answering
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let mut words = request.split_whitespace();
+    let (method, path) = (words.next().unwrap_or(""), words.next().unwrap_or(""));
+    if method != "GET" {
+        return respond(&mut stream, "405 Method Not Allowed", "text/plain", "only GET is supported\n");
+    }
+    if path == "/events" {
+        return stream_events(&mut stream, &shared);
+    }
+
+    let page = {
+        let live = shared.0.lock().unwrap();
+        let dag = &live.dag;
+        match path {
+            "/" => Some(("text/plain", INDEX.to_string())),
+            "/metrics" => Some(("text/plain; version=0.0.4", metrics(&live))),
+            "/api/stats" => Some(("application/json", DagStats::new(dag).json().to_string())),
+            "/api/chain" => Some(("application/json", Json::from(dag.selected_chain()).to_string())),
+            "/api/dag" => {
+                let mut rows = Vec::new();
+                analyze::export(dag, ExportFormat::Json, &mut rows)?;
+                Some(("application/x-ndjson", String::from_utf8_lossy(&rows).into_owned()))
+            }
+            "/api/series" => {
+                let mut rows = Vec::new();
+                series::write_series(dag, ExportFormat::Csv, &mut rows)?;
+                Some(("text/csv", String::from_utf8_lossy(&rows).into_owned()))
+            }
+            _ => path
+                .strip_prefix("/api/blocks/")
+                .and_then(|id| id.parse::<u64>().ok())
+                .filter(|id| dag.blocks.contains_key(id))
+                .map(|id| ("application/json", ghostdag::explain_json(dag, id).to_string())),
+        }
+    };
+    match page {
+        Some((content_type, body)) => respond(&mut stream, "200 OK", content_type, &body),
+        None => respond(&mut stream, "404 Not Found", "text/plain", "no such endpoint or block\n"),
+    }
+}
+
+pub fn run(args: ServeArgs) -> io::Result<()> {
+    let listener = TcpListener::bind(args.addr)?;
+    let dag = ToyDag::with_params(args.consensus);
+    let shared: Shared = Arc::new((Mutex::new(Live { dag, rounds: 0, merges: 0 }), Condvar::new()));
+    println!("Serving a live DAG (k = {}, {} rounds/s) on http://{}/", args.consensus.k, args.rate, args.addr);
+
+    let sim_shared = shared.clone();
+    thread::spawn(move || simulate(&args, sim_shared));
+
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else { continue };
+        let shared = shared.clone();
+        // A client hanging up mid-response is its own problem, not the server's
+        thread::spawn(move || {
+            let _ = handle(stream, shared);
+        });
+    }
+    Ok(())
+}
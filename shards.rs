@@ -0,0 +1,97 @@
+// Erasure protection for a blob in one call each way, for callers who want
+// neither OTIs nor packet formats. Each shard is a serialized packet carrying
+// its own symbol ID, so shards can be stored or sent in any order and any of
+// them lost; the Shards value keeps what the decoder needs besides them.
+
+use crate::erasure::{self, CodedPacket};
+use crate::fec::{DecodeError, SymbolArgs};
+use crate::pipeline::FecPipeline;
+use crate::PAYLOAD_ID_LEN;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shards {
+    pipeline: FecPipeline,
+    data_len: usize,
+    pub shards: Vec<Vec<u8>>,           // Source shards first, then repair; drop or reorder freely
+}
+
+impl Shards {
+    pub fn data_len(&self) -> usize {
+        self.data_len
+    }
+
+    // Shards any decoder needs at the very least
+    pub fn source_shards(&self) -> usize {
+        if self.data_len == 0 { 0 } else { self.pipeline.source_symbols(self.data_len) }
+    }
+}
+
+pub const MAX_REDUNDANCY: f64 = 64.0;   // Repair shards per source shard protect will make at most
+
+// RaptorQ with the symbol size sized to the blob as the demo sizes it, plus
+// `redundancy` repair shards per source shard (negative or NaN means none,
+// anything above MAX_REDUNDANCY, infinity included, means MAX_REDUNDANCY)
+pub fn protect(data: &[u8], redundancy: f64) -> Shards {
+    let symbol_size = SymbolArgs::default().choose(data.len(), PAYLOAD_ID_LEN).size;
+    let redundancy = if redundancy.is_nan() { 0.0 } else { redundancy.clamp(0.0, MAX_REDUNDANCY) };
+    let pipeline = FecPipeline::builder()
+        .symbol_size(symbol_size)
+        .overhead_ratio(redundancy)
+        .build()
+        .expect("a clamped ratio is finite and non-negative, and chosen symbol sizes suit RaptorQ");
+    let shards = if data.is_empty() { Vec::new() } else { pipeline.encode(data).iter().map(CodedPacket::to_bytes).collect() };
+    Shards { pipeline, data_len: data.len(), shards }
+}
+
+// The blob back from whichever shards survived. Shards too short to hold a
+// symbol, or holding one of the wrong size, are skipped rather than trusted.
+pub fn recover(shards: &Shards) -> Result<Vec<u8>, DecodeError> {
+    if shards.data_len == 0 {
+        return Ok(Vec::new());
+    }
+    let (pipeline, len) = (shards.pipeline, shards.data_len);
+    let symbol_size = pipeline.symbol_size() as usize;
+    let packets = shards.shards.iter().filter_map(|s| CodedPacket::from_bytes(s)).filter(|p| p.data.len() == symbol_size).collect();
+    erasure::decode_with(pipeline.erasure_code().as_ref(), len, pipeline.repair_for(len), packets).result().map(<[u8]>::to_vec)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recovers_from_any_large_enough_subset() {
+        let data: Vec<u8> = (0..10_000u32).map(|i| (i * 31 % 251) as u8).collect();
+        let mut shards = protect(&data, 0.5);
+        let source = shards.source_shards();
+        assert_eq!(shards.shards.len(), source + source.div_ceil(2));
+
+        // Lose every third shard, then shuffle what is left by reversing it
+        let mut kept: Vec<Vec<u8>> = shards.shards.iter().enumerate().filter(|(i, _)| i % 3 != 0).map(|(_, s)| s.clone()).collect();
+        kept.reverse();
+        shards.shards = kept;
+        assert_eq!(recover(&shards).unwrap(), data);
+
+        // Half the source count is too few, whatever mix of source and repair it is
+        shards.shards.truncate(source / 2);
+        let DecodeError::InsufficientSymbols { received, estimated_needed, loss_observed } = recover(&shards).unwrap_err();
+        assert_eq!((received, estimated_needed), (source / 2, source));
+        assert!(loss_observed > 0.0 && loss_observed < 1.0, "{}", loss_observed);
+        assert_eq!(recover(&protect(&[], 1.0)).unwrap(), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn redundancy_is_clamped() {
+        let data = [7u8; 1000];
+        let source = protect(&data, 0.0).shards.len();
+        for redundancy in [f64::NEG_INFINITY, -1.0, f64::NAN] {
+            assert_eq!(protect(&data, redundancy).shards.len(), source, "{}", redundancy);
+        }
+        let most = source + (source as f64 * MAX_REDUNDANCY) as usize;
+        for redundancy in [MAX_REDUNDANCY, 1e300, f64::INFINITY] {
+            let shards = protect(&data, redundancy);
+            assert_eq!(shards.shards.len(), most, "{}", redundancy);
+            assert_eq!(recover(&shards).unwrap(), data);
+        }
+    }
+}
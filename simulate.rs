@@ -0,0 +1,255 @@
+use std::path::PathBuf;
+
+use clap::{Args, ValueEnum};
+use rand::seq::SliceRandom;
+use rand::{Rng, RngCore};
+
+use crate::agent::{AgentParams, StitchBot};
+use crate::rng::{self, SimRng};
+use crate::stitch::StitchArgs;
+use crate::{ghostdag, snapshot, tips, Color, ConsensusParams, ToyDag};
+
+// How many parents a miner references, drawn per block
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum Fanout {
+    /// Always --max-fanout (the original miner)
+    #[default]
+    Fixed,
+    /// Uniform between 1 and --max-fanout
+    Uniform,
+    /// 1 plus a geometric tail (p = 1/2), capped at --max-fanout
+    Geometric,
+}
+
+impl Fanout {
+    pub fn draw<R: Rng>(self, max: usize, rng: &mut R) -> usize {
+        let max = max.max(1);
+        match self {
+            Fanout::Fixed => max,
+            Fanout::Uniform => rng.gen_range(1..=max),
+            Fanout::Geometric => {
+                let mut n = 1;
+                while n < max && rng.gen_bool(0.5) {
+                    n += 1;
+                }
+                n
+            }
+        }
+    }
+}
+
+// How much narration a run prints
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    Quiet,                              // One key=value summary line for scripts
+    Normal,
+    Verbose,                            // Plus per-packet detail
+    Debug,                              // Plus every coloring decision and the DAG after every round
+}
+
+#[derive(Args, Debug, Clone, Copy, Default)]
+pub struct OutputArgs {
+    /// Print only a single machine-parsable summary line
+    #[arg(short, long, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// More per-block and per-packet output (-v, -vv)
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+}
+
+impl OutputArgs {
+    pub fn level(&self) -> Verbosity {
+        match (self.quiet, self.verbose) {
+            (true, _) => Verbosity::Quiet,
+            (false, 0) => Verbosity::Normal,
+            (false, 1) => Verbosity::Verbose,
+            _ => Verbosity::Debug,
+        }
+    }
+}
+
+// key=value fields describing a DAG, the start of every quiet summary line
+pub fn summary_fields(dag: &ToyDag) -> String {
+    let reds = dag.blocks.values().filter(|b| b.color == Color::Red).count();
+    format!(
+        "blocks={} tips={} red={} red_ratio={:.4} chain={} reorgs={} max_reorg={}",
+        dag.blocks.len(),
+        dag.tips.len(),
+        reds,
+        reds as f64 / dag.blocks.len() as f64,
+        dag.selected_chain().len(),
+        dag.reorgs.len(),
+        dag.reorgs.iter().map(|r| r.depth).max().unwrap_or(0)
+    )
+}
+
+// Who mines each round and how many parents they pick
+#[derive(Args, Debug, Clone, Copy)]
+pub struct MiningArgs {
+    /// Miners per round, all building on the same view of the tips
+    #[arg(long, default_value_t = 1)]
+    pub miners: usize,
+
+    /// Distribution of the number of parents per block
+    #[arg(long, value_enum, default_value_t)]
+    pub fanout: Fanout,
+
+    /// Most parents a miner picks (consensus may still cap it at --max-parents)
+    #[arg(long, default_value_t = 3)]
+    pub max_fanout: usize,
+}
+
+impl Default for MiningArgs {
+    fn default() -> Self {
+        MiningArgs { miners: 1, fanout: Fanout::Fixed, max_fanout: 3 }
+    }
+}
+
+// Blocks a round added: what the miners found, then StitchBot's merge blocks
+pub struct Round {
+    pub mined: Vec<u64>,
+    pub merges: Vec<u64>,
+    pub tips_before_stitch: usize,
+}
+
+// One round: up to `limit` miners build concurrently on the same tips, then StitchBot gets its turn.
+// Each draws from its own stream, so a change to one never shifts the other's luck.
+pub fn mine_round<R: Rng, B: RngCore>(
+    mining: &MiningArgs,
+    dag: &mut ToyDag,
+    bot: &mut StitchBot,
+    round: u64,
+    limit: u64,
+    miners: &mut R,
+    stitchbot: &mut B,
+) -> Round {
+    let tips = tips::fresh_tips(dag, None);
+    let mined = (0..(mining.miners.max(1) as u64).min(limit))
+        .map(|_| {
+            let count = mining.fanout.draw(mining.max_fanout, miners).min(tips.len());
+            let parents = tips.choose_multiple(miners, count).copied().collect();
+            dag.create_block(parents)
+        })
+        .collect();
+    let tips_before_stitch = dag.tips.len();
+    let merges = bot.step(dag, round, stitchbot);
+    Round { mined, merges, tips_before_stitch }
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct SimArgs {
+    /// Blocks mined (StitchBot's merge blocks come on top)
+    #[arg(long, default_value_t = 150)]
+    pub blocks: u64,
+
+    #[command(flatten)]
+    pub mining: MiningArgs,
+
+    /// Print the DAG every this many rounds (0 = never)
+    #[arg(long, default_value_t = 30)]
+    pub print_every: u64,
+
+    /// Explain every coloring decision as blocks are added
+    #[arg(long)]
+    pub explain: bool,
+
+    /// Write the final DAG here for `analyze`
+    #[arg(long)]
+    pub save_dag: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub output: OutputArgs,
+
+    #[command(flatten)]
+    pub stitch: StitchArgs,
+
+    #[command(flatten)]
+    pub agent: AgentParams,
+
+    #[command(flatten)]
+    pub consensus: ConsensusParams,
+}
+
+impl Default for SimArgs {
+    fn default() -> Self {
+        SimArgs {
+            blocks: 150,
+            mining: MiningArgs::default(),
+            print_every: 30,
+            explain: false,
+            save_dag: None,
+            output: OutputArgs::default(),
+            stitch: StitchArgs::default(),
+            agent: AgentParams::default(),
+            consensus: ConsensusParams::default(),
+        }
+    }
+}
+
+// Grow a DAG round by round with StitchBot watching, narrating as it goes
+pub fn grow<S: SimRng>(args: &SimArgs, root: &S) -> ToyDag {
+    let (mut miners, mut stitchbot) = (root.fork(rng::MINERS), root.fork(rng::STITCHBOT));
+    let mut dag = ToyDag::with_params(args.consensus);
+    let mut bot = StitchBot::new(args.stitch.policy(), args.agent, &dag);
+    let level = args.output.level();
+    let normal = level >= Verbosity::Normal;
+    let explain = args.explain || level >= Verbosity::Debug;
+
+    if normal {
+        println!(
+            "Starting high-throughput DAG simulation with k={} and StitchBot ({} policy)...\n",
+            dag.params.k,
+            bot.name()
+        );
+    }
+
+    let mut mined = 0;
+    let mut round = 0;
+    while mined < args.blocks {
+        round += 1;
+        let reorgs = dag.reorgs.len();
+        let added = mine_round(&args.mining, &mut dag, &mut bot, round, args.blocks - mined, &mut miners, &mut stitchbot);
+        mined += added.mined.len() as u64;
+        if explain {
+            for &id in &added.mined {
+                println!("{}", ghostdag::explain(&dag, id));
+            }
+        }
+
+        for merge in added.merges {
+            if normal {
+                println!(" StitchBot ACTIVATED! Tips: {} → merging!", added.tips_before_stitch);
+                println!(" Created merge block {} referencing {} tips", merge, dag.blocks[&merge].parents.len());
+            }
+            if explain {
+                println!("{}", ghostdag::explain(&dag, merge));
+            }
+        }
+
+        if level >= Verbosity::Verbose {
+            for r in &dag.reorgs[reorgs..] {
+                println!(" Reorg: selected tip {} → {}, {} chain block(s) dropped", r.old_tip, r.new_tip, r.depth);
+            }
+        }
+
+        let scheduled = args.print_every > 0 && round % args.print_every == 0;
+        if (normal && scheduled) || level >= Verbosity::Debug {
+            dag.print_dag();
+        }
+    }
+
+    if normal {
+        println!("Final state: {} blocks, {} tips, selected parent {}",
+            dag.blocks.len(), dag.tips.len(), dag.selected_parent);
+        println!("Reorgs: {} (count×depth: {})\n", dag.reorgs.len(), ghostdag::describe_reorgs(&dag.reorgs));
+    }
+    if let Some(path) = &args.save_dag {
+        match snapshot::save(&dag, path) {
+            Ok(()) if normal => println!("Saved DAG to {}\n", path.display()),
+            Ok(()) => {}
+            Err(e) => eprintln!("Could not save DAG to {}: {}", path.display(), e),
+        }
+    }
+    dag
+}
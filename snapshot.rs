@@ -0,0 +1,92 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::failure::Failure;
+use crate::{parse_hash, ConsensusParams, ToyDag};
+
+// Plain-text DAG file: consensus parameters, then one `id: parents` line per
+// block in creation order, with `@ timestamp` appended when the block did not
+// use the honest clock. Colors and scores are not stored; loading replays
+// every block, so they come out exactly as GHOSTDAG computes them. Bootstrap
+// blocks are stored like any other block.
+pub fn save(dag: &ToyDag, path: &Path) -> io::Result<()> {
+    let mut out = io::BufWriter::new(fs::File::create(path)?);
+    write(dag, &mut out)?;
+    out.flush()
+}
+
+pub fn write<W: Write>(dag: &ToyDag, out: &mut W) -> io::Result<()> {
+    writeln!(out, "# toy-fec DAG, {} blocks", dag.blocks.len())?;
+    writeln!(out, "k {}", dag.params.k)?;
+    writeln!(out, "max_parents {}", dag.params.max_parents)?;
+    writeln!(out, "mtp_window {}", dag.params.mtp_window)?;
+    if let Some(genesis) = dag.params.genesis {
+        writeln!(out, "genesis {}", hex::encode(genesis))?;
+    }
+    for id in 1..dag.next_id {
+        let block = &dag.blocks[&id];
+        let parents: Vec<String> = block.parents.iter().map(u64::to_string).collect();
+        if block.timestamp == id {
+            writeln!(out, "{}: {}", id, parents.join(" "))?;
+        } else {
+            writeln!(out, "{}: {} @ {}", id, parents.join(" "), block.timestamp)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn load(path: &Path) -> Result<ToyDag, Failure> {
+    let text = fs::read_to_string(path).map_err(|e| Failure::Io(format!("cannot read {}: {}", path.display(), e)))?;
+    parse(&text, &path.display().to_string())
+}
+
+// Replay a snapshot's text; `origin` names it in error messages. Anything
+// malformed is a Config failure, whatever the input holds.
+pub fn parse(text: &str, origin: &str) -> Result<ToyDag, Failure> {
+    let mut params = ConsensusParams::default();
+    let mut dag: Option<ToyDag> = None;
+
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let bad = |what: &str| Failure::Config(format!("{} line {}: {}", origin, n + 1, what));
+        let number = |word: &str| word.parse::<u64>().map_err(|_| bad(&format!("'{}' is not a number", word)));
+
+        if let Some((id, parents)) = line.split_once(':') {
+            let dag = dag.get_or_insert_with(|| ToyDag::with_params(params));
+            let id = number(id.trim())?;
+            if id != dag.next_id {
+                return Err(bad(&format!("expected block {}, found {}", dag.next_id, id)));
+            }
+            let (parents, timestamp) = match parents.split_once('@') {
+                Some((parents, time)) => (parents, number(time.trim())?),
+                None => (parents, id),
+            };
+            let parents = parents.split_whitespace().map(number).collect::<Result<Vec<_>, _>>()?;
+            if parents.is_empty() || parents.iter().any(|p| !dag.blocks.contains_key(p)) {
+                return Err(bad("parents must be earlier blocks"));
+            }
+            if parents.iter().collect::<HashSet<_>>().len() != parents.len() {
+                return Err(bad("parents must be distinct"));
+            }
+            dag.create_block_at(parents, timestamp).map_err(|e| bad(&e))?;
+        } else {
+            match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+                _ if dag.is_some() => return Err(bad("parameters must come before the blocks")),
+                ["k", k] => params.k = number(k)? as usize,
+                ["max_parents", m] => match number(m)? {
+                    0 => return Err(bad("max_parents must be at least 1")),
+                    m => params.max_parents = m as usize,
+                },
+                ["mtp_window", w] => params.mtp_window = number(w)? as usize,
+                ["genesis", hash] => params.genesis = Some(parse_hash(hash).map_err(|e| bad(&e))?),
+                _ => return Err(bad("expected `k N`, `max_parents N`, `mtp_window N`, `genesis HASH` or `ID: PARENTS [@ TIME]`")),
+            }
+        }
+    }
+    Ok(dag.unwrap_or_else(|| ToyDag::with_params(params)))
+}
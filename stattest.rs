@@ -0,0 +1,95 @@
+// Goodness-of-fit checks for the samplers' tests. Every check runs at a
+// significance of 0.001, so a correct sampler with a fixed seed passes and a
+// wrong parameter fails by a wide margin.
+
+const Z: f64 = 3.09;                    // Upper 0.001 quantile of the standard normal
+
+// Pearson's chi-square of observed bin counts against bin probabilities, with
+// sparse bins (expected under 5) pooled into their neighbour so the statistic
+// stays valid. Returns the statistic and its degrees of freedom.
+pub fn chi_square(observed: &[usize], probabilities: &[f64]) -> (f64, usize) {
+    assert_eq!(observed.len(), probabilities.len());
+    let n = observed.iter().sum::<usize>() as f64;
+    let mut bins: Vec<(f64, f64)> = Vec::new();
+    let mut pending = (0.0, 0.0);
+    for (&o, &p) in observed.iter().zip(probabilities) {
+        pending = (pending.0 + o as f64, pending.1 + p * n);
+        if pending.1 >= 5.0 {
+            bins.push(pending);
+            pending = (0.0, 0.0);
+        }
+    }
+    match bins.last_mut() {
+        Some(last) => *last = (last.0 + pending.0, last.1 + pending.1),
+        None => bins.push(pending),
+    }
+    let statistic = bins.iter().map(|(o, e)| (o - e) * (o - e) / e).sum();
+    (statistic, bins.len().saturating_sub(1).max(1))
+}
+
+// Chi-square critical value at 0.001, by the Wilson-Hilferty approximation
+pub fn chi_square_critical(dof: usize) -> f64 {
+    let k = dof as f64;
+    let h = 2.0 / (9.0 * k);
+    k * (1.0 - h + Z * h.sqrt()).powi(3)
+}
+
+pub fn assert_fits(what: &str, observed: &[usize], probabilities: &[f64]) {
+    let (statistic, dof) = chi_square(observed, probabilities);
+    let critical = chi_square_critical(dof);
+    assert!(statistic < critical, "{}: chi-square {:.1} on {} dof exceeds {:.1}", what, statistic, dof, critical);
+}
+
+// Kolmogorov-Smirnov: largest gap between the samples' empirical CDF and `cdf`
+pub fn assert_ks(what: &str, mut samples: Vec<f64>, cdf: impl Fn(f64) -> f64) {
+    samples.sort_by(f64::total_cmp);
+    let n = samples.len() as f64;
+    let d = samples
+        .iter()
+        .enumerate()
+        .map(|(i, &x)| {
+            let f = cdf(x);
+            (f - i as f64 / n).max((i + 1) as f64 / n - f)
+        })
+        .fold(0.0, f64::max);
+    let critical = 1.95 / n.sqrt();
+    assert!(d < critical, "{}: KS distance {:.4} exceeds {:.4} over {} samples", what, d, critical, samples.len());
+}
+
+// Two-sided z-test of an observed proportion against the expected one
+pub fn assert_proportion(what: &str, hits: usize, trials: usize, expected: f64) {
+    let n = trials as f64;
+    let z = (hits as f64 - n * expected) / (n * expected * (1.0 - expected)).sqrt();
+    assert!(z.abs() < Z + 0.2, "{}: {} of {} is {:.1} sigma from {:.4}", what, hits, trials, z, expected);
+}
+
+// Lengths of the runs of `value` in `flags`, leaving out the first and last
+// runs, which the sample boundaries cut short
+pub fn run_lengths(flags: &[bool], value: bool) -> Vec<usize> {
+    let mut runs: Vec<(bool, usize)> = Vec::new();
+    for &f in flags {
+        match runs.last_mut() {
+            Some((v, len)) if *v == f => *len += 1,
+            _ => runs.push((f, 1)),
+        }
+    }
+    let inner = runs.len().saturating_sub(1);
+    runs.into_iter().take(inner).skip(1).filter(|r| r.0 == value).map(|r| r.1).collect()
+}
+
+// Geometric distribution on 1, 2, ...: P(len = k) = (1-p)^(k-1) p, with the
+// tail past `max` folded into the last bin
+pub fn geometric(p: f64, max: usize) -> Vec<f64> {
+    let mut probabilities: Vec<f64> = (1..=max).map(|k| (1.0 - p).powi(k as i32 - 1) * p).collect();
+    *probabilities.last_mut().unwrap() += (1.0 - p).powi(max as i32);
+    probabilities
+}
+
+// Counts of each value in min..=max, anything outside counted at the nearer end
+pub fn histogram(values: &[usize], min: usize, max: usize) -> Vec<usize> {
+    let mut counts = vec![0; max - min + 1];
+    for &v in values {
+        counts[v.clamp(min, max) - min] += 1;
+    }
+    counts
+}
@@ -0,0 +1,299 @@
+use std::cmp::Reverse;
+
+use clap::{Args, ValueEnum};
+
+use crate::tips::{self, TipPolicy};
+use crate::{ConsensusParams, ToyDag, K, STITCH_THRESHOLD};
+
+// When (and over which tips) StitchBot creates merge blocks
+pub trait StitchPolicy {
+    fn name(&self) -> &'static str;
+
+    // Parent lists of the merge blocks to create now; empty means leave the tips alone
+    fn plan(&mut self, dag: &ToyDag) -> Vec<Vec<u64>>;
+}
+
+fn all_tips(dag: &ToyDag) -> Vec<u64> {
+    let mut tips: Vec<u64> = dag.tips.iter().copied().collect();
+    tips.sort();
+    tips
+}
+
+// The original StitchBot: merge everything once there are too many tips
+pub struct TipThreshold {
+    pub threshold: usize,
+}
+
+impl StitchPolicy for TipThreshold {
+    fn name(&self) -> &'static str {
+        "threshold"
+    }
+
+    fn plan(&mut self, dag: &ToyDag) -> Vec<Vec<u64>> {
+        if dag.tips.len() > self.threshold {
+            vec![all_tips(dag)]
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+// Merge once the selected tip's anticone grows past a bound, however many tips there are
+pub struct AnticoneBound {
+    pub bound: usize,
+}
+
+impl StitchPolicy for AnticoneBound {
+    fn name(&self) -> &'static str {
+        "anticone"
+    }
+
+    fn plan(&mut self, dag: &ToyDag) -> Vec<Vec<u64>> {
+        if dag.tips.len() > 1 && dag.anticone_size(dag.selected_parent) > self.bound {
+            vec![all_tips(dag)]
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+// Merge every `every` blocks, whatever the DAG looks like
+pub struct Timer {
+    pub every: u64,
+    last: u64,                          // Block count at the previous merge
+}
+
+impl StitchPolicy for Timer {
+    fn name(&self) -> &'static str {
+        "timer"
+    }
+
+    fn plan(&mut self, dag: &ToyDag) -> Vec<Vec<u64>> {
+        let now = dag.blocks.len() as u64;
+        if now < self.last + self.every || dag.tips.len() < 2 {
+            return Vec::new();
+        }
+        self.last = now;
+        vec![all_tips(dag)]
+    }
+}
+
+// Threshold-triggered, but only merges tips that the merge block itself would
+// color blue: tips join a merge block best-first while every candidate's blue
+// anticone stays within k (and the parent limit holds); leftovers start the
+// next merge block.
+pub struct KSafe {
+    pub threshold: usize,
+}
+
+impl StitchPolicy for KSafe {
+    fn name(&self) -> &'static str {
+        "k-safe"
+    }
+
+    fn plan(&mut self, dag: &ToyDag) -> Vec<Vec<u64>> {
+        if dag.tips.len() <= self.threshold {
+            return Vec::new();
+        }
+
+        let mut pending = all_tips(dag);
+        pending.sort_by_key(|id| (Reverse(dag.blocks[id].blue_score), *id));
+
+        let mut merges = Vec::new();
+        while let Some(first) = pending.first().copied() {
+            let mut group = vec![first];
+            let mut leftover = Vec::new();
+            for &tip in &pending[1..] {
+                if group.len() == dag.params.max_parents {
+                    leftover.push(tip);
+                    continue;
+                }
+                group.push(tip);
+                let (_, mergeset) = dag.color_mergeset(&group);
+                if mergeset.iter().any(|c| c.blue_anticone > dag.params.k) {
+                    leftover.push(group.pop().unwrap());
+                }
+            }
+            // A lone tip needs no merge block
+            if group.len() > 1 {
+                merges.push(group);
+            }
+            pending = leftover;
+        }
+        merges
+    }
+}
+
+// Threshold that moves with the tip growth rate: an EWMA of tips gained per
+// check predicts where the tip count is heading, and StitchBot merges as soon
+// as that prediction crosses the base threshold
+pub struct Adaptive {
+    pub base: usize,
+    pub alpha: f64,                     // Weight of the newest observation
+    rate: f64,                          // EWMA of tips gained between checks
+    last_tips: usize,                   // Tip count right after the previous check
+}
+
+impl Adaptive {
+    pub fn new(base: usize, alpha: f64) -> Self {
+        Adaptive { base, alpha, rate: 0.0, last_tips: 1 }
+    }
+
+    pub fn threshold(&self) -> usize {
+        (self.base as f64 - self.rate).round().max(2.0) as usize
+    }
+}
+
+impl StitchPolicy for Adaptive {
+    fn name(&self) -> &'static str {
+        "adaptive"
+    }
+
+    fn plan(&mut self, dag: &ToyDag) -> Vec<Vec<u64>> {
+        let gained = dag.tips.len() as f64 - self.last_tips as f64;
+        self.rate = self.alpha * gained.max(0.0) + (1.0 - self.alpha) * self.rate;
+
+        if dag.tips.len() > self.threshold() {
+            self.last_tips = 1;         // A merge of every tip leaves exactly one
+            vec![all_tips(dag)]
+        } else {
+            self.last_tips = dag.tips.len();
+            Vec::new()
+        }
+    }
+}
+
+pub struct Never;
+
+impl StitchPolicy for Never {
+    fn name(&self) -> &'static str {
+        "never"
+    }
+
+    fn plan(&mut self, _dag: &ToyDag) -> Vec<Vec<u64>> {
+        Vec::new()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum StitchKind {
+    #[default]
+    Threshold,
+    Anticone,
+    Timer,
+    KSafe,
+    Adaptive,
+    Never,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct StitchArgs {
+    /// StitchBot merge policy
+    #[arg(long, value_enum, default_value_t)]
+    pub stitch: StitchKind,
+
+    /// Tips that trigger a merge (threshold and k-safe; base for adaptive)
+    #[arg(long, default_value_t = STITCH_THRESHOLD)]
+    pub stitch_threshold: usize,
+
+    /// Largest tolerated anticone of the selected tip (anticone policy)
+    #[arg(long, default_value_t = K)]
+    pub stitch_bound: usize,
+
+    /// Blocks between merges (timer policy)
+    #[arg(long, default_value_t = 20)]
+    pub stitch_every: u64,
+
+    /// EWMA weight of the latest tip growth sample (adaptive policy)
+    #[arg(long, default_value_t = 0.3)]
+    pub stitch_alpha: f64,
+}
+
+impl Default for StitchArgs {
+    fn default() -> Self {
+        StitchArgs {
+            stitch: StitchKind::default(),
+            stitch_threshold: STITCH_THRESHOLD,
+            stitch_bound: K,
+            stitch_every: 20,
+            stitch_alpha: 0.3,
+        }
+    }
+}
+
+impl StitchArgs {
+    pub fn policy(&self) -> Box<dyn StitchPolicy> {
+        match self.stitch {
+            StitchKind::Threshold => Box::new(TipThreshold { threshold: self.stitch_threshold }),
+            StitchKind::Anticone => Box::new(AnticoneBound { bound: self.stitch_bound }),
+            StitchKind::Timer => Box::new(Timer { every: self.stitch_every, last: 0 }),
+            StitchKind::KSafe => Box::new(KSafe { threshold: self.stitch_threshold }),
+            StitchKind::Adaptive => Box::new(Adaptive::new(self.stitch_threshold, self.stitch_alpha)),
+            StitchKind::Never => Box::new(Never),
+        }
+    }
+}
+
+#[derive(Args, Debug)]
+pub struct StitchCompareArgs {
+    /// Blocks to grow per run
+    #[arg(long, default_value_t = 300)]
+    pub blocks: u64,
+
+    /// Concurrent miners per round
+    #[arg(long, default_value_t = 8)]
+    pub miners: usize,
+
+    /// Tip selection used by every miner
+    #[arg(long, value_enum, default_value_t = TipPolicy::HighestBlue)]
+    pub tips: TipPolicy,
+
+    /// Runs averaged per stitch policy
+    #[arg(long, default_value_t = 10)]
+    pub runs: usize,
+
+    /// Stitch policies to compare (their parameters come from the --stitch-* flags)
+    #[arg(long, value_enum, value_delimiter = ',', default_values_t = vec![StitchKind::Threshold, StitchKind::Adaptive])]
+    pub policies: Vec<StitchKind>,
+
+    #[command(flatten)]
+    pub stitch: StitchArgs,
+
+    #[command(flatten)]
+    pub consensus: ConsensusParams,
+}
+
+// Fixed vs adaptive (or any other) merge policies under the same miners
+pub fn run_compare(args: &StitchCompareArgs) {
+    let runs = args.runs.max(1);
+    println!(
+        "=== Stitch policies ({} blocks, {} x{} miners, {} runs) ===",
+        args.blocks,
+        args.tips.selector().name(),
+        args.miners,
+        runs
+    );
+    println!("{:<10} {:>10} {:>9} {:>10} {:>8}", "policy", "mean tips", "max tips", "red ratio", "merges");
+
+    for &kind in &args.policies {
+        let params = StitchArgs { stitch: kind, ..args.stitch.clone() };
+        let reports: Vec<tips::ShapeReport> = (0..runs)
+            .map(|_| {
+                let miners: Vec<_> = (0..args.miners.max(1)).map(|_| args.tips.selector()).collect();
+                tips::grow_with(args.blocks, args.consensus, &miners, None, params.policy().as_mut(), String::new()).1
+            })
+            .collect();
+
+        let blocks: usize = reports.iter().map(|r| r.blocks).sum();
+        let reds: usize = reports.iter().map(|r| r.reds).sum();
+        println!(
+            "{:<10} {:>10.2} {:>9} {:>10.3} {:>8.1}",
+            params.policy().name(),
+            reports.iter().map(|r| r.mean_tips).sum::<f64>() / runs as f64,
+            reports.iter().map(|r| r.max_tips).max().unwrap_or(0),
+            reds as f64 / blocks as f64,
+            reports.iter().map(|r| r.merges).sum::<usize>() as f64 / runs as f64
+        );
+    }
+    println!("=========================================================");
+}
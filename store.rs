@@ -0,0 +1,242 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::erasure::{CodedPacket, ErasureCode};
+use crate::faults::{FaultPoint, Faults};
+use crate::merkle::{self, Hash, MerkleProof, MerkleTree};
+
+// Encoded symbols of one block, kept with the tree that proves them
+struct StoredBlock {
+    data_len: usize,
+    packets: Vec<CodedPacket>,
+    tree: MerkleTree,
+    withheld: HashSet<usize>,           // Indices this node refuses to serve
+}
+
+// One answered query: a symbol plus its proof against the block's symbol root
+#[derive(Debug, Clone)]
+pub struct SymbolResponse {
+    pub block: u64,
+    pub index: usize,
+    pub packet: CodedPacket,
+    pub proof: MerkleProof,
+}
+
+// Full-node side of DAS: every block is erasure-coded once, its symbols kept
+// (optionally on disk), and any symbol index can be served with a proof.
+pub struct EncodedBlockStore {
+    code: Box<dyn ErasureCode>,
+    repair: u32,
+    blocks: HashMap<u64, StoredBlock>,
+    dir: Option<PathBuf>,
+    faults: Faults,
+}
+
+pub fn symbol_leaf(packet: &CodedPacket) -> Hash {
+    merkle::leaf_hash(&packet.to_bytes())
+}
+
+// Whether a response is the symbol at `index` of a block with `symbols` symbols.
+// The proof has to climb from that very leaf: a valid proof for some other
+// symbol the node still holds does not answer the query.
+pub fn verify_symbol(root: &Hash, symbols: usize, index: usize, response: &SymbolResponse) -> bool {
+    response.index == index
+        && response.proof.leaf_index(symbols) == Some(index)
+        && response.proof.verify(symbol_leaf(&response.packet), root)
+}
+
+impl EncodedBlockStore {
+    pub fn new(code: Box<dyn ErasureCode>, repair: u32) -> Self {
+        EncodedBlockStore {
+            code,
+            repair,
+            blocks: HashMap::new(),
+            dir: None,
+            faults: Faults::none(),
+        }
+    }
+
+    // Fail writes at the given rate, to exercise callers' error paths
+    pub fn inject(mut self, faults: Faults) -> Self {
+        self.faults = faults;
+        self
+    }
+
+    // Also write every stored block's symbols under `dir` (one file per block)
+    pub fn persist_to(mut self, dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        self.dir = Some(dir);
+        Ok(self)
+    }
+
+    // Encode and keep a block's data; returns the root clients sample against
+    pub fn put(&mut self, block: u64, data: &[u8]) -> io::Result<Hash> {
+        if self.faults.strike(FaultPoint::StoreWrite) {
+            return Err(io::Error::other(format!("injected write failure for block {}", block)));
+        }
+        let packets = self.code.encode(data, self.repair);
+        if let Some(dir) = &self.dir {
+            write_symbols(&block_path(dir, block), data.len(), &packets)?;
+        }
+        let tree = MerkleTree::new(packets.iter().map(symbol_leaf).collect());
+        let root = tree.root();
+        self.blocks.insert(block, StoredBlock { data_len: data.len(), packets, tree, withheld: HashSet::new() });
+        Ok(root)
+    }
+
+    // Reload a block previously written by a persisting store
+    pub fn load(&mut self, block: u64) -> io::Result<Hash> {
+        let dir = self.dir.as_ref().ok_or_else(|| io::Error::other("store is not persistent"))?;
+        let (data_len, packets) = read_symbols(&block_path(dir, block))?;
+        let tree = MerkleTree::new(packets.iter().map(symbol_leaf).collect());
+        let root = tree.root();
+        self.blocks.insert(block, StoredBlock { data_len, packets, tree, withheld: HashSet::new() });
+        Ok(root)
+    }
+
+    pub fn root(&self, block: u64) -> Option<Hash> {
+        self.blocks.get(&block).map(|b| b.tree.root())
+    }
+
+    pub fn symbol_count(&self, block: u64) -> usize {
+        self.blocks.get(&block).map_or(0, |b| b.packets.len())
+    }
+
+    pub fn data_len(&self, block: u64) -> usize {
+        self.blocks.get(&block).map_or(0, |b| b.data_len)
+    }
+
+    // Model a dishonest node: these indices are silently refused from now on
+    pub fn withhold(&mut self, block: u64, indices: impl IntoIterator<Item = usize>) {
+        if let Some(stored) = self.blocks.get_mut(&block) {
+            stored.withheld.extend(indices);
+        }
+    }
+
+    // One entry per requested index; None when the symbol is missing or withheld
+    pub fn query(&self, block: u64, indices: &[usize]) -> Vec<Option<SymbolResponse>> {
+        let Some(stored) = self.blocks.get(&block) else {
+            return vec![None; indices.len()];
+        };
+        indices
+            .iter()
+            .map(|&index| {
+                if index >= stored.packets.len() || stored.withheld.contains(&index) {
+                    return None;
+                }
+                Some(SymbolResponse {
+                    block,
+                    index,
+                    packet: stored.packets[index].clone(),
+                    proof: stored.tree.proof(index),
+                })
+            })
+            .collect()
+    }
+
+    // Try to rebuild a block from whatever the store is willing to serve
+    pub fn reconstruct(&self, block: u64) -> Option<Vec<u8>> {
+        if !self.blocks.contains_key(&block) {
+            return None;    // No data length to build a decoder config from
+        }
+        let indices: Vec<usize> = (0..self.symbol_count(block)).collect();
+        let available = self.query(block, &indices).into_iter().flatten().map(|r| r.packet).collect();
+        crate::erasure::decode_with(self.code.as_ref(), self.data_len(block), self.repair, available).data
+    }
+}
+
+fn block_path(dir: &Path, block: u64) -> PathBuf {
+    dir.join(format!("{}.sym", block))
+}
+
+// data length u64 | symbol count u32 | per symbol: length u32, packet bytes
+fn write_symbols(path: &Path, data_len: usize, packets: &[CodedPacket]) -> io::Result<()> {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&(data_len as u64).to_be_bytes());
+    bytes.extend_from_slice(&(packets.len() as u32).to_be_bytes());
+    for packet in packets {
+        let encoded = packet.to_bytes();
+        bytes.extend_from_slice(&(encoded.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(&encoded);
+    }
+    fs::write(path, bytes)
+}
+
+fn read_symbols(path: &Path) -> io::Result<(usize, Vec<CodedPacket>)> {
+    let bytes = fs::read(path)?;
+    let corrupt = || io::Error::new(io::ErrorKind::InvalidData, format!("corrupt symbol file {}", path.display()));
+    let mut rest = bytes.as_slice();
+    let mut take = |n: usize| -> io::Result<&[u8]> {
+        if rest.len() < n {
+            return Err(corrupt());
+        }
+        let (head, tail) = rest.split_at(n);
+        rest = tail;
+        Ok(head)
+    };
+
+    let data_len = u64::from_be_bytes(take(8)?.try_into().unwrap()) as usize;
+    let count = u32::from_be_bytes(take(4)?.try_into().unwrap()) as usize;
+    // Every record is a length and at least a payload ID; a count the file cannot hold is not allocated for
+    if count > (bytes.len() - 12) / (4 + 4) {
+        return Err(corrupt());
+    }
+    let mut packets = Vec::with_capacity(count);
+    for _ in 0..count {
+        let len = u32::from_be_bytes(take(4)?.try_into().unwrap()) as usize;
+        packets.push(CodedPacket::from_bytes(take(len)?).ok_or_else(corrupt)?);
+    }
+    Ok((data_len, packets))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::erasure::RaptorQCode;
+
+    #[test]
+    fn proofs_pin_the_requested_index() {
+        // Odd symbol counts leave unpaired nodes carried up, which the proof path skips
+        for symbols in [1, 2, 5, 7, 12] {
+            let tree = MerkleTree::new((0..symbols).map(|i| merkle::leaf_hash(&[i as u8])).collect());
+            for index in 0..symbols {
+                assert_eq!(tree.proof(index).leaf_index(symbols), Some(index), "{} of {}", index, symbols);
+            }
+        }
+    }
+
+    #[test]
+    fn a_substituted_symbol_does_not_answer_the_query() {
+        let mut store = EncodedBlockStore::new(Box::new(RaptorQCode { symbol_size: 64 }), 4);
+        let root = store.put(1, &[9; 500]).unwrap();
+        let symbols = store.symbol_count(1);
+        let held = store.query(1, &[3]).pop().flatten().unwrap();
+        assert!(verify_symbol(&root, symbols, 3, &held));
+
+        // A node missing symbol 5 answers with symbol 3 and its genuine proof
+        let substituted = SymbolResponse { index: 5, ..held.clone() };
+        assert!(!verify_symbol(&root, symbols, 5, &substituted));
+        assert!(!verify_symbol(&root, symbols, 5, &held));
+    }
+
+    #[test]
+    fn symbol_counts_the_file_cannot_hold_are_corrupt() {
+        let path = std::env::temp_dir().join(format!("toy-fec-symbols-{}.bin", std::process::id()));
+        let mut bytes = 500u64.to_be_bytes().to_vec();
+        bytes.extend_from_slice(&u32::MAX.to_be_bytes());
+        bytes.extend_from_slice(&[0, 0, 0, 4, 0, 0, 0, 1]);
+        fs::write(&path, &bytes).unwrap();
+        let huge = read_symbols(&path);
+
+        // The same record under an honest count still reads
+        bytes[8..12].copy_from_slice(&1u32.to_be_bytes());
+        fs::write(&path, &bytes).unwrap();
+        let one = read_symbols(&path);
+        let _ = fs::remove_file(&path);
+        assert_eq!(huge.unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(one.unwrap().1.len(), 1);
+    }
+}
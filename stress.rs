@@ -0,0 +1,198 @@
+use std::collections::HashSet;
+use std::panic::{self, AssertUnwindSafe};
+use std::time::{Duration, Instant};
+
+use clap::{Parser, ValueEnum};
+use raptorq::Encoder;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::agent::{AgentParams, StitchBot};
+use crate::bodies::BodyArgs;
+use crate::failure::{self, Failure};
+use crate::rng::{self, SimRng, SimStream};
+use crate::simulate::{self, Fanout, MiningArgs};
+use crate::stitch::{StitchArgs, StitchKind};
+use crate::{fec, headers, snapshot, Color, ConsensusParams, ToyDag, PAYLOAD_ID_LEN};
+
+/// Grow random DAGs without end, checking invariants as they grow, for soak runs
+#[derive(Parser, Debug)]
+#[command(name = "stress")]
+pub struct StressArgs {
+    /// Seed of the first run; run N uses seed + N (random when omitted)
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Stop after this many runs (never, when omitted)
+    #[arg(long)]
+    pub runs: Option<u64>,
+
+    /// Most blocks mined in one run; each run picks its size at random up to this
+    #[arg(long, default_value_t = 300)]
+    pub max_blocks: u64,
+
+    /// Check the invariants after every this many rounds, and once more at the end
+    #[arg(long, default_value_t = 10)]
+    pub check_every: u64,
+
+    /// Seconds between progress lines with throughput and memory use
+    #[arg(long, default_value_t = 10)]
+    pub report_every: u64,
+}
+
+// One run's randomly drawn configuration, printed with any failure
+#[derive(Debug)]
+struct Scenario {
+    blocks: u64,
+    mining: MiningArgs,
+    stitch: StitchKind,
+    consensus: ConsensusParams,
+    loss: f64,
+}
+
+impl Scenario {
+    fn draw<R: Rng>(max_blocks: u64, rng: &mut R) -> Self {
+        Scenario {
+            blocks: rng.gen_range(1..=max_blocks.max(1)),
+            mining: MiningArgs {
+                miners: rng.gen_range(1..=8),
+                fanout: *Fanout::value_variants().choose(rng).unwrap(),
+                max_fanout: rng.gen_range(1..=6),
+            },
+            stitch: *StitchKind::value_variants().choose(rng).unwrap(),
+            consensus: ConsensusParams {
+                k: rng.gen_range(0..=12),
+                max_parents: rng.gen_range(1..=12),
+                mtp_window: rng.gen_range(1..=15),
+                ..ConsensusParams::default()
+            },
+            loss: rng.gen_range(0.0..0.5),
+        }
+    }
+}
+
+// Structural checks that must hold after every block
+fn check_dag(dag: &ToyDag) -> Result<(), String> {
+    dag.check_invariants()?;
+    let tip = &dag.blocks[&dag.selected_parent];
+    if !dag.is_tip(tip.id) || tip.color != Color::Blue {
+        return Err(format!("selected parent {} is not a blue tip", tip.id));
+    }
+    let order = dag.consensus_order();
+    if order.len() as u64 != dag.next_id || order.iter().collect::<HashSet<_>>().len() != order.len() {
+        return Err(format!("consensus order lists {} blocks, not each of {} once", order.len(), dag.next_id));
+    }
+    Ok(())
+}
+
+// The finished DAG has to survive both of its serializations and a lossy FEC transfer
+fn check_round_trips<R: Rng>(dag: &ToyDag, loss: f64, rng: &mut R) -> Result<(), String> {
+    let mut text = Vec::new();
+    snapshot::write(dag, &mut text).map_err(|e| e.to_string())?;
+    let reloaded = snapshot::parse(&String::from_utf8_lossy(&text), "snapshot").map_err(|f| f.to_string())?;
+    if !reloaded.structurally_equal(dag) {
+        return Err("snapshot reloads as a different DAG".into());
+    }
+
+    let (data, _) = headers::serialize(dag, &BodyArgs::default());
+    let size = fec::SymbolArgs::default().choose(data.len(), PAYLOAD_ID_LEN).size;
+    let encoder = Encoder::with_defaults(&data, size);
+    let source = data.len().div_ceil(size as usize) as f64;
+    let repair = (source * loss / (1.0 - loss)).ceil() as u32 + 8;
+    let mut packets = encoder.get_encoded_packets(repair);
+    packets.shuffle(rng);
+    packets.retain(|_| !rng.gen_bool(loss));
+    // Losing too many for the repair sent is the channel's doing, not a bug
+    let Some(recovered) = fec::decode_packets(encoder.get_config(), packets).data else {
+        return Ok(());
+    };
+    let set = headers::parse(&recovered)?;
+    if !headers::rebuild(&set)?.structurally_equal(dag) {
+        return Err("headers decoded but rebuilt a different DAG".into());
+    }
+    Ok(())
+}
+
+fn run_once(seed: u64, args: &StressArgs) -> Result<u64, String> {
+    let root = SimStream::seeded(seed);
+    let scenario = Scenario::draw(args.max_blocks, &mut root.fork("scenario"));
+    let (mut miners, mut stitchbot) = (root.fork(rng::MINERS), root.fork(rng::STITCHBOT));
+    let stitch = StitchArgs { stitch: scenario.stitch, ..StitchArgs::default() };
+    let mut dag = ToyDag::with_params(scenario.consensus);
+    let mut bot = StitchBot::new(stitch.policy(), AgentParams::default(), &dag);
+
+    let mut mined = 0;
+    let mut round = 0;
+    while mined < scenario.blocks {
+        round += 1;
+        let added = simulate::mine_round(&scenario.mining, &mut dag, &mut bot, round, scenario.blocks - mined, &mut miners, &mut stitchbot);
+        mined += added.mined.len() as u64;
+        if args.check_every > 0 && round % args.check_every == 0 {
+            check_dag(&dag).map_err(|e| format!("round {}: {}\n{:?}", round, e, scenario))?;
+        }
+    }
+    check_dag(&dag)
+        .and_then(|()| check_round_trips(&dag, scenario.loss, &mut root.fork(rng::CHANNEL)))
+        .map_err(|e| format!("after {} rounds: {}\n{:?}", round, e, scenario))?;
+    Ok(dag.next_id)
+}
+
+// Resident memory of this process, from /proc where there is one
+fn resident_kib() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|l| l.starts_with("VmRSS:"))?;
+    line.split_whitespace().nth(1)?.parse().ok()
+}
+
+fn run(args: &StressArgs) -> Result<(), Failure> {
+    let first = args.seed.unwrap_or_else(|| SimStream::unseeded().seed());
+    println!("=== Stress run from seed {} ({}) ===", first, args.runs.map_or("until stopped".to_string(), |n| format!("{} runs", n)));
+
+    // Panics are caught and reported with their seed; keep the default hook from printing them twice
+    panic::set_hook(Box::new(|_| {}));
+    let started = Instant::now();
+    let mut last_report = Instant::now();
+    let (mut runs, mut blocks) = (0u64, 0u64);
+    while args.runs.is_none_or(|n| runs < n) {
+        let seed = first.wrapping_add(runs);
+        let outcome = panic::catch_unwind(AssertUnwindSafe(|| run_once(seed, args))).unwrap_or_else(|payload| {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".into());
+            Err(format!("panicked: {}", message))
+        });
+        match outcome {
+            Ok(added) => blocks += added,
+            Err(e) => {
+                let _ = panic::take_hook();
+                println!("\nRun {} FAILED: {}", runs, e);
+                println!("Reproduce with: stress --seed {} --runs 1 --max-blocks {}", seed, args.max_blocks);
+                return Err(Failure::Mismatch(format!("invariant broken with seed {}", seed)));
+            }
+        }
+        runs += 1;
+
+        if last_report.elapsed() >= Duration::from_secs(args.report_every) {
+            last_report = Instant::now();
+            let secs = started.elapsed().as_secs_f64();
+            println!(
+                "{:>8.0}s  {} runs  {} blocks  {:.0} blocks/s  rss {}",
+                secs,
+                runs,
+                blocks,
+                blocks as f64 / secs,
+                resident_kib().map_or("n/a".to_string(), |k| format!("{} MiB", k / 1024))
+            );
+        }
+    }
+    let _ = panic::take_hook();
+    println!("{} runs, {} blocks, every invariant held", runs, blocks);
+    Ok(())
+}
+
+// The `stress` binary
+pub fn run_cli() {
+    run(&StressArgs::parse()).unwrap_or_else(|f| failure::exit("stress", f))
+}
@@ -0,0 +1,267 @@
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use clap::Args;
+use hex::encode;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use sha2::{Digest, Sha256};
+
+use crate::channel::{LossModel, LossTrace};
+use crate::columnar::{self, RunMetadata};
+use crate::confidence;
+use crate::erasure::{self, CodeKind};
+use crate::failure::Failure;
+use crate::fec::OverheadStats;
+use crate::pipeline::FecPipeline;
+use crate::SYMBOL_SIZE;
+
+#[derive(Args, Debug)]
+pub struct SweepArgs {
+    /// Code family to sweep
+    #[arg(long, value_enum, default_value_t)]
+    pub code: CodeKind,
+
+    /// Object size in bytes (default: the demo's 151 block hashes)
+    #[arg(long, default_value_t = 151 * 32)]
+    pub size: usize,
+
+    /// Bytes per symbol
+    #[arg(long, default_value_t = SYMBOL_SIZE)]
+    pub symbol_size: u16,
+
+    /// Loss rates to test (each packet dropped independently)
+    #[arg(long, value_delimiter = ',', default_values_t = vec![0.0, 0.05, 0.1, 0.15, 0.2, 0.25, 0.3, 0.4, 0.5])]
+    pub loss: Vec<f64>,
+
+    /// Replay a recorded loss pattern instead of --loss (0/1 flags per packet, or timestamped delivered/lost records)
+    #[arg(long)]
+    pub trace: Option<PathBuf>,
+
+    /// Repair packet counts to test
+    #[arg(long, value_delimiter = ',', default_values_t = vec![0, 2, 5, 10, 15, 20, 30, 40, 50])]
+    pub repair: Vec<u32>,
+
+    /// Seeded loss patterns per cell (seeds 0..seeds)
+    #[arg(long, default_value_t = 100)]
+    pub seeds: u64,
+
+    /// Write the CSV here instead of stdout; a .arrow or .parquet path gets a row per run instead (needs --features arrow)
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+
+    /// Also draw an ASCII heatmap of the decode success rate
+    #[arg(long)]
+    pub plot: bool,
+
+    /// For each loss rate, report the fewest repair packets whose success rate is above this with 95% confidence
+    #[arg(long)]
+    pub target: Option<f64>,
+
+    /// Two repair counts from --repair to compare at every loss rate, by McNemar's test on the shared loss patterns
+    #[arg(long, value_delimiter = ',')]
+    pub compare: Vec<u32>,
+}
+
+pub struct SweepCell {
+    pub loss: f64,
+    pub repair: u32,
+    pub source_packets: usize,
+    pub stats: OverheadStats,
+    pub outcomes: Vec<Option<usize>>,   // Extra packets each seed needed, None where decoding failed
+}
+
+impl SweepCell {
+    fn success_rate(&self) -> f64 {
+        self.stats.successes() as f64 / (self.stats.successes() + self.stats.failures()).max(1) as f64
+    }
+
+    fn success_interval(&self) -> (f64, f64) {
+        confidence::wilson(self.stats.successes(), self.outcomes.len())
+    }
+
+    fn overhead_interval(&self) -> (f64, f64) {
+        let extra: Vec<f64> = self.outcomes.iter().flatten().map(|&e| e as f64).collect();
+        confidence::bootstrap_mean(&extra)
+    }
+
+    fn succeeded(&self) -> Vec<bool> {
+        self.outcomes.iter().map(Option::is_some).collect()
+    }
+}
+
+// Every (loss, repair) cell decoded under the same seeded loss patterns. A
+// trace stands in for the loss rates as a single column at its mean loss,
+// each seed starting the playback somewhere else.
+pub fn sweep(args: &SweepArgs) -> Result<Vec<SweepCell>, Failure> {
+    let pipeline = FecPipeline::builder().symbol_size(args.symbol_size).code(args.code).build().map_err(Failure::Config)?;
+    let code = pipeline.erasure_code();
+    let mut data = vec![0u8; args.size.max(1)];
+    StdRng::seed_from_u64(0).fill(&mut data[..]);
+    let source_packets = pipeline.source_symbols(data.len());
+
+    let channels: Vec<(f64, LossModel)> = match &args.trace {
+        Some(path) => {
+            let trace = Rc::new(LossTrace::load(path)?);
+            vec![(trace.mean_loss(), LossModel::Trace(trace))]
+        }
+        None => args.loss.iter().map(|&loss| (loss, LossModel::Uniform { rate: loss.clamp(0.0, 1.0) })).collect(),
+    };
+
+    let mut cells = Vec::new();
+    for &repair in &args.repair {
+        let packets = code.encode(&data, repair);
+        for (loss, channel) in &channels {
+            let loss = *loss;
+            let mut stats = OverheadStats::default();
+            let mut outcomes = Vec::new();
+            for seed in 0..args.seeds {
+                let mut rng = StdRng::seed_from_u64(seed);
+                let received = channel.transmit(packets.clone(), &mut rng);
+                let outcome = erasure::decode_with(code.as_ref(), data.len(), repair, received);
+                stats.record(&outcome);
+                outcomes.push(outcome.overhead());
+            }
+            cells.push(SweepCell { loss, repair, source_packets, stats, outcomes });
+        }
+    }
+    Ok(cells)
+}
+
+// Everything that decides a sweep's outcomes, and a short hash of it that
+// tells rows from different sweeps apart once they share a dataframe
+fn metadata(args: &SweepArgs) -> RunMetadata {
+    let config = format!(
+        "code={:?} size={} symbol_size={} loss={:?} trace={:?} repair={:?} seeds={}",
+        args.code, args.size, args.symbol_size, args.loss, args.trace, args.repair, args.seeds
+    );
+    let config_hash = encode(&Sha256::digest(config.as_bytes())[..8]);
+    RunMetadata { config, config_hash }
+}
+
+pub fn write_csv<W: Write>(cells: &[SweepCell], out: &mut W) -> io::Result<()> {
+    writeln!(
+        out,
+        "loss_rate,repair_packets,source_packets,repair_overhead,runs,successes,success_rate,mean_overhead,success_low,success_high,overhead_low,overhead_high"
+    )?;
+    for c in cells {
+        let (success_low, success_high) = c.success_interval();
+        let (overhead_low, overhead_high) = c.overhead_interval();
+        writeln!(
+            out,
+            "{},{},{},{:.4},{},{},{:.4},{:.4},{:.4},{:.4},{:.4},{:.4}",
+            c.loss,
+            c.repair,
+            c.source_packets,
+            c.repair as f64 / c.source_packets as f64,
+            c.stats.successes() + c.stats.failures(),
+            c.stats.successes(),
+            c.success_rate(),
+            c.stats.mean_overhead(),
+            success_low,
+            success_high,
+            overhead_low,
+            overhead_high
+        )?;
+    }
+    Ok(())
+}
+
+// Repair counts down the side, loss rates across; darker means more decodes succeeded
+fn plot(args: &SweepArgs, cells: &[SweepCell]) {
+    const SHADES: &[u8] = b" .:-=+*#%@";
+    println!("\nDecode success rate (' ' = 0%, '@' = 100%), {} seeds per cell:", args.seeds);
+    let columns = cells.iter().take_while(|c| c.repair == cells[0].repair).count();
+    print!("repair \\ loss");
+    for cell in &cells[..columns] {
+        print!(" {:>4.0}%", cell.loss * 100.0);
+    }
+    println!();
+    for row in cells.chunks(columns) {
+        print!("{:>13}", row[0].repair);
+        for cell in row {
+            let shade = SHADES[(cell.success_rate() * (SHADES.len() - 1) as f64).round() as usize] as char;
+            print!("  {}{}{} ", shade, shade, shade);
+        }
+        println!();
+    }
+}
+
+// The fewest repair packets per loss rate that clear the target even at the
+// bottom of their interval; a point estimate over a handful of seeds is not enough
+fn report_target(cells: &[SweepCell], target: f64) {
+    println!("\nFewest repair packets whose success rate is above {} with 95% confidence (Wilson):", target);
+    println!("{:>6} {:>7} {:>9} {:>17}", "loss", "repair", "success", "interval");
+    let mut losses: Vec<f64> = cells.iter().map(|c| c.loss).collect();
+    losses.sort_by(f64::total_cmp);
+    losses.dedup();
+    for loss in losses {
+        let mut column: Vec<&SweepCell> = cells.iter().filter(|c| c.loss == loss).collect();
+        column.sort_by_key(|c| c.repair);
+        match column.iter().find(|c| c.success_interval().0 > target) {
+            Some(c) => {
+                let (lo, hi) = c.success_interval();
+                println!("{:>5.1}% {:>7} {:>9.3} {:>8.3}..{:.3}", loss * 100.0, c.repair, c.success_rate(), lo, hi);
+            }
+            None => println!("{:>5.1}% {:>7} {:>9} {:>17}", loss * 100.0, "none", "", "(more repair or seeds)"),
+        }
+    }
+}
+
+// Two repair counts decoded under the same loss patterns, compared pattern by pattern
+fn report_comparison(cells: &[SweepCell], first: u32, second: u32) {
+    println!("\n{} against {} repair packets, McNemar's exact test on the shared loss patterns:", first, second);
+    println!("{:>6} {:>9} {:>9} {:>11} {:>11} {:>9}", "loss", format!("{} ok", first), format!("{} ok", second), "only first", "only second", "p-value");
+    for a in cells.iter().filter(|c| c.repair == first) {
+        let Some(b) = cells.iter().find(|c| c.repair == second && c.loss == a.loss) else { continue };
+        let test = confidence::mcnemar(&a.succeeded(), &b.succeeded());
+        println!(
+            "{:>5.1}% {:>9.3} {:>9.3} {:>11} {:>11} {:>9.4}{}",
+            a.loss * 100.0,
+            a.success_rate(),
+            b.success_rate(),
+            test.only_first,
+            test.only_second,
+            test.p_value,
+            if test.p_value < 0.05 { " *" } else { "" }
+        );
+    }
+    println!("* the two differ at the 5% level");
+}
+
+pub fn run(args: &SweepArgs) -> Result<(), Failure> {
+    if !matches!(args.compare.len(), 0 | 2) {
+        return Err(Failure::Config("--compare takes two repair counts, e.g. --compare 40,50".into()));
+    }
+    if let [first, second] = args.compare[..]
+        && (!args.repair.contains(&first) || !args.repair.contains(&second))
+    {
+        return Err(Failure::Config(format!("--compare {},{} needs both counts in --repair", first, second)));
+    }
+    if let Some(path) = args.output.as_deref().filter(|p| columnar::wants(p) && !columnar::AVAILABLE) {
+        return Err(columnar::unavailable(path));
+    }
+    let cells = sweep(args)?;
+    let written = match &args.output {
+        Some(path) if columnar::wants(path) => {
+            let rows = columnar::write_sweep(&cells, &metadata(args), path)?;
+            println!("Wrote {} runs to {}", rows, path.display());
+            Ok(())
+        }
+        Some(path) => File::create(path).and_then(|mut f| write_csv(&cells, &mut f)).map(|_| println!("Wrote {} cells to {}", cells.len(), path.display())),
+        None => write_csv(&cells, &mut io::stdout().lock()),
+    };
+    written.map_err(|e| Failure::Io(e.to_string()))?;
+    if args.plot && !cells.is_empty() {
+        plot(args, &cells);
+    }
+    if let Some(target) = args.target {
+        report_target(&cells, target);
+    }
+    if let [first, second] = args.compare[..] {
+        report_comparison(&cells, first, second);
+    }
+    Ok(())
+}
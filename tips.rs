@@ -0,0 +1,308 @@
+use std::cmp::Reverse;
+
+use clap::{Args, ValueEnum};
+use rand::seq::SliceRandom;
+use rand::RngCore;
+
+use crate::rng::{self, SimRng, SimStream};
+use crate::stitch::{StitchArgs, StitchPolicy};
+use crate::{Color, ConsensusParams, ToyDag};
+
+const MAX_PARENTS: usize = 3;           // Parents a miner references at most
+
+// How a miner picks the parents of its next block from the tips it is willing to use
+pub trait TipSelector {
+    fn name(&self) -> &'static str;
+
+    // `tips` is sorted by ID and never empty
+    fn select(&self, dag: &ToyDag, tips: &[u64], rng: &mut dyn RngCore) -> Vec<u64>;
+}
+
+// Tips a miner still considers, sorted by ID. With a bound, tips that have gone
+// unreferenced for more than `max_age` blocks are ignored, like real miners
+// ignore ancient tips; the freshest tip always survives.
+pub fn fresh_tips(dag: &ToyDag, max_age: Option<u64>) -> Vec<u64> {
+    let mut tips: Vec<u64> = dag.tips.iter().copied().collect();
+    tips.sort();
+    if let Some(bound) = max_age {
+        let freshest = *tips.last().unwrap();
+        tips.retain(|&id| id == freshest || dag.tip_age(id) <= bound);
+    }
+    tips
+}
+
+// Up to 3 distinct tips chosen uniformly at random (the original miner)
+pub struct UniformRandom;
+
+impl TipSelector for UniformRandom {
+    fn name(&self) -> &'static str {
+        "uniform"
+    }
+
+    fn select(&self, _dag: &ToyDag, tips: &[u64], rng: &mut dyn RngCore) -> Vec<u64> {
+        tips.choose_multiple(rng, tips.len().min(MAX_PARENTS)).copied().collect()
+    }
+}
+
+// The tips with the highest blue score, ties to the lower ID
+pub struct HighestBlueScore;
+
+impl TipSelector for HighestBlueScore {
+    fn name(&self) -> &'static str {
+        "highest-blue"
+    }
+
+    fn select(&self, dag: &ToyDag, tips: &[u64], _rng: &mut dyn RngCore) -> Vec<u64> {
+        let mut tips = tips.to_vec();
+        tips.sort_by_key(|id| (Reverse(dag.blocks[id].blue_score), *id));
+        tips.truncate(MAX_PARENTS);
+        tips
+    }
+}
+
+// What an honest miner does: build on the highest blue score tip and use
+// every remaining parent slot consensus allows on the next-best tips
+pub struct HonestBlueScore;
+
+impl TipSelector for HonestBlueScore {
+    fn name(&self) -> &'static str {
+        "honest"
+    }
+
+    fn select(&self, dag: &ToyDag, tips: &[u64], _rng: &mut dyn RngCore) -> Vec<u64> {
+        let mut tips = tips.to_vec();
+        tips.sort_by_key(|id| (Reverse(dag.blocks[id].blue_score), *id));
+        tips.truncate(dag.params.max_parents);
+        tips
+    }
+}
+
+// The most recently created tips
+pub struct MostRecent;
+
+impl TipSelector for MostRecent {
+    fn name(&self) -> &'static str {
+        "recent"
+    }
+
+    fn select(&self, _dag: &ToyDag, tips: &[u64], _rng: &mut dyn RngCore) -> Vec<u64> {
+        let mut tips = tips.to_vec();
+        tips.reverse();
+        tips.truncate(MAX_PARENTS);
+        tips
+    }
+}
+
+// Like honest, but ranks tips by accumulated blue work rather than blue score,
+// as real nodes do once block difficulty varies
+pub struct HeaviestBlueWork;
+
+impl TipSelector for HeaviestBlueWork {
+    fn name(&self) -> &'static str {
+        "heaviest"
+    }
+
+    fn select(&self, dag: &ToyDag, tips: &[u64], _rng: &mut dyn RngCore) -> Vec<u64> {
+        let mut tips = tips.to_vec();
+        tips.sort_by_key(|id| (Reverse(dag.blocks[id].blue_work + dag.blocks[id].work()), *id));
+        tips.truncate(dag.params.max_parents);
+        tips
+    }
+}
+
+// Builds on the single weakest tip and never merges, so every concurrent
+// block opens another branch
+pub struct AdversariallyWide;
+
+impl TipSelector for AdversariallyWide {
+    fn name(&self) -> &'static str {
+        "wide"
+    }
+
+    fn select(&self, dag: &ToyDag, tips: &[u64], _rng: &mut dyn RngCore) -> Vec<u64> {
+        let weakest = tips.iter().copied().min_by_key(|id| dag.blocks[id].blue_score).unwrap();
+        vec![weakest]
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum TipPolicy {
+    #[default]
+    Uniform,
+    HighestBlue,
+    Honest,
+    Heaviest,
+    Recent,
+    Wide,
+}
+
+impl TipPolicy {
+    pub fn selector(self) -> Box<dyn TipSelector> {
+        match self {
+            TipPolicy::Uniform => Box::new(UniformRandom),
+            TipPolicy::HighestBlue => Box::new(HighestBlueScore),
+            TipPolicy::Honest => Box::new(HonestBlueScore),
+            TipPolicy::Heaviest => Box::new(HeaviestBlueWork),
+            TipPolicy::Recent => Box::new(MostRecent),
+            TipPolicy::Wide => Box::new(AdversariallyWide),
+        }
+    }
+}
+
+// One round of concurrent mining: every miner selects against the same view,
+// so blocks found in the same round never reference each other
+pub fn mine_round(
+    dag: &mut ToyDag,
+    miners: &[Box<dyn TipSelector>],
+    max_tip_age: Option<u64>,
+    rng: &mut dyn RngCore,
+) -> Vec<u64> {
+    let tips = fresh_tips(dag, max_tip_age);
+    let choices: Vec<Vec<u64>> = miners.iter().map(|m| m.select(dag, &tips, rng)).collect();
+    choices.into_iter().map(|parents| dag.create_block(parents)).collect()
+}
+
+#[derive(Args, Debug)]
+pub struct TipPolicyArgs {
+    /// Blocks to grow per run
+    #[arg(long, default_value_t = 150)]
+    pub blocks: u64,
+
+    /// Concurrent miners per round
+    #[arg(long, default_value_t = 5)]
+    pub miners: usize,
+
+    /// Policies to compare, each run with every miner using it
+    #[arg(long, value_enum, value_delimiter = ',', default_values_t = vec![TipPolicy::Uniform, TipPolicy::HighestBlue, TipPolicy::Honest, TipPolicy::Heaviest, TipPolicy::Recent, TipPolicy::Wide])]
+    pub policies: Vec<TipPolicy>,
+
+    /// Extra run where miner i uses the i-th policy of this list
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub mix: Vec<TipPolicy>,
+
+    /// Miners ignore tips left unreferenced for more than this many blocks
+    #[arg(long)]
+    pub max_tip_age: Option<u64>,
+
+    /// Age (in blocks) from which a tip counts as stale in the report
+    #[arg(long, default_value_t = 20)]
+    pub stale_after: u64,
+
+    #[command(flatten)]
+    pub stitch: StitchArgs,
+
+    #[command(flatten)]
+    pub consensus: ConsensusParams,
+}
+
+// DAG shape after one run
+pub struct ShapeReport {
+    pub label: String,
+    pub blocks: usize,
+    pub mean_tips: f64,
+    pub max_tips: usize,
+    pub chain: usize,
+    pub reds: usize,
+    pub mean_mergeset: f64,
+    pub merges: usize,                  // Blocks StitchBot created
+    pub merge_reds: usize,              // Red candidates inside those merge blocks' mergesets
+    pub mean_tip_age: f64,              // Blocks a tip has gone unreferenced, averaged over rounds and tips
+    pub max_tip_age: u64,
+    pub tip_ages: Vec<u64>,             // Ages of the tips left at the end
+}
+
+// Mine rounds with these miners, letting StitchBot act after each, until the DAG has `blocks` blocks
+pub fn grow_with(
+    blocks: u64,
+    consensus: ConsensusParams,
+    miners: &[Box<dyn TipSelector>],
+    max_tip_age: Option<u64>,
+    stitch: &mut dyn StitchPolicy,
+    label: String,
+) -> (ToyDag, ShapeReport) {
+    let mut rng = SimStream::unseeded().fork(rng::MINERS);
+    let mut dag = ToyDag::with_params(consensus);
+    let mut tip_samples = Vec::new();
+    let mut stitched = Vec::new();
+    let (mut age_sum, mut age_samples, mut oldest) = (0, 0, 0);
+    while (dag.blocks.len() as u64) < blocks {
+        mine_round(&mut dag, miners, max_tip_age, &mut rng);
+        stitched.extend(dag.stitch(stitch));
+        tip_samples.push(dag.tips.len());
+        for &tip in &dag.tips {
+            let age = dag.tip_age(tip);
+            age_sum += age;
+            age_samples += 1;
+            oldest = oldest.max(age);
+        }
+    }
+
+    let merges: usize = dag.blocks.values().map(|b| b.mergeset.len()).sum();
+    let report = ShapeReport {
+        label,
+        blocks: dag.blocks.len(),
+        mean_tips: tip_samples.iter().sum::<usize>() as f64 / tip_samples.len().max(1) as f64,
+        max_tips: tip_samples.iter().copied().max().unwrap_or(1),
+        chain: dag.selected_chain().len(),
+        reds: dag.blocks.values().filter(|b| b.color == Color::Red).count(),
+        mean_mergeset: merges as f64 / dag.blocks.len() as f64,
+        merges: stitched.len(),
+        merge_reds: stitched.iter().map(|id| dag.blocks[id].mergeset.iter().filter(|c| !c.blue).count()).sum(),
+        mean_tip_age: age_sum as f64 / age_samples.max(1) as f64,
+        max_tip_age: oldest,
+        tip_ages: dag.tips.iter().map(|&tip| dag.tip_age(tip)).collect(),
+    };
+    (dag, report)
+}
+
+pub fn run(args: &TipPolicyArgs) {
+    let miners = args.miners.max(1);
+    let mut reports: Vec<ShapeReport> = args
+        .policies
+        .iter()
+        .map(|policy| {
+            let selectors: Vec<_> = (0..miners).map(|_| policy.selector()).collect();
+            let label = format!("{} x{}", selectors[0].name(), miners);
+            grow_with(args.blocks, args.consensus, &selectors, args.max_tip_age, args.stitch.policy().as_mut(), label).1
+        })
+        .collect();
+
+    if !args.mix.is_empty() {
+        let selectors: Vec<_> = args.mix.iter().map(|p| p.selector()).collect();
+        let label = selectors.iter().map(|s| s.name()).collect::<Vec<_>>().join("+");
+        reports.push(grow_with(args.blocks, args.consensus, &selectors, args.max_tip_age, args.stitch.policy().as_mut(), label).1);
+    }
+
+    println!(
+        "=== Tip selection policies ({} blocks, StitchBot: {}) ===",
+        args.blocks,
+        args.stitch.policy().name()
+    );
+    println!(
+        "{:<28} {:>7} {:>10} {:>9} {:>7} {:>6} {:>10} {:>7} {:>11}",
+        "miners", "blocks", "mean tips", "max tips", "chain", "reds", "mergeset", "merges", "merge reds"
+    );
+    for r in &reports {
+        println!(
+            "{:<28} {:>7} {:>10.2} {:>9} {:>7} {:>6} {:>10.2} {:>7} {:>11}",
+            r.label, r.blocks, r.mean_tips, r.max_tips, r.chain, r.reds, r.mean_mergeset, r.merges, r.merge_reds
+        );
+    }
+
+    match args.max_tip_age {
+        Some(bound) => println!("\nTip staleness (miners ignore tips older than {} blocks):", bound),
+        None => println!("\nTip staleness:"),
+    }
+    println!(
+        "{:<28} {:>9} {:>8} {:>16}",
+        "miners", "mean age", "max age", format!("stale (> {})", args.stale_after)
+    );
+    for r in &reports {
+        let stale = r.tip_ages.iter().filter(|&&age| age > args.stale_after).count();
+        println!(
+            "{:<28} {:>9.2} {:>8} {:>9} of {}",
+            r.label, r.mean_tip_age, r.max_tip_age, stale, r.tip_ages.len()
+        );
+    }
+    println!("==========================================================");
+}
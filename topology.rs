@@ -0,0 +1,297 @@
+// Peer graphs for the network simulation. The mesh is the original model, the
+// miner relaying straight to every node; the others make blocks travel hop by
+// hop, so propagation delay grows with the graph's diameter.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::PathBuf;
+
+use clap::{Args, ValueEnum};
+use rand::Rng;
+
+use crate::failure::Failure;
+use crate::network::{self, NetworkArgs};
+use crate::Color;
+
+#[derive(Args, Debug)]
+pub struct TopologiesArgs {
+    #[command(flatten)]
+    pub network: NetworkArgs,
+
+    /// Topologies to compare, each run with the same miners, links and FEC settings (--topology is ignored)
+    #[arg(long, value_enum, value_delimiter = ',', default_values_t = [Topology::Ring, Topology::Random, Topology::ScaleFree, Topology::Mesh])]
+    pub topologies: Vec<Topology>,
+
+    /// Also write the comparison table as CSV
+    #[arg(long)]
+    pub table: Option<PathBuf>,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Topology {
+    /// Every node a direct peer of every other
+    #[default]
+    Mesh,
+    /// Each node peers with the one before and after it
+    Ring,
+    /// Each node picks --degree/2 peers uniformly, then stray components are joined up
+    Random,
+    /// Preferential attachment (Barabási–Albert): newcomers peer with --degree/2 nodes, favouring well-connected ones
+    ScaleFree,
+}
+
+impl Topology {
+    pub fn name(self) -> &'static str {
+        match self {
+            Topology::Mesh => "mesh",
+            Topology::Ring => "ring",
+            Topology::Random => "random",
+            Topology::ScaleFree => "scale-free",
+        }
+    }
+}
+
+// Undirected peers of each of `n` nodes, sorted. The mesh draws nothing from
+// `rng`, so mesh runs stay identical to runs from before topologies existed.
+pub fn build<R: Rng>(topology: Topology, n: usize, degree: usize, rng: &mut R) -> Vec<Vec<usize>> {
+    let mut peers: Vec<BTreeSet<usize>> = vec![BTreeSet::new(); n];
+    let connect = |a: usize, b: usize, peers: &mut Vec<BTreeSet<usize>>| {
+        if a != b {
+            peers[a].insert(b);
+            peers[b].insert(a);
+        }
+    };
+    let m = (degree / 2).max(1).min(n.saturating_sub(1));
+    match topology {
+        Topology::Mesh => {
+            for a in 0..n {
+                for b in a + 1..n {
+                    connect(a, b, &mut peers);
+                }
+            }
+        }
+        Topology::Ring => {
+            for a in 0..n {
+                connect(a, (a + 1) % n, &mut peers);
+            }
+        }
+        Topology::Random => {
+            for a in 0..n {
+                while peers[a].len() < m {
+                    let b = rng.gen_range(0..n);
+                    connect(a, b, &mut peers);
+                }
+            }
+            // Picking peers at random can leave islands; tie each to the rest through a random member
+            let mut component = components(&peers);
+            while let Some(stray) = (0..n).find(|&v| component[v] != component[0]) {
+                let joined: Vec<usize> = (0..n).filter(|&v| component[v] == component[0]).collect();
+                connect(stray, joined[rng.gen_range(0..joined.len())], &mut peers);
+                component = components(&peers);
+            }
+        }
+        Topology::ScaleFree => {
+            // Seed with a clique of m + 1, then pick each newcomer's peers from the list of edge
+            // endpoints, where a node appears once per peer it already has
+            let seed = (m + 1).min(n);
+            let mut ends = Vec::new();
+            for a in 0..seed {
+                for b in a + 1..seed {
+                    connect(a, b, &mut peers);
+                    ends.extend([a, b]);
+                }
+            }
+            for a in seed..n {
+                while peers[a].len() < m {
+                    let b = ends[rng.gen_range(0..ends.len())];
+                    if !peers[a].contains(&b) {
+                        connect(a, b, &mut peers);
+                        ends.push(b);
+                    }
+                }
+                ends.extend(std::iter::repeat_n(a, m));
+            }
+        }
+    }
+    peers.into_iter().map(|p| p.into_iter().collect()).collect()
+}
+
+// Component label of every node
+fn components(peers: &[BTreeSet<usize>]) -> Vec<usize> {
+    let mut label = vec![usize::MAX; peers.len()];
+    for start in 0..peers.len() {
+        if label[start] != usize::MAX {
+            continue;
+        }
+        let mut stack = vec![start];
+        label[start] = start;
+        while let Some(v) = stack.pop() {
+            for &w in &peers[v] {
+                if label[w] == usize::MAX {
+                    label[w] = start;
+                    stack.push(w);
+                }
+            }
+        }
+    }
+    label
+}
+
+// One row of the comparison
+#[derive(Debug, Clone, PartialEq)]
+pub struct TopologyRow {
+    pub topology: Topology,
+    pub edges: usize,
+    pub diameter: usize,
+    pub delays: [u64; 4],               // p50, p90, p99 and max rounds from mining to arrival
+    pub red_ratio: f64,
+    pub packets_per_block: f64,         // Every packet any link carried, per block mined
+    pub relays_per_block: f64,
+}
+
+// Longest shortest path in hops, by a breadth-first search from every node
+fn diameter(peers: &[Vec<usize>]) -> usize {
+    let mut longest = 0;
+    for start in 0..peers.len() {
+        let mut hops = vec![usize::MAX; peers.len()];
+        hops[start] = 0;
+        let mut queue = std::collections::VecDeque::from([start]);
+        while let Some(v) = queue.pop_front() {
+            for &w in &peers[v] {
+                if hops[w] == usize::MAX {
+                    hops[w] = hops[v] + 1;
+                    queue.push_back(w);
+                }
+            }
+        }
+        longest = longest.max(hops.into_iter().filter(|&h| h != usize::MAX).max().unwrap_or(0));
+    }
+    longest
+}
+
+pub fn compare(args: &NetworkArgs, topology: Topology) -> TopologyRow {
+    let args = NetworkArgs { topology, ..args.clone() };
+    let run = network::simulate(&args);
+    let mut delays: Vec<u64> = run.blocks[run.known..]
+        .iter()
+        .flat_map(|b| (0..b.arrived_at.len()).filter(move |&node| node != b.miner).map(move |node| b.delay(node)))
+        .collect();
+    delays.sort();
+    let mined = (run.blocks.len() - run.known).max(1) as f64;
+    let (sent, relays) = run.links.iter().flatten().fold((0, 0), |(s, r), l| (s + l.sent, r + l.relays));
+    TopologyRow {
+        topology,
+        edges: run.neighbors.iter().map(Vec::len).sum::<usize>() / 2,
+        diameter: diameter(&run.neighbors),
+        delays: [
+            network::percentile(&delays, 0.5),
+            network::percentile(&delays, 0.9),
+            network::percentile(&delays, 0.99),
+            delays.last().copied().unwrap_or(0),
+        ],
+        red_ratio: run.dag.blocks.values().filter(|b| b.color == Color::Red).count() as f64 / run.dag.blocks.len() as f64,
+        packets_per_block: sent as f64 / mined,
+        relays_per_block: relays as f64 / mined,
+    }
+}
+
+pub fn run(args: &TopologiesArgs) -> Result<(), Failure> {
+    let network = &args.network;
+    println!(
+        "=== Topology study ({} nodes, {} blocks, mine chance {}, latency {}..={} + jitter {}, loss {}..{}, degree {}, seed {}) ===",
+        network.nodes,
+        network.blocks,
+        network.mine_chance,
+        network.min_latency.max(1),
+        network.max_latency.max(network.min_latency.max(1)),
+        network.jitter,
+        network.min_loss,
+        network.max_loss,
+        network.degree,
+        network.seed
+    );
+    let rows: Vec<TopologyRow> = args.topologies.iter().map(|&t| compare(network, t)).collect();
+    println!(
+        "{:<11} {:>6} {:>5} {:>5} {:>5} {:>5} {:>5} {:>7} {:>12} {:>12}",
+        "topology", "edges", "hops", "p50", "p90", "p99", "max", "red", "packets/blk", "relays/blk"
+    );
+    for row in &rows {
+        println!(
+            "{:<11} {:>6} {:>5} {:>5} {:>5} {:>5} {:>5} {:>7.3} {:>12.1} {:>12.1}",
+            row.topology.name(),
+            row.edges,
+            row.diameter,
+            row.delays[0],
+            row.delays[1],
+            row.delays[2],
+            row.delays[3],
+            row.red_ratio,
+            row.packets_per_block,
+            row.relays_per_block
+        );
+    }
+    println!("Delays in rounds from mining to arrival at each other node; hops is the graph's diameter.");
+    println!("==================================================================");
+
+    if let Some(path) = &args.table {
+        let mut csv = String::from("topology,edges,diameter,p50_delay,p90_delay,p99_delay,max_delay,red_ratio,packets_per_block,relays_per_block\n");
+        for row in &rows {
+            csv += &format!(
+                "{},{},{},{},{},{},{},{:.4},{:.2},{:.2}\n",
+                row.topology.name(),
+                row.edges,
+                row.diameter,
+                row.delays[0],
+                row.delays[1],
+                row.delays[2],
+                row.delays[3],
+                row.red_ratio,
+                row.packets_per_block,
+                row.relays_per_block
+            );
+        }
+        fs::write(path, csv).map_err(|e| Failure::Io(format!("cannot write {}: {}", path.display(), e)))?;
+        println!("Wrote {} rows to {}", rows.len(), path.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn every_topology_is_connected_and_symmetric() {
+        let mut rng = StdRng::seed_from_u64(3);
+        for topology in Topology::value_variants() {
+            for n in [1, 2, 5, 40] {
+                let peers = build(*topology, n, 4, &mut rng);
+                let sets: Vec<BTreeSet<usize>> = peers.iter().map(|p| p.iter().copied().collect()).collect();
+                assert!(components(&sets).iter().all(|&c| c == 0), "{:?} with {} nodes is split", topology, n);
+                for (a, row) in peers.iter().enumerate() {
+                    assert!(row.iter().all(|&b| b != a && peers[b].contains(&a)), "{:?} {} -> {:?}", topology, a, row);
+                }
+            }
+        }
+        let mut edges = |t| build(t, 40, 4, &mut rng).iter().map(Vec::len).sum::<usize>() / 2;
+        assert_eq!(edges(Topology::Ring), 40);
+        assert_eq!(edges(Topology::Mesh), 40 * 39 / 2);
+        // Barabási–Albert with m = 2: the seed triangle and two edges per newcomer
+        assert_eq!(edges(Topology::ScaleFree), 3 + 2 * 37);
+    }
+
+    #[test]
+    fn sparser_graphs_relay_further_for_less_bandwidth() {
+        let mut args = network::tests::args(16, 150);
+        (args.min_loss, args.max_loss) = (0.0, 0.0);
+        let ring = compare(&args, Topology::Ring);
+        let mesh = compare(&args, Topology::Mesh);
+        assert_eq!((ring.edges, ring.diameter, mesh.diameter), (16, 8, 1));
+        assert!(ring.delays[1] > mesh.delays[1], "{:?} vs {:?}", ring, mesh);
+        // Lossless, so every relay is one block's worth of packets: the mesh sends to all 15 peers,
+        // a ring at most twice per node
+        assert!(mesh.relays_per_block == 15.0 && ring.relays_per_block <= 32.0, "{:?} vs {:?}", ring, mesh);
+    }
+}
@@ -0,0 +1,366 @@
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use hex::encode;
+use raptorq::{Encoder, EncodingPacket, ObjectTransmissionInformation};
+
+use crate::aead::CipherArgs;
+use crate::auth::{self, AuthArgs, PacketAuth};
+use crate::bodies::BodyArgs;
+use crate::failure::Failure;
+use crate::fec::{self, SymbolArgs};
+use crate::frame;
+use crate::{headers, snapshot, ToyDag, PAYLOAD_ID_LEN, REPAIR_PACKETS};
+
+const OTI_FILE: &str = "object.oti";    // Directory layout: the object's RaptorQ config next to one file per packet
+
+// What to protect: exactly one of these
+#[derive(Args, Debug)]
+#[group(required = true, multiple = false)]
+pub struct InputArgs {
+    /// Encode the contents of a file
+    #[arg(long)]
+    pub file: Option<PathBuf>,
+
+    /// Encode bytes given as a hex string
+    #[arg(long)]
+    pub hex: Option<String>,
+
+    /// Encode the block headers of a saved DAG, in ID order (decode with --dag-out to rebuild it)
+    #[arg(long)]
+    pub dag: Option<PathBuf>,
+}
+
+// Where packets live: a directory with one file per packet, or a single stream
+#[derive(Args, Debug)]
+#[group(required = true, multiple = false)]
+pub struct PacketsArgs {
+    /// Directory holding object.oti and one <block>-<esi>.pkt file per packet; delete files to simulate loss
+    #[arg(long)]
+    pub dir: Option<PathBuf>,
+
+    /// Single stream file (`-` for stdout/stdin): the config, then length-prefixed packets
+    #[arg(long)]
+    pub stream: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct EncodeArgs {
+    #[command(flatten)]
+    pub input: InputArgs,
+
+    #[command(flatten)]
+    pub packets: PacketsArgs,
+
+    #[command(flatten)]
+    pub symbols: SymbolArgs,
+
+    /// Repair packets per source block
+    #[arg(long, default_value_t = REPAIR_PACKETS)]
+    pub repair: u32,
+
+    /// With --dag: only send the blocks a receiver holding this selected-chain block lacks
+    #[arg(long, requires = "dag")]
+    pub since: Option<u64>,
+
+    #[command(flatten)]
+    pub auth: AuthArgs,
+
+    #[command(flatten)]
+    pub cipher: CipherArgs,
+}
+
+#[derive(Args, Debug)]
+pub struct DecodeArgs {
+    #[command(flatten)]
+    pub packets: PacketsArgs,
+
+    /// Write the recovered object here (hex on stdout when omitted)
+    #[arg(long)]
+    pub out: Option<PathBuf>,
+
+    /// Treat the object as block headers sent with --dag: rebuild and validate the DAG, then save it here
+    #[arg(long)]
+    pub dag_out: Option<PathBuf>,
+
+    /// Saved DAG that headers sent with --since extend; the result goes to --dag-out
+    #[arg(long, requires = "dag_out")]
+    pub base: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub auth: AuthArgs,
+
+    #[command(flatten)]
+    pub cipher: CipherArgs,
+}
+
+pub fn read_input(input: &InputArgs, since: Option<u64>) -> Result<Vec<u8>, Failure> {
+    if let Some(path) = &input.file {
+        return fs::read(path).map_err(|e| Failure::Io(format!("cannot read {}: {}", path.display(), e)));
+    }
+    if let Some(text) = &input.hex {
+        return hex::decode(text.trim()).map_err(|e| Failure::Config(format!("invalid hex input: {}", e)));
+    }
+    let path = input.dag.as_ref().expect("clap requires one input");
+    let dag = snapshot::load(path)?;
+    let body = BodyArgs::default();
+    match since {
+        None => Ok(headers::serialize(&dag, &body).0),
+        Some(checkpoint) if dag.selected_chain().contains(&checkpoint) => {
+            Ok(headers::serialize_delta(&dag, checkpoint, &body))
+        }
+        Some(checkpoint) => Err(Failure::Config(format!("--since {} is not on the selected chain", checkpoint))),
+    }
+}
+
+fn is_stdio(path: &Path) -> bool {
+    path.as_os_str() == "-"
+}
+
+// Packets go out as serialized, or followed by their tag in an authenticated session
+fn frame(packet: &EncodingPacket, auth: Option<&PacketAuth>) -> Vec<u8> {
+    match auth {
+        Some(auth) => auth.seal(packet),
+        None => packet.serialize(),
+    }
+}
+
+pub fn write_packets(
+    target: &PacketsArgs,
+    config: ObjectTransmissionInformation,
+    packets: &[EncodingPacket],
+    auth: Option<&PacketAuth>,
+) -> io::Result<()> {
+    if let Some(dir) = &target.dir {
+        fs::create_dir_all(dir)?;
+        fs::write(dir.join(OTI_FILE), config.serialize())?;
+        for packet in packets {
+            let id = packet.payload_id();
+            let name = format!("{}-{}.pkt", id.source_block_number(), id.encoding_symbol_id());
+            fs::write(dir.join(name), frame(packet, auth))?;
+        }
+        return Ok(());
+    }
+
+    let path = target.stream.as_ref().expect("clap requires one target");
+    let mut bytes = config.serialize().to_vec();
+    for packet in packets {
+        let encoded = frame(packet, auth);
+        bytes.extend_from_slice(&(encoded.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(&encoded);
+    }
+    if is_stdio(path) {
+        io::stdout().lock().write_all(&bytes)
+    } else {
+        fs::write(path, bytes)
+    }
+}
+
+// The object config and every packet frame as received, still unchecked
+pub fn read_packets(source: &PacketsArgs) -> io::Result<(ObjectTransmissionInformation, Vec<Vec<u8>>)> {
+    let corrupt = |what: String| io::Error::new(io::ErrorKind::InvalidData, what);
+    let read_oti = |bytes: &[u8]| -> io::Result<ObjectTransmissionInformation> {
+        let oti: &[u8; 12] = bytes.try_into().map_err(|_| corrupt("object config must be 12 bytes".into()))?;
+        let config = ObjectTransmissionInformation::deserialize(oti);
+        // A zero symbol size or block count would crash the decoder rather than fail it
+        if !frame::valid_config(&config) {
+            return Err(corrupt("object config describes no decodable object".into()));
+        }
+        Ok(config)
+    };
+
+    if let Some(dir) = &source.dir {
+        let config = read_oti(&fs::read(dir.join(OTI_FILE))?)?;
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<io::Result<_>>()?;
+        paths.retain(|p| p.extension().is_some_and(|ext| ext == "pkt"));
+        let mut frames = Vec::with_capacity(paths.len());
+        for path in paths {
+            let bytes = fs::read(&path)?;
+            if bytes.len() < 4 {
+                return Err(corrupt(format!("{} is too short to be a packet", path.display())));
+            }
+            frames.push(bytes);
+        }
+        // The payload ID leads every frame: source block, then a 3-byte ESI
+        frames.sort_by(|a, b| a[..4].cmp(&b[..4]));
+        return Ok((config, frames));
+    }
+
+    let path = source.stream.as_ref().expect("clap requires one source");
+    let bytes = if is_stdio(path) {
+        let mut bytes = Vec::new();
+        io::stdin().lock().read_to_end(&mut bytes)?;
+        bytes
+    } else {
+        fs::read(path)?
+    };
+    if bytes.len() < 12 {
+        return Err(corrupt("stream too short for the object config".into()));
+    }
+    let config = read_oti(&bytes[..12])?;
+    let mut rest = &bytes[12..];
+    let mut frames = Vec::new();
+    while !rest.is_empty() {
+        // A truncated stream just loses its tail, like any other lossy channel
+        if rest.len() < 4 {
+            break;
+        }
+        let len = u32::from_be_bytes(rest[..4].try_into().unwrap()) as usize;
+        if len < 4 || rest.len() < 4 + len {
+            break;
+        }
+        frames.push(rest[4..4 + len].to_vec());
+        rest = &rest[4 + len..];
+    }
+    Ok((config, frames))
+}
+
+pub fn run_encode(args: &EncodeArgs) -> Result<(), Failure> {
+    let mut data = read_input(&args.input, args.since)?;
+    if data.is_empty() {
+        return Err(Failure::Config("nothing to encode: the input is empty".into()));
+    }
+    let plain_len = data.len();
+    if let Some(sealed) = args.cipher.encrypt(&data)? {
+        data = sealed;
+    }
+    let tag = if args.auth.auth_key.is_some() { auth::TAG_LEN } else { 0 };
+    let symbols = args.symbols.choose(data.len(), PAYLOAD_ID_LEN + tag);
+    let encoder = Encoder::with_defaults(&data, symbols.size);
+    let config = encoder.get_config();
+    let packets = encoder.get_encoded_packets(args.repair);
+    let auth = args.auth.session(&config)?;
+    write_packets(&args.packets, config, &packets, auth.as_ref()).map_err(|e| Failure::Io(format!("cannot write packets: {}", e)))?;
+
+    // Keep stdout clean when it carries the stream itself
+    let summary = format!(
+        "Encoded {} bytes{} into {} packets ({} repair; {})",
+        plain_len,
+        if data.len() > plain_len { format!(" ({} encrypted)", data.len()) } else { String::new() },
+        packets.len(),
+        args.repair,
+        symbols.describe()
+    );
+    if args.packets.stream.as_deref().is_some_and(is_stdio) {
+        eprintln!("{}", summary);
+    } else {
+        println!("{}", summary);
+    }
+    Ok(())
+}
+
+pub fn run_decode(args: &DecodeArgs) -> Result<(), Failure> {
+    // Mangled packet data is as fatal to recovery as missing packets; anything else is the filesystem's fault
+    let (config, frames) = read_packets(&args.packets).map_err(|e| match e.kind() {
+        io::ErrorKind::InvalidData => Failure::Decode(format!("corrupt packets: {}", e)),
+        _ => Failure::Io(format!("cannot read packets: {}", e)),
+    })?;
+    let received = frames.len();
+
+    // In an authenticated session nothing reaches the decoder without a valid tag
+    let packets = match args.auth.session(&config)? {
+        Some(auth) => {
+            let (packets, rejected) = auth::filter_frames(&auth, frames);
+            if rejected > 0 {
+                println!("Rejected {} of {} packets failing authentication", rejected, received);
+            }
+            packets
+        }
+        None => frames.iter().map(|f| EncodingPacket::deserialize(f)).collect(),
+    };
+    let (packets, misfits): (Vec<EncodingPacket>, Vec<EncodingPacket>) = packets.into_iter().partition(|p| frame::fits(&config, p));
+    if !misfits.is_empty() {
+        println!("Dropped {} packets that do not fit the object config", misfits.len());
+    }
+    let accepted = packets.len();
+    let outcome = fec::decode_packets(config, packets);
+
+    let data = match outcome.result() {
+        Ok(data) => data,
+        Err(e) => {
+            println!("Decoding failed: {} packets for {} source symbols", accepted, outcome.source_symbols);
+            for symbol in &outcome.missing {
+                println!("  missing source symbol {} (bytes {:?})", symbol.esi, symbol.byte_range);
+            }
+            return Err(Failure::Decode(e.to_string()));
+        }
+    };
+
+    println!("Recovered {} bytes from {} of {} packets", data.len(), outcome.packets_used, received);
+    println!(
+        "Object parameters: {} byte symbols, {} source symbols in {} source block(s)",
+        config.symbol_size(),
+        outcome.source_symbols,
+        config.source_blocks()
+    );
+    outcome.print_packet_usage();
+    let data = match args.cipher.decrypt(data)? {
+        Some(plain) => {
+            println!("Decrypted and authenticated {} bytes", plain.len());
+            plain
+        }
+        None => data.to_vec(),
+    };
+    if let Some(path) = &args.dag_out {
+        let set = headers::parse(&data).map_err(|e| Failure::Mismatch(format!("recovered object is not block headers: {}", e)))?;
+        // Without a base, replay from a bare genesis
+        let mut dag = match &args.base {
+            Some(base) => snapshot::load(base)?,
+            None => ToyDag::with_params(set.params),
+        };
+        let added = headers::apply(&mut dag, &set)
+            .map_err(|e| Failure::Mismatch(format!("recovered headers do not rebuild a valid DAG: {}", e)))?;
+        snapshot::save(&dag, path).map_err(|e| Failure::Io(format!("cannot write {}: {}", path.display(), e)))?;
+        println!(
+            "Applied {} new of {} headers since block {}: {} blocks validated, saved to {}",
+            added,
+            set.headers.len(),
+            set.checkpoint,
+            dag.blocks.len(),
+            path.display()
+        );
+    }
+    match &args.out {
+        Some(path) => {
+            fs::write(path, &data).map_err(|e| Failure::Io(format!("cannot write {}: {}", path.display(), e)))?;
+            println!("Wrote {}", path.display());
+        }
+        None if args.dag_out.is_none() => {
+            for chunk in data.chunks(32) {
+                println!("{}", encode(chunk));
+            }
+        }
+        None => {}
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_args(packets: PacketsArgs) -> DecodeArgs {
+        DecodeArgs { packets, out: None, dag_out: None, base: None, auth: AuthArgs::default(), cipher: CipherArgs::default() }
+    }
+
+    #[test]
+    fn a_zero_object_config_is_a_decode_failure_not_a_panic() {
+        let dir = std::env::temp_dir().join(format!("toy-fec-zero-oti-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let stream = dir.join("zero.stream");
+        fs::write(&stream, [0u8; 12]).unwrap();
+        fs::write(dir.join(OTI_FILE), [0u8; 12]).unwrap();
+
+        let from_stream = run_decode(&decode_args(PacketsArgs { dir: None, stream: Some(stream) }));
+        let from_dir = run_decode(&decode_args(PacketsArgs { dir: Some(dir.clone()), stream: None }));
+        let _ = fs::remove_dir_all(&dir);
+        for result in [from_stream, from_dir] {
+            let failure = result.unwrap_err();
+            assert_eq!(failure.code(), 3, "{}", failure);
+        }
+    }
+}
@@ -0,0 +1,242 @@
+use clap::{Args, ValueEnum};
+use raptorq::Encoder;
+
+use crate::branches::abandoned_branches;
+use crate::channel::LossModel;
+use crate::rng::{self, SimRng, SimStream};
+use crate::stitch::{Never, TipThreshold};
+use crate::tips::{self, TipPolicy};
+use crate::{fec, ghostdag, grow_dag, ConsensusParams, ToyDag};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Scenario {
+    /// Two miners fork, then a merge block decides which branch the chain follows
+    ForkRace,
+    /// Many concurrent miners: how width turns into red blocks, and what stitching does about it
+    WideDag,
+    /// Same average packet loss, bursty vs independent: why bursts hurt FEC
+    BurstLoss,
+    /// Lying about block timestamps, and how far the median time past lets a miner get
+    TimeWarp,
+}
+
+#[derive(Args, Debug)]
+pub struct TutorialArgs {
+    /// Scenario to run (omit to list them)
+    #[arg(value_enum)]
+    pub scenario: Option<Scenario>,
+}
+
+pub fn run(args: &TutorialArgs) {
+    match args.scenario {
+        Some(Scenario::ForkRace) => fork_race(),
+        Some(Scenario::WideDag) => wide_dag(),
+        Some(Scenario::BurstLoss) => burst_loss(),
+        Some(Scenario::TimeWarp) => time_warp(),
+        None => {
+            println!("Tutorial scenarios (run `toy-fec tutorial <name>`):");
+            for scenario in Scenario::value_variants() {
+                let value = scenario.to_possible_value().unwrap();
+                println!("  {:<12} {}", value.get_name(), value.get_help().map(|h| h.to_string()).unwrap_or_default());
+            }
+        }
+    }
+}
+
+// One narration paragraph, set off from the program output around it
+fn say(lines: &[&str]) {
+    println!("\n» {}", lines.join("\n  "));
+}
+
+// ====================== fork-race ======================
+
+// Genesis, a 3-block branch A and a 2-block branch B, then one block merging both
+fn race(k: usize) -> (ToyDag, u64) {
+    let mut dag = ToyDag::with_params(ConsensusParams { k, ..ConsensusParams::default() });
+    let a1 = dag.create_block(vec![0]);
+    let b1 = dag.create_block(vec![0]);
+    let a2 = dag.create_block(vec![a1]);
+    let b2 = dag.create_block(vec![b1]);
+    let a3 = dag.create_block(vec![a2]);
+    let merge = dag.create_block(vec![a3, b2]);
+    (dag, merge)
+}
+
+fn fork_race() {
+    println!("=== Tutorial: fork race ===");
+    say(&["Miner A builds blocks 1, 3, 5 on genesis; miner B, unaware, builds 2 and 4. Block 6 sees both tips."]);
+    let (dag, merge) = race(15);
+    dag.print_dag();
+
+    say(&[
+        "GHOSTDAG does not throw B's work away. Block 6 follows the branch with the higher blue score (A) and",
+        "merges B as its mergeset. With k = 15, each B block has only 3 blues it cannot see, so both stay blue:",
+    ]);
+    println!("{}", ghostdag::explain(&dag, merge));
+
+    say(&[
+        "Now the same race with k = 1: the network promises that at most one honest block is ever concurrent.",
+        "B's blocks each miss three of A's blues, which breaks that promise, so block 6 colors them red:",
+    ]);
+    let (strict, merge) = race(1);
+    println!("{}", ghostdag::explain(&strict, merge));
+
+    say(&[
+        "What to observe: k is a latency assumption. The same DAG is all-blue under a generous k and partly",
+        "red under a tight one; red blocks stay in the ordering but never add to anyone's blue score.",
+    ]);
+    println!("===========================");
+}
+
+// ====================== wide-dag ======================
+
+fn wide_dag() {
+    println!("=== Tutorial: wide DAG ===");
+    say(&[
+        "Ten honest miners find blocks in the same round, every round, so each round adds ten parallel tips.",
+        "We grow 200 blocks three ways and compare how many end up red.",
+    ]);
+
+    let miners: Vec<_> = (0..10).map(|_| TipPolicy::Honest.selector()).collect();
+    let runs = [
+        ("k = 15, no StitchBot", 15, false),
+        ("k = 3, no StitchBot", 3, false),
+        ("k = 3, StitchBot at 10 tips", 3, true),
+    ];
+    println!("\n{:<30} {:>10} {:>6} {:>10}", "setup", "mean tips", "reds", "branches");
+    for (label, k, stitching) in runs {
+        let params = ConsensusParams { k, ..ConsensusParams::default() };
+        let (dag, report) = if stitching {
+            tips::grow_with(200, params, &miners, None, &mut TipThreshold { threshold: 10 }, label.to_string())
+        } else {
+            tips::grow_with(200, params, &miners, None, &mut Never, label.to_string())
+        };
+        println!(
+            "{:<30} {:>10.2} {:>6} {:>10}",
+            label,
+            report.mean_tips,
+            report.reds,
+            abandoned_branches(&dag).len()
+        );
+    }
+
+    say(&[
+        "Honest miners reference every tip they see, so the DAG stays about ten blocks wide whatever k is.",
+        "Width itself is fine: with k = 15 ten concurrent blocks are within tolerance and stay blue. With",
+        "k = 3 the same width exceeds what the protocol tolerates and blocks start turning red.",
+        "Stitching cannot make the network faster; it only merges tips that exist. What to observe: red",
+        "blocks measure a mismatch between k and real concurrency, not misbehavior.",
+    ]);
+    println!("==========================");
+}
+
+// ====================== burst-loss ======================
+
+fn burst_loss() {
+    println!("=== Tutorial: burst loss ===");
+    let root = SimStream::unseeded();
+    let dag = grow_dag(100, &mut root.fork(rng::MINERS));
+    let mut channel = root.fork(rng::CHANNEL);
+    let mut ids: Vec<_> = dag.blocks.keys().copied().collect();
+    ids.sort();
+    let data: Vec<u8> = ids.iter().flat_map(|id| dag.blocks[id].hash).collect();
+
+    let encoder = Encoder::with_defaults(&data, 128);
+    let packets = encoder.get_encoded_packets(15);
+    let source = fec::source_symbol_count(&encoder.get_config());
+    let intro = format!(
+        "We protect {} block hashes ({} bytes) with RaptorQ: {} source packets plus 15 repair packets.",
+        ids.len(),
+        data.len(),
+        source
+    );
+    say(&[
+        &intro,
+        "Any 25 of the 40 packets will do, so we can absorb 15 losses (37%). Both channels below lose 20%",
+        "of packets on average. The first drops packets independently, the second in bursts.",
+    ]);
+
+    let models = [
+        LossModel::Uniform { rate: 0.2 },
+        LossModel::GilbertElliott { to_bad: 0.05, to_good: 0.2, bad_loss: 1.0 },
+    ];
+    let trials = 500;
+    println!("\n{:<16} {:>10} {:>10} {:>10} {:>10}", "channel", "mean loss", "mean lost", "max lost", "failures");
+    for model in models {
+        let mut lost_total = 0;
+        let mut lost_max = 0;
+        let mut failures = 0;
+        for _ in 0..trials {
+            let received = model.transmit(packets.clone(), &mut channel);
+            let lost = packets.len() - received.len();
+            lost_total += lost;
+            lost_max = lost_max.max(lost);
+            if fec::decode_packets(encoder.get_config(), received).data.is_none() {
+                failures += 1;
+            }
+        }
+        println!(
+            "{:<16} {:>9.0}% {:>10.1} {:>10} {:>9.1}%",
+            model.name(),
+            100.0 * model.mean_loss(),
+            lost_total as f64 / trials as f64,
+            lost_max,
+            100.0 * failures as f64 / trials as f64
+        );
+    }
+
+    say(&[
+        "RaptorQ does not care which packets go missing, only how many. Both channels lose about eight",
+        "packets per object on average, but bursts make that count swing widely: some objects lose",
+        "almost nothing, others lose more than the 15 repair packets can cover.",
+        "What to observe: size repair overhead for the worst bursts you expect, not the average loss rate.",
+    ]);
+    println!("============================");
+}
+
+// ====================== time-warp ======================
+
+fn time_warp() {
+    println!("=== Tutorial: time warp ===");
+    let mut dag = ToyDag::new();
+    let mut tip = 0;
+    for _ in 0..20 {
+        tip = dag.create_block(vec![tip]);
+    }
+    let window = dag.params.mtp_window;
+    let mtp = dag.median_time_past(tip);
+    say(&[
+        "Twenty honest blocks in a row, each stamped with the honest clock (block N at time N).",
+        "A new block must claim a time later than the median of the last few chain timestamps:",
+    ]);
+    println!("Tip {} at time {}, median time past over {} blocks: {}", tip, dag.blocks[&tip].timestamp, window, mtp);
+
+    say(&["A miner back-dates its block to the median itself, then to one tick past it:"]);
+    for claim in [mtp, mtp + 1] {
+        match dag.create_block_at(vec![tip], claim) {
+            Ok(id) => println!("  claim time {:>4}: accepted as block {}", claim, id),
+            Err(e) => println!("  claim time {:>4}: rejected, {}", claim, e),
+        }
+    }
+    say(&[
+        "The rule only bounds lies into the past, and only to about half a window behind the chain.",
+        "Now the attacker mines the next chain blocks alone, stamping each far into the future:",
+    ]);
+    let mut tip = dag.selected_parent;
+    for i in 0..=window / 2 {
+        tip = dag.create_block_at(vec![tip], 1000 + i as u64).unwrap();
+        println!("  block {} claims time {}, median time past now {}", tip, 1000 + i as u64, dag.median_time_past(tip));
+    }
+
+    let honest = dag.next_id;
+    say(&["Once future stamps hold the majority of the window, the median jumps. An honest miner stamping the real time:"]);
+    match dag.create_block_at(vec![tip], honest) {
+        Ok(id) => println!("  claim time {:>4}: accepted as block {}", honest, id),
+        Err(e) => println!("  claim time {:>4}: rejected, {}", honest, e),
+    }
+    say(&[
+        "What to observe: the median time past stops single liars cheaply, but whoever controls most of",
+        "a window controls the clock. Real chains add an upper bound (no timestamps too far past local time).",
+    ]);
+    println!("===========================");
+}